@@ -0,0 +1,96 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use ergo_headless_dapp_framework::encoding::{address_string_to_ergo_tree, build_token};
+use ergo_headless_dapp_framework::{
+    create_candidate, find_and_sum_other_tokens, BoxSpec, ErgoBox, TokenSpec, TxFeeBox,
+};
+use ergo_lib::chain::transaction::TxId;
+use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
+use ergo_lib::ergotree_ir::chain::ergo_box::NonMandatoryRegisters;
+
+/// The miner fee P2S address, used as a stand-in protocol address
+const ADDRESS: &str = "2iHkR7CWvD1R4j1yZg5bkeDRQavjAaVPeTDFGGLZduHyfWMuYpmhHocX8GJoaieTx78FntzJbCBVL6rf96ocJoZdmWBL2fci7NqWgAirppPQmZ7fN9V6z13Ay6brPriBKYqLp1bT2Fk4FkFLCfdPpe";
+const TOKEN_ID: &str = "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1";
+/// The maximum page size returned by the explorer for address queries
+const PAGE_SIZE: u64 = 500;
+
+/// Builds a page of boxes where every other box holds the spec token
+fn build_boxes() -> Vec<ErgoBox> {
+    let ergo_tree = address_string_to_ergo_tree(&ADDRESS.to_string()).unwrap();
+    (0..PAGE_SIZE)
+        .map(|i| {
+            let tokens = match i % 2 {
+                0 => vec![build_token(TOKEN_ID, 1).unwrap()],
+                _ => vec![],
+            };
+            ErgoBox::new(
+                BoxValue::new(1000000 + i).unwrap(),
+                ergo_tree.clone(),
+                tokens,
+                NonMandatoryRegisters::empty(),
+                i as u32,
+                TxId::zero(),
+                0,
+            )
+            .unwrap()
+        })
+        .collect()
+}
+
+/// Wraps the boxes in the same JSON structure the explorer returns
+fn build_explorer_response(boxes: &Vec<ErgoBox>) -> String {
+    let items: Vec<String> = boxes
+        .iter()
+        .map(|b| serde_json::to_string(b).unwrap())
+        .collect();
+    format!("{{\"items\": [{}], \"total\": {}}}", items.join(","), boxes.len())
+}
+
+fn build_spec() -> BoxSpec {
+    BoxSpec::new(
+        Some(ADDRESS.to_string()),
+        Some(1000000..u64::MAX),
+        vec![],
+        vec![Some(TokenSpec::new(1..2, TOKEN_ID))],
+    )
+}
+
+fn bench_verify_box(c: &mut Criterion) {
+    let spec = build_spec();
+    let boxes = build_boxes();
+    c.bench_function("verify_box", |b| {
+        b.iter(|| {
+            for ergo_box in &boxes {
+                let _ = black_box(spec.verify_box(ergo_box));
+            }
+        })
+    });
+}
+
+fn bench_process_explorer_response(c: &mut Criterion) {
+    let spec = build_spec();
+    let response = build_explorer_response(&build_boxes());
+    c.bench_function("process_explorer_response", |b| {
+        b.iter(|| spec.process_explorer_response(black_box(&response)).unwrap())
+    });
+}
+
+fn bench_candidate_creation(c: &mut Criterion) {
+    let boxes = build_boxes();
+    let address = ADDRESS.to_string();
+    c.bench_function("create_candidate", |b| {
+        b.iter(|| {
+            let tokens = find_and_sum_other_tokens(&vec![], black_box(&boxes)).unwrap();
+            let change = create_candidate(1000000, &address, &tokens, &vec![], 0).unwrap();
+            let fee = TxFeeBox::output_candidate(1000000, 0).unwrap();
+            (change, fee)
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_verify_box,
+    bench_process_explorer_response,
+    bench_candidate_creation
+);
+criterion_main!(benches);
@@ -15,8 +15,8 @@ fn impl_wrapped_box(ast: &syn::DeriveInput) -> TokenStream {
     let name = &ast.ident;
     let gen = quote! {
         impl WrappedBox for #name {
-            fn get_box(&self) -> ErgoBox {
-                self.ergo_box.clone()
+            fn get_box_ref(&self) -> &ErgoBox {
+                &self.ergo_box
             }
         }
     };
@@ -55,6 +55,10 @@ fn impl_specified_box(ast: &syn::DeriveInput) -> TokenStream {
                 }
                 Ok(specified_boxes)
             }
+
+            fn from_ergo_box(ergo_box: &ErgoBox) -> std::result::Result<#name, HeadlessDappError> {
+                Self::new(ergo_box)
+            }
         }
 
     };
@@ -102,6 +106,27 @@ fn impl_wasm_box(ast: &syn::DeriveInput) -> TokenStream {
                 Self::box_spec().explorer_endpoint(explorer_api_url)
                                 .map_err(|err| JsValue::from_str(&format!("{}", err)))
             }
+
+            /// Fetches the boxes matching the spec from the explorer API
+            /// using the provided JS `fetch_body` function, which takes the
+            /// endpoint url and returns a `Promise` resolving to the response
+            /// body as a string. Resolves to an array of instances.
+            #[wasm_bindgen]
+            pub async fn w_fetch(explorer_api_url: String, fetch_body: ergo_headless_dapp_framework::js_sys::Function)
+                -> std::result::Result<JsValue, JsValue> {
+                let endpoint = Self::box_spec().explorer_endpoint(&explorer_api_url)
+                                .map_err(|err| JsValue::from_str(&format!("{}", err)))?;
+                let promise: ergo_headless_dapp_framework::js_sys::Promise = fetch_body
+                    .call1(&JsValue::NULL, &JsValue::from_str(&endpoint))?
+                    .into();
+                let body = ergo_headless_dapp_framework::wasm_bindgen_futures::JsFuture::from(promise)
+                    .await?
+                    .as_string()
+                    .ok_or_else(|| JsValue::from_str("The fetched response body is not a string"))?;
+                let boxes = Self::process_explorer_response(&body)
+                                .map_err(|err| JsValue::from_str(&format!("{}", err)))?;
+                Ok(boxes.into_iter().map(JsValue::from).collect::<ergo_headless_dapp_framework::js_sys::Array>().into())
+            }
         }
 
     };
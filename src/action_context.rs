@@ -0,0 +1,137 @@
+/// This file holds the `ActionContext` which bundles the parameters shared
+/// by every Action of a protocol (the current height, the fee policy, the
+/// network, the change address, and the explorer backend). Action builders
+/// take a single `&ActionContext` rather than threading these as loose
+/// parameters through every Action signature.
+use crate::error::{HeadlessDappError, Result};
+use crate::explorer_backend::ExplorerBackend;
+use crate::fee_guard::FeeGuard;
+use crate::network::Network;
+use crate::output_builders::{ChangeBox, TxFeeBox};
+use crate::{BlockHeight, NanoErg, P2PKAddressString};
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBoxCandidate;
+use ergo_lib::ergotree_ir::chain::token::Token;
+
+/// The default transaction fee (0.001 Erg)
+pub const DEFAULT_TX_FEE: NanoErg = 1000000;
+
+/// The parameters shared by the Actions of a protocol
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActionContext {
+    /// The current height of the chain
+    pub current_height: BlockHeight,
+    /// The fee paid by every transaction
+    pub transaction_fee: NanoErg,
+    /// The sanity checks applied to the `transaction_fee`
+    pub fee_guard: FeeGuard,
+    /// The network the protocol runs on
+    pub network: Network,
+    /// The address which receives the change of every transaction
+    pub change_address: P2PKAddressString,
+    /// The explorer backend used to fetch boxes, if any
+    pub backend: Option<ExplorerBackend>,
+}
+
+impl ActionContext {
+    /// Create a new `ActionContext` at the `current_height` with the
+    /// `change_address`, paying `DEFAULT_TX_FEE` on mainnet
+    pub fn new(current_height: BlockHeight, change_address: &P2PKAddressString) -> ActionContext {
+        ActionContext {
+            current_height,
+            transaction_fee: DEFAULT_TX_FEE,
+            fee_guard: FeeGuard::default(),
+            network: Network::Mainnet,
+            change_address: change_address.clone(),
+            backend: None,
+        }
+    }
+
+    /// Sets the fee paid by every transaction
+    pub fn with_transaction_fee(mut self, transaction_fee: NanoErg) -> Self {
+        self.transaction_fee = transaction_fee;
+        self
+    }
+
+    /// Sets the sanity checks applied to the transaction fee
+    pub fn with_fee_guard(mut self, fee_guard: FeeGuard) -> Self {
+        self.fee_guard = fee_guard;
+        self
+    }
+
+    /// Sets the network the protocol runs on
+    pub fn with_network(mut self, network: Network) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Sets the explorer backend used to fetch boxes
+    pub fn with_backend(mut self, backend: ExplorerBackend) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Returns a copy of the context at a new height, as is done when a
+    /// new block is mined
+    pub fn at_height(&self, current_height: BlockHeight) -> ActionContext {
+        ActionContext {
+            current_height,
+            ..self.clone()
+        }
+    }
+
+    /// Verifies that the change address is valid and belongs to the
+    /// network of the context, and that the transaction fee passes the
+    /// fee guard
+    pub fn validate(&self) -> Result<()> {
+        if Network::from_address(&self.change_address) != Some(self.network) {
+            return Err(HeadlessDappError::InvalidAddress(
+                self.change_address.clone(),
+            ));
+        }
+        self.fee_guard.check(self.transaction_fee, 0)
+    }
+
+    /// Acquires the explorer backend, erroring if none was provided
+    pub fn backend(&self) -> Result<&ExplorerBackend> {
+        self.backend
+            .as_ref()
+            .ok_or_else(|| HeadlessDappError::Other("The context has no backend".to_string()))
+    }
+
+    /// Creates the transaction fee output
+    pub fn tx_fee_output(&self) -> Result<ErgoBoxCandidate> {
+        TxFeeBox::output_candidate(self.transaction_fee, self.current_height)
+    }
+
+    /// Creates a change output holding the `value` and `tokens` for the
+    /// change address
+    pub fn change_output(&self, tokens: &Vec<Token>, value: NanoErg) -> Result<ErgoBoxCandidate> {
+        ChangeBox::output_candidate(tokens, value, &self.change_address, self.current_height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "9fSgJ7BmUxBQJ454prQDQ7fQMBkXPLaAmDnimgTtjym6FYPHjAV";
+
+    #[test]
+    fn validates_context() {
+        let ctx = ActionContext::new(100, &ADDRESS.to_string());
+        assert!(ctx.validate().is_ok());
+        assert!(ctx
+            .clone()
+            .with_network(Network::Testnet)
+            .validate()
+            .is_err());
+        assert!(ctx
+            .clone()
+            .with_transaction_fee(1000000000)
+            .validate()
+            .is_err());
+        assert!(ctx.backend().is_err());
+        assert_eq!(ctx.at_height(101).current_height, 101);
+        assert_eq!(*ctx.tx_fee_output().unwrap().value.as_u64(), DEFAULT_TX_FEE);
+    }
+}
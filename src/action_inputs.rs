@@ -0,0 +1,259 @@
+/// This file holds the `ActionInputs` wrapper which collects the inputs
+/// and data-inputs of an Action, each typed by its `SpecifiedBox`, and
+/// validates them together before the transaction is built.
+use crate::box_traits::DynSpecifiedBox;
+use crate::error::{HeadlessDappError, Result};
+use crate::explorer_url::ExplorerUrl;
+use crate::tx_creation::{check_unsigned_tx, new_unsigned_tx};
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::chain::transaction::{DataInput, UnsignedInput};
+use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, ErgoBoxCandidate};
+
+/// The inputs of an Action. Every input is a `SpecifiedBox` (or any other
+/// `DynSpecifiedBox`), and `validate()` re-verifies every box against its
+/// spec and rejects the same box being used as more than one input (ie.
+/// passing the same `ErgsBox` for both the fee and a payment).
+#[derive(Default)]
+pub struct ActionInputs<'a> {
+    inputs: Vec<&'a dyn DynSpecifiedBox>,
+    data_inputs: Vec<&'a dyn DynSpecifiedBox>,
+}
+
+impl<'a> ActionInputs<'a> {
+    /// Create a new empty `ActionInputs`
+    pub fn new() -> ActionInputs<'a> {
+        ActionInputs {
+            inputs: vec![],
+            data_inputs: vec![],
+        }
+    }
+
+    /// Adds an input. Inputs are kept in the order they are added.
+    pub fn add(mut self, input: &'a dyn DynSpecifiedBox) -> Self {
+        self.inputs.push(input);
+        self
+    }
+
+    /// Adds a list of inputs of the same `SpecifiedBox` type
+    pub fn add_all<T: DynSpecifiedBox>(mut self, inputs: &'a [T]) -> Self {
+        for input in inputs {
+            self.inputs.push(input);
+        }
+        self
+    }
+
+    /// Adds a data-input (ie. an oracle pool box which is read but not
+    /// spent). Data-inputs are kept in the order they are added.
+    pub fn with_data_input(mut self, data_input: &'a dyn DynSpecifiedBox) -> Self {
+        self.data_inputs.push(data_input);
+        self
+    }
+
+    /// Adds a list of data-inputs
+    pub fn with_data_inputs(mut self, data_inputs: Vec<&'a dyn DynSpecifiedBox>) -> Self {
+        self.data_inputs.extend(data_inputs);
+        self
+    }
+
+    /// Verifies every input and data-input against its spec, checks that
+    /// no box is used as more than one input, and that no input is also
+    /// used as a data-input.
+    pub fn validate(&self) -> Result<()> {
+        let mut box_ids: Vec<String> = vec![];
+        for input in &self.inputs {
+            input.verify().map_err(|e| {
+                HeadlessDappError::InvalidActionInput(format!(
+                    "{} failed to match its spec: {}",
+                    input.spec_name(),
+                    e
+                ))
+            })?;
+            let box_id = input.box_id();
+            if box_ids.contains(&box_id) {
+                return Err(HeadlessDappError::DuplicateInput(box_id));
+            }
+            box_ids.push(box_id);
+        }
+        for data_input in &self.data_inputs {
+            data_input.verify().map_err(|e| {
+                HeadlessDappError::InvalidActionInput(format!(
+                    "Data-input {} failed to match its spec: {}",
+                    data_input.spec_name(),
+                    e
+                ))
+            })?;
+            let box_id = data_input.box_id();
+            if box_ids.contains(&box_id) {
+                return Err(HeadlessDappError::InputUsedAsDataInput(box_id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Generates the Ergo Explorer Backend API endpoint of every
+    /// data-input box as `(box id, url)` pairs. The response bodies of
+    /// these endpoints are then provided to `check_data_inputs_unspent()`.
+    pub fn data_input_endpoints(&self, explorer_api_url: &str) -> Vec<(String, String)> {
+        self.data_inputs
+            .iter()
+            .map(|d| {
+                let box_id = d.box_id();
+                let url = ExplorerUrl::new(explorer_api_url)
+                    .path("v1/boxes")
+                    .segment(&box_id)
+                    .build();
+                (box_id, url)
+            })
+            .collect()
+    }
+
+    /// Checks that every data-input box is still unspent using the
+    /// explorer response bodies of the endpoints generated by
+    /// `data_input_endpoints()`, provided in the same order.
+    pub fn check_data_inputs_unspent(&self, explorer_response_bodies: &[String]) -> Result<()> {
+        if explorer_response_bodies.len() != self.data_inputs.len() {
+            return Err(HeadlessDappError::InvalidActionInput(
+                "A response body must be provided for every data-input".to_string(),
+            ));
+        }
+        for (data_input, body) in self.data_inputs.iter().zip(explorer_response_bodies) {
+            let json = json::parse(body).map_err(|_| {
+                HeadlessDappError::Other(
+                    "Failed to extract json from Ergo Explorer Backend API Response".to_string(),
+                )
+            })?;
+            if !json["spentTransactionId"].is_null() {
+                return Err(HeadlessDappError::DataInputSpent(data_input.box_id()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates the inputs and then converts the data-inputs into
+    /// `DataInput`s
+    pub fn data_inputs(&self) -> Result<Vec<DataInput>> {
+        self.validate()?;
+        Ok(self.data_inputs.iter().map(|d| d.as_data_input()).collect())
+    }
+
+    /// Validates the inputs and builds an `UnsignedTransaction` which
+    /// spends the inputs, reads the data-inputs, and creates the provided
+    /// output candidates.
+    pub fn build_tx(
+        &self,
+        output_candidates: Vec<ErgoBoxCandidate>,
+    ) -> Result<UnsignedTransaction> {
+        let unsigned_tx = new_unsigned_tx(
+            self.unsigned_inputs()?,
+            self.data_inputs()?,
+            output_candidates,
+        )
+        .map_err(HeadlessDappError::InvalidTxComposition)?;
+        check_unsigned_tx(&unsigned_tx)?;
+        Ok(unsigned_tx)
+    }
+
+    /// Validates the inputs and then converts them into `UnsignedInput`s
+    pub fn unsigned_inputs(&self) -> Result<Vec<UnsignedInput>> {
+        self.validate()?;
+        Ok(self.inputs.iter().map(|i| i.as_unsigned_input()).collect())
+    }
+
+    /// Validates the inputs and then returns their `ErgoBox`es
+    pub fn boxes(&self) -> Result<Vec<ErgoBox>> {
+        self.validate()?;
+        Ok(self.inputs.iter().map(|i| i.get_box()).collect())
+    }
+
+    /// The number of inputs
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// Whether no inputs have been added
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::box_traits::{ExplorerFindable, WrappedBox};
+    use crate::output_builders::TxFeeBox;
+    use crate::specified_boxes::ErgsBox;
+    use crate::test_utils::{test_box_at, TEST_ADDRESS};
+
+    fn ergs_box(index: u16) -> ErgsBox {
+        ErgsBox::from_ergo_box(&test_box_at(
+            2000000,
+            TEST_ADDRESS,
+            vec![],
+            vec![],
+            0,
+            index,
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn builds_tx_from_validated_inputs() {
+        let (first, second, oracle) = (ergs_box(0), ergs_box(1), ergs_box(2));
+        let inputs = ActionInputs::new()
+            .add(&first)
+            .add(&second)
+            .with_data_input(&oracle);
+        assert_eq!(inputs.len(), 2);
+        assert!(!inputs.is_empty());
+
+        let tx = inputs
+            .build_tx(vec![TxFeeBox::output_candidate(4000000, 0).unwrap()])
+            .unwrap();
+        let input_ids: Vec<String> = tx
+            .inputs
+            .as_vec()
+            .iter()
+            .map(|i| i.box_id.clone().into())
+            .collect();
+        assert_eq!(input_ids, vec![first.box_id(), second.box_id()]);
+        assert_eq!(tx.data_inputs.unwrap().as_vec().len(), 1);
+    }
+
+    #[test]
+    fn rejects_reused_boxes() {
+        let (first, second) = (ergs_box(0), ergs_box(1));
+        let duplicated = ActionInputs::new().add(&first).add(&first);
+        assert!(matches!(
+            duplicated.validate(),
+            Err(HeadlessDappError::DuplicateInput(_))
+        ));
+
+        let boxes = vec![first.clone(), second];
+        let read_and_spent = ActionInputs::new().add_all(&boxes).with_data_input(&first);
+        assert!(matches!(
+            read_and_spent.validate(),
+            Err(HeadlessDappError::InputUsedAsDataInput(_))
+        ));
+        assert!(read_and_spent.boxes().is_err());
+    }
+
+    #[test]
+    fn checks_data_inputs_unspent() {
+        let (input, oracle) = (ergs_box(0), ergs_box(1));
+        let inputs = ActionInputs::new().add(&input).with_data_input(&oracle);
+        let endpoints = inputs.data_input_endpoints("https://api.ergoplatform.com/api");
+        assert_eq!(endpoints[0].0, oracle.box_id());
+        assert!(endpoints[0]
+            .1
+            .ends_with(&format!("v1/boxes/{}", oracle.box_id())));
+
+        let unspent = "{\"spentTransactionId\": null}".to_string();
+        let spent = "{\"spentTransactionId\": \"abcd\"}".to_string();
+        assert!(inputs.check_data_inputs_unspent(&[unspent]).is_ok());
+        assert!(matches!(
+            inputs.check_data_inputs_unspent(&[spent]),
+            Err(HeadlessDappError::DataInputSpent(_))
+        ));
+        assert!(inputs.check_data_inputs_unspent(&[]).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+/// This file holds `ActionParams`, a validation layer which lets an Action
+/// declare the constraints on its parameters (such as
+/// `bounty_amount >= min_box_value + fee`). Every constraint is checked
+/// before the transaction is constructed, and all violations are reported
+/// together with the names of the offending fields rather than surfacing
+/// as a panic on an underflowing subtraction mid-construction.
+use crate::error::{HeadlessDappError, Result};
+use crate::NanoErg;
+use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
+use std::fmt;
+
+/// The smallest value a plain output box may safely hold
+pub fn min_output_value() -> NanoErg {
+    *BoxValue::SAFE_USER_MIN.as_u64()
+}
+
+/// A single constraint of an Action which its parameters failed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParamViolation {
+    /// The name of the parameter which failed the constraint
+    pub field: String,
+    /// A description of the failed constraint
+    pub reason: String,
+}
+
+impl fmt::Display for ParamViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` {}", self.field, self.reason)
+    }
+}
+
+/// The declared constraints on the parameters of an Action. Constraints
+/// are added via the builder methods and then all checked at once with
+/// `check()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActionParams {
+    action_name: String,
+    violations: Vec<ParamViolation>,
+}
+
+impl ActionParams {
+    /// Begin declaring the parameter constraints of the Action with the
+    /// given name
+    pub fn new(action_name: &str) -> ActionParams {
+        ActionParams {
+            action_name: action_name.to_string(),
+            violations: vec![],
+        }
+    }
+
+    /// Requires `value` of the `field` to be at least `minimum`
+    pub fn at_least(self, field: &str, value: u64, minimum: u64) -> Self {
+        self.require(
+            field,
+            value >= minimum,
+            format!("must be at least {} but is {}", minimum, value),
+        )
+    }
+
+    /// Requires `value` of the `field` to be at most `maximum`
+    pub fn at_most(self, field: &str, value: u64, maximum: u64) -> Self {
+        self.require(
+            field,
+            value <= maximum,
+            format!("must be at most {} but is {}", maximum, value),
+        )
+    }
+
+    /// Requires the nanoErgs `available` in the `field` to cover each of
+    /// the `required` amounts summed together
+    pub fn covers(self, field: &str, available: NanoErg, required: &[NanoErg]) -> Self {
+        let total = required
+            .iter()
+            .fold(0u64, |acc, amount| acc.saturating_add(*amount));
+        self.require(
+            field,
+            available >= total,
+            format!(
+                "must hold at least {} nanoErgs but holds {}",
+                total, available
+            ),
+        )
+    }
+
+    /// Requires an arbitrary `condition` to hold for the `field`, with the
+    /// `reason` describing the constraint when it does not
+    pub fn require(mut self, field: &str, condition: bool, reason: String) -> Self {
+        if !condition {
+            self.violations.push(ParamViolation {
+                field: field.to_string(),
+                reason,
+            });
+        }
+        self
+    }
+
+    /// The constraints which the parameters have failed so far
+    pub fn violations(&self) -> &Vec<ParamViolation> {
+        &self.violations
+    }
+
+    /// Checks that the parameters satisfy every declared constraint,
+    /// returning an error naming each failed field otherwise
+    pub fn check(self) -> Result<()> {
+        if self.violations.is_empty() {
+            return Ok(());
+        }
+        let reasons: Vec<String> = self.violations.iter().map(|v| v.to_string()).collect();
+        Err(HeadlessDappError::InvalidActionParameters(
+            self.action_name,
+            reasons.join("; "),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_every_violated_field() {
+        let fee = 1000000;
+        let params = ActionParams::new("bootstrap")
+            .at_least("bounty_amount", 500, min_output_value() + fee)
+            .covers("ergs_box_for_fee", 2000000, &[fee])
+            .at_most("transaction_fee", fee, 1000);
+        assert_eq!(params.violations().len(), 2);
+        assert_eq!(params.violations()[0].field, "bounty_amount");
+        assert_eq!(params.violations()[1].field, "transaction_fee");
+        let error = params.check().unwrap_err().to_string();
+        assert!(error.contains("bootstrap"));
+        assert!(error.contains("`bounty_amount`"));
+
+        assert!(ActionParams::new("solve")
+            .covers("math_bounty_box", 2000000, &[min_output_value(), fee])
+            .check()
+            .is_ok());
+    }
+}
@@ -0,0 +1,143 @@
+/// This file holds the `AddressBook` which maps human-readable names to
+/// Ergo addresses. Protocols can load their addresses (ie. contract or
+/// treasury addresses) from config once and reference them by name in
+/// their Actions rather than hard-coding Base58 strings inline.
+use crate::encoding::address_string_to_ergo_tree;
+use crate::error::{HeadlessDappError, Result};
+use crate::ErgoAddressString;
+use std::collections::HashMap;
+
+/// A reference to an address which is either provided directly, or
+/// by name to be resolved via an `AddressBook`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AddressRef {
+    Address(ErgoAddressString),
+    Named(String),
+}
+
+/// A name of an address in an `AddressBook`. Used as
+/// `.address(Named("treasury"))` in builders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Named<'a>(pub &'a str);
+
+impl From<&str> for AddressRef {
+    fn from(address: &str) -> Self {
+        AddressRef::Address(address.to_string())
+    }
+}
+
+impl From<&String> for AddressRef {
+    fn from(address: &String) -> Self {
+        AddressRef::Address(address.clone())
+    }
+}
+
+impl From<String> for AddressRef {
+    fn from(address: String) -> Self {
+        AddressRef::Address(address)
+    }
+}
+
+impl<'a> From<Named<'a>> for AddressRef {
+    fn from(name: Named<'a>) -> Self {
+        AddressRef::Named(name.0.to_string())
+    }
+}
+
+/// A mapping of names to Ergo addresses.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AddressBook {
+    addresses: HashMap<String, ErgoAddressString>,
+}
+
+impl AddressBook {
+    /// Create a new empty `AddressBook`
+    pub fn new() -> AddressBook {
+        AddressBook::default()
+    }
+
+    /// Loads an `AddressBook` from a JSON object `String` which maps
+    /// names to addresses, ie. `{"treasury": "9f..."}`.
+    /// Every address is verified to be valid.
+    pub fn from_json(json: &str) -> Result<AddressBook> {
+        let addresses: HashMap<String, ErgoAddressString> =
+            serde_json::from_str(json).map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+        let mut book = AddressBook::new();
+        for (name, address) in addresses {
+            book.insert(&name, &address)?;
+        }
+        Ok(book)
+    }
+
+    /// Adds an address to the `AddressBook` under the given name,
+    /// replacing any address previously stored under said name.
+    pub fn insert(&mut self, name: &str, address: &str) -> Result<()> {
+        address_string_to_ergo_tree(&address.to_string())
+            .map_err(|_| HeadlessDappError::InvalidAddress(address.to_string()))?;
+        self.addresses.insert(name.to_string(), address.to_string());
+        Ok(())
+    }
+
+    /// Acquire the address stored under the given name
+    pub fn get(&self, name: &str) -> Result<ErgoAddressString> {
+        self.addresses
+            .get(name)
+            .cloned()
+            .ok_or_else(|| HeadlessDappError::UnknownAddressName(name.to_string()))
+    }
+
+    /// Resolves an `AddressRef` into an address
+    pub fn resolve(&self, address_ref: &AddressRef) -> Result<ErgoAddressString> {
+        match address_ref {
+            AddressRef::Address(address) => Ok(address.clone()),
+            AddressRef::Named(name) => self.get(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TEST_ADDRESS;
+
+    #[test]
+    fn resolves_named_and_direct_addresses() {
+        let mut book = AddressBook::new();
+        book.insert("treasury", TEST_ADDRESS).unwrap();
+
+        assert_eq!(book.get("treasury").unwrap(), TEST_ADDRESS);
+        assert_eq!(
+            book.resolve(&Named("treasury").into()).unwrap(),
+            TEST_ADDRESS
+        );
+        assert_eq!(
+            book.resolve(&"9hy2fs8KKPr2MZNuhUcs8vxkq4TLLX9gftqHXV4Kp7iKFXYe6Ww".into())
+                .unwrap(),
+            "9hy2fs8KKPr2MZNuhUcs8vxkq4TLLX9gftqHXV4Kp7iKFXYe6Ww"
+        );
+        assert!(matches!(
+            book.resolve(&Named("unknown").into()),
+            Err(HeadlessDappError::UnknownAddressName(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_addresses() {
+        let mut book = AddressBook::new();
+        assert!(matches!(
+            book.insert("treasury", "not an address"),
+            Err(HeadlessDappError::InvalidAddress(_))
+        ));
+        assert!(book.get("treasury").is_err());
+    }
+
+    #[test]
+    fn loads_from_json() {
+        let json = format!("{{\"treasury\": \"{}\"}}", TEST_ADDRESS);
+        let book = AddressBook::from_json(&json).unwrap();
+        assert_eq!(book.get("treasury").unwrap(), TEST_ADDRESS);
+
+        assert!(AddressBook::from_json("{\"treasury\": \"not an address\"}").is_err());
+        assert!(AddressBook::from_json("[]").is_err());
+    }
+}
@@ -0,0 +1,492 @@
+/// This file holds the async API surface of the framework (behind the
+/// `async` feature), which composes with existing tokio services. The
+/// HTTP client itself is provided by implementing `AsyncTransport` (ie.
+/// using reqwest or hyper), while the framework generates the requests,
+/// enforces their timeouts, and processes the responses. All futures are
+/// `Send`. The sync and WASM paths are unaffected.
+use crate::box_spec::BoxSpec;
+use crate::box_traits::{ExplorerFindable, SpecifiedBox};
+use crate::cancellation::CancellationToken;
+use crate::dry_run::{DryRunLog, ExecutionMode};
+use crate::error::{HeadlessDappError, Result};
+use crate::explorer_backend::{ExplorerBackend, ExplorerRequest};
+use crate::metrics::{record_tx_confirmed, record_tx_submitted, Metrics, NoopMetrics};
+use crate::replay_guard::ActionMarker;
+use crate::signer::{SigningRequest, TxSigner};
+use crate::state_store::{BotState, StateStore, SubmittedTx, TxStatus};
+use crate::tx_creation::unsigned_tx_fingerprint;
+use crate::value_query::{total_value_matching, TotalValueQuery};
+use crate::{ErgoAddressString, TxId};
+use async_trait::async_trait;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+
+/// An async HTTP transport which performs `ExplorerRequest`s, returning
+/// the response body. Non-success responses should be returned as
+/// `HeadlessDappError::HttpStatus` errors holding the status code.
+#[async_trait]
+pub trait AsyncTransport: Send + Sync {
+    /// Performs a GET request
+    async fn get(&self, request: &ExplorerRequest) -> Result<String>;
+    /// Performs a POST request with a JSON body
+    async fn post(&self, request: &ExplorerRequest, body: String) -> Result<String>;
+}
+
+/// Performs a GET request, aborting it if the timeout of the request
+/// elapses
+pub async fn get_with_timeout<T: AsyncTransport + ?Sized>(
+    transport: &T,
+    request: &ExplorerRequest,
+) -> Result<String> {
+    match request.timeout {
+        Some(timeout) => tokio::time::timeout(timeout, transport.get(request))
+            .await
+            .map_err(|_| HeadlessDappError::RequestTimedOut(request.url.clone()))?,
+        None => transport.get(request).await,
+    }
+}
+
+/// Performs a POST request, aborting it if the timeout of the request
+/// elapses
+pub async fn post_with_timeout<T: AsyncTransport + ?Sized>(
+    transport: &T,
+    request: &ExplorerRequest,
+    body: String,
+) -> Result<String> {
+    match request.timeout {
+        Some(timeout) => tokio::time::timeout(timeout, transport.post(request, body))
+            .await
+            .map_err(|_| HeadlessDappError::RequestTimedOut(request.url.clone()))?,
+        None => transport.post(request, body).await,
+    }
+}
+
+/// Fetches the boxes which match the provided `BoxSpec`
+pub async fn fetch_box_spec<T: AsyncTransport + ?Sized>(
+    transport: &T,
+    backend: &ExplorerBackend,
+    box_spec: &BoxSpec,
+) -> Result<Vec<ErgoBox>> {
+    let request = backend.box_spec_request(box_spec)?;
+    let body = get_with_timeout(transport, &request).await?;
+    box_spec.process_explorer_response(&body)
+}
+
+/// Fetches the boxes which match the `BoxSpec` of the `SpecifiedBox` as
+/// instances of said `SpecifiedBox`
+pub async fn fetch_specified<S: ExplorerFindable, T: AsyncTransport + ?Sized>(
+    transport: &T,
+    backend: &ExplorerBackend,
+) -> Result<Vec<S>> {
+    let request = backend.specified_box_request::<S>()?;
+    let body = get_with_timeout(transport, &request).await?;
+    S::process_explorer_response(&body)
+}
+
+/// Errors if the Action of the `marker` has already been executed, by
+/// fetching the boxes which match the `BoxSpec` of the marked output and
+/// the mempool transactions with outputs locked under its `address`
+pub async fn check_action_not_executed<T: AsyncTransport + ?Sized>(
+    transport: &T,
+    backend: &ExplorerBackend,
+    marker: &ActionMarker,
+    marked_output_spec: &BoxSpec,
+    address: &ErgoAddressString,
+) -> Result<()> {
+    let onchain_boxes = fetch_box_spec(transport, backend, marked_output_spec).await?;
+    let request = backend.request(&ActionMarker::mempool_path(address));
+    let mempool_body = get_with_timeout(transport, &request).await?;
+    marker.check_not_executed(&onchain_boxes, address, Some(&mempool_body))
+}
+
+/// Counts the live boxes which may match the provided `BoxSpec` without
+/// downloading full pages (see `BoxSpec::process_count_response()`)
+pub async fn count<T: AsyncTransport + ?Sized>(
+    transport: &T,
+    backend: &ExplorerBackend,
+    box_spec: &BoxSpec,
+) -> Result<u64> {
+    let request = backend.count_request(box_spec)?;
+    let body = get_with_timeout(transport, &request).await?;
+    box_spec.process_count_response(&body)
+}
+
+/// Whether any box which may match the provided `BoxSpec` is live (ie.
+/// "is the bounty still unclaimed?")
+pub async fn exists<T: AsyncTransport + ?Sized>(
+    transport: &T,
+    backend: &ExplorerBackend,
+    box_spec: &BoxSpec,
+) -> Result<bool> {
+    Ok(count(transport, backend, box_spec).await? > 0)
+}
+
+/// Counts the live boxes which may match the `BoxSpec` of the
+/// `SpecifiedBox`
+pub async fn count_specified<S: SpecifiedBox, T: AsyncTransport + ?Sized>(
+    transport: &T,
+    backend: &ExplorerBackend,
+) -> Result<u64> {
+    count(transport, backend, &S::box_spec()).await
+}
+
+/// Whether any box which may match the `BoxSpec` of the `SpecifiedBox`
+/// is live
+pub async fn exists_specified<S: SpecifiedBox, T: AsyncTransport + ?Sized>(
+    transport: &T,
+    backend: &ExplorerBackend,
+) -> Result<bool> {
+    exists(transport, backend, &S::box_spec()).await
+}
+
+/// Fetches every page of boxes matching the provided `BoxSpec` and sums
+/// their value (see `total_value_matching()`). Aborts with
+/// `HeadlessDappError::Cancelled` if the `CancellationToken` is cancelled.
+pub async fn fetch_total_value<T: AsyncTransport + ?Sized>(
+    transport: &T,
+    backend: &ExplorerBackend,
+    box_spec: &BoxSpec,
+    cancellation: Option<CancellationToken>,
+) -> Result<TotalValueQuery> {
+    let mut query = total_value_matching(box_spec);
+    if let Some(token) = cancellation {
+        query = query.with_cancellation(token);
+    }
+    while let Some(url) = query.next_endpoint(backend.api_url())? {
+        let request = backend.url_request(url);
+        let body = get_with_timeout(transport, &request).await?;
+        query.process_page(&body)?;
+    }
+    Ok(query)
+}
+
+/// Submits a signed transaction (as JSON) via the explorer API, returning
+/// its Transaction ID
+pub async fn submit_tx<T: AsyncTransport + ?Sized>(
+    transport: &T,
+    backend: &ExplorerBackend,
+    signed_tx_json: String,
+) -> Result<TxId> {
+    submit_tx_with_metrics(transport, backend, signed_tx_json, &NoopMetrics).await
+}
+
+/// Same as `submit_tx()` but additionally reports the submitted
+/// transaction to the provided `Metrics` implementation.
+pub async fn submit_tx_with_metrics<T: AsyncTransport + ?Sized>(
+    transport: &T,
+    backend: &ExplorerBackend,
+    signed_tx_json: String,
+    metrics: &dyn Metrics,
+) -> Result<TxId> {
+    let request = backend.request("/v1/mempool/transactions/submit");
+    let body = post_with_timeout(transport, &request, signed_tx_json).await?;
+    let json = json::parse(&body).map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+    let tx_id = json["id"]
+        .as_str()
+        .map(|id| id.to_string())
+        .ok_or_else(|| HeadlessDappError::Other(format!("Failed to submit the tx: {}", body)))?;
+    record_tx_submitted(metrics, &tx_id);
+    Ok(tx_id)
+}
+
+/// Signs the transaction of the `request` using the `signer` and submits
+/// it via the explorer API, returning its Transaction ID
+pub async fn sign_and_submit_tx<S: TxSigner + ?Sized, T: AsyncTransport + ?Sized>(
+    signer: &S,
+    transport: &T,
+    backend: &ExplorerBackend,
+    request: &SigningRequest,
+) -> Result<TxId> {
+    let signed_tx_json = signer.sign(request).await?;
+    submit_tx(transport, backend, signed_tx_json).await
+}
+
+/// Same as `sign_and_submit_tx()` but follows the `ExecutionMode` of the
+/// service. Transactions are only signed and submitted in `Live` mode. In
+/// `DryRun` mode the transaction is recorded in the `dry_run_log` under
+/// the `action_name` and `None` is returned, while `ReadOnly` mode errors
+/// with `HeadlessDappError::ReadOnlyViolation`.
+pub async fn sign_and_submit_with_mode<S: TxSigner + ?Sized, T: AsyncTransport + ?Sized>(
+    mode: ExecutionMode,
+    dry_run_log: &mut DryRunLog,
+    action_name: &str,
+    signer: &S,
+    transport: &T,
+    backend: &ExplorerBackend,
+    request: &SigningRequest,
+) -> Result<Option<TxId>> {
+    mode.check_can_transact()?;
+    match mode {
+        ExecutionMode::Live => Ok(Some(
+            sign_and_submit_tx(signer, transport, backend, request).await?,
+        )),
+        ExecutionMode::DryRun => {
+            dry_run_log.record_tx(action_name, request.unsigned_tx.clone())?;
+            Ok(None)
+        }
+        ExecutionMode::ReadOnly => Err(HeadlessDappError::ReadOnlyViolation),
+    }
+}
+
+/// Same as `sign_and_submit_tx()` but skips transactions which the
+/// `bot_state` already recorded as `Pending` or `Confirmed` (keyed by the
+/// fingerprint of the unsigned transaction), and records the newly
+/// submitted transaction as `Pending`. This prevents a bot from
+/// re-submitting the same Action after a restart, while transactions
+/// recorded as `Failed` are re-submitted.
+pub async fn sign_and_submit_tracked<
+    S: TxSigner + ?Sized,
+    T: AsyncTransport + ?Sized,
+    B: StateStore,
+>(
+    signer: &S,
+    transport: &T,
+    backend: &ExplorerBackend,
+    bot_state: &BotState<B>,
+    request: &SigningRequest,
+) -> Result<TxId> {
+    let fingerprint = unsigned_tx_fingerprint(&request.unsigned_tx)?;
+    if let Some(submitted_tx) = bot_state.submitted(&fingerprint)? {
+        if submitted_tx.status != TxStatus::Failed {
+            return Ok(submitted_tx.tx_id);
+        }
+    }
+    let tx_id = sign_and_submit_tx(signer, transport, backend, request).await?;
+    bot_state.record_submitted(&SubmittedTx {
+        fingerprint,
+        tx_id: tx_id.clone(),
+        status: TxStatus::Pending,
+    })?;
+    Ok(tx_id)
+}
+
+/// Acquires the confirmation state of a submitted transaction. A
+/// transaction which is not yet included in a block (and so which the
+/// explorer responds to with a 404) is `Pending`. Any other failure of
+/// the request is returned as an error.
+pub async fn track_tx<T: AsyncTransport + ?Sized>(
+    transport: &T,
+    backend: &ExplorerBackend,
+    tx_id: &TxId,
+) -> Result<TxStatus> {
+    track_tx_with_metrics(transport, backend, tx_id, &NoopMetrics).await
+}
+
+/// Same as `track_tx()` but additionally reports the transaction to the
+/// provided `Metrics` implementation whenever it is found confirmed.
+pub async fn track_tx_with_metrics<T: AsyncTransport + ?Sized>(
+    transport: &T,
+    backend: &ExplorerBackend,
+    tx_id: &TxId,
+    metrics: &dyn Metrics,
+) -> Result<TxStatus> {
+    let request = backend.url_request(backend.url().path("v1/transactions").segment(tx_id));
+    let body = match get_with_timeout(transport, &request).await {
+        Ok(body) => body,
+        Err(HeadlessDappError::HttpStatus(404, _)) => return Ok(TxStatus::Pending),
+        Err(e) => return Err(e),
+    };
+    let json = json::parse(&body).map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+    match json["numConfirmations"].as_u64() {
+        Some(confirmations) => {
+            record_tx_confirmed(metrics, tx_id);
+            Ok(TxStatus::Confirmed(confirmations))
+        }
+        None => Ok(TxStatus::Pending),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_store::MemoryStore;
+    use crate::test_utils::{test_box, MockTransport, TEST_ADDRESS};
+    use crate::tx_creation::{create_candidate, new_unsigned_tx};
+
+    /// A `TxSigner` which "signs" every transaction into the same JSON
+    struct StaticSigner;
+
+    #[async_trait(?Send)]
+    impl TxSigner for StaticSigner {
+        async fn sign(&self, _: &SigningRequest) -> Result<String> {
+            Ok("{\"signed\":true}".to_string())
+        }
+    }
+
+    fn signing_request() -> SigningRequest {
+        let input_box = test_box(2000000, TEST_ADDRESS, vec![], vec![]);
+        let output =
+            create_candidate(1000000, &TEST_ADDRESS.to_string(), &vec![], &vec![], 100).unwrap();
+        let unsigned_tx =
+            new_unsigned_tx(vec![input_box.clone().into()], vec![], vec![output]).unwrap();
+        SigningRequest::new(unsigned_tx, vec![input_box], vec![])
+    }
+
+    #[tokio::test]
+    async fn sign_and_submit_posts_the_signed_tx() {
+        let transport = MockTransport::new()
+            .with_response("/v1/mempool/transactions/submit", "{\"id\":\"t1\"}");
+        let backend = ExplorerBackend::new("https://explorer.test/api");
+        let tx_id = sign_and_submit_tx(&StaticSigner, &transport, &backend, &signing_request())
+            .await
+            .unwrap();
+        assert_eq!(tx_id, "t1");
+        assert_eq!(
+            transport.requests(),
+            vec![(
+                "https://explorer.test/api/v1/mempool/transactions/submit".to_string(),
+                Some("{\"signed\":true}".to_string())
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn sign_and_submit_follows_execution_mode() {
+        let transport = MockTransport::new()
+            .with_response("/v1/mempool/transactions/submit", "{\"id\":\"t1\"}");
+        let backend = ExplorerBackend::new("https://explorer.test/api");
+        let request = signing_request();
+        let mut log = DryRunLog::new();
+        let mut results = vec![];
+        for mode in &[
+            ExecutionMode::ReadOnly,
+            ExecutionMode::DryRun,
+            ExecutionMode::Live,
+        ] {
+            results.push(
+                sign_and_submit_with_mode(
+                    *mode,
+                    &mut log,
+                    "pay",
+                    &StaticSigner,
+                    &transport,
+                    &backend,
+                    &request,
+                )
+                .await,
+            );
+        }
+
+        assert!(matches!(
+            results[0],
+            Err(HeadlessDappError::ReadOnlyViolation)
+        ));
+        assert_eq!(results[1].as_ref().unwrap(), &None);
+        assert_eq!(results[2].as_ref().unwrap(), &Some("t1".to_string()));
+        // Only the `Live` transaction was submitted
+        assert_eq!(transport.requests().len(), 1);
+        assert_eq!(log.exports().len(), 1);
+        assert_eq!(log.exports()[0].action_name, "pay");
+    }
+
+    #[tokio::test]
+    async fn track_tx_treats_only_not_found_as_pending() {
+        let transport = MockTransport::new()
+            .with_response("/v1/transactions/confirmed", "{\"numConfirmations\":3}")
+            .with_status("/v1/transactions/unknown", 404)
+            .with_status("/v1/transactions/overloaded", 503)
+            .with_failure("/v1/transactions/unreachable", "Connection refused");
+        let backend = ExplorerBackend::new("https://explorer.test/api");
+        let track = |tx_id: &str| {
+            let tx_id = tx_id.to_string();
+            let transport = &transport;
+            let backend = &backend;
+            async move { track_tx(transport, backend, &tx_id).await }
+        };
+
+        assert_eq!(track("confirmed").await.unwrap(), TxStatus::Confirmed(3));
+        assert_eq!(track("unknown").await.unwrap(), TxStatus::Pending);
+        assert!(matches!(
+            track("overloaded").await,
+            Err(HeadlessDappError::HttpStatus(503, _))
+        ));
+        assert!(track("unreachable").await.is_err());
+    }
+
+    /// A `Metrics` which records the events it receives
+    #[derive(Default)]
+    struct RecordingMetrics {
+        events: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl Metrics for RecordingMetrics {
+        fn tx_submitted(&self, tx_id: &TxId) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("submitted {}", tx_id));
+        }
+
+        fn tx_confirmed(&self, tx_id: &TxId) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("confirmed {}", tx_id));
+        }
+    }
+
+    #[tokio::test]
+    async fn submit_and_track_report_metrics() {
+        let transport = MockTransport::new()
+            .with_response("/v1/mempool/transactions/submit", "{\"id\":\"t1\"}")
+            .with_response("/v1/transactions/t1", "{\"numConfirmations\":1}")
+            .with_status("/v1/transactions/t2", 404);
+        let backend = ExplorerBackend::new("https://explorer.test/api");
+        let metrics = RecordingMetrics::default();
+
+        let tx_id = submit_tx_with_metrics(&transport, &backend, "{}".to_string(), &metrics)
+            .await
+            .unwrap();
+        assert_eq!(
+            track_tx_with_metrics(&transport, &backend, &tx_id, &metrics)
+                .await
+                .unwrap(),
+            TxStatus::Confirmed(1)
+        );
+        assert_eq!(
+            track_tx_with_metrics(&transport, &backend, &"t2".to_string(), &metrics)
+                .await
+                .unwrap(),
+            TxStatus::Pending
+        );
+        assert_eq!(
+            *metrics.events.lock().unwrap(),
+            vec!["submitted t1".to_string(), "confirmed t1".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn sign_and_submit_tracked_resubmits_only_failed_txs() {
+        let transport = MockTransport::new()
+            .with_response("/v1/mempool/transactions/submit", "{\"id\":\"t1\"}");
+        let backend = ExplorerBackend::new("https://explorer.test/api");
+        let bot_state = BotState::new(MemoryStore::new());
+        let request = signing_request();
+        let fingerprint = unsigned_tx_fingerprint(&request.unsigned_tx).unwrap();
+        let submit =
+            || sign_and_submit_tracked(&StaticSigner, &transport, &backend, &bot_state, &request);
+
+        assert_eq!(submit().await.unwrap(), "t1");
+        assert_eq!(
+            bot_state.submitted(&fingerprint).unwrap().unwrap().status,
+            TxStatus::Pending
+        );
+        // Pending and confirmed transactions are not re-submitted
+        assert_eq!(submit().await.unwrap(), "t1");
+        bot_state
+            .set_status(&fingerprint, TxStatus::Confirmed(2))
+            .unwrap();
+        assert_eq!(submit().await.unwrap(), "t1");
+        assert_eq!(transport.requests().len(), 1);
+
+        // Failed transactions are re-submitted and tracked as pending again
+        bot_state
+            .set_status(&fingerprint, TxStatus::Failed)
+            .unwrap();
+        assert_eq!(submit().await.unwrap(), "t1");
+        assert_eq!(transport.requests().len(), 2);
+        assert_eq!(
+            bot_state.submitted(&fingerprint).unwrap().unwrap().status,
+            TxStatus::Pending
+        );
+    }
+}
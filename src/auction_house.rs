@@ -0,0 +1,312 @@
+/// This file holds the specified boxes and Actions of the ErgoAuctionHouse
+/// protocol, which auctions off tokens (most commonly NFTs) in exchange for
+/// Ergs. An active auction box holds the auctioned token and the current
+/// highest bid, with the following registers:
+/// R4: `Coll[Byte]` ErgoTree bytes of the seller
+/// R5: `Coll[Byte]` ErgoTree bytes of the current highest bidder
+/// R6: `Long` minimum step between consecutive bids in nanoErgs
+/// R7: `Int` block height at which the auction ends
+use crate::box_spec::{BoxSpec, RegisterSpec};
+use crate::box_traits::{SpecSource, Specified, WrappedBox};
+use crate::encoding::{deserialize_ergo_tree_constant, unwrap_int, unwrap_long};
+use crate::error::{HeadlessDappError, Result};
+use crate::output_builders::{ChangeBox, TxFeeBox};
+use crate::register_constants::RegisterConstant;
+use crate::specified_boxes::ErgsBox;
+use crate::tx_creation::{check_unsigned_tx, create_candidate, new_unsigned_tx, sum_tokens};
+use crate::{BlockHeight, ErgoAddressString, NanoErg, P2PKAddressString, P2SAddressString};
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::chain::transaction::UnsignedInput;
+use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, ErgoBoxCandidate};
+use ergo_lib::ergotree_ir::chain::token::Token;
+use ergo_lib::ergotree_ir::mir::constant::Constant;
+use ergo_lib::ergotree_ir::types::stype::SType;
+
+/// An active auction box of the `AuctionHouse` it was found with
+pub type ActiveAuctionBox = Specified<AuctionHouse>;
+
+/// A deployment of the ErgoAuctionHouse contract. The contract address is
+/// provided at runtime, as the auction house has been redeployed under
+/// new addresses over time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuctionHouse {
+    contract_address: P2SAddressString,
+}
+
+impl SpecSource for AuctionHouse {
+    /// A box spec for an active auction box locked under the contract
+    /// which holds the auctioned token
+    fn box_spec(&self) -> BoxSpec {
+        let byte_coll = SType::SColl(Box::new(SType::SByte));
+        let registers = vec![
+            RegisterSpec::new(Some(byte_coll.clone()), None),
+            RegisterSpec::new(Some(byte_coll), None),
+            RegisterSpec::new(Some(SType::SLong), None),
+            RegisterSpec::new(Some(SType::SInt), None),
+        ];
+        BoxSpec::new(
+            Some(self.contract_address.clone()),
+            None,
+            registers,
+            vec![None],
+        )
+    }
+}
+
+impl AuctionHouse {
+    /// Create a new `AuctionHouse` for the contract at `contract_address`
+    pub fn new(contract_address: &P2SAddressString) -> AuctionHouse {
+        AuctionHouse {
+            contract_address: contract_address.clone(),
+        }
+    }
+
+    /// Returns the address of the auction contract
+    pub fn contract_address(&self) -> P2SAddressString {
+        self.contract_address.clone()
+    }
+
+    /// Generates the explorer endpoint for active auction boxes
+    pub fn explorer_endpoint(&self, explorer_api_url: &str) -> Result<String> {
+        ActiveAuctionBox::explorer_endpoint(self, explorer_api_url)
+    }
+
+    /// Processes the explorer response body from the endpoint generated by
+    /// `explorer_endpoint()` into `ActiveAuctionBox`es
+    pub fn process_explorer_response(
+        &self,
+        explorer_response_body: &str,
+    ) -> Result<Vec<ActiveAuctionBox>> {
+        ActiveAuctionBox::process_explorer_response(explorer_response_body, self)
+    }
+
+    /// Action to place a bid of `bid` nanoErgs on an active auction.
+    /// The bid is paid for using the `ergs_boxes` of the bidder, and the
+    /// previous highest bid is returned to the previous bidder.
+    pub fn action_place_bid(
+        &self,
+        auction: &ActiveAuctionBox,
+        bid: NanoErg,
+        bidder_address: &P2PKAddressString,
+        ergs_boxes: &Vec<ErgsBox>,
+        transaction_fee: NanoErg,
+        current_height: BlockHeight,
+    ) -> Result<UnsignedTransaction> {
+        if auction.is_ended(current_height)? {
+            return Err(HeadlessDappError::InvalidAuctionAction(
+                "The auction has already ended".to_string(),
+            ));
+        }
+        let min_bid = auction.min_next_bid()?;
+        if bid < min_bid {
+            return Err(HeadlessDappError::InvalidAuctionAction(format!(
+                "The bid of {} nanoErgs is below the minimum bid of {} nanoErgs",
+                bid, min_bid
+            )));
+        }
+
+        // The auction box is recreated with the new bid and bidder
+        let mut registers = auction.registers();
+        registers[1] = Constant::address_bytes(bidder_address)?;
+        let auction_output = create_candidate(
+            bid,
+            &self.contract_address,
+            &auction.tokens(),
+            &registers,
+            current_height,
+        )?;
+        let refund_output = create_candidate(
+            auction.current_bid(),
+            &auction.current_bidder()?,
+            &vec![],
+            &vec![],
+            current_height,
+        )?;
+
+        let input_value = ErgsBox::sum_ergs_boxes_value(ergs_boxes);
+        let change_value = input_value
+            .checked_sub(bid)
+            .and_then(|v| v.checked_sub(transaction_fee))
+            .ok_or_else(|| {
+                HeadlessDappError::InvalidAuctionAction(
+                    "The provided boxes do not hold enough nanoErgs for the bid and fee"
+                        .to_string(),
+                )
+            })?;
+        let input_boxes: Vec<ErgoBox> = ergs_boxes.iter().map(|b| b.get_box()).collect();
+        let change_tokens = sum_tokens(&input_boxes)?;
+
+        let mut outputs = vec![auction_output, refund_output];
+        if change_value > 0 || !change_tokens.is_empty() {
+            outputs.push(ChangeBox::output_candidate(
+                &change_tokens,
+                change_value,
+                bidder_address,
+                current_height,
+            )?);
+        }
+        outputs.push(TxFeeBox::output_candidate(transaction_fee, current_height)?);
+
+        let mut inputs: Vec<UnsignedInput> = vec![auction.as_unsigned_input()];
+        inputs.extend(ergs_boxes.iter().map(|b| b.as_unsigned_input()));
+        build_tx(inputs, outputs)
+    }
+
+    /// Action to settle an auction which has ended. The auctioned token is
+    /// sent to the highest bidder in a box holding `winner_box_value`
+    /// nanoErgs, and the rest of the bid (minus the tx fee) is sent to the
+    /// seller. Both are paid for out of the final bid.
+    pub fn action_settle(
+        &self,
+        auction: &ActiveAuctionBox,
+        winner_box_value: NanoErg,
+        transaction_fee: NanoErg,
+        current_height: BlockHeight,
+    ) -> Result<UnsignedTransaction> {
+        if !auction.is_ended(current_height)? {
+            return Err(HeadlessDappError::InvalidAuctionAction(format!(
+                "The auction only ends at height {}",
+                auction.end_height()?
+            )));
+        }
+        let seller_value = auction
+            .current_bid()
+            .checked_sub(winner_box_value)
+            .and_then(|v| v.checked_sub(transaction_fee))
+            .ok_or_else(|| {
+                HeadlessDappError::InvalidAuctionAction(
+                    "The final bid does not cover the winner box value and fee".to_string(),
+                )
+            })?;
+
+        let winner_output = create_candidate(
+            winner_box_value,
+            &auction.current_bidder()?,
+            &auction.tokens(),
+            &vec![],
+            current_height,
+        )?;
+        let seller_output = create_candidate(
+            seller_value,
+            &auction.seller()?,
+            &vec![],
+            &vec![],
+            current_height,
+        )?;
+        let fee_output = TxFeeBox::output_candidate(transaction_fee, current_height)?;
+
+        build_tx(
+            vec![auction.as_unsigned_input()],
+            vec![winner_output, seller_output, fee_output],
+        )
+    }
+}
+
+/// Rust ActiveAuctionBox Methods
+impl ActiveAuctionBox {
+    /// Returns the address of the seller from R4
+    pub fn seller(&self) -> Result<ErgoAddressString> {
+        Ok(deserialize_ergo_tree_constant(&self.checked_register(0)?)?)
+    }
+
+    /// Returns the address of the current highest bidder from R5
+    pub fn current_bidder(&self) -> Result<ErgoAddressString> {
+        Ok(deserialize_ergo_tree_constant(&self.checked_register(1)?)?)
+    }
+
+    /// Returns the current highest bid, which is the value of the box
+    pub fn current_bid(&self) -> NanoErg {
+        self.nano_ergs()
+    }
+
+    /// Returns the minimum step between consecutive bids from R6
+    pub fn min_bid_step(&self) -> Result<NanoErg> {
+        Ok(unwrap_long(&self.checked_register(2)?)? as NanoErg)
+    }
+
+    /// Returns the block height at which the auction ends from R7
+    pub fn end_height(&self) -> Result<BlockHeight> {
+        Ok(unwrap_int(&self.checked_register(3)?)? as BlockHeight)
+    }
+
+    /// Returns the token being auctioned
+    pub fn auctioned_token(&self) -> Token {
+        self.tokens()[0].clone()
+    }
+
+    /// The minimum value of the next bid
+    pub fn min_next_bid(&self) -> Result<NanoErg> {
+        self.current_bid()
+            .checked_add(self.min_bid_step()?)
+            .ok_or(HeadlessDappError::ArithmeticOverflow)
+    }
+
+    /// Whether the auction has ended at the `current_height`
+    pub fn is_ended(&self, current_height: BlockHeight) -> Result<bool> {
+        Ok(current_height >= self.end_height()?)
+    }
+}
+
+/// Builds and checks the `UnsignedTransaction` of an auction Action
+fn build_tx(
+    inputs: Vec<UnsignedInput>,
+    outputs: Vec<ErgoBoxCandidate>,
+) -> Result<UnsignedTransaction> {
+    let unsigned_tx = new_unsigned_tx(inputs, vec![], outputs)
+        .map_err(HeadlessDappError::InvalidAuctionAction)?;
+    check_unsigned_tx(&unsigned_tx)?;
+    Ok(unsigned_tx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::box_traits::ExplorerFindable;
+    use crate::encoding::build_token;
+    use crate::output_builders::MINER_FEE_P2S_ADDRESS;
+    use crate::test_utils::test_box;
+
+    #[test]
+    fn places_bid_and_settles() {
+        let address = MINER_FEE_P2S_ADDRESS.to_string();
+        let house = AuctionHouse::new(&address);
+        let registers = vec![
+            Constant::address_bytes(&address).unwrap(),
+            Constant::address_bytes(&address).unwrap(),
+            100000i64.into(),
+            50i32.into(),
+        ];
+        let nft = build_token(
+            "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1",
+            1,
+        )
+        .unwrap();
+        let auction = ActiveAuctionBox::new(
+            &test_box(2000000, MINER_FEE_P2S_ADDRESS, vec![nft], registers),
+            house.clone(),
+        )
+        .unwrap();
+        assert_eq!(auction.min_next_bid().unwrap(), 2100000);
+
+        let ergs_box =
+            ErgsBox::from_ergo_box(&test_box(5000000, MINER_FEE_P2S_ADDRESS, vec![], vec![]))
+                .unwrap();
+        let too_low = house.action_place_bid(
+            &auction,
+            2050000,
+            &address,
+            &vec![ergs_box.clone()],
+            1000000,
+            10,
+        );
+        assert!(too_low.is_err());
+        let tx = house
+            .action_place_bid(&auction, 3000000, &address, &vec![ergs_box], 1000000, 10)
+            .unwrap();
+        assert_eq!(*tx.output_candidates.as_vec()[0].value.as_u64(), 3000000);
+
+        assert!(house.action_settle(&auction, 500000, 1000000, 10).is_err());
+        let tx = house.action_settle(&auction, 500000, 1000000, 50).unwrap();
+        assert_eq!(tx.output_candidates.as_vec()[0].tokens.len(), 1);
+    }
+}
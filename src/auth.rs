@@ -0,0 +1,299 @@
+/// This file holds helpers for the ErgoAuth (EIP-28) flow, which allows a
+/// headless backend to authenticate the owner of a P2PK address before
+/// serving protocol actions tied to said address. The backend builds an
+/// `AuthRequest` which is sent to the user's wallet, and then verifies the
+/// `AuthResponse` signed by the wallet.
+use crate::encoding::parse_address;
+use crate::error::{HeadlessDappError, Result};
+use crate::P2PKAddressString;
+use ergo_lib::chain::ergo_state_context::ErgoStateContext;
+use ergo_lib::ergotree_interpreter::eval::context::Context;
+use ergo_lib::ergotree_interpreter::eval::env::Env;
+use ergo_lib::ergotree_interpreter::eval::Evaluator;
+use ergo_lib::ergotree_interpreter::sigma_protocol::prover::{ContextExtension, ProofBytes};
+use ergo_lib::ergotree_interpreter::sigma_protocol::verifier::Verifier;
+use ergo_lib::ergotree_ir::chain::address::Address;
+use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
+use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, NonMandatoryRegisters};
+use ergo_lib::ergotree_ir::chain::tx_id::TxId;
+use ergo_lib::ergotree_ir::ergo_tree::ErgoTree;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+use ergo_lib::ergotree_ir::sigma_protocol::sigma_boolean::SigmaBoolean;
+use serde::{Deserialize, Serialize};
+use std::rc::Rc;
+
+/// An ErgoAuth request which is sent to the wallet of the user
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthRequest {
+    /// The message which the wallet must sign (as part of the signed
+    /// message)
+    pub signing_message: String,
+    /// The base64-encoded `SigmaBoolean` of the address to authenticate
+    pub sigma_boolean: String,
+    /// A message displayed to the user by the wallet
+    pub user_message: String,
+    /// The severity of the message displayed to the user
+    pub message_severity: String,
+    /// The url which the wallet posts the `AuthResponse` to
+    pub reply_to: String,
+}
+
+impl AuthRequest {
+    /// Create a new `AuthRequest` for the given P2PK address. The `nonce`
+    /// must be unique per request (ie. random bytes or a session id) to
+    /// prevent replaying a previous response.
+    pub fn new(
+        address: &P2PKAddressString,
+        nonce: &str,
+        user_message: &str,
+        reply_to: &str,
+    ) -> Result<AuthRequest> {
+        let sigma_boolean_bytes = p2pk_sigma_boolean(address)?
+            .sigma_serialize_bytes()
+            .map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+        Ok(AuthRequest {
+            signing_message: nonce.to_string(),
+            sigma_boolean: base64::encode(&sigma_boolean_bytes),
+            user_message: user_message.to_string(),
+            message_severity: "INFORMATION".to_string(),
+            reply_to: reply_to.to_string(),
+        })
+    }
+
+    /// Converts the `AuthRequest` into the JSON which is sent to the wallet
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| HeadlessDappError::Other(e.to_string()))
+    }
+
+    /// Verifies the `AuthResponse` provided by the wallet of the user who
+    /// owns `address`. As per EIP-28 the signed message must be exactly the
+    /// `signing_message` of the request followed by the `origin` (the host
+    /// of the backend which the wallet replied to).
+    pub fn verify_response(
+        &self,
+        response: &AuthResponse,
+        address: &P2PKAddressString,
+        origin: &str,
+    ) -> Result<()> {
+        if response.signed_message != self.signing_message.clone() + origin {
+            return Err(HeadlessDappError::AuthVerificationFailed(
+                "The signed message is not the signing message followed by the origin.".to_string(),
+            ));
+        }
+        verify_signed_message(address, &response.signed_message, &response.proof)
+    }
+}
+
+/// An ErgoAuth response which is signed by the wallet of the user
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthResponse {
+    /// The message which was signed by the wallet
+    pub signed_message: String,
+    /// The base64-encoded signature of the signed message
+    pub proof: String,
+}
+
+impl AuthResponse {
+    /// Parses the JSON of an `AuthResponse` posted by the wallet
+    pub fn from_json(json: &str) -> Result<AuthResponse> {
+        serde_json::from_str(json).map_err(|e| HeadlessDappError::Other(e.to_string()))
+    }
+}
+
+/// Verifies that `message` was signed by the owner of the P2PK `address`,
+/// where `proof` is the base64-encoded signature.
+pub fn verify_signed_message(
+    address: &P2PKAddressString,
+    message: &str,
+    proof: &str,
+) -> Result<()> {
+    let proof_bytes = base64::decode(proof)
+        .map_err(|e| HeadlessDappError::AuthVerificationFailed(e.to_string()))?;
+    let tree = p2pk_ergo_tree(address)?;
+    let verified = MessageVerifier
+        .verify(
+            &tree,
+            &Env::empty(),
+            p2pk_context(&tree)?,
+            ProofBytes::from(proof_bytes),
+            message.as_bytes(),
+        )
+        .map_err(|e| HeadlessDappError::AuthVerificationFailed(e.to_string()))?
+        .result;
+    if !verified {
+        return Err(HeadlessDappError::AuthVerificationFailed(
+            "The signature is invalid.".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Verifies signatures of arbitrary messages (rather than of transactions)
+struct MessageVerifier;
+
+impl Evaluator for MessageVerifier {}
+impl Verifier for MessageVerifier {}
+
+/// Builds the context which the P2PK `tree` is evaluated in when verifying
+/// a message signature. The tree of a P2PK address is a constant, so none
+/// of the values in the context are ever read.
+fn p2pk_context(tree: &ErgoTree) -> Result<Rc<Context>> {
+    let self_box = ErgoBox::new(
+        BoxValue::SAFE_USER_MIN,
+        tree.clone(),
+        vec![],
+        NonMandatoryRegisters::empty(),
+        0,
+        TxId::zero(),
+        0,
+    )
+    .map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+    let state_context = ErgoStateContext::dummy();
+    Ok(Rc::new(Context {
+        height: 0,
+        self_box: Rc::new(self_box),
+        outputs: vec![],
+        data_inputs: vec![],
+        inputs: vec![],
+        pre_header: state_context.pre_header,
+        headers: state_context.headers,
+        extension: ContextExtension::empty(),
+    }))
+}
+
+/// Acquires the `ErgoTree` of a P2PK address
+fn p2pk_ergo_tree(address: &P2PKAddressString) -> Result<ErgoTree> {
+    match parse_address(address)? {
+        p2pk @ Address::P2Pk(_) => p2pk
+            .script()
+            .map_err(|e| HeadlessDappError::Other(e.to_string())),
+        _ => Err(HeadlessDappError::InvalidP2PKAddress(address.clone())),
+    }
+}
+
+/// Acquires the `SigmaBoolean` of a P2PK address
+fn p2pk_sigma_boolean(address: &P2PKAddressString) -> Result<SigmaBoolean> {
+    match parse_address(address)? {
+        Address::P2Pk(prove_dlog) => Ok(prove_dlog.into()),
+        _ => Err(HeadlessDappError::InvalidP2PKAddress(address.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TEST_ADDRESS;
+    use ergo_lib::ergotree_interpreter::sigma_protocol::private_input::{
+        DlogProverInput, PrivateInput,
+    };
+    use ergo_lib::ergotree_interpreter::sigma_protocol::prover::hint::HintsBag;
+    use ergo_lib::ergotree_interpreter::sigma_protocol::prover::{Prover, TestProver};
+    use ergo_lib::ergotree_ir::chain::address::{AddressEncoder, NetworkPrefix};
+    use ergo_lib::wallet::secret_key::SecretKey;
+
+    const ORIGIN: &str = "dapp.example.com";
+
+    fn secret() -> SecretKey {
+        SecretKey::DlogSecretKey(DlogProverInput::from_bytes(&[3u8; 32]).unwrap())
+    }
+
+    fn secret_address() -> P2PKAddressString {
+        AddressEncoder::new(NetworkPrefix::Mainnet)
+            .address_to_str(&secret().get_address_from_public_image())
+    }
+
+    fn sign(message: &str) -> AuthResponse {
+        let prover = TestProver {
+            secrets: vec![PrivateInput::DlogProverInput(
+                DlogProverInput::from_bytes(&[3u8; 32]).unwrap(),
+            )],
+        };
+        let tree = p2pk_ergo_tree(&secret_address()).unwrap();
+        let proof: Vec<u8> = prover
+            .prove(
+                &tree,
+                &Env::empty(),
+                p2pk_context(&tree).unwrap(),
+                message.as_bytes(),
+                &HintsBag::empty(),
+            )
+            .unwrap()
+            .proof
+            .into();
+        AuthResponse {
+            signed_message: message.to_string(),
+            proof: base64::encode(&proof),
+        }
+    }
+
+    fn request() -> AuthRequest {
+        AuthRequest::new(
+            &secret_address(),
+            "nonce123",
+            "Sign in",
+            "https://dapp.example.com/auth",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn request_encodes_sigma_boolean_as_base64() {
+        let sigma_boolean = base64::decode(&request().sigma_boolean).unwrap();
+        let expected = p2pk_sigma_boolean(&secret_address())
+            .unwrap()
+            .sigma_serialize_bytes()
+            .unwrap();
+        assert_eq!(sigma_boolean, expected);
+    }
+
+    #[test]
+    fn accepts_valid_signature() {
+        let response = sign(&("nonce123".to_string() + ORIGIN));
+        assert!(request()
+            .verify_response(&response, &secret_address(), ORIGIN)
+            .is_ok());
+    }
+
+    #[test]
+    fn rejects_wrong_key() {
+        let response = sign(&("nonce123".to_string() + ORIGIN));
+        let result = request().verify_response(&response, &TEST_ADDRESS.to_string(), ORIGIN);
+        assert!(matches!(
+            result,
+            Err(HeadlessDappError::AuthVerificationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_wrong_message() {
+        // A message which merely contains the signing message is rejected
+        let response = sign(&("prefix nonce123".to_string() + ORIGIN));
+        let result = request().verify_response(&response, &secret_address(), ORIGIN);
+        assert!(matches!(
+            result,
+            Err(HeadlessDappError::AuthVerificationFailed(_))
+        ));
+
+        // A tampered message does not match the proof
+        let mut response = sign(&("nonce123".to_string() + ORIGIN));
+        response.signed_message = "nonce124".to_string() + ORIGIN;
+        assert!(verify_signed_message(
+            &secret_address(),
+            &response.signed_message,
+            &response.proof
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_origin() {
+        let response = sign("nonce123evil.example.com");
+        let result = request().verify_response(&response, &secret_address(), ORIGIN);
+        assert!(matches!(
+            result,
+            Err(HeadlessDappError::AuthVerificationFailed(_))
+        ));
+    }
+}
@@ -1,14 +1,35 @@
-use crate::encoding::address_string_to_ergo_tree;
+use crate::encoding::{
+    address_string_to_ergo_tree, constant_from_json, ergo_tree_template_hash, parse_stype,
+    unwrap_hex_encoded_string,
+};
 use crate::error::{HeadlessDappError, Result};
-use crate::{ErgoAddressString, NanoErg};
+use crate::explorer_stream::FilteredResponseSeed;
+use crate::explorer_url::ExplorerUrl;
+use crate::metrics::{Metrics, NoopMetrics};
+use crate::network::Network;
+use crate::pagination::{explorer_response_total, PageCursor};
+use crate::predicate_registry::lookup_predicate;
+use crate::refund::RefundPath;
+use crate::render::stype_name;
+use crate::response_adapter::ResponseAdapter;
+use crate::timelock::Timelock;
+use crate::{BlockHeight, ErgoAddressString, NanoErg};
+use ergo_lib::chain::transaction::TxId;
+use ergo_lib::ergotree_ir::base16_str::Base16Str;
 use ergo_lib::ergotree_ir::chain::digest32::Digest32;
-use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, ErgoBoxCandidate};
 use ergo_lib::ergotree_ir::ergo_tree::ErgoTree;
 use ergo_lib::ergotree_ir::mir::constant::Constant;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
 use ergo_lib::ergotree_ir::types::stype::SType;
 use ergo_lib_wasm::box_coll::ErgoBoxes;
 use ergo_lib_wasm::ergo_box::ErgoBox as WErgoBox;
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+use rayon::prelude::*;
+use serde::de::DeserializeSeed;
+use serde::{Deserialize, Serialize};
 use serde_json::from_str;
+use std::io::Read;
 use std::ops::Range;
 use wasm_bindgen::prelude::*;
 
@@ -47,6 +68,46 @@ impl RegisterSpec {
             value_type: value_type,
         }
     }
+
+    /// Parses a `RegisterSpec` from a string descriptor holding the name of
+    /// the register's type, optionally followed by `=` and its expected
+    /// value. Values are provided as JSON (ie. `SLong = 5` or
+    /// `Coll[SInt] = [1, 2]`), while a `Coll[Byte]` value may also be
+    /// provided as `0x` prefixed hex (ie. `Coll[Byte] = 0x0e20`).
+    /// An empty descriptor specifies neither the type nor the value.
+    pub fn parse(descriptor: &str) -> Result<RegisterSpec> {
+        let fail = || HeadlessDappError::InvalidRegisterDescriptor(descriptor.to_string());
+        let mut parts = descriptor.splitn(2, '=');
+        let type_name = parts.next().unwrap_or("").trim();
+        let value_str = parts.next().map(|v| v.trim());
+        if type_name.is_empty() {
+            if value_str.is_some() {
+                return Err(fail());
+            }
+            return Ok(RegisterSpec::new(None, None));
+        }
+        let value_type = parse_stype(type_name).map_err(|_| fail())?;
+        let value = match value_str {
+            None => None,
+            Some(v) => {
+                let json_value = match v.strip_prefix("0x") {
+                    Some(hex) => serde_json::Value::String(hex.to_string()),
+                    None => from_str(v).map_err(|_| fail())?,
+                };
+                Some(constant_from_json(&value_type, &json_value).map_err(|_| fail())?)
+            }
+        };
+        Ok(RegisterSpec::new(Some(value_type), value))
+    }
+}
+
+/// Method definitions for `RegisterSpec` that are WASM-compatible
+#[wasm_bindgen]
+impl RegisterSpec {
+    #[wasm_bindgen]
+    pub fn w_parse(descriptor: &str) -> std::result::Result<RegisterSpec, JsValue> {
+        RegisterSpec::parse(descriptor).map_err(|e| JsValue::from_str(&format! {"{:?}", e}))
+    }
 }
 
 /// A specification which specifies parameters of an `ErgoBox`.
@@ -61,8 +122,14 @@ impl RegisterSpec {
 pub struct BoxSpec {
     /// The address of the box
     address: Option<ErgoAddressString>,
+    /// The hex-encoded blake2b256 hash of the `ErgoTree` template of the
+    /// box, which matches every instance of a parameterized P2S contract
+    ergo_tree_template_hash: Option<String>,
     /// The allowed range of nanoErgs
     value_range: Option<Range<NanoErg>>,
+    /// The allowed range of the creation height of the box, used to reject
+    /// boxes which were created too long ago
+    creation_height_range: Option<Range<BlockHeight>>,
     /// A sorted list of `RegisterSpec`s which define registers
     /// of an `ErgoBox`.
     /// First element is treated as R4, second as R5, and so on.
@@ -76,6 +143,51 @@ pub struct BoxSpec {
     /// specification logic which gets processed when verifying
     /// the box.
     predicate: Option<fn(&ErgoBox) -> bool>,
+    /// An optional name of a predicate which was registered in the
+    /// predicate registry via `register_predicate()`. Unlike `predicate`
+    /// this can be serialized and shared between services.
+    predicate_name: Option<String>,
+    /// An optional declaration of the height-based lock of the box's
+    /// script. This is not checked by `verify_box()`, but rather by
+    /// `check_timelock()` before the box is used as an input.
+    timelock: Option<Timelock>,
+    /// An optional declaration of the refund path of the box's script,
+    /// which is used by `action_refund()` to build the refund transaction.
+    refund_path: Option<RefundPath>,
+    /// The version of the spec, incremented when the protocol upgrades
+    /// the contract of the stage
+    version: SpecVersion,
+    /// The specs of the legacy versions of the stage, whose boxes may
+    /// still linger on-chain after a contract upgrade
+    migrations: Vec<BoxSpec>,
+}
+
+/// The name of the node scan registered via `BoxSpec::utxo_scan_json()`
+pub const DEFAULT_SCAN_NAME: &str = "headless-dapp-box-spec";
+
+/// The version of a `BoxSpec`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SpecVersion(pub u32);
+
+impl Default for SpecVersion {
+    fn default() -> Self {
+        SpecVersion(1)
+    }
+}
+
+/// The discovery mechanisms which a `BoxSpec` can be exported to via
+/// `BoxSpec::export()`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TargetFormat {
+    /// The JSON body to POST to the `/scan/register` endpoint of a full
+    /// node, registering a scan with the provided name
+    NodeScan(String),
+    /// The JSON body to POST to an ergo-graphql endpoint, taking up to the
+    /// provided number of boxes
+    GraphQl(u64),
+    /// The URL of the Ergo Explorer Backend API, using the provided
+    /// explorer API url as the base
+    ExplorerRest(String),
 }
 
 /// Method definitions for `BoxSpec` that are WASM-compatible by default
@@ -83,9 +195,27 @@ pub struct BoxSpec {
 /// are made WASM-compatible.
 #[wasm_bindgen]
 impl BoxSpec {
+    /// Generates the JSON body to POST to the `/scan/register` endpoint of
+    /// a full node, registering a scan named `DEFAULT_SCAN_NAME` which
+    /// tracks the UTXOs that may match the `BoxSpec`. This allows running
+    /// a headless dApp against a local full node instead of the explorer.
+    /// Use `node_scan_json()` to register the scan under another name.
     #[wasm_bindgen]
-    pub fn utxo_scan_json(&self) -> String {
-        todo!()
+    pub fn utxo_scan_json(&self) -> std::result::Result<String, JsValue> {
+        self.node_scan_json(DEFAULT_SCAN_NAME)
+            .map_err(|e| JsValue::from_str(&format! {"{:?}", e}))
+    }
+
+    /// Returns a human-readable description of the constraints of the
+    /// `BoxSpec`, one constraint per line. Intended for telling users
+    /// exactly what kind of box is being searched for.
+    #[wasm_bindgen]
+    pub fn describe(&self) -> String {
+        let constraints = self.describe_constraints();
+        if constraints.is_empty() {
+            return "Any box".to_string();
+        }
+        constraints.join("\n")
     }
 
     /// Returns a new `BoxSpec` with all fields exactly the same
@@ -102,6 +232,19 @@ impl BoxSpec {
         }
     }
 
+    /// Serializes the `BoxSpec` into JSON (see `to_json()`)
+    #[wasm_bindgen]
+    pub fn w_to_json(&self) -> std::result::Result<String, JsValue> {
+        self.to_json()
+            .map_err(|e| JsValue::from_str(&format! {"{:?}", e}))
+    }
+
+    /// Parses a `BoxSpec` from JSON produced by `w_to_json()`
+    #[wasm_bindgen]
+    pub fn w_from_json(json: &str) -> std::result::Result<BoxSpec, JsValue> {
+        BoxSpec::from_json(json).map_err(|e| JsValue::from_str(&format! {"{:?}", e}))
+    }
+
     #[wasm_bindgen]
     pub fn w_verify_box(&self, wrapped_ergo_box: WErgoBox) -> bool {
         let b: ErgoBox = wrapped_ergo_box.into();
@@ -131,6 +274,17 @@ impl BoxSpec {
             .map_err(|e| JsValue::from_str(&format! {"{:?}", e}))?;
         Ok(ErgoBoxes::from(boxes))
     }
+
+    #[wasm_bindgen]
+    pub fn w_process_explorer_response_streaming(
+        &self,
+        explorer_response_body: &str,
+    ) -> std::result::Result<ErgoBoxes, JsValue> {
+        let boxes = self
+            .process_explorer_response_streaming(explorer_response_body)
+            .map_err(|e| JsValue::from_str(&format! {"{:?}", e}))?;
+        Ok(ErgoBoxes::from(boxes))
+    }
 }
 
 /// Method definitions for `BoxSpec` that are intended to be used in
@@ -146,7 +300,10 @@ impl BoxSpec {
         BoxSpec::new_predicated(address, value_range, registers, tokens, None)
     }
 
-    /// Create a new `BoxSpec` with a custom predicate defined.
+    /// Create a new `BoxSpec` with a custom predicate defined. The predicate
+    /// cannot be serialized, so `to_json()` errors for the spec. Specs
+    /// which need to be serialized should use `new_named_predicated()`
+    /// instead.
     pub fn new_predicated(
         address: Option<ErgoAddressString>,
         value_range: Option<Range<NanoErg>>,
@@ -157,13 +314,181 @@ impl BoxSpec {
         // Create the BoxSpec
         return BoxSpec {
             address: address,
+            ergo_tree_template_hash: None,
             value_range: value_range,
+            creation_height_range: None,
             registers: registers,
             tokens: tokens,
             predicate: predicate,
+            predicate_name: None,
+            timelock: None,
+            refund_path: None,
+            version: SpecVersion::default(),
+            migrations: vec![],
         };
     }
 
+    /// Create a new `BoxSpec` which uses a predicate that was registered
+    /// in the predicate registry under `predicate_name`.
+    pub fn new_named_predicated(
+        address: Option<ErgoAddressString>,
+        value_range: Option<Range<NanoErg>>,
+        registers: Vec<RegisterSpec>,
+        tokens: Vec<Option<TokenSpec>>,
+        predicate_name: &str,
+    ) -> BoxSpec {
+        BoxSpec {
+            predicate_name: Some(predicate_name.to_string()),
+            ..BoxSpec::new(address, value_range, registers, tokens)
+        }
+    }
+
+    /// Serializes the `BoxSpec` into JSON. Register types are encoded by
+    /// their names and register values as base16 encoded constants. A
+    /// custom `predicate` cannot be serialized, and so only a registered
+    /// `predicate_name` is preserved. Errors if the spec has a custom
+    /// predicate which is not registered under a name.
+    pub fn to_json(&self) -> Result<String> {
+        if self.predicate.is_some() && self.predicate_name.is_none() {
+            return Err(HeadlessDappError::UnnamedPredicate);
+        }
+        let spec_json = BoxSpecJson::from_spec(self)?;
+        serde_json::to_string(&spec_json).map_err(|e| HeadlessDappError::Other(e.to_string()))
+    }
+
+    /// Parses a `BoxSpec` from JSON produced by `to_json()`
+    pub fn from_json(json: &str) -> Result<BoxSpec> {
+        let spec_json: BoxSpecJson =
+            from_str(json).map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+        spec_json.into_spec()
+    }
+
+    /// Returns a human-readable description of every constraint of the
+    /// `BoxSpec`. Unspecified fields are omitted.
+    pub fn describe_constraints(&self) -> Vec<String> {
+        let mut constraints = vec![];
+        if let Some(address) = &self.address {
+            constraints.push(format!("Address: {}", address));
+        }
+        if let Some(template_hash) = &self.ergo_tree_template_hash {
+            constraints.push(format!("ErgoTree template hash: {}", template_hash));
+        }
+        if let Some(range) = &self.value_range {
+            constraints.push(format!(
+                "Value: at least {} and below {} nanoErgs",
+                range.start, range.end
+            ));
+        }
+        if let Some(range) = &self.creation_height_range {
+            constraints.push(format!(
+                "Creation height: at least {} and below {}",
+                range.start, range.end
+            ));
+        }
+        for (i, ot) in self.tokens.iter().enumerate() {
+            match ot {
+                Some(t) => constraints.push(format!(
+                    "Token {}: {} with amount at least {} and below {}",
+                    i + 1,
+                    t.token_id,
+                    t.value_range.start,
+                    t.value_range.end
+                )),
+                None => constraints.push(format!("Token {}: any token", i + 1)),
+            }
+        }
+        for (i, rs) in self.registers.iter().enumerate() {
+            let mut register = format!("Register R{}:", i + 4);
+            match &rs.value_type {
+                Some(t) => register += &format!(" of type {:?}", t),
+                None => register += " of any type",
+            }
+            if let Some(c) = &rs.value {
+                register += &format!(
+                    " with value {}",
+                    c.base16_str().unwrap_or_else(|_| format!("{:?}", c))
+                );
+            }
+            constraints.push(register);
+        }
+        if self.predicate.is_some() {
+            constraints.push("Predicate: custom predicate".to_string());
+        }
+        if let Some(name) = &self.predicate_name {
+            constraints.push(format!("Predicate: registered predicate \"{}\"", name));
+        }
+        if let Some(timelock) = &self.timelock {
+            constraints.push(format!("Timelock: {:?}", timelock));
+        }
+        constraints
+    }
+
+    /// Returns the `ErgoTree` template hash declared on the `BoxSpec`
+    pub fn ergo_tree_template_hash(&self) -> Option<String> {
+        self.ergo_tree_template_hash.clone()
+    }
+
+    /// Returns the `Timelock` declared on the `BoxSpec`
+    pub fn timelock(&self) -> Option<Timelock> {
+        self.timelock
+    }
+
+    /// Returns the allowed range of the creation height declared on the
+    /// `BoxSpec`
+    pub fn creation_height_range(&self) -> Option<Range<BlockHeight>> {
+        self.creation_height_range.clone()
+    }
+
+    /// Checks that the box is spendable at the `current_height` according
+    /// to the `Timelock` declared on the `BoxSpec`. Always succeeds if no
+    /// `Timelock` is declared.
+    pub fn check_timelock(&self, ergo_box: &ErgoBox, current_height: BlockHeight) -> Result<()> {
+        match &self.timelock {
+            Some(timelock) => timelock.check(ergo_box, current_height),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the version of the `BoxSpec`
+    pub fn version(&self) -> SpecVersion {
+        self.version
+    }
+
+    /// Returns the specs of the legacy versions of the stage, ordered from
+    /// newest to oldest
+    pub fn migrations(&self) -> Vec<BoxSpec> {
+        let mut migrations = self.migrations.clone();
+        migrations.sort_by_key(|m| std::cmp::Reverse(m.version));
+        migrations
+    }
+
+    /// Verify that a provided `ErgoBox` matches either the current spec or
+    /// one of its legacy versions, returning the version which matched. The
+    /// current spec is tried first, then the legacy versions from newest to
+    /// oldest. Errors with the error of the current spec if none match.
+    pub fn verify_box_version(&self, ergo_box: &ErgoBox) -> Result<SpecVersion> {
+        let current_error = match self.verify_box(ergo_box) {
+            Ok(()) => return Ok(self.version),
+            Err(e) => e,
+        };
+        for legacy in self.migrations() {
+            if legacy.verify_box(ergo_box).is_ok() {
+                return Ok(legacy.version);
+            }
+        }
+        Err(current_error)
+    }
+
+    /// Returns the `RefundPath` declared on the `BoxSpec`
+    pub fn refund_path(&self) -> Option<RefundPath> {
+        self.refund_path
+    }
+
+    /// Returns the name of the registered predicate used by the `BoxSpec`
+    pub fn predicate_name(&self) -> Option<String> {
+        self.predicate_name.clone()
+    }
+
     /// Acquire the `ErgoTree` of the address in the `BoxSpec`
     pub fn ergo_tree(&self) -> Result<ErgoTree> {
         if let Some(address) = self.address.clone() {
@@ -173,6 +498,24 @@ impl BoxSpec {
         Err(HeadlessDappError::InvalidSpecAddress)
     }
 
+    /// Verify that a provided `ErgoBoxCandidate` (ie. a stage box which an
+    /// Action is about to output) matches the spec before the tx is built
+    pub fn verify_candidate(&self, candidate: &ErgoBoxCandidate) -> Result<()> {
+        // The candidate is converted into an `ErgoBox` with a placeholder
+        // Transaction ID so that predicates can be applied to it as well.
+        let ergo_box = ErgoBox::new(
+            candidate.value,
+            candidate.ergo_tree.clone(),
+            candidate.tokens.clone(),
+            candidate.additional_registers.clone(),
+            candidate.creation_height,
+            TxId::zero(),
+            0,
+        )
+        .map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+        self.verify_box(&ergo_box)
+    }
+
     /// Verify that a provided `ErgoBox` matches the spec
     pub fn verify_box(&self, ergo_box: &ErgoBox) -> Result<()> {
         let ergo_box_regs = ergo_box.additional_registers.get_ordered_values();
@@ -186,6 +529,14 @@ impl BoxSpec {
                 )),
             }?;
         }
+        // Verify the template of the ErgoTree locking script
+        if let Some(template_hash) = &self.ergo_tree_template_hash {
+            if ergo_tree_template_hash(&ergo_box.ergo_tree)? != *template_hash {
+                return Err(HeadlessDappError::InvalidErgoTreeTemplate(
+                    template_hash.clone(),
+                ));
+            }
+        }
         // Verify value held in the box is within the valid range
         if let Some(value_range) = self.value_range.clone() {
             match value_range.contains(&ergo_box.value.as_u64()) {
@@ -193,6 +544,15 @@ impl BoxSpec {
                 false => Err(HeadlessDappError::InvalidSpecErgsValue),
             }?;
         }
+        // Verify the box was created within the valid range of heights
+        if let Some(creation_height_range) = &self.creation_height_range {
+            let creation_height = ergo_box.creation_height as BlockHeight;
+            if !creation_height_range.contains(&creation_height) {
+                return Err(HeadlessDappError::InvalidSpecCreationHeight(
+                    creation_height,
+                ));
+            }
+        }
 
         // Verify all of the RegisterSpecs
         if self.registers.len() > 0 {
@@ -255,10 +615,112 @@ impl BoxSpec {
             }
         }
 
+        // Verify the registered predicate
+        if let Some(predicate_name) = &self.predicate_name {
+            let predicate = lookup_predicate(predicate_name)?;
+            if !(predicate)(&ergo_box) {
+                return Err(HeadlessDappError::FailedSpecPredicate);
+            }
+        }
+
         // Verification successful
         Ok(())
     }
 
+    /// Verifies every box against the `BoxSpec`, partitioning them into
+    /// the boxes which match, and the boxes which do not along with the
+    /// error explaining why. Useful for diagnostics, as well as for
+    /// protocols which secondary-process near-miss boxes (ie. boxes in a
+    /// refundable rather than an active state).
+    pub fn partition_boxes(
+        &self,
+        boxes: Vec<ErgoBox>,
+    ) -> (Vec<ErgoBox>, Vec<(ErgoBox, HeadlessDappError)>) {
+        let mut matches = vec![];
+        let mut non_matches = vec![];
+        for ergo_box in boxes {
+            match self.verify_box(&ergo_box) {
+                Ok(()) => matches.push(ergo_box),
+                Err(e) => non_matches.push((ergo_box, e)),
+            }
+        }
+        (matches, non_matches)
+    }
+
+    /// Exports the `BoxSpec` to the provided discovery mechanism. Every
+    /// format is generated from the same spec, so the node scan, the
+    /// GraphQL query, and the explorer URL stay consistent as the spec
+    /// evolves.
+    pub fn export(&self, format: TargetFormat) -> Result<String> {
+        match format {
+            TargetFormat::NodeScan(scan_name) => self.node_scan_json(&scan_name),
+            TargetFormat::GraphQl(take) => self.graphql_query(take),
+            TargetFormat::ExplorerRest(explorer_api_url) => {
+                self.explorer_endpoint(&explorer_api_url)
+            }
+        }
+    }
+
+    /// Generates the JSON body to POST to the `/scan/register` endpoint of
+    /// a full node in order to register a scan with the provided name
+    /// which tracks boxes that may match your `BoxSpec`.
+    pub fn node_scan_json(&self, scan_name: &str) -> Result<String> {
+        let tracking_rule = self.scan_tracking_rule()?;
+        Ok(serde_json::json!({
+            "scanName": scan_name,
+            "trackingRule": tracking_rule,
+        })
+        .to_string())
+    }
+
+    /// Builds the node scanning predicate tree which tracks boxes that may
+    /// match the `BoxSpec`. The ErgoTree of the address is matched with
+    /// `equals` on R1, every token with `containsAsset`, and every
+    /// `Coll[Byte]` register value with `equals` on its register. The
+    /// node's predicates cannot express the value range, token amounts,
+    /// other register types, the template hash or the custom predicate,
+    /// and so the boxes returned by the scan must still be checked with
+    /// `verify_box()`.
+    fn scan_tracking_rule(&self) -> Result<serde_json::Value> {
+        let mut predicates = vec![];
+        if let Some(address) = &self.address {
+            let ergo_tree_bytes = address_string_to_ergo_tree(address)?
+                .sigma_serialize_bytes()
+                .map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+            predicates.push(serde_json::json!({
+                "predicate": "equals",
+                "register": "R1",
+                "value": base16::encode_lower(&ergo_tree_bytes),
+            }));
+        }
+        for token in self.tokens.iter().flatten() {
+            predicates.push(serde_json::json!({
+                "predicate": "containsAsset",
+                "assetId": token.token_id,
+            }));
+        }
+        if predicates.is_empty() {
+            return Err(HeadlessDappError::Other("Your `BoxSpec` must either have an address or tokens defined in order to generate a node scan.".to_string()));
+        }
+        for (i, rs) in self.registers.iter().enumerate() {
+            if let Some(bytes_hex) = rs
+                .value
+                .as_ref()
+                .and_then(|c| unwrap_hex_encoded_string(c).ok())
+            {
+                predicates.push(serde_json::json!({
+                    "predicate": "equals",
+                    "register": format!("R{}", i + 4),
+                    "value": bytes_hex,
+                }));
+            }
+        }
+        match predicates.len() {
+            1 => Ok(predicates.remove(0)),
+            _ => Ok(serde_json::json!({ "predicate": "and", "args": predicates })),
+        }
+    }
+
     /// Generates a URL for the Ergo Explorer Backend API
     /// to find boxes which may match your `BoxSpec`. This method uses
     /// the `explorer_api_url` you provide as input which
@@ -267,9 +729,20 @@ impl BoxSpec {
     /// This method is intended to be used in tandem with
     /// `process_explorer_response()`
     pub fn explorer_endpoint(&self, explorer_api_url: &str) -> Result<String> {
+        let (url, default_limit) = self.explorer_url(explorer_api_url)?;
+        Ok(url.query("limit", default_limit).build())
+    }
+
+    /// Builds the `ExplorerUrl` (without query parameters) used to find
+    /// boxes which may match the `BoxSpec`, along with the default page
+    /// limit for said endpoint.
+    fn explorer_url(&self, explorer_api_url: &str) -> Result<(ExplorerUrl, u64)> {
         // Verify an address exists
-        if self.address.is_none() && self.tokens.len() == 0 {
-            return Err(HeadlessDappError::Other("Your `BoxStruct` must either have an address or tokens defined in order to generate an endpoint url for the explorer API.".to_string()));
+        if self.address.is_none()
+            && self.ergo_tree_template_hash.is_none()
+            && self.tokens.len() == 0
+        {
+            return Err(HeadlessDappError::Other("Your `BoxStruct` must either have an address, an ErgoTree template hash, or tokens defined in order to generate an endpoint url for the explorer API.".to_string()));
         }
 
         // Check if the spec specifies that one of the tokens has a value of 1
@@ -285,27 +758,151 @@ impl BoxSpec {
             }
             return false;
         });
+        let base_url = ExplorerUrl::new(explorer_api_url).path("v1/boxes/unspent");
         if let Some(ot) = has_token_value_one {
-            return Ok(explorer_api_url.to_string()
-                + "/v1/boxes/unspent/byTokenId/"
-                + &ot.clone().unwrap().token_id
-                + "?limit=100");
+            let url = base_url
+                .path("byTokenId")
+                .segment(&ot.clone().unwrap().token_id);
+            return Ok((url, 100));
         }
         // If no token value one, but has address
         if let Some(address) = self.address.clone() {
-            return Ok(explorer_api_url.to_string()
-                + "/v1/boxes/unspent/byAddress/"
-                + &address
-                + "?limit=500");
+            return Ok((base_url.path("byAddress").segment(&address), 500));
+        }
+        // If no address, but has an ErgoTree template hash, every instance
+        // of the parameterized contract is found
+        if let Some(template_hash) = self.ergo_tree_template_hash.clone() {
+            return Ok((
+                base_url
+                    .path("byErgoTreeTemplateHash")
+                    .segment(&template_hash),
+                500,
+            ));
         }
         // Else if no token value one and no address. Meaning other tokens
         // exist with value greater than 1.
         else {
-            return Ok(explorer_api_url.to_string()
-                + "/v1/boxes/unspent/byTokenId/"
-                + &self.tokens[0].clone().unwrap().token_id
-                + "?limit=100");
+            let token_id = self
+                .tokens
+                .iter()
+                .flatten()
+                .next()
+                .map(|t| t.token_id.clone())
+                .ok_or_else(|| HeadlessDappError::Other("Your `BoxStruct` must either have an address or tokens defined in order to generate an endpoint url for the explorer API.".to_string()))?;
+            return Ok((base_url.path("byTokenId").segment(&token_id), 100));
+        }
+    }
+
+    /// Generates an ergo-graphql query which finds unspent boxes that may
+    /// match your `BoxSpec`. Unlike the explorer REST API, the address,
+    /// the first token, and the exact register values of the `BoxSpec` are
+    /// all pushed down as filters, reducing the number of boxes which must
+    /// be fetched and verified client-side. The returned String is the JSON
+    /// body to POST to the GraphQL endpoint.
+    pub fn graphql_query(&self, take: u64) -> Result<String> {
+        let mut filters = vec!["spent: false".to_string()];
+        if let Some(address) = &self.address {
+            filters.push(format!("address: \"{}\"", address));
+        }
+        if let Some(Some(token)) = self.tokens.first() {
+            filters.push(format!("tokenId: \"{}\"", token.token_id));
         }
+        let mut register_filters = vec![];
+        for (i, rs) in self.registers.iter().enumerate() {
+            if let Some(c) = &rs.value {
+                let value = c
+                    .base16_str()
+                    .map_err(|_| HeadlessDappError::FailedRegisterSpec)?;
+                register_filters.push(format!("R{}: \"{}\"", i + 4, value));
+            }
+        }
+        if !register_filters.is_empty() {
+            filters.push(format!("registers: {{{}}}", register_filters.join(", ")));
+        }
+        filters.push(format!("take: {}", take));
+
+        let query = format!(
+            "query {{ boxes({}) {{ boxId transactionId index value creationHeight ergoTree additionalRegisters assets {{ tokenId amount }} }} }}",
+            filters.join(", ")
+        );
+        Ok(serde_json::json!({ "query": query }).to_string())
+    }
+
+    /// Using the response JSON (as a String) from a GraphQL endpoint for
+    /// the query generated by `graphql_query()`, filter all returned
+    /// `ErgoBox`es against the `BoxSpec`.
+    pub fn process_graphql_response(&self, graphql_response_body: &str) -> Result<Vec<ErgoBox>> {
+        let json: serde_json::Value =
+            from_str(graphql_response_body).map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+        let box_jsons = json["data"]["boxes"].as_array().ok_or_else(|| {
+            HeadlessDappError::Other("GraphQL response holds no boxes".to_string())
+        })?;
+        let mut boxes = vec![];
+        for box_json in box_jsons {
+            let ergo_box: ErgoBox = serde_json::from_value(graphql_box_to_ergo_box_json(box_json))
+                .map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+            if self.verify_box(&ergo_box).is_ok() {
+                boxes.push(ergo_box);
+            }
+        }
+        Ok(boxes)
+    }
+
+    /// Same as `explorer_endpoint()` but requests a specific page of
+    /// results using the provided `offset` and `limit`.
+    pub fn explorer_endpoint_paginated(
+        &self,
+        explorer_api_url: &str,
+        offset: u64,
+        limit: u64,
+    ) -> Result<String> {
+        let (url, _) = self.explorer_url(explorer_api_url)?;
+        Ok(url.query("offset", offset).query("limit", limit).build())
+    }
+
+    /// Same as `explorer_endpoint_paginated()` but uses the position of
+    /// the provided `PageCursor`.
+    pub fn explorer_endpoint_for_page(
+        &self,
+        explorer_api_url: &str,
+        cursor: &PageCursor,
+    ) -> Result<String> {
+        self.explorer_endpoint_paginated(explorer_api_url, cursor.offset, cursor.limit)
+    }
+
+    /// Generates a URL for the Ergo Explorer Backend API which only
+    /// requests a single box, intended to be used in tandem with
+    /// `process_count_response()` to cheaply count the live boxes
+    /// without downloading and verifying full pages.
+    pub fn count_endpoint(&self, explorer_api_url: &str) -> Result<String> {
+        self.explorer_endpoint_paginated(explorer_api_url, 0, 1)
+    }
+
+    /// Acquires the number of boxes found by the explorer from the response
+    /// of the endpoint generated by `count_endpoint()`.
+    /// Note that the explorer only searches by address, template hash or
+    /// token, and so the count is not verified against the rest of the
+    /// spec (value range, registers, etc.). It is exact for specs which
+    /// only constrain what the explorer searches by, and otherwise an
+    /// upper bound. A count of zero always means no matching box is live.
+    pub fn process_count_response(&self, explorer_response_body: &str) -> Result<u64> {
+        explorer_response_total(explorer_response_body).ok_or_else(|| {
+            HeadlessDappError::Other(
+                "The Ergo Explorer Backend API response holds no total".to_string(),
+            )
+        })
+    }
+
+    /// Generates a URL for the Ergo Explorer Backend API of the provided
+    /// `Network` to find boxes which may match your `BoxSpec`.
+    pub fn explorer_endpoint_for_network(&self, network: Network) -> Result<String> {
+        self.explorer_endpoint(network.explorer_api_url())
+    }
+
+    /// Detects the `Network` of the address in the `BoxSpec`, if one is
+    /// defined.
+    pub fn network(&self) -> Option<Network> {
+        self.address.as_ref().and_then(Network::from_address)
     }
 
     /// Using the response JSON (as a String) from the Ergo Explorer API
@@ -313,32 +910,21 @@ impl BoxSpec {
     /// filter all returned `ErgoBox`es against the `BoxSpec`
     /// using the `verify_box()` method.
     pub fn process_explorer_response(&self, explorer_response_body: &str) -> Result<Vec<ErgoBox>> {
-        // Get the `JsonValue` from the explorer respone body
-        let json_res = json::parse(explorer_response_body).map_err(|_| {
-            HeadlessDappError::Other(
-                "Failed to extract json from Ergo Explorer Backend API Response".to_string(),
-            )
-        });
+        self.process_explorer_response_with_metrics(explorer_response_body, &NoopMetrics)
+    }
 
-        // Unwrap the `JsonValue`
-        let json = json_res?;
-
-        // Parse the json into `Vec<ErgoBox>`
-        let mut box_list: Vec<ErgoBox> = vec![];
-        for i in 0.. {
-            let box_json = &json["items"][i];
-            if box_json.is_null() {
-                break;
-            } else {
-                let res_ergo_box = from_str(&box_json.to_string());
-                if let Ok(ergo_box) = res_ergo_box {
-                    box_list.push(ergo_box);
-                } else if let Err(e) = res_ergo_box {
-                    let mess = format!("Box Json: {}\nError: {:?}", box_json.to_string(), e);
-                    return Err(HeadlessDappError::Other(mess));
-                }
-            }
-        }
+    /// Same as `process_explorer_response()` but additionally reports the
+    /// number of fetched boxes and the spec match rate to the provided
+    /// `Metrics` implementation.
+    pub fn process_explorer_response_with_metrics(
+        &self,
+        explorer_response_body: &str,
+        metrics: &dyn Metrics,
+    ) -> Result<Vec<ErgoBox>> {
+        let box_list = parse_explorer_response_boxes(explorer_response_body)?;
+
+        let total = box_list.len();
+        report_boxes_fetched(metrics, total);
 
         // Filter the boxes based on whether they pass the `BoxSpec`
         // verificaiton.
@@ -348,8 +934,353 @@ impl BoxSpec {
             }
             return acc;
         });
+
+        report_spec_matched(metrics, filtered_boxes.len(), total);
         Ok(filtered_boxes.clone())
     }
+
+    /// Same as `process_explorer_response()` but for the response of a
+    /// non-standard explorer or indexer, which is parsed using the
+    /// provided `ResponseAdapter`.
+    pub fn process_explorer_response_with_adapter(
+        &self,
+        explorer_response_body: &str,
+        adapter: &dyn ResponseAdapter,
+    ) -> Result<Vec<ErgoBox>> {
+        Ok(adapter
+            .parse_boxes(explorer_response_body)?
+            .into_iter()
+            .filter(|b| self.verify_box(b).is_ok())
+            .collect())
+    }
+
+    /// Same as `process_explorer_response()` but for a page fetched from
+    /// the endpoint generated by `explorer_endpoint_for_page()`. Also
+    /// returns the `PageCursor` of the next page.
+    pub fn process_explorer_response_page(
+        &self,
+        explorer_response_body: &str,
+        cursor: &PageCursor,
+    ) -> Result<(Vec<ErgoBox>, PageCursor)> {
+        let box_list = parse_explorer_response_boxes(explorer_response_body)?;
+        let next_cursor = cursor.advanced(
+            box_list.len() as u64,
+            explorer_response_total(explorer_response_body),
+        );
+        let boxes = box_list
+            .into_iter()
+            .filter(|b| self.verify_box(b).is_ok())
+            .collect();
+        Ok((boxes, next_cursor))
+    }
+
+    /// Same as `process_explorer_response()` but verifies each `ErgoBox`
+    /// against the `BoxSpec` as it is parsed, never holding boxes which
+    /// fail verification in memory. Intended for maximum-size explorer
+    /// pages in memory-constrained environments.
+    pub fn process_explorer_response_streaming(
+        &self,
+        explorer_response_body: &str,
+    ) -> Result<Vec<ErgoBox>> {
+        let mut deserializer = serde_json::Deserializer::from_str(explorer_response_body);
+        FilteredResponseSeed { spec: self }
+            .deserialize(&mut deserializer)
+            .map_err(|e| HeadlessDappError::Other(e.to_string()))
+    }
+
+    /// Same as `process_explorer_response_streaming()` but reads the
+    /// explorer response from an `io::Read` (ie. a HTTP response body)
+    /// so the full response never has to be held in memory as a `String`.
+    pub fn process_explorer_response_reader<R: Read>(&self, reader: R) -> Result<Vec<ErgoBox>> {
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        FilteredResponseSeed { spec: self }
+            .deserialize(&mut deserializer)
+            .map_err(|e| HeadlessDappError::Other(e.to_string()))
+    }
+
+    /// Same as `process_explorer_response()` but the returned `ErgoBox`es
+    /// are sorted deterministically (see `sort_boxes_deterministically()`)
+    /// so that logic which selects a specific box (ie. the first) is stable
+    /// across runs regardless of the ordering returned by the explorer.
+    pub fn process_explorer_response_sorted(
+        &self,
+        explorer_response_body: &str,
+    ) -> Result<Vec<ErgoBox>> {
+        let mut boxes = self.process_explorer_response(explorer_response_body)?;
+        sort_boxes_deterministically(&mut boxes);
+        Ok(boxes)
+    }
+
+    /// Same as `process_explorer_response()` but verifies the returned
+    /// `ErgoBox`es against the `BoxSpec` in parallel using all available
+    /// cores. Only available outside of WASM with the `parallel` feature.
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    pub fn process_explorer_response_parallel(
+        &self,
+        explorer_response_body: &str,
+    ) -> Result<Vec<ErgoBox>> {
+        self.process_explorer_response_parallel_with_metrics(explorer_response_body, &NoopMetrics)
+    }
+
+    /// Same as `process_explorer_response_parallel()` but additionally
+    /// reports the number of fetched boxes and the spec match rate to the
+    /// provided `Metrics` implementation.
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    pub fn process_explorer_response_parallel_with_metrics(
+        &self,
+        explorer_response_body: &str,
+        metrics: &dyn Metrics,
+    ) -> Result<Vec<ErgoBox>> {
+        // Neither `ErgoBox`es nor the `BoxSpec` can be shared between
+        // threads, so each worker rebuilds the spec from its JSON and only
+        // the JSON of the matching boxes is passed back. A spec with an
+        // unnamed custom predicate cannot be rebuilt, and so is processed
+        // sequentially instead.
+        let spec_json = match self.to_json() {
+            Ok(spec_json) => spec_json,
+            Err(_) => {
+                return self.process_explorer_response_with_metrics(explorer_response_body, metrics)
+            }
+        };
+        let box_jsons = parse_explorer_response_box_jsons(explorer_response_body)?;
+        let total = box_jsons.len();
+        report_boxes_fetched(metrics, total);
+        let matched_jsons = box_jsons
+            .into_par_iter()
+            .map_init(
+                || BoxSpec::from_json(&spec_json),
+                |spec, box_json| {
+                    let spec = spec
+                        .as_ref()
+                        .map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+                    let ergo_box = parse_box_json(&box_json)?;
+                    Ok(spec.verify_box(&ergo_box).ok().map(|_| box_json))
+                },
+            )
+            .collect::<Result<Vec<Option<String>>>>()?;
+        let filtered_boxes = matched_jsons
+            .iter()
+            .flatten()
+            .map(|box_json| parse_box_json(box_json))
+            .collect::<Result<Vec<ErgoBox>>>()?;
+        report_spec_matched(metrics, filtered_boxes.len(), total);
+        Ok(filtered_boxes)
+    }
+}
+
+/// The JSON form of a `BoxSpec`
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BoxSpecJson {
+    address: Option<ErgoAddressString>,
+    ergo_tree_template_hash: Option<String>,
+    value_range: Option<Range<NanoErg>>,
+    #[serde(default)]
+    creation_height_range: Option<Range<BlockHeight>>,
+    registers: Vec<RegisterSpecJson>,
+    tokens: Vec<Option<TokenSpecJson>>,
+    predicate_name: Option<String>,
+    timelock: Option<Timelock>,
+    refund_path: Option<RefundPath>,
+    version: u32,
+    migrations: Vec<BoxSpecJson>,
+}
+
+/// The JSON form of a `RegisterSpec`
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RegisterSpecJson {
+    value_type: Option<String>,
+    value: Option<String>,
+}
+
+/// The JSON form of a `TokenSpec`
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenSpecJson {
+    token_id: String,
+    value_range: Range<u64>,
+}
+
+impl BoxSpecJson {
+    fn from_spec(spec: &BoxSpec) -> Result<BoxSpecJson> {
+        let registers = spec
+            .registers
+            .iter()
+            .map(|r| {
+                let value = match &r.value {
+                    Some(c) => Some(
+                        c.base16_str()
+                            .map_err(|e| HeadlessDappError::Other(e.to_string()))?,
+                    ),
+                    None => None,
+                };
+                Ok(RegisterSpecJson {
+                    value_type: r.value_type.as_ref().map(stype_name),
+                    value,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let tokens = spec
+            .tokens
+            .iter()
+            .map(|ot| {
+                ot.as_ref().map(|t| TokenSpecJson {
+                    token_id: t.token_id.clone(),
+                    value_range: t.value_range.clone(),
+                })
+            })
+            .collect();
+        Ok(BoxSpecJson {
+            address: spec.address.clone(),
+            ergo_tree_template_hash: spec.ergo_tree_template_hash.clone(),
+            value_range: spec.value_range.clone(),
+            creation_height_range: spec.creation_height_range.clone(),
+            registers,
+            tokens,
+            predicate_name: spec.predicate_name.clone(),
+            timelock: spec.timelock,
+            refund_path: spec.refund_path,
+            version: spec.version.0,
+            migrations: spec
+                .migrations
+                .iter()
+                .map(BoxSpecJson::from_spec)
+                .collect::<Result<Vec<_>>>()?,
+        })
+    }
+
+    fn into_spec(self) -> Result<BoxSpec> {
+        let registers = self
+            .registers
+            .into_iter()
+            .map(|r| {
+                let value_type = match r.value_type {
+                    Some(name) => Some(parse_stype(&name)?),
+                    None => None,
+                };
+                let value = match r.value {
+                    Some(hex) => {
+                        let bytes = base16::decode(&hex)
+                            .map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+                        Some(
+                            Constant::sigma_parse_bytes(&bytes)
+                                .map_err(|e| HeadlessDappError::Other(e.to_string()))?,
+                        )
+                    }
+                    None => None,
+                };
+                Ok(RegisterSpec::new(value_type, value))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let tokens = self
+            .tokens
+            .into_iter()
+            .map(|ot| ot.map(|t| TokenSpec::new(t.value_range, &t.token_id)))
+            .collect();
+        let migrations = self
+            .migrations
+            .into_iter()
+            .map(BoxSpecJson::into_spec)
+            .collect::<Result<Vec<_>>>()?;
+        let mut spec = BoxSpec::new(self.address, self.value_range, registers, tokens)
+            .modified_ergo_tree_template_hash(self.ergo_tree_template_hash)
+            .modified_creation_height_range(self.creation_height_range)
+            .modified_predicate_name(self.predicate_name)
+            .modified_timelock(self.timelock)
+            .modified_refund_path(self.refund_path)
+            .modified_version(SpecVersion(self.version));
+        spec.migrations = migrations;
+        Ok(spec)
+    }
+}
+
+/// Sorts a list of `ErgoBox`es by a stable key: first by creation height
+/// (ascending), then by Box ID (ascending, as base16).
+pub fn sort_boxes_deterministically(boxes: &mut Vec<ErgoBox>) {
+    boxes.sort_by_cached_key(|b| {
+        let box_id: String = b.box_id().into();
+        (b.creation_height, box_id)
+    });
+}
+
+/// GraphQL returns nanoErg values and token amounts as Strings, which are
+/// converted into numbers as expected by the `ErgoBox` JSON format.
+fn graphql_box_to_ergo_box_json(box_json: &serde_json::Value) -> serde_json::Value {
+    let to_number = |v: &mut serde_json::Value| {
+        if let Some(n) = v.as_str().and_then(|s| s.parse::<u64>().ok()) {
+            *v = serde_json::Value::from(n);
+        }
+    };
+    let mut box_json = box_json.clone();
+    to_number(&mut box_json["value"]);
+    if let Some(assets) = box_json["assets"].as_array_mut() {
+        for asset in assets {
+            to_number(&mut asset["amount"]);
+        }
+    }
+    box_json
+}
+
+/// Reports the number of boxes parsed out of a response, emitting a
+/// tracing event if the `tracing` feature is enabled.
+fn report_boxes_fetched(metrics: &dyn Metrics, total: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(fetched = total, "parsed explorer response");
+    metrics.boxes_fetched(total);
+}
+
+/// Reports how many of the parsed boxes matched the `BoxSpec`, emitting a
+/// tracing event if the `tracing` feature is enabled.
+fn report_spec_matched(metrics: &dyn Metrics, matched: usize, total: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        matched = matched,
+        total = total,
+        "verified boxes against spec"
+    );
+    metrics.spec_matched(matched, total);
+}
+
+/// Parses the response JSON (as a String) from the Ergo Explorer API
+/// into a list of `ErgoBox`es.
+pub(crate) fn parse_explorer_response_boxes(explorer_response_body: &str) -> Result<Vec<ErgoBox>> {
+    parse_explorer_response_box_jsons(explorer_response_body)?
+        .iter()
+        .map(|box_json| parse_box_json(box_json))
+        .collect()
+}
+
+/// Extracts the JSON of each box from the response JSON (as a String)
+/// from the Ergo Explorer API.
+fn parse_explorer_response_box_jsons(explorer_response_body: &str) -> Result<Vec<String>> {
+    // Get the `JsonValue` from the explorer respone body
+    let json_res = json::parse(explorer_response_body).map_err(|_| {
+        HeadlessDappError::Other(
+            "Failed to extract json from Ergo Explorer Backend API Response".to_string(),
+        )
+    });
+
+    // Unwrap the `JsonValue`
+    let json = json_res?;
+
+    let mut box_jsons = vec![];
+    for i in 0.. {
+        let box_json = &json["items"][i];
+        if box_json.is_null() {
+            break;
+        }
+        box_jsons.push(box_json.to_string());
+    }
+
+    Ok(box_jsons)
+}
+
+/// Parses the JSON of a single box from the Ergo Explorer API into an
+/// `ErgoBox`.
+fn parse_box_json(box_json: &str) -> Result<ErgoBox> {
+    from_str(box_json)
+        .map_err(|e| HeadlessDappError::Other(format!("Box Json: {}\nError: {:?}", box_json, e)))
 }
 
 /// Methods related to modifying fields (which aren't WASM-compatible)
@@ -363,6 +1294,18 @@ impl BoxSpec {
         }
     }
     /// Returns a new `BoxSpec` with all fields exactly the same
+    /// except the creation_height_range is set to the range provided as
+    /// input.
+    pub fn modified_creation_height_range(
+        &self,
+        creation_height_range: Option<Range<BlockHeight>>,
+    ) -> BoxSpec {
+        BoxSpec {
+            creation_height_range: creation_height_range,
+            ..self.clone()
+        }
+    }
+    /// Returns a new `BoxSpec` with all fields exactly the same
     /// except the registers are set to the registers provided as input.
     pub fn modified_registers(&self, registers: Vec<RegisterSpec>) -> BoxSpec {
         BoxSpec {
@@ -388,6 +1331,73 @@ impl BoxSpec {
             ..self.clone()
         }
     }
+
+    /// Returns a new `BoxSpec` with all fields exactly the same
+    /// except the registered predicate name is set to the one provided
+    /// as input.
+    pub fn modified_predicate_name(&self, predicate_name: Option<String>) -> BoxSpec {
+        BoxSpec {
+            predicate_name: predicate_name,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a new `BoxSpec` with all fields exactly the same
+    /// except the ErgoTree template hash is set to the one provided as input.
+    pub fn modified_ergo_tree_template_hash(
+        &self,
+        ergo_tree_template_hash: Option<String>,
+    ) -> BoxSpec {
+        BoxSpec {
+            ergo_tree_template_hash: ergo_tree_template_hash,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a new `BoxSpec` with all fields exactly the same
+    /// except the timelock is set to the one provided as input.
+    pub fn modified_timelock(&self, timelock: Option<Timelock>) -> BoxSpec {
+        BoxSpec {
+            timelock: timelock,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a new `BoxSpec` with all fields exactly the same
+    /// except the version is set to the one provided as input.
+    pub fn modified_version(&self, version: SpecVersion) -> BoxSpec {
+        BoxSpec {
+            version: version,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a new `BoxSpec` with all fields exactly the same
+    /// except the provided legacy spec is added to the migrations. The
+    /// legacy spec must have a lower version than the current spec.
+    pub fn with_migration(&self, legacy_spec: BoxSpec) -> Result<BoxSpec> {
+        if legacy_spec.version >= self.version {
+            return Err(HeadlessDappError::Other(format!(
+                "The legacy spec version {:?} must be lower than the current version {:?}",
+                legacy_spec.version, self.version
+            )));
+        }
+        let mut migrations = self.migrations.clone();
+        migrations.push(legacy_spec);
+        Ok(BoxSpec {
+            migrations: migrations,
+            ..self.clone()
+        })
+    }
+
+    /// Returns a new `BoxSpec` with all fields exactly the same
+    /// except the refund path is set to the one provided as input.
+    pub fn modified_refund_path(&self, refund_path: Option<RefundPath>) -> BoxSpec {
+        BoxSpec {
+            refund_path: refund_path,
+            ..self.clone()
+        }
+    }
 }
 
 #[cfg(test)]
@@ -395,6 +1405,138 @@ mod tests {
     use super::*;
     use crate::box_traits::SpecifiedBox;
     use crate::specified_boxes::ErgUsdOraclePoolBox;
+    use crate::test_utils::test_box_at;
+
+    fn build_test_box(value: NanoErg, creation_height: u32) -> ErgoBox {
+        let address = "2iHkR7CWvD1R4j1yZg5bkeDRQavjAaVPeTDFGGLZduHyfWMuYpmhHocX8GJoaieTx78FntzJbCBVL6rf96ocJoZdmWBL2fci7NqWgAirppPQmZ7fN9V6z13Ay6brPriBKYqLp1bT2Fk4FkFLCfdPpe";
+        test_box_at(value, address, vec![], vec![], creation_height, 0)
+    }
+
+    fn build_explorer_response(boxes: &Vec<ErgoBox>) -> String {
+        let items: Vec<String> = boxes
+            .iter()
+            .map(|b| serde_json::to_string(b).unwrap())
+            .collect();
+        format!("{{\"items\": [{}]}}", items.join(","))
+    }
+
+    /// A `Metrics` which keeps the last reported counts
+    #[derive(Default)]
+    struct CountingMetrics {
+        fetched: std::cell::Cell<usize>,
+        matched: std::cell::Cell<(usize, usize)>,
+    }
+
+    impl Metrics for CountingMetrics {
+        fn boxes_fetched(&self, count: usize) {
+            self.fetched.set(count);
+        }
+
+        fn spec_matched(&self, matched: usize, total: usize) {
+            self.matched.set((matched, total));
+        }
+    }
+
+    fn metrics_test_response() -> String {
+        build_explorer_response(&vec![
+            build_test_box(3000000, 0),
+            build_test_box(100000, 0),
+            build_test_box(2000000, 1),
+        ])
+    }
+
+    #[test]
+    fn process_explorer_response_reports_metrics() {
+        let box_spec = BoxSpec::new(None, Some(1000000..u64::MAX), vec![], vec![]);
+        let metrics = CountingMetrics::default();
+        let boxes = box_spec
+            .process_explorer_response_with_metrics(&metrics_test_response(), &metrics)
+            .unwrap();
+        assert_eq!(boxes.len(), 2);
+        assert_eq!(metrics.fetched.get(), 3);
+        assert_eq!(metrics.matched.get(), (2, 3));
+    }
+
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    #[test]
+    fn parallel_processing_matches_and_reports_metrics() {
+        let box_spec = BoxSpec::new(None, Some(1000000..u64::MAX), vec![], vec![]);
+        let response = metrics_test_response();
+        let metrics = CountingMetrics::default();
+        let boxes = box_spec
+            .process_explorer_response_parallel_with_metrics(&response, &metrics)
+            .unwrap();
+        assert_eq!(
+            boxes,
+            box_spec.process_explorer_response(&response).unwrap()
+        );
+        assert_eq!(metrics.fetched.get(), 3);
+        assert_eq!(metrics.matched.get(), (2, 3));
+    }
+
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    #[test]
+    fn parallel_processing_supports_unnamed_predicates() {
+        fn above_two_ergs(b: &ErgoBox) -> bool {
+            b.value.as_u64() > &2000000
+        }
+        let box_spec = BoxSpec::new_predicated(None, None, vec![], vec![], Some(above_two_ergs));
+        let boxes = box_spec
+            .process_explorer_response_parallel(&metrics_test_response())
+            .unwrap();
+        assert_eq!(boxes.len(), 1);
+        assert_eq!(boxes[0].value.as_u64(), &3000000);
+    }
+
+    #[test]
+    fn explorer_endpoint_by_template_hash_test() {
+        let ergo_box = build_test_box(1000000, 0);
+        let template_hash = ergo_tree_template_hash(&ergo_box.ergo_tree).unwrap();
+        let spec = BoxSpec::new(None, None, vec![], vec![])
+            .modified_ergo_tree_template_hash(Some(template_hash.clone()));
+        assert_eq!(
+            spec.explorer_endpoint("https://api.ergoplatform.com/api")
+                .unwrap(),
+            format!(
+                "https://api.ergoplatform.com/api/v1/boxes/unspent/byErgoTreeTemplateHash/{}?limit=500",
+                template_hash
+            )
+        );
+        assert!(spec.verify_box(&ergo_box).is_ok());
+        let other_spec = spec.modified_ergo_tree_template_hash(Some("00".to_string()));
+        assert!(other_spec.verify_box(&ergo_box).is_err());
+    }
+
+    #[test]
+    fn count_endpoint_test() {
+        let spec = BoxSpec::new(Some("address".to_string()), None, vec![], vec![]);
+        assert_eq!(
+            spec.count_endpoint("https://api.ergoplatform.com/api")
+                .unwrap(),
+            "https://api.ergoplatform.com/api/v1/boxes/unspent/byAddress/address?offset=0&limit=1"
+        );
+        assert_eq!(
+            spec.process_count_response(r#"{"items": [], "total": 3}"#)
+                .unwrap(),
+            3
+        );
+        assert!(spec.process_count_response(r#"{"items": []}"#).is_err());
+    }
+
+    #[test]
+    fn partition_boxes_test() {
+        let spec = BoxSpec::new(None, Some(1000000..2000000), vec![], vec![]);
+        let boxes = vec![build_test_box(1500000, 0), build_test_box(2500000, 0)];
+        let (matches, non_matches) = spec.partition_boxes(boxes);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(*matches[0].value.as_u64(), 1500000);
+        assert_eq!(non_matches.len(), 1);
+        assert!(matches!(
+            non_matches[0].1,
+            HeadlessDappError::InvalidSpecErgsValue
+        ));
+    }
+
     #[test]
     fn create_ergo_box_spec() {
         let address = Some(
@@ -425,6 +1567,25 @@ mod tests {
         assert!(url == "https://api.ergoplatform.com/api/v1/boxes/unspent/byAddress/9aFbqNsmDwSxCdcLDKmSxVTL58ms2A39Rpn2zodVzkBN5MzB8zvW5PFX551W1A5vUdFJ3yxwvwgYTTS4JrPQcb5qxBbRDJkGNikuqHRXhnbniK4ajumEj7ot2o7DbcNFaM674fWufQzSGS1KtgMw95ZojyqhswUNbKpYDV1PhKw62bEMdJL9vAvzea4KwKXGUTdYYkcPdQKFWXfrdo2nTS3ucFNxqyTRB3VtZk7AWE3eeNHFcXZ1kLkfrX1ZBjpQ7qrBemHk4KZgS8fzmm6hPSZThiVVtBfQ2CZhJQdAZjRwGrw5TDcZ4BBDAZxg9h13vZ7tQSPsdAtjMFQT1DxbqAruKxX38ZwaQ3UfWmbBpbJEThAQaS4gsCBBSjswrv8BvupxaHZ4oQmA2LZiz4nYaPr8MJtR4fbM9LErwV4yDVMb873bRE5TBF59NipUyHAir7ysajPjbGc8aRLqsMVjntFSCFYx7822RBrj7RRX11CpiGK6vdfKHe3k14EH6YaNXvGSq8DrfNHEK4SgreknTqCgjL6i3EMZKPCW8Lao3Q5tbJFnFjEyntpUDf5zfGgFURxzobeEY4USqFaxyppHkgLjQuFQtDWbYVu3ztQL6hdWHjZXMK4VVvEDeLd1woebD1CyqS5kJHpGa78wQZ4iKygw4ijYrodZpqqEwTXdqwEB6xaLfkxZCBPrYPST3xz67GGTBUFy6zkXP5vwVVM5gWQJFdWCZniAAzBpzHeVq1yzaBp5GTJgr9bfrrAmuX8ra1m125yfeT9sTWroVu?limit=500".to_string())
     }
 
+    #[test]
+    fn produce_testnet_explorer_url() {
+        let box_spec = BoxSpec::new(
+            None,
+            None,
+            vec![],
+            vec![Some(TokenSpec::new(
+                1..2,
+                "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1",
+            ))],
+        );
+
+        let url = box_spec
+            .explorer_endpoint_for_network(Network::Testnet)
+            .unwrap();
+
+        assert_eq!(url, "https://api-testnet.ergoplatform.com/api/v1/boxes/unspent/byTokenId/0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1?limit=100");
+    }
+
     #[test]
     fn produce_explorer_url_using_token() {
         let box_spec = ErgUsdOraclePoolBox::box_spec();
@@ -435,6 +1596,308 @@ mod tests {
 
         println!("Url: {}", url);
 
-        assert!(url == "https://api.ergoplatform.com/api/v1/boxes/unspent/byTokenId/0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1?limit=100".to_string())
+        assert!(url == "https://api.ergoplatform.com/api/v1/boxes/unspent/byTokenId/008a94c8c76bbaa1f0a346697d1794eb31d94b37e5533af9cc0b6932bf159339?limit=100".to_string())
+    }
+
+    #[test]
+    fn process_explorer_response_sorted_is_deterministic() {
+        let boxes = vec![
+            build_test_box(3000000, 30),
+            build_test_box(1000000, 10),
+            build_test_box(2000000, 20),
+            build_test_box(4000000, 10),
+        ];
+        let box_spec = BoxSpec::new(None, Some(1000000..u64::MAX), vec![], vec![]);
+
+        let mut reversed = boxes.clone();
+        reversed.reverse();
+        let sorted = box_spec
+            .process_explorer_response_sorted(&build_explorer_response(&boxes))
+            .unwrap();
+        let sorted_reversed = box_spec
+            .process_explorer_response_sorted(&build_explorer_response(&reversed))
+            .unwrap();
+
+        assert_eq!(sorted, sorted_reversed);
+        let heights: Vec<u32> = sorted.iter().map(|b| b.creation_height).collect();
+        assert_eq!(heights, vec![10, 10, 20, 30]);
+    }
+
+    #[test]
+    fn describe_box_spec_constraints() {
+        let spec = BoxSpec::new(
+            None,
+            Some(1000000..u64::MAX),
+            vec![RegisterSpec::new(Some(SType::SLong), None)],
+            vec![None],
+        );
+        assert_eq!(
+            spec.describe_constraints(),
+            vec![
+                format!("Value: at least 1000000 and below {} nanoErgs", u64::MAX),
+                "Token 1: any token".to_string(),
+                "Register R4: of type SLong".to_string(),
+            ]
+        );
+        assert_eq!(
+            BoxSpec::new(None, None, vec![], vec![]).describe(),
+            "Any box"
+        );
+    }
+
+    #[test]
+    fn verify_box_version_matches_legacy_spec() {
+        let ergo_box = build_test_box(1500000, 0);
+        let legacy_spec = BoxSpec::new(None, Some(1000000..2000000), vec![], vec![]);
+        let current_spec = BoxSpec::new(None, Some(2000000..u64::MAX), vec![], vec![])
+            .modified_version(SpecVersion(2))
+            .with_migration(legacy_spec.clone())
+            .unwrap();
+        assert!(current_spec.verify_box(&ergo_box).is_err());
+        assert_eq!(
+            current_spec.verify_box_version(&ergo_box).unwrap(),
+            SpecVersion(1)
+        );
+        assert!(legacy_spec.with_migration(current_spec).is_err());
+    }
+
+    #[test]
+    fn verify_box_with_registered_predicate() {
+        fn above_two_ergs(b: &ErgoBox) -> bool {
+            *b.value.as_u64() > 2000000000
+        }
+        crate::register_predicate("test_above_two_ergs", above_two_ergs);
+
+        let box_spec =
+            BoxSpec::new_named_predicated(None, None, vec![], vec![], "test_above_two_ergs");
+        assert!(box_spec.verify_box(&build_test_box(3000000000, 0)).is_ok());
+        assert!(box_spec.verify_box(&build_test_box(1000000, 0)).is_err());
+
+        let unknown_spec = box_spec.modified_predicate_name(Some("unregistered".to_string()));
+        assert!(unknown_spec
+            .verify_box(&build_test_box(3000000000, 0))
+            .is_err());
+    }
+
+    #[test]
+    fn parse_register_spec_descriptors() {
+        let long_spec = RegisterSpec::parse("SLong").unwrap();
+        assert_eq!(long_spec.value_type, Some(SType::SLong));
+        assert!(long_spec.value.is_none());
+
+        let long_value_spec = RegisterSpec::parse("Long = 5").unwrap();
+        assert_eq!(long_value_spec.value, Some(Constant::from(5i64)));
+
+        let bytes_spec = RegisterSpec::parse("Coll[Byte] = 0x01ff").unwrap();
+        assert_eq!(
+            bytes_spec.value_type,
+            Some(SType::SColl(Box::new(SType::SByte)))
+        );
+        assert_eq!(bytes_spec.value, Some(Constant::from(vec![1i8, -1])));
+
+        let ints_spec = RegisterSpec::parse("Coll[SInt] = [1, 2]").unwrap();
+        assert_eq!(ints_spec.value, Some(Constant::from(vec![1i32, 2])));
+
+        assert!(RegisterSpec::parse("").unwrap().value_type.is_none());
+        assert!(RegisterSpec::parse("SFoo").is_err());
+        assert!(RegisterSpec::parse("SInt = 5000000000").is_err());
+    }
+
+    #[test]
+    fn box_spec_json_round_trip() {
+        let spec = BoxSpec::new(
+            Some("9fSgJ7BmUxBQJ454prQDQ7fQMBkXPLaAmDnimgTtjym6FYPHjAV".to_string()),
+            Some(1000000..2000000),
+            vec![
+                RegisterSpec::parse("SLong = 5").unwrap(),
+                RegisterSpec::parse("Coll[Byte]").unwrap(),
+            ],
+            vec![
+                Some(TokenSpec::new(
+                    1..2,
+                    "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1",
+                )),
+                None,
+            ],
+        )
+        .modified_timelock(Some(Timelock::RelativeToCreation(720)));
+        let json = spec.to_json().unwrap();
+        let parsed = BoxSpec::from_json(&json).unwrap();
+        assert_eq!(parsed.to_json().unwrap(), json);
+        assert_eq!(parsed.describe(), spec.describe());
+    }
+
+    #[test]
+    fn named_predicate_survives_json_round_trip() {
+        fn above_two_ergs(b: &ErgoBox) -> bool {
+            *b.value.as_u64() > 2000000000
+        }
+        crate::register_predicate("test_round_trip_above_two_ergs", above_two_ergs);
+
+        let spec = BoxSpec::new_named_predicated(
+            None,
+            None,
+            vec![],
+            vec![],
+            "test_round_trip_above_two_ergs",
+        );
+        let parsed = BoxSpec::from_json(&spec.to_json().unwrap()).unwrap();
+        assert_eq!(
+            parsed.predicate_name(),
+            Some("test_round_trip_above_two_ergs".to_string())
+        );
+        assert!(parsed.verify_box(&build_test_box(3000000000, 0)).is_ok());
+        assert!(parsed.verify_box(&build_test_box(1000000, 0)).is_err());
+
+        // An unnamed custom predicate cannot be serialized
+        let custom = BoxSpec::new_predicated(None, None, vec![], vec![], Some(above_two_ergs));
+        assert!(matches!(
+            custom.to_json(),
+            Err(HeadlessDappError::UnnamedPredicate)
+        ));
+    }
+
+    #[test]
+    fn export_to_every_target_format() {
+        let address = "9fSgJ7BmUxBQJ454prQDQ7fQMBkXPLaAmDnimgTtjym6FYPHjAV".to_string();
+        let token_id = "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1";
+        let box_spec = BoxSpec::new(
+            Some(address.clone()),
+            None,
+            vec![],
+            vec![Some(TokenSpec::new(1..2, token_id))],
+        );
+
+        let scan: serde_json::Value = serde_json::from_str(
+            &box_spec
+                .export(TargetFormat::NodeScan("spec".to_string()))
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(scan["scanName"], "spec");
+        assert_eq!(scan["trackingRule"]["predicate"], "and");
+        assert_eq!(scan["trackingRule"]["args"][1]["assetId"], token_id);
+
+        let query = box_spec.export(TargetFormat::GraphQl(10)).unwrap();
+        assert!(query.contains(&address));
+        assert!(query.contains(token_id));
+
+        let url = box_spec
+            .export(TargetFormat::ExplorerRest(
+                "https://api.ergoplatform.com/api".to_string(),
+            ))
+            .unwrap();
+        assert!(url.ends_with(&format!("byTokenId/{}?limit=100", token_id)));
+    }
+
+    #[test]
+    fn graphql_query_pushes_down_filters() {
+        let address = "9fSgJ7BmUxBQJ454prQDQ7fQMBkXPLaAmDnimgTtjym6FYPHjAV";
+        let token_id = "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1";
+        let box_spec = BoxSpec::new(
+            Some(address.to_string()),
+            None,
+            vec![RegisterSpec::parse("SLong = 5").unwrap()],
+            vec![Some(TokenSpec::new(1..2, token_id))],
+        );
+        let body: serde_json::Value =
+            serde_json::from_str(&box_spec.graphql_query(25).unwrap()).unwrap();
+        let query = body["query"].as_str().unwrap();
+        let register = Constant::from(5i64).base16_str().unwrap();
+        assert!(query.starts_with("query { boxes(spent: false, "));
+        assert!(query.contains(&format!("address: \"{}\"", address)));
+        assert!(query.contains(&format!("tokenId: \"{}\"", token_id)));
+        assert!(query.contains(&format!("registers: {{R4: \"{}\"}}", register)));
+        assert!(query.contains("take: 25)"));
+        assert!(query.contains("assets { tokenId amount }"));
+    }
+
+    #[test]
+    fn process_graphql_response_parses_string_amounts() {
+        let address = "9fSgJ7BmUxBQJ454prQDQ7fQMBkXPLaAmDnimgTtjym6FYPHjAV";
+        let token_id = "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1";
+        let matching = test_box_at(
+            2000000,
+            address,
+            vec![crate::encoding::build_token(token_id, 1).unwrap()],
+            vec![],
+            100,
+            0,
+        );
+        let other = test_box_at(2000000, address, vec![], vec![], 100, 1);
+
+        // ergo-graphql returns nanoErg values and token amounts as Strings
+        let graphql_box = |b: &ErgoBox| {
+            let mut json = serde_json::to_value(b).unwrap();
+            json["value"] = serde_json::Value::from(json["value"].to_string());
+            for asset in json["assets"].as_array_mut().unwrap() {
+                asset["amount"] = serde_json::Value::from(asset["amount"].to_string());
+            }
+            json
+        };
+        let response = serde_json::json!({
+            "data": { "boxes": [graphql_box(&matching), graphql_box(&other)] }
+        })
+        .to_string();
+        assert!(response.contains("\"value\":\"2000000\""));
+
+        let box_spec = BoxSpec::new(
+            Some(address.to_string()),
+            None,
+            vec![],
+            vec![Some(TokenSpec::new(1..2, token_id))],
+        );
+        let boxes = box_spec.process_graphql_response(&response).unwrap();
+        assert_eq!(boxes, vec![matching]);
+        assert!(box_spec
+            .process_graphql_response("{\"errors\": []}")
+            .is_err());
+    }
+
+    #[test]
+    fn node_scan_json_maps_registers() {
+        let token_id = "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1";
+        let box_spec = BoxSpec::new(
+            None,
+            Some(1000000..2000000),
+            vec![
+                RegisterSpec::new(None, Some(Constant::from(vec![1u8, 2, 3]))),
+                RegisterSpec::new(Some(SType::SLong), Some(Constant::from(5i64))),
+            ],
+            vec![Some(TokenSpec::new(1..2, token_id))],
+        );
+        let scan: serde_json::Value =
+            serde_json::from_str(&box_spec.node_scan_json("scan").unwrap()).unwrap();
+        let args = scan["trackingRule"]["args"].as_array().unwrap();
+        assert_eq!(args.len(), 2);
+        assert_eq!(args[0]["predicate"], "containsAsset");
+        assert_eq!(args[1]["register"], "R4");
+        assert_eq!(args[1]["value"], "010203");
+
+        let empty_spec = BoxSpec::new(None, None, vec![], vec![]);
+        assert!(empty_spec.node_scan_json("scan").is_err());
+    }
+
+    #[test]
+    fn verify_creation_height_range() {
+        let address = "9fSgJ7BmUxBQJ454prQDQ7fQMBkXPLaAmDnimgTtjym6FYPHjAV".to_string();
+        let ergo_box = test_box_at(1000000, &address, vec![], vec![], 500, 0);
+        let box_spec = BoxSpec::new(Some(address), None, vec![], vec![]);
+        assert!(box_spec
+            .modified_creation_height_range(Some(400..501))
+            .verify_box(&ergo_box)
+            .is_ok());
+        assert!(box_spec
+            .modified_creation_height_range(Some(501..1000))
+            .verify_box(&ergo_box)
+            .is_err());
+        let json = box_spec
+            .modified_creation_height_range(Some(501..1000))
+            .to_json()
+            .unwrap();
+        assert_eq!(
+            BoxSpec::from_json(&json).unwrap().creation_height_range(),
+            Some(501..1000)
+        );
     }
 }
@@ -1,19 +1,26 @@
-use crate::box_spec::BoxSpec;
-use crate::encoding::serialize_p2s_from_ergo_tree;
-use crate::error::Result;
-use crate::{NanoErg, P2SAddressString};
+use crate::box_spec::{BoxSpec, DEFAULT_SCAN_NAME};
+use crate::encoding::{serialize_p2s_from_ergo_tree, serialize_p2s_from_ergo_tree_for_network};
+use crate::error::{HeadlessDappError, Result};
+use crate::network::Network;
+use crate::provenance::{process_explorer_response_with_provenance, WithProvenance};
+use crate::{BlockHeight, NanoErg, P2SAddressString};
 use ergo_lib::chain::transaction::DataInput;
 use ergo_lib::chain::transaction::UnsignedInput;
-use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, ErgoBoxCandidate};
 use ergo_lib::ergotree_ir::chain::token::Token;
 use ergo_lib::ergotree_ir::mir::constant::Constant;
 
 /// A trait which represents an `ErgoBox` wrapped in an overarching struct.
 pub trait WrappedBox {
-    fn get_box(&self) -> ErgoBox;
+    /// Returns a reference to the wrapped `ErgoBox` without cloning it
+    fn get_box_ref(&self) -> &ErgoBox;
+    /// Returns a clone of the wrapped `ErgoBox`
+    fn get_box(&self) -> ErgoBox {
+        self.get_box_ref().clone()
+    }
     /// Converts the `WrappedBox` into a `DataInput`
     fn as_data_input(&self) -> DataInput {
-        self.get_box().box_id().into()
+        self.get_box_ref().box_id().into()
     }
     /// Converts the `WrappedBox` into an `UnsignedInput`
     fn as_unsigned_input(&self) -> UnsignedInput {
@@ -21,41 +28,78 @@ pub trait WrappedBox {
     }
     /// Returns the Box ID of the wrapped `ErgoBox` as a base16 String
     fn box_id(&self) -> String {
-        self.get_box().box_id().into()
+        self.get_box_ref().box_id().into()
     }
     /// Returns the amount of nanoErgs held in the wrapped `ErgoBox` as u64
     fn nano_ergs(&self) -> NanoErg {
-        self.get_box().value.as_u64().clone()
+        self.get_box_ref().value.as_u64().clone()
     }
     // Returns the P2S Address of wrapped `ErgoBox` as a String
     fn p2s_address(&self) -> P2SAddressString {
-        serialize_p2s_from_ergo_tree(self.get_box().ergo_tree)
+        serialize_p2s_from_ergo_tree(self.get_box_ref().ergo_tree.clone())
+    }
+    /// Returns the P2S Address of wrapped `ErgoBox` as a String for the
+    /// provided `Network`
+    fn p2s_address_for_network(&self, network: Network) -> P2SAddressString {
+        serialize_p2s_from_ergo_tree_for_network(self.get_box_ref().ergo_tree.clone(), network)
     }
     /// Returns the registers of the wrapped `ErgoBox` as an ordered Vector
     /// of `Constant`s. First element is R4, second element is R5, etc.
     fn registers(&self) -> Vec<Constant> {
-        self.get_box()
+        self.get_box_ref()
             .additional_registers
             .get_ordered_values()
             .clone()
     }
+    /// Returns the register at `index` of the ordered registers (where 0
+    /// is R4), erroring if the wrapped `ErgoBox` holds fewer registers.
+    /// Accessors of specified boxes use this rather than indexing into
+    /// `registers()` so a box which does not hold the registers its spec
+    /// promised errors rather than panics.
+    fn checked_register(&self, index: usize) -> Result<Constant> {
+        self.get_box_ref()
+            .additional_registers
+            .get_ordered_values()
+            .get(index)
+            .cloned()
+            .ok_or_else(|| HeadlessDappError::MissingRegister(self.box_id(), index + 4))
+    }
     /// Returns the `Token`s inside of the wrapped `ErgoBox`
     fn tokens(&self) -> Vec<Token> {
-        self.get_box().tokens
+        self.get_box_ref().tokens.clone()
+    }
+    /// Returns a slice of the `Token`s inside of the wrapped `ErgoBox`
+    /// without cloning them
+    fn tokens_ref(&self) -> &[Token] {
+        &self.get_box_ref().tokens
     }
     /// Returns the creation height of the wrapped `ErgoBox`
     fn creation_height(&self) -> u64 {
-        self.get_box().creation_height.clone() as u64
+        self.get_box_ref().creation_height.clone() as u64
+    }
+    /// Returns the wrapped `ErgoBox` as JSON in the canonical form used by
+    /// the Ergo node (rather than the explorer), as expected by node
+    /// endpoints such as `/transactions/check` and `/script`
+    fn to_node_json(&self) -> Result<String> {
+        serde_json::to_string(self.get_box_ref())
+            .map_err(|e| HeadlessDappError::Other(e.to_string()))
     }
 }
 
+/// Parses an `ErgoBox` from JSON in the canonical form used by the
+/// Ergo node
+pub fn ergo_box_from_node_json(node_json: &str) -> Result<ErgoBox> {
+    serde_json::from_str(node_json)
+        .map_err(|e| HeadlessDappError::Other(format!("Failed to parse node box JSON: {}", e)))
+}
+
 pub trait SpecifiedBox: WrappedBox {
     // Associated fn which returns the `BoxSpec` for said `SpecifiedBox`
     fn box_spec() -> BoxSpec;
 
     // Acquire UTXO-set scan JSON from the `BoxSpec`
-    fn get_utxo_scan_json_string() -> String {
-        Self::box_spec().utxo_scan_json()
+    fn get_utxo_scan_json_string() -> Result<String> {
+        Self::box_spec().node_scan_json(DEFAULT_SCAN_NAME)
     }
 
     /// Verify that a provided `ErgoBox` matches the `BoxSpec`
@@ -64,6 +108,19 @@ pub trait SpecifiedBox: WrappedBox {
         Self::box_spec().verify_box(ergo_box)
     }
 
+    /// Verify that a provided `ErgoBoxCandidate` matches the `BoxSpec`
+    /// tied to your `SpecifiedBox`, ie. before outputting it in a tx
+    fn verify_candidate(candidate: &ErgoBoxCandidate) -> Result<()> {
+        Self::box_spec().verify_candidate(candidate)
+    }
+
+    /// Checks that the wrapped `ErgoBox` is spendable at the
+    /// `current_height` according to the `Timelock` declared on the
+    /// `BoxSpec` tied to your `SpecifiedBox`
+    fn check_timelock(&self, current_height: BlockHeight) -> Result<()> {
+        Self::box_spec().check_timelock(self.get_box_ref(), current_height)
+    }
+
     /// Generates a URL for the Ergo Explorer Backend API
     /// to find boxes which may match your `BoxSpec`. This method uses
     /// the `explorer_api_url` you provide as input which
@@ -74,6 +131,53 @@ pub trait SpecifiedBox: WrappedBox {
     fn explorer_endpoint(explorer_api_url: &str) -> Result<String> {
         Self::box_spec().explorer_endpoint(explorer_api_url)
     }
+
+    /// Generates a URL for the Ergo Explorer Backend API of the provided
+    /// `Network` to find boxes which may match your `BoxSpec`.
+    fn explorer_endpoint_for_network(network: Network) -> Result<String> {
+        Self::box_spec().explorer_endpoint_for_network(network)
+    }
+
+    /// Generates a URL for the Ergo Explorer Backend API which is used
+    /// with `process_count_response()` to cheaply count the live boxes
+    /// which may match your `BoxSpec`.
+    fn count_endpoint(explorer_api_url: &str) -> Result<String> {
+        Self::box_spec().count_endpoint(explorer_api_url)
+    }
+
+    /// Acquires the number of boxes which may match your `BoxSpec` from
+    /// the response of the endpoint generated by `count_endpoint()`.
+    fn process_count_response(explorer_response_body: &str) -> Result<u64> {
+        Self::box_spec().process_count_response(explorer_response_body)
+    }
+}
+
+/// A dyn-safe companion trait to `SpecifiedBox` which is automatically
+/// implemented for every `SpecifiedBox`. This allows heterogeneous
+/// collections of protocol boxes (ie. all inputs of an Action) to be stored
+/// as `Vec<Box<dyn DynSpecifiedBox>>` and iterated uniformly.
+pub trait DynSpecifiedBox: WrappedBox {
+    /// Returns the `BoxSpec` of the underlying `SpecifiedBox`
+    fn spec(&self) -> BoxSpec;
+    /// Verifies the wrapped `ErgoBox` against the `BoxSpec` of the
+    /// underlying `SpecifiedBox`
+    fn verify(&self) -> Result<()>;
+    /// Returns the type name of the underlying `SpecifiedBox` struct
+    fn spec_name(&self) -> &'static str;
+}
+
+impl<T: SpecifiedBox> DynSpecifiedBox for T {
+    fn spec(&self) -> BoxSpec {
+        T::box_spec()
+    }
+
+    fn verify(&self) -> Result<()> {
+        T::box_spec().verify_box(self.get_box_ref())
+    }
+
+    fn spec_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
 }
 
 /// A trait which is implemented via deriving the procedural macro `SpecBox`.
@@ -103,4 +207,172 @@ pub trait ExplorerFindable: SpecifiedBox {
     ) -> Result<Vec<Self>>
     where
         Self: Sized;
+
+    /// Creates a new instance of your `SpecifiedBox` struct from an
+    /// `ErgoBox` after verifying it against the default `BoxSpec`.
+    fn from_ergo_box(ergo_box: &ErgoBox) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Creates a new instance of your `SpecifiedBox` struct from the JSON
+    /// of a box in the canonical form used by the Ergo node, after
+    /// verifying it against the default `BoxSpec`.
+    fn from_node_json(node_json: &str) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Self::from_ergo_box(&ergo_box_from_node_json(node_json)?)
+    }
+
+    /// Same as `process_explorer_response()` but each instance carries the
+    /// `BoxProvenance` returned by the explorer (inclusion height and
+    /// spending Transaction ID).
+    fn process_explorer_response_with_provenance(
+        explorer_response_body: &str,
+    ) -> Result<Vec<WithProvenance<Self>>>
+    where
+        Self: Sized,
+    {
+        process_explorer_response_with_provenance(&Self::box_spec(), explorer_response_body)?
+            .into_iter()
+            .map(|(b, provenance)| Ok(WithProvenance::new(Self::from_ergo_box(&b)?, provenance)))
+            .collect()
+    }
+
+    /// Same as `process_explorer_response()` but verifies the returned
+    /// `ErgoBox`es against the `BoxSpec` in parallel using all available
+    /// cores. Only available outside of WASM with the `parallel` feature.
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    fn process_explorer_response_parallel(explorer_response_body: &str) -> Result<Vec<Self>>
+    where
+        Self: Sized,
+    {
+        let boxes = Self::box_spec().process_explorer_response_parallel(explorer_response_body)?;
+        boxes.iter().map(|b| Self::from_ergo_box(b)).collect()
+    }
+}
+
+/// A source of a `BoxSpec` which is held at runtime. This allows specs to
+/// depend on runtime parameters (ie. a user's address or a token id which
+/// is only known after a protocol is bootstrapped).
+pub trait SpecSource {
+    /// Returns the `BoxSpec` defined by the source
+    fn box_spec(&self) -> BoxSpec;
+}
+
+impl SpecSource for BoxSpec {
+    fn box_spec(&self) -> BoxSpec {
+        self.clone()
+    }
+}
+
+/// A wrapped `ErgoBox` which has been verified against a `BoxSpec`
+/// acquired from a runtime `SpecSource`. This is the instance-level
+/// counterpart to deriving `SpecBox` on a struct, for boxes whose spec
+/// cannot be defined by an associated function.
+#[derive(Clone, Debug)]
+pub struct Specified<S: SpecSource> {
+    ergo_box: ErgoBox,
+    source: S,
+}
+
+impl<S: SpecSource> Specified<S> {
+    /// Verifies the `ErgoBox` against the spec of the `source` and wraps it
+    pub fn new(ergo_box: &ErgoBox, source: S) -> Result<Specified<S>> {
+        source.box_spec().verify_box(ergo_box)?;
+        Ok(Specified {
+            ergo_box: ergo_box.clone(),
+            source: source,
+        })
+    }
+
+    /// Parses the JSON of a box in the canonical form used by the Ergo
+    /// node, verifying it against the spec of the `source`
+    pub fn from_node_json(node_json: &str, source: S) -> Result<Specified<S>> {
+        Specified::new(&ergo_box_from_node_json(node_json)?, source)
+    }
+
+    /// Returns the `SpecSource` which the box was verified against
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Generates a URL for the Ergo Explorer Backend API to find boxes
+    /// which may match the spec of the `source`.
+    pub fn explorer_endpoint(source: &S, explorer_api_url: &str) -> Result<String> {
+        source.box_spec().explorer_endpoint(explorer_api_url)
+    }
+
+    /// Using the response JSON (as a String) from the Ergo Explorer API
+    /// endpoint generated by the `explorer_endpoint()` method,
+    /// filter all returned `ErgoBox`es against the spec of the `source`
+    /// and wrap them as `Specified` boxes.
+    pub fn process_explorer_response(
+        explorer_response_body: &str,
+        source: &S,
+    ) -> Result<Vec<Specified<S>>>
+    where
+        S: Clone,
+    {
+        let boxes = source
+            .box_spec()
+            .process_explorer_response(explorer_response_body)?;
+        Ok(boxes
+            .into_iter()
+            .map(|b| Specified {
+                ergo_box: b,
+                source: source.clone(),
+            })
+            .collect())
+    }
+}
+
+impl<S: SpecSource> WrappedBox for Specified<S> {
+    fn get_box_ref(&self) -> &ErgoBox {
+        &self.ergo_box
+    }
+}
+
+impl<S: SpecSource> DynSpecifiedBox for Specified<S> {
+    fn spec(&self) -> BoxSpec {
+        self.source.box_spec()
+    }
+
+    fn verify(&self) -> Result<()> {
+        self.source.box_spec().verify_box(&self.ergo_box)
+    }
+
+    fn spec_name(&self) -> &'static str {
+        std::any::type_name::<S>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output_builders::MINER_FEE_P2S_ADDRESS;
+    use crate::test_utils::test_box;
+
+    #[test]
+    fn node_json_round_trip() {
+        let ergo_box = test_box(
+            1000000,
+            MINER_FEE_P2S_ADDRESS,
+            vec![],
+            vec![Constant::from(5i64)],
+        );
+        let spec = BoxSpec::new(None, Some(1000000..u64::MAX), vec![], vec![]);
+        let specified = Specified::new(&ergo_box, spec.clone()).unwrap();
+
+        let node_json = specified.to_node_json().unwrap();
+        assert!(node_json.contains("\"ergoTree\""));
+        let parsed = Specified::from_node_json(&node_json, spec).unwrap();
+        assert_eq!(parsed.get_box(), ergo_box);
+
+        assert_eq!(parsed.checked_register(0).unwrap(), Constant::from(5i64));
+        assert!(matches!(
+            parsed.checked_register(1),
+            Err(HeadlessDappError::MissingRegister(_, 5))
+        ));
+    }
 }
@@ -0,0 +1,55 @@
+/// This file holds the `CancellationToken` which allows cooperative
+/// cancellation of long running lookups (ie. paginated explorer scans)
+/// from another thread or task, such as when a user navigates away in an
+/// interactive app embedding the framework.
+use crate::error::{HeadlessDappError, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A token which is shared between the owner of a lookup and the lookup
+/// itself. Clones of the token share the same cancellation state.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a new `CancellationToken` which is not cancelled
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Cancels all lookups using the token (or any of its clones)
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether the token has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Errors with `HeadlessDappError::Cancelled` if the token has been
+    /// cancelled
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(HeadlessDappError::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_cancellation() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(clone.check().is_ok());
+        token.cancel();
+        assert!(clone.is_cancelled());
+        assert!(clone.check().is_err());
+    }
+}
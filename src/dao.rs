@@ -0,0 +1,226 @@
+/// This file holds the specified boxes of DAO governance protocols (ie.
+/// Paideia), namely proposal boxes and vote boxes. The token ids which
+/// identify the boxes of a given DAO are provided at runtime, as every DAO
+/// mints its own.
+/// A proposal box holds the proposal token, with the following registers:
+/// R4: `Int` id of the proposal
+/// R5: `Int` block height at which voting ends
+/// R6: `Coll[Long]` vote tally of every option of the proposal
+/// A vote box holds the vote token (the amount being the voting power),
+/// with the following registers:
+/// R4: `Int` id of the proposal being voted on
+/// R5: `Int` index of the option being voted for
+/// A vote box holds no other registers, which distinguishes it from a
+/// proposal box should a DAO use the same token for both.
+use crate::box_spec::{BoxSpec, RegisterSpec, TokenSpec};
+use crate::box_traits::{SpecSource, Specified, WrappedBox};
+use crate::encoding::{unwrap_int, unwrap_long_coll};
+use crate::error::{HeadlessDappError, Result};
+use crate::{BlockHeight, TokenID};
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib::ergotree_ir::types::stype::SType;
+
+/// A proposal box of the DAO whose `DaoProposals` it was found with
+pub type DaoProposalBox = Specified<DaoProposals>;
+/// A vote box of the DAO whose `DaoVotes` it was found with
+pub type DaoVoteBox = Specified<DaoVotes>;
+
+/// The proposals of a DAO, identified by the DAO's proposal token
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DaoProposals {
+    proposal_token_id: TokenID,
+}
+
+impl DaoProposals {
+    /// Create a new `DaoProposals` for the DAO's `proposal_token_id`
+    pub fn new(proposal_token_id: &str) -> DaoProposals {
+        DaoProposals {
+            proposal_token_id: proposal_token_id.to_string(),
+        }
+    }
+
+    /// Returns the token id which identifies proposal boxes
+    pub fn proposal_token_id(&self) -> TokenID {
+        self.proposal_token_id.clone()
+    }
+}
+
+impl SpecSource for DaoProposals {
+    /// A box spec for a proposal box holding the proposal token
+    fn box_spec(&self) -> BoxSpec {
+        let registers = vec![
+            RegisterSpec::new(Some(SType::SInt), None),
+            RegisterSpec::new(Some(SType::SInt), None),
+            RegisterSpec::new(Some(SType::SColl(Box::new(SType::SLong))), None),
+        ];
+        let tokens = vec![Some(TokenSpec::new(1..u64::MAX, &self.proposal_token_id))];
+        BoxSpec::new(None, None, registers, tokens)
+    }
+}
+
+/// The votes cast in a DAO, identified by the DAO's vote token
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DaoVotes {
+    vote_token_id: TokenID,
+}
+
+impl DaoVotes {
+    /// Create a new `DaoVotes` for the DAO's `vote_token_id`
+    pub fn new(vote_token_id: &str) -> DaoVotes {
+        DaoVotes {
+            vote_token_id: vote_token_id.to_string(),
+        }
+    }
+
+    /// Returns the token id which identifies vote boxes
+    pub fn vote_token_id(&self) -> TokenID {
+        self.vote_token_id.clone()
+    }
+}
+
+impl SpecSource for DaoVotes {
+    /// A box spec for a vote box holding the vote token and exactly the
+    /// two vote registers
+    fn box_spec(&self) -> BoxSpec {
+        let registers = vec![
+            RegisterSpec::new(Some(SType::SInt), None),
+            RegisterSpec::new(Some(SType::SInt), None),
+        ];
+        let tokens = vec![Some(TokenSpec::new(1..u64::MAX, &self.vote_token_id))];
+        BoxSpec::new_predicated(None, None, registers, tokens, Some(has_only_vote_registers))
+    }
+}
+
+/// Checks that the box holds no registers beyond R4 and R5, so that
+/// proposal boxes are never mistaken for vote boxes
+fn has_only_vote_registers(ergo_box: &ErgoBox) -> bool {
+    ergo_box.additional_registers.get_ordered_values().len() == 2
+}
+
+/// Rust DaoProposalBox Methods
+impl DaoProposalBox {
+    /// Returns the id of the proposal from R4
+    pub fn proposal_id(&self) -> Result<i32> {
+        Ok(unwrap_int(&self.checked_register(0)?)?)
+    }
+
+    /// Returns the block height at which voting ends from R5
+    pub fn end_height(&self) -> Result<BlockHeight> {
+        Ok(unwrap_int(&self.checked_register(1)?)? as BlockHeight)
+    }
+
+    /// Returns the vote tally of every option from R6
+    pub fn tallies(&self) -> Result<Vec<i64>> {
+        Ok(unwrap_long_coll(&self.checked_register(2)?)?)
+    }
+
+    /// Returns the total number of votes cast across all options
+    pub fn total_votes(&self) -> Result<i64> {
+        self.tallies()?
+            .iter()
+            .try_fold(0i64, |acc, t| acc.checked_add(*t))
+            .ok_or(HeadlessDappError::ArithmeticOverflow)
+    }
+
+    /// Returns the index of the option with the most votes. If multiple
+    /// options are tied, the lowest index is returned.
+    pub fn leading_option(&self) -> Result<Option<usize>> {
+        let tallies = self.tallies()?;
+        let mut leading: Option<usize> = None;
+        for (i, tally) in tallies.iter().enumerate() {
+            match leading {
+                Some(l) if tallies[l] >= *tally => (),
+                _ => leading = Some(i),
+            }
+        }
+        Ok(leading)
+    }
+
+    /// Whether voting is still open at the `current_height`
+    pub fn is_active(&self, current_height: BlockHeight) -> Result<bool> {
+        Ok(current_height < self.end_height()?)
+    }
+}
+
+/// Rust DaoVoteBox Methods
+impl DaoVoteBox {
+    /// Returns the id of the proposal being voted on from R4
+    pub fn proposal_id(&self) -> Result<i32> {
+        Ok(unwrap_int(&self.checked_register(0)?)?)
+    }
+
+    /// Returns the index of the option being voted for from R5
+    pub fn option(&self) -> Result<i32> {
+        Ok(unwrap_int(&self.checked_register(1)?)?)
+    }
+
+    /// Returns the voting power of the vote, ie. the amount of vote tokens
+    pub fn voting_power(&self) -> u64 {
+        self.tokens()[0].amount.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::build_token;
+    use crate::output_builders::MINER_FEE_P2S_ADDRESS;
+    use crate::test_utils::test_box;
+    use ergo_lib::ergotree_ir::mir::constant::Constant;
+
+    #[test]
+    fn reads_proposal_registers() {
+        let token_id = "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1";
+        let registers: Vec<Constant> = vec![7i32.into(), 500i32.into(), vec![10i64, 30, 30].into()];
+        let ergo_box = test_box(
+            1000000,
+            MINER_FEE_P2S_ADDRESS,
+            vec![build_token(token_id, 1).unwrap()],
+            registers,
+        );
+
+        let proposal = DaoProposalBox::new(&ergo_box, DaoProposals::new(token_id)).unwrap();
+        assert_eq!(proposal.proposal_id().unwrap(), 7);
+        assert_eq!(proposal.total_votes().unwrap(), 70);
+        assert_eq!(proposal.leading_option().unwrap(), Some(1));
+        assert!(proposal.is_active(499).unwrap());
+        assert!(!proposal.is_active(500).unwrap());
+        assert!(DaoVoteBox::new(&ergo_box, DaoVotes::new(token_id)).is_err());
+    }
+
+    #[test]
+    fn reads_vote_registers() {
+        let token_id = "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1";
+        let registers: Vec<Constant> = vec![7i32.into(), 2i32.into()];
+        let ergo_box = test_box(
+            1000000,
+            MINER_FEE_P2S_ADDRESS,
+            vec![build_token(token_id, 25).unwrap()],
+            registers,
+        );
+
+        let vote = DaoVoteBox::new(&ergo_box, DaoVotes::new(token_id)).unwrap();
+        assert_eq!(vote.proposal_id().unwrap(), 7);
+        assert_eq!(vote.option().unwrap(), 2);
+        assert_eq!(vote.voting_power(), 25);
+        assert!(DaoProposalBox::new(&ergo_box, DaoProposals::new(token_id)).is_err());
+    }
+
+    #[test]
+    fn total_votes_overflow_is_an_error() {
+        let token_id = "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1";
+        let registers: Vec<Constant> = vec![7i32.into(), 500i32.into(), vec![i64::MAX, 1].into()];
+        let ergo_box = test_box(
+            1000000,
+            MINER_FEE_P2S_ADDRESS,
+            vec![build_token(token_id, 1).unwrap()],
+            registers,
+        );
+
+        let proposal = DaoProposalBox::new(&ergo_box, DaoProposals::new(token_id)).unwrap();
+        assert!(matches!(
+            proposal.total_votes(),
+            Err(HeadlessDappError::ArithmeticOverflow)
+        ));
+    }
+}
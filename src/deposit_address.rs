@@ -0,0 +1,124 @@
+/// This file holds `DepositTemplate` which derives a unique P2S deposit
+/// contract for every user from a template contract, by substituting a
+/// user-specific constant (ie. a user id or public key) into the
+/// template's ErgoTree. The matching `BoxSpec` of every derived contract is
+/// also provided, so that deposits can be attributed to users.
+use crate::box_spec::BoxSpec;
+use crate::encoding::{deserialize_p2s_to_ergo_tree, serialize_p2s_from_ergo_tree};
+use crate::error::{HeadlessDappError, Result};
+use crate::P2SAddressString;
+use ergo_lib::ergotree_ir::ergo_tree::ErgoTree;
+use ergo_lib::ergotree_ir::mir::constant::Constant;
+
+/// A template deposit contract from which per-user deposit contracts are
+/// derived
+#[derive(Clone)]
+pub struct DepositTemplate {
+    ergo_tree: ErgoTree,
+    user_constant_index: usize,
+    base_spec: BoxSpec,
+}
+
+impl DepositTemplate {
+    /// Create a new `DepositTemplate` from the P2S address of the template
+    /// contract (compiled with segregated constants), where the constant
+    /// at `user_constant_index` is substituted for every user.
+    pub fn new(
+        template_address: &P2SAddressString,
+        user_constant_index: usize,
+    ) -> Result<DepositTemplate> {
+        let ergo_tree = deserialize_p2s_to_ergo_tree(template_address.clone())?;
+        Ok(DepositTemplate {
+            ergo_tree,
+            user_constant_index,
+            base_spec: BoxSpec::new(None, None, vec![], vec![]),
+        })
+    }
+
+    /// Sets the `BoxSpec` which the derived `BoxSpec`s are based on (ie.
+    /// to require a minimum deposit value). The address of the base spec
+    /// is replaced by the derived address.
+    pub fn with_base_spec(mut self, base_spec: BoxSpec) -> Self {
+        self.base_spec = base_spec;
+        self
+    }
+
+    /// Derives the ErgoTree of the deposit contract of a user
+    pub fn derive_ergo_tree(&self, user_constant: Constant) -> Result<ErgoTree> {
+        self.ergo_tree
+            .clone()
+            .with_constant(self.user_constant_index, user_constant)
+            .map_err(|e| HeadlessDappError::InvalidDepositTemplate(format!("{:?}", e)))
+    }
+
+    /// Derives the P2S address of the deposit contract of a user
+    pub fn derive_address(&self, user_constant: Constant) -> Result<P2SAddressString> {
+        Ok(serialize_p2s_from_ergo_tree(
+            self.derive_ergo_tree(user_constant)?,
+        ))
+    }
+
+    /// Derives the `BoxSpec` matching deposits made to the deposit
+    /// contract of a user
+    pub fn derive_box_spec(&self, user_constant: Constant) -> Result<BoxSpec> {
+        let address = self.derive_address(user_constant)?;
+        Ok(self.base_spec.modified_address(Some(address)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_box;
+    use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+
+    /// `sigmaProp(SELF.R4[Long].get == 5L)` compiled with the `5L`
+    /// segregated as constant 0
+    const TEMPLATE_TREE: &str = "1001050ad193e4c6a704057300";
+
+    fn template() -> DepositTemplate {
+        let ergo_tree =
+            ErgoTree::sigma_parse_bytes(&base16::decode(TEMPLATE_TREE).unwrap()).unwrap();
+        DepositTemplate::new(&serialize_p2s_from_ergo_tree(ergo_tree), 0).unwrap()
+    }
+
+    #[test]
+    fn derives_unique_addresses_per_user() {
+        let template = template();
+        let alice = template.derive_address(Constant::from(1i64)).unwrap();
+        let bob = template.derive_address(Constant::from(2i64)).unwrap();
+        assert_ne!(alice, bob);
+        assert_eq!(
+            alice,
+            template.derive_address(Constant::from(1i64)).unwrap()
+        );
+    }
+
+    #[test]
+    fn out_of_range_constant_index_is_an_error() {
+        let ergo_tree =
+            ErgoTree::sigma_parse_bytes(&base16::decode(TEMPLATE_TREE).unwrap()).unwrap();
+        let template = DepositTemplate::new(&serialize_p2s_from_ergo_tree(ergo_tree), 1).unwrap();
+        assert!(template.derive_address(Constant::from(1i64)).is_err());
+    }
+
+    #[test]
+    fn derived_box_spec_matches_only_the_users_deposits() {
+        let template =
+            template().with_base_spec(BoxSpec::new(None, Some(1000000..u64::MAX), vec![], vec![]));
+        let alice = template.derive_address(Constant::from(1i64)).unwrap();
+        let bob = template.derive_address(Constant::from(2i64)).unwrap();
+        let alice_spec = template.derive_box_spec(Constant::from(1i64)).unwrap();
+
+        assert!(alice_spec
+            .verify_box(&test_box(2000000, &alice, vec![], vec![]))
+            .is_ok());
+        assert!(alice_spec
+            .verify_box(&test_box(2000000, &bob, vec![], vec![]))
+            .is_err());
+        // The base spec's value range is kept
+        assert!(alice_spec
+            .verify_box(&test_box(100000, &alice, vec![], vec![]))
+            .is_err());
+    }
+}
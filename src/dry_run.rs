@@ -0,0 +1,131 @@
+/// This file holds support for running a headless dApp in dry-run mode.
+/// In dry-run mode the unsigned transactions produced by Actions are not
+/// submitted, but are instead recorded and exported in the Ergo
+/// Transaction Assembler format for staging environments and
+/// human-in-the-loop operation.
+use crate::encoding::address_string_to_ergo_tree;
+use crate::error::{HeadlessDappError, Result};
+use crate::output_builders::MINER_FEE_P2S_ADDRESS;
+use crate::tx_assembler::{AssemblerSpec, TxAssemblerSpecBuilder};
+use crate::NanoErg;
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use serde::Serialize;
+
+/// Whether produced transactions are submitted or only exported. Followed
+/// by `async_api::sign_and_submit_with_mode()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExecutionMode {
+    /// Transactions are submitted
+    #[default]
+    Live,
+    /// Transactions are only recorded and exported
+    DryRun,
+    /// No transactions may be produced at all
+    ReadOnly,
+}
+
+impl ExecutionMode {
+    /// Errors if the mode does not allow transactions to be built
+    pub fn check_can_transact(&self) -> Result<()> {
+        match self {
+            ExecutionMode::ReadOnly => Err(HeadlessDappError::ReadOnlyViolation),
+            ExecutionMode::Live | ExecutionMode::DryRun => Ok(()),
+        }
+    }
+}
+
+/// A single unsigned transaction produced by an Action in dry-run mode
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DryRunExport {
+    /// The name of the Action which produced the transaction
+    pub action_name: String,
+    /// The transaction in the Ergo Transaction Assembler format
+    pub assembler_spec: AssemblerSpec,
+}
+
+/// A log of the unsigned transactions produced in dry-run mode
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct DryRunLog {
+    exports: Vec<DryRunExport>,
+}
+
+impl DryRunLog {
+    /// Create a new empty `DryRunLog`
+    pub fn new() -> DryRunLog {
+        DryRunLog::default()
+    }
+
+    /// Records an unsigned transaction produced by the named Action
+    pub fn record(
+        &mut self,
+        action_name: &str,
+        unsigned_tx: UnsignedTransaction,
+        transaction_fee: NanoErg,
+    ) -> &DryRunExport {
+        #[cfg(feature = "tracing")]
+        tracing::info!(action = action_name, "dry-run transaction recorded");
+        let assembler_spec =
+            TxAssemblerSpecBuilder::new(unsigned_tx).build_assembler_spec_struct(transaction_fee);
+        self.exports.push(DryRunExport {
+            action_name: action_name.to_string(),
+            assembler_spec,
+        });
+        // Safe as an export was just pushed
+        self.exports.last().unwrap()
+    }
+
+    /// Same as `record()` but reads the transaction fee from the miner
+    /// fee outputs of the `unsigned_tx`
+    pub fn record_tx(
+        &mut self,
+        action_name: &str,
+        unsigned_tx: UnsignedTransaction,
+    ) -> Result<&DryRunExport> {
+        let miner_fee_tree = address_string_to_ergo_tree(&MINER_FEE_P2S_ADDRESS.to_string())?;
+        let transaction_fee = unsigned_tx
+            .output_candidates
+            .as_vec()
+            .iter()
+            .filter(|o| o.ergo_tree == miner_fee_tree)
+            .try_fold(0u64, |acc, o| acc.checked_add(*o.value.as_u64()))
+            .ok_or(HeadlessDappError::ArithmeticOverflow)?;
+        Ok(self.record(action_name, unsigned_tx, transaction_fee))
+    }
+
+    /// The recorded exports in the order they were recorded
+    pub fn exports(&self) -> &Vec<DryRunExport> {
+        &self.exports
+    }
+
+    /// Serializes all of the recorded exports into a pretty-printed
+    /// JSON `String`
+    pub fn to_json(&self) -> String {
+        // Serializing a struct made of Strings/integers/maps cannot fail
+        serde_json::to_string_pretty(&self.exports).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output_builders::TxFeeBox;
+    use crate::test_utils::{test_box, TEST_ADDRESS};
+    use crate::tx_creation::{create_candidate, new_unsigned_tx};
+
+    #[test]
+    fn records_tx_with_its_miner_fee() {
+        let input_box = test_box(3000000, TEST_ADDRESS, vec![], vec![]);
+        let outputs = vec![
+            create_candidate(2000000, &TEST_ADDRESS.to_string(), &vec![], &vec![], 10).unwrap(),
+            TxFeeBox::output_candidate(1000000, 10).unwrap(),
+        ];
+        let unsigned_tx = new_unsigned_tx(vec![input_box.into()], vec![], outputs).unwrap();
+
+        let mut log = DryRunLog::new();
+        let export = log.record_tx("pay", unsigned_tx).unwrap();
+        assert_eq!(export.action_name, "pay");
+        assert_eq!(export.assembler_spec.fee, 1000000);
+        assert_eq!(log.exports().len(), 1);
+        assert!(log.to_json().contains("\"action_name\": \"pay\""));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::network::Network;
 use crate::{ErgoAddressString, P2SAddressString};
 use base16;
 use blake2b_simd::Params;
@@ -12,10 +13,14 @@ use ergo_lib::ergotree_ir::mir::constant::Constant;
 /// to the oracle core.
 use ergo_lib::ergotree_ir::mir::constant::TryExtractFrom;
 use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+use ergo_lib::ergotree_ir::types::stype::SType;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::convert::TryFrom;
 use std::fmt::{Debug, Display};
 use std::str;
 use thiserror::Error;
+use wasm_bindgen::prelude::*;
 
 pub type Result<T> = std::result::Result<T, EncodingError<String>>;
 
@@ -41,6 +46,19 @@ pub fn string_to_blake2b_hash(b: String) -> Result<String> {
     Ok(a)
 }
 
+/// Computes the hex-encoded blake2b256 hash of the template of an
+/// `ErgoTree` (the tree with its constants segregated out). Contracts
+/// which only differ in their constants (ie. per-user parameterized P2S
+/// contracts) share the same template hash.
+pub fn ergo_tree_template_hash(ergo_tree: &ErgoTree) -> Result<String> {
+    let template_bytes = ergo_tree
+        .template_bytes()
+        .map_err(|e| EncodingError::FailedToSerialize(format!("{:?}", e)))?;
+    let mut params = Params::new();
+    params.hash_length(32);
+    Ok(params.hash(&template_bytes).to_hex().to_string())
+}
+
 /// Serialize a `String` value into a signed hex-encoded byte string
 /// and then convert it into a `Constant` to be used in registers.
 pub fn serialize_string(s: &String) -> Constant {
@@ -48,6 +66,85 @@ pub fn serialize_string(s: &String) -> Constant {
     b.into()
 }
 
+/// The convention used to store a `String` in a register. Deployed
+/// contracts differ in the encoding they expect, and so the encoding is
+/// selected per protocol (see `ProtocolConfig::string_encoding`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StringEncoding {
+    /// The UTF-8 bytes of the String as a `Coll[Byte]`
+    #[default]
+    Utf8,
+    /// The UTF-8 bytes of the String as a `Coll[Byte]`, prefixed by their
+    /// length encoded as a VLQ (as in the sigma serialization of strings)
+    LengthPrefixedUtf8,
+}
+
+/// Serialize a `String` value into a `Constant` to be used in registers
+/// using the provided `StringEncoding`
+pub fn serialize_string_with(s: &str, encoding: StringEncoding) -> Constant {
+    let utf8_bytes = s.as_bytes().to_vec();
+    let bytes = match encoding {
+        StringEncoding::Utf8 => utf8_bytes,
+        StringEncoding::LengthPrefixedUtf8 => {
+            let mut bytes = vlq_encode(utf8_bytes.len() as u64);
+            bytes.extend(utf8_bytes);
+            bytes
+        }
+    };
+    convert_to_signed_bytes(&bytes).into()
+}
+
+/// Unwrap a String which is inside of a `Constant` acquired from a register
+/// of an `ErgoBox` using the provided `StringEncoding`
+pub fn unwrap_string_with(c: &Constant, encoding: StringEncoding) -> Result<String> {
+    let fail = || EncodingError::FailedToDeserialize(c.base16_str().unwrap());
+    let bytes = match Vec::<i8>::try_extract_from(c.clone()) {
+        Ok(ba) => convert_to_unsigned_bytes(&ba),
+        _ => return Err(EncodingError::FailedToUnwrap(c.base16_str().unwrap())),
+    };
+    let utf8_bytes = match encoding {
+        StringEncoding::Utf8 => &bytes[..],
+        StringEncoding::LengthPrefixedUtf8 => {
+            let (len, prefix_len) = vlq_decode(&bytes).ok_or_else(fail)?;
+            let utf8_bytes = &bytes[prefix_len..];
+            if utf8_bytes.len() as u64 != len {
+                return Err(fail());
+            }
+            utf8_bytes
+        }
+    };
+    Ok(str::from_utf8(utf8_bytes).map_err(|_| fail())?.to_string())
+}
+
+/// Encodes an unsigned integer as a VLQ (7 bits per byte, least
+/// significant group first)
+fn vlq_encode(mut n: u64) -> Vec<u8> {
+    let mut bytes = vec![];
+    loop {
+        let group = (n & 0x7F) as u8;
+        n >>= 7;
+        if n == 0 {
+            bytes.push(group);
+            return bytes;
+        }
+        bytes.push(group | 0x80);
+    }
+}
+
+/// Decodes a VLQ from the start of the bytes, returning the integer and
+/// the number of bytes it spanned
+fn vlq_decode(bytes: &[u8]) -> Option<(u64, usize)> {
+    let mut n: u64 = 0;
+    for (i, b) in bytes.iter().enumerate().take(10) {
+        n |= ((b & 0x7F) as u64) << (7 * i);
+        if b & 0x80 == 0 {
+            return Some((n, i + 1));
+        }
+    }
+    None
+}
+
 /// Decodes a hex-encoded string into bytes and then serializes it into a properly formatted signed hex-encoded string and converted into a `Constant`
 pub fn serialize_hex_encoded_string(s: &String) -> Result<Constant> {
     let b = decode_hex(s)?;
@@ -79,6 +176,12 @@ pub fn unwrap_long(c: &Constant) -> Result<i64> {
         .map_err(|_| EncodingError::FailedToUnwrap(c.base16_str().unwrap()))
 }
 
+/// Unwrap a `Coll[Long]` inside of a `Constant` acquired from a register of an `ErgoBox`
+pub fn unwrap_long_coll(c: &Constant) -> Result<Vec<i64>> {
+    Vec::<i64>::try_extract_from(c.clone())
+        .map_err(|_| EncodingError::FailedToUnwrap(c.base16_str().unwrap()))
+}
+
 /// Unwrap a String which is inside of a `Constant` acquired from a register of an `ErgoBox`
 pub fn unwrap_string(c: &Constant) -> Result<String> {
     let byte_array: Result<Vec<u8>> = match Vec::<i8>::try_extract_from(c.clone()) {
@@ -111,15 +214,33 @@ pub fn deserialize_p2s_to_ergo_tree(p2s_address: P2SAddressString) -> Result<Erg
 
 /// Acquires the Base58 encoded P2S Address from an `ErgoTree`
 pub fn serialize_p2s_from_ergo_tree(ergo_tree: ErgoTree) -> P2SAddressString {
+    serialize_p2s_from_ergo_tree_for_network(ergo_tree, Network::Mainnet)
+}
+
+/// Acquires the Base58 encoded P2S Address from an `ErgoTree` for the
+/// provided `Network`
+pub fn serialize_p2s_from_ergo_tree_for_network(
+    ergo_tree: ErgoTree,
+    network: Network,
+) -> P2SAddressString {
     let address = Address::P2S(ergo_tree.sigma_serialize_bytes().unwrap());
-    let encoder = AddressEncoder::new(NetworkPrefix::Mainnet);
+    let encoder = AddressEncoder::new(network.network_prefix());
     encoder.address_to_str(&address)
 }
 
 /// Attempts to acquire the Base58 encoded P2S or P2PK Address from an `ErgoTree`
 pub fn serialize_address_from_ergo_tree(ergo_tree: ErgoTree) -> Result<ErgoAddressString> {
+    serialize_address_from_ergo_tree_for_network(ergo_tree, Network::Mainnet)
+}
+
+/// Attempts to acquire the Base58 encoded P2S or P2PK Address from an
+/// `ErgoTree` for the provided `Network`
+pub fn serialize_address_from_ergo_tree_for_network(
+    ergo_tree: ErgoTree,
+    network: Network,
+) -> Result<ErgoAddressString> {
     if let Ok(address) = Address::recreate_from_ergo_tree(&ergo_tree) {
-        let encoder = AddressEncoder::new(NetworkPrefix::Mainnet);
+        let encoder = AddressEncoder::new(network.network_prefix());
         return Ok(encoder.address_to_str(&address));
     }
     Err(EncodingError::FailedToSerialize(
@@ -148,7 +269,7 @@ pub fn parse_address(address_str: &ErgoAddressString) -> Result<Address> {
     let address = mainnet_encoder.parse_address_from_str(address_str);
     return match address {
         Ok(addr) => Ok(addr),
-        Err(error) => testnet_encoder
+        Err(_) => testnet_encoder
             .parse_address_from_str(address_str)
             .map_err(|_| EncodingError::FailedToSerialize(address_str.to_string())),
     };
@@ -190,6 +311,88 @@ pub fn build_token(token_id: &str, token_amount: u64) -> Result<Token> {
     })
 }
 
+/// Constructs a `Constant` of the provided `SType` from a plain JSON value.
+/// Numbers are used for numeric types, booleans for `SBoolean`, and arrays
+/// for collections. A `Coll[Byte]` can also be provided as a hex-encoded
+/// string.
+pub fn constant_from_json(stype: &SType, json_value: &Value) -> Result<Constant> {
+    let fail = || EncodingError::FailedToSerialize(format!("{:?} as {:?}", json_value, stype));
+    let constant = match stype {
+        SType::SBoolean => json_value.as_bool().ok_or_else(fail)?.into(),
+        SType::SByte => json_to_int::<i8>(json_value).ok_or_else(fail)?.into(),
+        SType::SShort => json_to_int::<i16>(json_value).ok_or_else(fail)?.into(),
+        SType::SInt => json_to_int::<i32>(json_value).ok_or_else(fail)?.into(),
+        SType::SLong => json_to_int::<i64>(json_value).ok_or_else(fail)?.into(),
+        SType::SColl(elem_type) => match (elem_type.as_ref(), json_value) {
+            (SType::SByte, Value::String(hex)) => serialize_hex_encoded_string(hex)?,
+            (SType::SByte, Value::Array(a)) => json_array_to_vec::<i8>(a).ok_or_else(fail)?.into(),
+            (SType::SShort, Value::Array(a)) => {
+                json_array_to_vec::<i16>(a).ok_or_else(fail)?.into()
+            }
+            (SType::SInt, Value::Array(a)) => json_array_to_vec::<i32>(a).ok_or_else(fail)?.into(),
+            (SType::SLong, Value::Array(a)) => json_array_to_vec::<i64>(a).ok_or_else(fail)?.into(),
+            (SType::SBoolean, Value::Array(a)) => a
+                .iter()
+                .map(|v| v.as_bool())
+                .collect::<Option<Vec<bool>>>()
+                .ok_or_else(fail)?
+                .into(),
+            _ => return Err(fail()),
+        },
+        _ => return Err(fail()),
+    };
+    Ok(constant)
+}
+
+/// Parses an `SType` from its name, as rendered by `render::stype_name()`
+/// (ie. `SLong` or `Coll[SByte]`). The `S` prefix of the type names is
+/// optional, so `Long` and `Coll[Byte]` are accepted as well.
+pub fn parse_stype(name: &str) -> Result<SType> {
+    let fail = || EncodingError::FailedToDeserialize(name.to_string());
+    let name = name.trim();
+    if let Some(inner) = name.strip_prefix("Coll[").and_then(|n| n.strip_suffix(']')) {
+        return Ok(SType::SColl(Box::new(parse_stype(inner)?)));
+    }
+    if let Some(inner) = name
+        .strip_prefix("Option[")
+        .and_then(|n| n.strip_suffix(']'))
+    {
+        return Ok(SType::SOption(Box::new(parse_stype(inner)?)));
+    }
+    let stype = match name.strip_prefix('S').unwrap_or(name) {
+        "Boolean" => SType::SBoolean,
+        "Byte" => SType::SByte,
+        "Short" => SType::SShort,
+        "Int" => SType::SInt,
+        "Long" => SType::SLong,
+        "BigInt" => SType::SBigInt,
+        "GroupElement" => SType::SGroupElement,
+        "SigmaProp" => SType::SSigmaProp,
+        "Box" => SType::SBox,
+        "AvlTree" => SType::SAvlTree,
+        _ => return Err(fail()),
+    };
+    Ok(stype)
+}
+
+/// Same as `constant_from_json()` but takes the JSON value as a `String`.
+pub fn constant_from_json_str(stype: &SType, json: &str) -> Result<Constant> {
+    let json_value: Value = serde_json::from_str(json)
+        .map_err(|_| EncodingError::FailedToDeserialize(json.to_string()))?;
+    constant_from_json(stype, &json_value)
+}
+
+/// Converts a JSON number into an integer of the given type, if it fits
+fn json_to_int<T: TryFrom<i64>>(json_value: &Value) -> Option<T> {
+    json_value.as_i64().and_then(|i| T::try_from(i).ok())
+}
+
+/// Converts a JSON array of numbers into a vector of integers of the
+/// given type, if all of them fit
+fn json_array_to_vec<T: TryFrom<i64>>(array: &Vec<Value>) -> Option<Vec<T>> {
+    array.iter().map(|v| json_to_int::<T>(v)).collect()
+}
+
 /// Convert Vec<i8> to Vec<u8>
 fn convert_to_unsigned_bytes(bytes: &Vec<i8>) -> Vec<u8> {
     bytes.iter().map(|x| x.clone() as u8).collect()
@@ -219,10 +422,58 @@ pub fn nano_erg_to_erg(nanoerg_amount: u64) -> f64 {
     (nanoerg_amount as f64) / (1000000000 as f64)
 }
 
+/// The number of decimals of the Erg currency in nanoErgs
+pub const NANO_ERG_DECIMALS: u32 = 9;
+
+/// Formats a raw token amount with the provided number of decimals into
+/// a locale-free String (`.` as the decimal separator, no grouping).
+/// Computed on the digits directly rather than via `f64`, so every amount
+/// is rendered exactly. Trailing zeros of the fraction are omitted.
+/// Ie. `format_token_amount(1230, 3)` is `"1.23"`.
+pub fn format_token_amount(amount: u64, decimals: u32) -> String {
+    let decimals = decimals as usize;
+    let digits = format!("{:0>width$}", amount, width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, fraction)
+    }
+}
+
+/// Formats an amount of nanoErgs as Ergs into a locale-free String.
+/// Ie. `format_nano_erg(1230000000)` is `"1.23"`.
+pub fn format_nano_erg(nano_ergs: u64) -> String {
+    format_token_amount(nano_ergs, NANO_ERG_DECIMALS)
+}
+
+/// WASM-compatible `format_token_amount()`
+#[wasm_bindgen]
+pub fn w_format_token_amount(amount: u64, decimals: u32) -> String {
+    format_token_amount(amount, decimals)
+}
+
+/// WASM-compatible `format_nano_erg()`
+#[wasm_bindgen]
+pub fn w_format_nano_erg(nano_ergs: u64) -> String {
+    format_nano_erg(nano_ergs)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn format_amounts_exactly() {
+        assert_eq!(format_token_amount(1230, 3), "1.23");
+        assert_eq!(format_token_amount(5, 2), "0.05");
+        assert_eq!(format_token_amount(100, 0), "100");
+        assert_eq!(format_token_amount(0, 4), "0");
+        assert_eq!(format_nano_erg(1000000000), "1");
+        assert_eq!(format_nano_erg(u64::MAX), "18446744073.709551615");
+    }
+
     #[test]
     fn erg_conv_is_valid() {
         assert_eq!((1 as f64), nano_erg_to_erg(1000000000));
@@ -236,9 +487,68 @@ mod tests {
         assert_eq!(erg_to_nano_erg(0.000000001), 1);
     }
 
+    #[test]
+    fn string_encodings_round_trip() {
+        let s = "x".repeat(200);
+        for encoding in [StringEncoding::Utf8, StringEncoding::LengthPrefixedUtf8].iter() {
+            let c = serialize_string_with(&s, *encoding);
+            assert_eq!(unwrap_string_with(&c, *encoding).unwrap(), s);
+        }
+        let prefixed = serialize_string_with(&s, StringEncoding::LengthPrefixedUtf8);
+        assert_eq!(
+            convert_to_unsigned_bytes(&Vec::<i8>::try_extract_from(prefixed).unwrap())[..2],
+            [0xC8, 0x01]
+        );
+        assert_eq!(
+            unwrap_string(&serialize_string_with(&s, StringEncoding::Utf8)).unwrap(),
+            s
+        );
+    }
+
+    #[test]
+    fn constant_from_json_test() {
+        let long = constant_from_json_str(&SType::SLong, "1234").unwrap();
+        assert_eq!(unwrap_long(&long).unwrap(), 1234);
+
+        let coll_type = Constant::from(Vec::<i8>::new()).tpe;
+        let bytes = constant_from_json_str(&coll_type, "\"0a0b\"").unwrap();
+        assert_eq!(unwrap_hex_encoded_string(&bytes).unwrap(), "0a0b");
+
+        assert!(constant_from_json_str(&SType::SInt, "\"not a number\"").is_err());
+        assert!(constant_from_json_str(&SType::SByte, "300").is_err());
+    }
+
+    #[test]
+    fn parse_stype_round_trips_rendered_names() {
+        let stypes = vec![
+            SType::SBoolean,
+            SType::SByte,
+            SType::SShort,
+            SType::SInt,
+            SType::SLong,
+            SType::SBigInt,
+            SType::SGroupElement,
+            SType::SSigmaProp,
+            SType::SBox,
+            SType::SAvlTree,
+            SType::SColl(Box::new(SType::SByte)),
+            SType::SOption(Box::new(SType::SColl(Box::new(SType::SLong)))),
+        ];
+        for stype in stypes {
+            let name = crate::render::stype_name(&stype);
+            assert_eq!(parse_stype(&name).unwrap(), stype);
+        }
+        assert_eq!(
+            parse_stype("Coll[Byte]").unwrap(),
+            SType::SColl(Box::new(SType::SByte))
+        );
+        assert!(parse_stype("Unit").is_err());
+        assert!(parse_stype("Coll[Unit]").is_err());
+    }
+
     #[test]
     fn build_token_test() {
-        let t = build_token(
+        let _t = build_token(
             "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1",
             100000,
         )
@@ -1,5 +1,5 @@
 use crate::encoding::EncodingError;
-use crate::{NanoErg, P2PKAddressString, P2SAddressString};
+use crate::{BlockHeight, NanoErg, P2PKAddressString, P2SAddressString};
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, HeadlessDappError>;
@@ -12,22 +12,126 @@ pub enum HeadlessDappError {
         "The number of Ergs held within the box is outside of the valid range for the `BoxSpec`."
     )]
     InvalidSpecErgsValue,
+    #[error("The creation height {0} of the box is outside of the valid range for the `BoxSpec`.")]
+    InvalidSpecCreationHeight(BlockHeight),
     #[error("One of the tokens failed to match the `BoxSpec`.")]
     FailedTokenSpec,
     #[error("One of the registers failed to match the `BoxSpec`.")]
     FailedRegisterSpec,
+    #[error("The register schema field is invalid: {0}")]
+    InvalidRegisterSchema(String),
+    #[error("No register field exists in the schema with the name: {0}")]
+    UnknownRegisterField(String),
     #[error("The encoded predicate on the BoxSpec failed.")]
     FailedSpecPredicate,
+    #[error("No predicate has been registered under the name: {0}")]
+    UnknownPredicate(String),
+    #[error("The box is timelocked until height {0}.")]
+    TimelockNotSatisfied(BlockHeight),
     #[error("The address provided is invalid: {0}")]
     InvalidAddress(String),
+    #[error("No address exists in the address book with the name: {0}")]
+    UnknownAddressName(String),
     #[error("The Box value {0} is invalid.")]
     InvalidBoxValue(NanoErg),
+    #[error("The Box value {0} is below the minimum value of {1} for its size.")]
+    BoxValueBelowMinimum(NanoErg, NanoErg),
+    #[error("The registers of the output are invalid: {0}")]
+    InvalidRegisterOrder(String),
     #[error("Invalid P2S Address: {0}")]
     InvalidP2SAddress(P2SAddressString),
     #[error("Invalid P2PK Address: {0}")]
     InvalidP2PKAddress(P2PKAddressString),
     #[error("The values attempted to be encoded within registers failed.")]
     InvalidRegisterValues(),
+    #[error("An arithmetic operation overflowed.")]
+    ArithmeticOverflow,
+    #[error("The token {0} is forbidden from appearing by the token policy.")]
+    ForbiddenToken(String),
+    #[error(
+        "Unable to find two distinct boxes holding at least {0} and {1} nanoErgs respectively."
+    )]
+    NoDistinctBoxPair(NanoErg, NanoErg),
+    #[error("Failed to derive a contract from the deposit template: {0}")]
+    InvalidDepositTemplate(String),
+    #[error("The `BoxSpec` does not declare a refund path.")]
+    MissingRefundPath,
+    #[error("Failed to verify the signed message: {0}")]
+    AuthVerificationFailed(String),
+    #[error("The operation was cancelled.")]
+    Cancelled,
+    #[error("The box {0} does not exist in the UTXO set.")]
+    UnknownBox(String),
+    #[error("The protocol transition {0} failed: {1}")]
+    TransitionFailed(String, String),
+    #[error("The data-input box {0} has already been spent.")]
+    DataInputSpent(String),
+    #[error("State storage failed: {0}")]
+    StorageError(String),
+    #[error("The request to {0} timed out.")]
+    RequestTimedOut(String),
+    #[error("Invalid token mint: {0}")]
+    InvalidTokenMint(String),
+    #[error("Invalid auction action: {0}")]
+    InvalidAuctionAction(String),
+    #[error("The box is not liquidatable as its health factor is {0} bps.")]
+    NotLiquidatable(u64),
+    #[error("Invalid liquidation: {0}")]
+    InvalidLiquidation(String),
+    #[error("The ErgoTree template of the box does not match the template hash {0}.")]
+    InvalidErgoTreeTemplate(String),
+    #[error("Invalid register descriptor: {0}")]
+    InvalidRegisterDescriptor(String),
+    #[error("The box {0} does not hold the register R{1}.")]
+    MissingRegister(String, usize),
+    #[error("The transaction fee of {0} nanoErgs was rejected: {1}")]
+    RejectedTxFee(NanoErg, String),
+    #[error("The outflow {2} of token {0} exceeds its inflow {1}.")]
+    TokenDeficit(String, u64, u64),
+    #[error("Transactions cannot be built in read-only mode.")]
+    ReadOnlyViolation,
+    #[error("The input box {0} cannot be spent by the transaction: {1}")]
+    UnspendableInput(String, String),
+    #[error(
+        "The selected boxes hold {1} nanoErgs, which does not cover the target of {0} nanoErgs."
+    )]
+    InsufficientErgs(NanoErg, NanoErg),
+    #[error("The Action {0} has already been executed with the nonce {1}.")]
+    ActionAlreadyExecuted(String, u64),
+    #[error("Key derivation failed: {0}")]
+    KeyDerivationFailed(String),
+    #[error("The parameters of the `{0}` Action are invalid: {1}")]
+    InvalidActionParameters(String, String),
+    #[error("Failed to migrate the boxes: {0}")]
+    MigrationFailed(String),
+    #[error("The oracle datapoint {0} deviates from the datapoint {1} cached at height {2} by more than {3}%.")]
+    OracleDeviationExceeded(u64, u64, BlockHeight, u64),
+    #[error("The request to {1} failed with the HTTP status {0}.")]
+    HttpStatus(u16, String),
+    #[error("The transaction holds {1} {0}, more than the {2} the Ledger app supports.")]
+    LedgerLimitExceeded(String, usize, usize),
+    #[error("The oracle datapoint {0} is negative.")]
+    NegativeDatapoint(i64),
+    #[error("The `BoxSpec` has a custom predicate which is not registered under a name, and so cannot be serialized.")]
+    UnnamedPredicate,
+    #[error("Summing token amounts overflowed the maximum valid token amount.")]
+    TokenAmountOverflow,
+    #[error("No stage exists in the protocol state with the name: {0}")]
+    UnknownStage(String),
+    #[error("The box {0} is used as more than one input.")]
+    DuplicateInput(String),
+    #[error("The box {0} is used as both an input and a data-input.")]
+    InputUsedAsDataInput(String),
+    #[error("Invalid Action input: {0}")]
+    InvalidActionInput(String),
+    #[error("Invalid outputs layout: {0}")]
+    InvalidOutputsLayout(String),
+    #[error("Failed to compose the transactions: {0}")]
+    InvalidTxComposition(String),
+    #[error("Invalid protocol config: {0}")]
+    InvalidConfig(String),
+    #[error("The protocol config is missing the value: {0}")]
+    MissingConfigValue(String),
     #[error("{0}")]
     Other(String),
     #[error(transparent)]
@@ -0,0 +1,170 @@
+/// This file holds the `ExplorerBackend` which is configured once with the
+/// URL of an explorer API and any headers it requires (ie. API keys for
+/// hosted providers), and then produces `ExplorerRequest`s which carry the
+/// generated endpoint together with said headers. As the framework is
+/// headless, the requests are then performed by the front-end/bot.
+use crate::box_spec::BoxSpec;
+use crate::box_traits::SpecifiedBox;
+use crate::error::Result;
+use crate::explorer_url::ExplorerUrl;
+use crate::network::Network;
+use std::time::Duration;
+
+/// A request to an explorer API, holding the url and the headers which
+/// must be attached
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExplorerRequest {
+    /// The full url of the endpoint
+    pub url: String,
+    /// The headers to attach as `(name, value)` pairs
+    pub headers: Vec<(String, String)>,
+    /// The time after which the request should be aborted, if any
+    pub timeout: Option<Duration>,
+}
+
+impl ExplorerRequest {
+    /// Overrides the timeout of this specific request
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+/// The configuration of an explorer API backend
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExplorerBackend {
+    api_url: String,
+    headers: Vec<(String, String)>,
+    timeout: Option<Duration>,
+}
+
+impl ExplorerBackend {
+    /// Create a new `ExplorerBackend` for the explorer API at `api_url`
+    pub fn new(api_url: &str) -> ExplorerBackend {
+        ExplorerBackend {
+            api_url: api_url.trim_end_matches('/').to_string(),
+            headers: vec![],
+            timeout: None,
+        }
+    }
+
+    /// Create a new `ExplorerBackend` for the public explorer API of the
+    /// provided `Network`
+    pub fn for_network(network: Network) -> ExplorerBackend {
+        ExplorerBackend::new(network.explorer_api_url())
+    }
+
+    /// Adds a header which is attached to every request. A header with
+    /// the same name which was previously added is replaced.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Adds an API key which is attached to every request using the
+    /// header name required by the provider
+    pub fn with_api_key(self, header_name: &str, api_key: &str) -> Self {
+        self.with_header(header_name, api_key)
+    }
+
+    /// Sets the default timeout of every request
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The base URL of the explorer API
+    pub fn api_url(&self) -> &str {
+        &self.api_url
+    }
+
+    /// The headers which are attached to every request
+    pub fn headers(&self) -> &Vec<(String, String)> {
+        &self.headers
+    }
+
+    /// An `ExplorerUrl` builder for the base URL of the explorer API
+    pub fn url(&self) -> ExplorerUrl {
+        ExplorerUrl::new(&self.api_url)
+    }
+
+    /// Builds an `ExplorerRequest` for the given path of the explorer API
+    /// (ie. `/v1/mempool/transactions/submit`). Paths holding user-provided
+    /// segments should be built via `url()` and `url_request()` instead, so
+    /// that said segments are percent-encoded.
+    pub fn request(&self, path: &str) -> ExplorerRequest {
+        self.url_request(self.url().path(path))
+    }
+
+    /// Builds an `ExplorerRequest` for a full url (ie. one built via
+    /// `url()`)
+    pub fn url_request<U: ToString>(&self, url: U) -> ExplorerRequest {
+        ExplorerRequest {
+            url: url.to_string(),
+            headers: self.headers.clone(),
+            timeout: self.timeout,
+        }
+    }
+
+    /// Builds an `ExplorerRequest` to find boxes which may match the
+    /// provided `BoxSpec`
+    pub fn box_spec_request(&self, box_spec: &BoxSpec) -> Result<ExplorerRequest> {
+        Ok(ExplorerRequest {
+            url: box_spec.explorer_endpoint(&self.api_url)?,
+            headers: self.headers.clone(),
+            timeout: self.timeout,
+        })
+    }
+
+    /// Builds an `ExplorerRequest` to count the boxes which may match the
+    /// provided `BoxSpec` (see `BoxSpec::count_endpoint()`)
+    pub fn count_request(&self, box_spec: &BoxSpec) -> Result<ExplorerRequest> {
+        Ok(ExplorerRequest {
+            url: box_spec.count_endpoint(&self.api_url)?,
+            headers: self.headers.clone(),
+            timeout: self.timeout,
+        })
+    }
+
+    /// Builds an `ExplorerRequest` to find boxes which may match the
+    /// `BoxSpec` of the `SpecifiedBox`
+    pub fn specified_box_request<T: SpecifiedBox>(&self) -> Result<ExplorerRequest> {
+        self.box_spec_request(&T::box_spec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers_are_attached_to_requests() {
+        let backend = ExplorerBackend::new("https://explorer.example.com/api/")
+            .with_api_key("X-Api-Key", "old")
+            .with_api_key("x-api-key", "secret");
+        let request = backend.request("/v1/info");
+        assert_eq!(request.url, "https://explorer.example.com/api/v1/info");
+        assert_eq!(
+            request.headers,
+            vec![("x-api-key".to_string(), "secret".to_string())]
+        );
+        assert_eq!(request.timeout, None);
+    }
+
+    #[test]
+    fn requests_are_built_via_explorer_url() {
+        let backend = ExplorerBackend::new("https://explorer.example.com/api/")
+            .with_timeout(Duration::from_secs(5));
+        assert_eq!(
+            backend.request("v1/info").url,
+            "https://explorer.example.com/api/v1/info"
+        );
+        let request = backend.url_request(backend.url().path("v1/transactions").segment("a b"));
+        assert_eq!(
+            request.url,
+            "https://explorer.example.com/api/v1/transactions/a%20b"
+        );
+        assert_eq!(request.timeout, Some(Duration::from_secs(5)));
+    }
+}
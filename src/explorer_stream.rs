@@ -0,0 +1,150 @@
+/// This file holds a streaming deserializer for Ergo Explorer API
+/// responses. Rather than parsing the whole response into an intermediate
+/// JSON value and then into a list of `ErgoBox`es, each box is verified
+/// against a `BoxSpec` as soon as it is parsed and discarded if it does
+/// not match. This keeps peak memory low when processing maximum-size
+/// pages in memory-constrained (WASM) environments.
+use crate::box_spec::BoxSpec;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use std::fmt;
+
+/// Deserializes the top-level explorer response object, only keeping the
+/// boxes inside of `items` which match the `BoxSpec`.
+pub(crate) struct FilteredResponseSeed<'a> {
+    pub(crate) spec: &'a BoxSpec,
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for FilteredResponseSeed<'a> {
+    type Value = Vec<ErgoBox>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for FilteredResponseSeed<'a> {
+    type Value = Vec<ErgoBox>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an explorer response object with an `items` list")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut boxes = vec![];
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "items" {
+                boxes = map.next_value_seed(FilteredItemsSeed { spec: self.spec })?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(boxes)
+    }
+}
+
+/// Deserializes the `items` list, verifying each box as it is parsed.
+struct FilteredItemsSeed<'a> {
+    spec: &'a BoxSpec,
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for FilteredItemsSeed<'a> {
+    type Value = Vec<ErgoBox>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for FilteredItemsSeed<'a> {
+    type Value = Vec<ErgoBox>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of boxes")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut boxes = vec![];
+        while let Some(ergo_box) = seq.next_element::<ErgoBox>()? {
+            if self.spec.verify_box(&ergo_box).is_ok() {
+                boxes.push(ergo_box);
+            }
+        }
+        Ok(boxes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::box_spec::TokenSpec;
+    use crate::encoding::build_token;
+    use crate::test_utils::{test_box_at, TEST_ADDRESS, TEST_TOKEN_ID};
+
+    fn build_explorer_response(boxes: &Vec<ErgoBox>) -> String {
+        let items: Vec<String> = boxes
+            .iter()
+            .map(|b| serde_json::to_string(b).unwrap())
+            .collect();
+        format!(
+            "{{\"total\": {}, \"items\": [{}], \"extra\": {{\"nested\": [1, 2]}}}}",
+            boxes.len(),
+            items.join(",")
+        )
+    }
+
+    #[test]
+    fn matches_process_explorer_response() {
+        let boxes: Vec<ErgoBox> = (0..6u16)
+            .map(|i| {
+                let tokens = match i % 2 {
+                    0 => vec![build_token(TEST_TOKEN_ID, 1).unwrap()],
+                    _ => vec![],
+                };
+                test_box_at(1000000 + i as u64, TEST_ADDRESS, tokens, vec![], 10, i)
+            })
+            .collect();
+        let response = build_explorer_response(&boxes);
+        let spec = BoxSpec::new(
+            Some(TEST_ADDRESS.to_string()),
+            None,
+            vec![],
+            vec![Some(TokenSpec::new(1..2, TEST_TOKEN_ID))],
+        );
+
+        let mut deserializer = serde_json::Deserializer::from_str(&response);
+        let streamed = FilteredResponseSeed { spec: &spec }
+            .deserialize(&mut deserializer)
+            .unwrap();
+        let processed = spec.process_explorer_response(&response).unwrap();
+        assert_eq!(streamed.len(), 3);
+        assert_eq!(streamed, processed);
+        assert_eq!(
+            spec.process_explorer_response_reader(response.as_bytes())
+                .unwrap(),
+            processed
+        );
+    }
+
+    #[test]
+    fn missing_items_yields_no_boxes() {
+        let spec = BoxSpec::new(Some(TEST_ADDRESS.to_string()), None, vec![], vec![]);
+        let mut deserializer = serde_json::Deserializer::from_str("{\"total\": 0}");
+        let boxes = FilteredResponseSeed { spec: &spec }
+            .deserialize(&mut deserializer)
+            .unwrap();
+        assert!(boxes.is_empty());
+    }
+}
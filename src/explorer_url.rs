@@ -0,0 +1,103 @@
+/// This file holds the `ExplorerUrl` builder which is used to generate
+/// explorer API endpoints from a base URL, path segments, and query
+/// parameters. Path segments and query parameters are percent-encoded,
+/// and trailing slashes on the base URL are handled, avoiding malformed
+/// URLs.
+use std::fmt;
+
+/// A typed builder for explorer API URLs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExplorerUrl {
+    base: String,
+    segments: Vec<String>,
+    query: Vec<(String, String)>,
+}
+
+impl ExplorerUrl {
+    /// Create a new `ExplorerUrl` from the base URL of the explorer API
+    pub fn new(base: &str) -> ExplorerUrl {
+        ExplorerUrl {
+            base: base.trim_end_matches('/').to_string(),
+            segments: vec![],
+            query: vec![],
+        }
+    }
+
+    /// Appends a path made up of `/` separated segments which are known to
+    /// be safe (ie. `v1/boxes/unspent`)
+    pub fn path(mut self, path: &str) -> Self {
+        self.segments.extend(
+            path.split('/')
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string()),
+        );
+        self
+    }
+
+    /// Appends a single path segment (ie. an address or token id) which
+    /// is percent-encoded
+    pub fn segment(mut self, segment: &str) -> Self {
+        self.segments.push(percent_encode(segment));
+        self
+    }
+
+    /// Appends a query parameter which is percent-encoded
+    pub fn query<V: ToString>(mut self, name: &str, value: V) -> Self {
+        self.query
+            .push((percent_encode(name), percent_encode(&value.to_string())));
+        self
+    }
+
+    /// Builds the URL as a `String`
+    pub fn build(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for ExplorerUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.base)?;
+        for segment in &self.segments {
+            write!(f, "/{}", segment)?;
+        }
+        for (i, (name, value)) in self.query.iter().enumerate() {
+            let separator = if i == 0 { '?' } else { '&' };
+            write!(f, "{}{}={}", separator, name, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Percent-encodes all characters other than the unreserved characters
+/// of RFC 3986
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::new();
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(b as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_encoded_url() {
+        let url = ExplorerUrl::new("https://api.ergoplatform.com/api/")
+            .path("/v1/boxes/unspent/byAddress")
+            .segment("9f a/b")
+            .query("offset", 0)
+            .query("limit", 100)
+            .build();
+        assert_eq!(
+            url,
+            "https://api.ergoplatform.com/api/v1/boxes/unspent/byAddress/9f%20a%2Fb?offset=0&limit=100"
+        );
+    }
+}
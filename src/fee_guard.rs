@@ -0,0 +1,142 @@
+/// This file holds the `FeeGuard`, a set of sanity checks on the
+/// transaction fee which reject obviously wrong fees (ie. a fat-fingered
+/// 1 Erg fee) before a bot signs and broadcasts the transaction. Protocols
+/// which intentionally pay high fees use `allow_high_fee()` to override the
+/// upper bounds.
+use crate::encoding::address_string_to_ergo_tree;
+use crate::error::{HeadlessDappError, Result};
+use crate::math::apply_bps;
+use crate::output_builders::MINER_FEE_P2S_ADDRESS;
+use crate::NanoErg;
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+
+/// The default maximum transaction fee (0.1 Erg)
+pub const DEFAULT_MAX_FEE: NanoErg = 100000000;
+
+/// Configurable sanity checks on the transaction fee
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeGuard {
+    max_fee: Option<NanoErg>,
+    fee_must_be_positive: bool,
+    fee_within_percent_of_value: Option<u64>,
+    allow_high_fee: bool,
+}
+
+impl Default for FeeGuard {
+    fn default() -> Self {
+        FeeGuard {
+            max_fee: Some(DEFAULT_MAX_FEE),
+            fee_must_be_positive: true,
+            fee_within_percent_of_value: None,
+            allow_high_fee: false,
+        }
+    }
+}
+
+impl FeeGuard {
+    /// Create a new `FeeGuard` which requires a positive fee of at most
+    /// `DEFAULT_MAX_FEE`
+    pub fn new() -> FeeGuard {
+        FeeGuard::default()
+    }
+
+    /// A `FeeGuard` which performs no checks
+    pub fn disabled() -> FeeGuard {
+        FeeGuard {
+            max_fee: None,
+            fee_must_be_positive: false,
+            fee_within_percent_of_value: None,
+            allow_high_fee: false,
+        }
+    }
+
+    /// Sets the maximum fee, or removes the bound if `None`
+    pub fn with_max_fee(mut self, max_fee: Option<NanoErg>) -> Self {
+        self.max_fee = max_fee;
+        self
+    }
+
+    /// Sets whether a fee of 0 is rejected
+    pub fn with_fee_must_be_positive(mut self, fee_must_be_positive: bool) -> Self {
+        self.fee_must_be_positive = fee_must_be_positive;
+        self
+    }
+
+    /// Rejects fees above `percent` of the nanoErgs moved by the
+    /// transaction
+    pub fn with_fee_within_percent_of_value(mut self, percent: u64) -> Self {
+        self.fee_within_percent_of_value = Some(percent);
+        self
+    }
+
+    /// Explicitly allows fees above the maximum fee and the percent of
+    /// value bound, for intentionally high-fee transactions
+    pub fn allow_high_fee(mut self) -> Self {
+        self.allow_high_fee = true;
+        self
+    }
+
+    /// Checks the `transaction_fee` of a transaction which moves `value`
+    /// nanoErgs in its (non-fee) outputs
+    pub fn check(&self, transaction_fee: NanoErg, value: NanoErg) -> Result<()> {
+        let reject = |reason: String| HeadlessDappError::RejectedTxFee(transaction_fee, reason);
+        if self.fee_must_be_positive && transaction_fee == 0 {
+            return Err(reject("The fee must be positive".to_string()));
+        }
+        if self.allow_high_fee {
+            return Ok(());
+        }
+        if let Some(max_fee) = self.max_fee {
+            if transaction_fee > max_fee {
+                return Err(reject(format!(
+                    "The fee is above the maximum of {}",
+                    max_fee
+                )));
+            }
+        }
+        if let Some(percent) = self.fee_within_percent_of_value {
+            let max_fee = apply_bps(value, percent.saturating_mul(100))?;
+            if transaction_fee > max_fee {
+                return Err(reject(format!(
+                    "The fee is above {}% of the transaction value {}",
+                    percent, value
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the fee of an `UnsignedTransaction`, which is the sum of its
+    /// outputs locked under the miner fee script. The value moved is the
+    /// sum of all other outputs.
+    pub fn check_unsigned_tx(&self, unsigned_tx: &UnsignedTransaction) -> Result<()> {
+        let miner_fee_tree = address_string_to_ergo_tree(&MINER_FEE_P2S_ADDRESS.to_string())?;
+        let (fee_outputs, value_outputs): (Vec<_>, Vec<_>) = unsigned_tx
+            .output_candidates
+            .as_vec()
+            .iter()
+            .partition(|o| o.ergo_tree == miner_fee_tree);
+        let transaction_fee = fee_outputs.iter().map(|o| *o.value.as_u64()).sum();
+        let value = value_outputs.iter().map(|o| *o.value.as_u64()).sum();
+        self.check(transaction_fee, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_wrong_fees() {
+        let guard = FeeGuard::new();
+        assert!(guard.check(1000000, 0).is_ok());
+        assert!(guard.check(0, 0).is_err());
+        assert!(guard.check(1000000000, 0).is_err());
+        assert!(guard.allow_high_fee().check(1000000000, 0).is_ok());
+
+        let percent_guard = FeeGuard::new().with_fee_within_percent_of_value(1);
+        assert!(percent_guard.check(1000000, 100000000).is_ok());
+        assert!(percent_guard.check(1000001, 100000000).is_err());
+        assert!(FeeGuard::disabled().check(0, 0).is_ok());
+    }
+}
@@ -0,0 +1,380 @@
+/// This file holds the generation of the APDU payloads which the Ergo
+/// Ledger app requires to sign a framework-built `UnsignedTransaction`.
+/// Signing on a Ledger happens in two phases:
+/// 1. Every input box is attested. The device hashes the box data it is
+///    sent and returns signed frames which prove the value and tokens of
+///    the box.
+/// 2. The transaction is streamed to the device (the attested frames of
+///    phase 1 included) for the user to confirm and sign.
+///
+/// The commands of each phase open a session on the device, and so the
+/// session id returned by the first command of a phase must be set as the
+/// `p2` of the following commands by the transport. The attested frames
+/// are returned by the device and thus are not generated here.
+use crate::encoding::address_string_to_ergo_tree;
+use crate::error::{HeadlessDappError, Result};
+use crate::output_builders::MINER_FEE_P2S_ADDRESS;
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::ergotree_ir::chain::digest32::Digest32;
+use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, NonMandatoryRegisters};
+use ergo_lib::ergotree_ir::chain::token::{Token, TokenId};
+use ergo_lib::ergotree_ir::ergo_tree::ErgoTree;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+use std::convert::TryFrom;
+
+/// The CLA byte of the Ergo Ledger app
+pub const LEDGER_CLA: u8 = 0xE0;
+/// The instruction of the input box attestation commands
+pub const INS_ATTEST_INPUT_BOX: u8 = 0x20;
+/// The instruction of the transaction signing commands
+pub const INS_SIGN_TX: u8 = 0x21;
+/// The maximum size of the data of a single APDU
+pub const MAX_APDU_DATA_SIZE: usize = 255;
+
+// Attestation commands
+const P1_BOX_START: u8 = 0x01;
+const P1_BOX_ERGO_TREE_CHUNK: u8 = 0x02;
+const P1_BOX_TOKENS: u8 = 0x03;
+const P1_BOX_REGISTERS_CHUNK: u8 = 0x04;
+// Signing commands
+const P1_START_TRANSACTION: u8 = 0x10;
+const P1_ADD_TOKEN_IDS: u8 = 0x11;
+const P1_ADD_DATA_INPUTS: u8 = 0x14;
+const P1_OUTPUT_BOX_START: u8 = 0x15;
+const P1_OUTPUT_BOX_ERGO_TREE_CHUNK: u8 = 0x16;
+const P1_OUTPUT_BOX_MINERS_FEE_TREE: u8 = 0x17;
+const P1_OUTPUT_BOX_TOKENS: u8 = 0x19;
+const P1_OUTPUT_BOX_REGISTERS_CHUNK: u8 = 0x1A;
+
+/// The number of tokens which fit into a single attestation command
+const TOKENS_PER_CHUNK: usize = 6;
+/// The number of 32 byte ids which fit into a single command
+const IDS_PER_CHUNK: usize = 7;
+
+/// A single APDU command of the Ergo Ledger app. The `p2` (session id) is
+/// set by the transport.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LedgerApdu {
+    pub ins: u8,
+    pub p1: u8,
+    pub data: Vec<u8>,
+}
+
+impl LedgerApdu {
+    fn new(ins: u8, p1: u8, data: Vec<u8>) -> LedgerApdu {
+        LedgerApdu { ins, p1, data }
+    }
+
+    /// Serializes the APDU with the provided session id as `p2`
+    pub fn to_bytes(&self, session_id: u8) -> Vec<u8> {
+        let mut bytes = vec![
+            LEDGER_CLA,
+            self.ins,
+            self.p1,
+            session_id,
+            self.data.len() as u8,
+        ];
+        bytes.extend(&self.data);
+        bytes
+    }
+}
+
+/// The attestation commands of a single input box
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LedgerInputAttestation {
+    /// The Box ID of the input box
+    pub box_id: String,
+    pub apdus: Vec<LedgerApdu>,
+}
+
+/// The payloads required to sign an `UnsignedTransaction` on a Ledger
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LedgerTxPayloads {
+    /// The attestation commands of every input, in the order of the inputs
+    pub input_attestations: Vec<LedgerInputAttestation>,
+    /// The signing commands which are sent before the attested input
+    /// frames (the transaction header and distinct token ids)
+    pub tx_start: Vec<LedgerApdu>,
+    /// The signing commands which are sent after the attested input
+    /// frames (the data-inputs and outputs). The final confirmation
+    /// command, which holds the signing path, is left to the transport.
+    pub tx_body: Vec<LedgerApdu>,
+}
+
+/// Generates the Ledger payloads of the `unsigned_tx`. `input_boxes` must
+/// hold the box of every input of the transaction.
+pub fn ledger_tx_payloads(
+    unsigned_tx: &UnsignedTransaction,
+    input_boxes: &[ErgoBox],
+) -> Result<LedgerTxPayloads> {
+    let mut ordered_input_boxes = vec![];
+    for input in unsigned_tx.inputs.as_vec() {
+        let input_box = input_boxes
+            .iter()
+            .find(|b| b.box_id() == input.box_id)
+            .ok_or_else(|| {
+                let box_id: String = input.box_id.clone().into();
+                HeadlessDappError::Other(format!(
+                    "The box of the input {} was not provided",
+                    box_id
+                ))
+            })?;
+        ordered_input_boxes.push(input_box);
+    }
+    let input_attestations = ordered_input_boxes
+        .iter()
+        .map(|b| input_attestation(b))
+        .collect::<Result<Vec<_>>>()?;
+
+    // The output tokens are referenced by their index in the list of
+    // distinct token ids
+    let outputs = unsigned_tx.output_candidates.as_vec();
+    let mut token_ids: Vec<TokenId> = vec![];
+    for output in outputs {
+        for t in &output.tokens {
+            if !token_ids.contains(&t.token_id) {
+                token_ids.push(t.token_id.clone());
+            }
+        }
+    }
+    let data_inputs = unsigned_tx
+        .data_inputs
+        .as_ref()
+        .map(|d| d.as_vec().clone())
+        .unwrap_or_default();
+
+    let mut start_data = vec![];
+    start_data.extend(&count_u16(ordered_input_boxes.len(), "inputs")?.to_be_bytes());
+    start_data.extend(&count_u16(data_inputs.len(), "data-inputs")?.to_be_bytes());
+    start_data.push(count_u8(token_ids.len(), "distinct tokens")?);
+    start_data.extend(&count_u16(outputs.len(), "outputs")?.to_be_bytes());
+    let mut tx_start = vec![LedgerApdu::new(
+        INS_SIGN_TX,
+        P1_START_TRANSACTION,
+        start_data,
+    )];
+    for ids in token_ids.chunks(IDS_PER_CHUNK) {
+        let mut data = vec![];
+        for id in ids {
+            data.extend(token_id_bytes(id)?);
+        }
+        tx_start.push(LedgerApdu::new(INS_SIGN_TX, P1_ADD_TOKEN_IDS, data));
+    }
+
+    let mut tx_body = vec![];
+    for data_input_ids in data_inputs.chunks(IDS_PER_CHUNK) {
+        let mut data = vec![];
+        for data_input in data_input_ids {
+            let box_id: String = data_input.box_id.clone().into();
+            data.extend(decode_hex(&box_id)?);
+        }
+        tx_body.push(LedgerApdu::new(INS_SIGN_TX, P1_ADD_DATA_INPUTS, data));
+    }
+    let miner_fee_tree = address_string_to_ergo_tree(&MINER_FEE_P2S_ADDRESS.to_string())?;
+    for output in outputs {
+        let is_miner_fee = output.ergo_tree == miner_fee_tree;
+        let tree_bytes = ergo_tree_bytes(&output.ergo_tree)?;
+        let register_bytes = registers_bytes(&output.additional_registers)?;
+        let mut data = vec![];
+        data.extend(&output.value.as_u64().to_be_bytes());
+        let tree_size = if is_miner_fee { 0 } else { tree_bytes.len() };
+        data.extend(&(tree_size as u32).to_be_bytes());
+        data.extend(&output.creation_height.to_be_bytes());
+        data.push(count_u8(output.tokens.len(), "tokens in an output")?);
+        data.extend(&(register_bytes.len() as u32).to_be_bytes());
+        tx_body.push(LedgerApdu::new(INS_SIGN_TX, P1_OUTPUT_BOX_START, data));
+
+        if is_miner_fee {
+            tx_body.push(LedgerApdu::new(
+                INS_SIGN_TX,
+                P1_OUTPUT_BOX_MINERS_FEE_TREE,
+                vec![],
+            ));
+        } else {
+            tx_body.extend(chunked(
+                INS_SIGN_TX,
+                P1_OUTPUT_BOX_ERGO_TREE_CHUNK,
+                &tree_bytes,
+            ));
+        }
+        for tokens in output.tokens.chunks(TOKENS_PER_CHUNK) {
+            let mut data = vec![];
+            for t in tokens {
+                // Safe as every output token id was collected above
+                let index = token_ids.iter().position(|id| *id == t.token_id).unwrap();
+                data.extend(&(index as u32).to_be_bytes());
+                data.extend(&u64::from(t.amount).to_be_bytes());
+            }
+            tx_body.push(LedgerApdu::new(INS_SIGN_TX, P1_OUTPUT_BOX_TOKENS, data));
+        }
+        tx_body.extend(chunked(
+            INS_SIGN_TX,
+            P1_OUTPUT_BOX_REGISTERS_CHUNK,
+            &register_bytes,
+        ));
+    }
+
+    Ok(LedgerTxPayloads {
+        input_attestations,
+        tx_start,
+        tx_body,
+    })
+}
+
+/// Generates the attestation commands of a single input box
+pub fn input_attestation(input_box: &ErgoBox) -> Result<LedgerInputAttestation> {
+    let tx_id_digest32: Digest32 = input_box.transaction_id.0.clone();
+    let tx_id: String = tx_id_digest32.into();
+    let tree_bytes = ergo_tree_bytes(&input_box.ergo_tree)?;
+    let register_bytes = registers_bytes(&input_box.additional_registers)?;
+
+    let mut data = decode_hex(&tx_id)?;
+    data.extend(&input_box.index.to_be_bytes());
+    data.extend(&input_box.value.as_u64().to_be_bytes());
+    data.extend(&(tree_bytes.len() as u32).to_be_bytes());
+    data.extend(&input_box.creation_height.to_be_bytes());
+    data.push(count_u8(input_box.tokens.len(), "tokens in an input")?);
+    data.extend(&(register_bytes.len() as u32).to_be_bytes());
+
+    let mut apdus = vec![LedgerApdu::new(INS_ATTEST_INPUT_BOX, P1_BOX_START, data)];
+    apdus.extend(chunked(
+        INS_ATTEST_INPUT_BOX,
+        P1_BOX_ERGO_TREE_CHUNK,
+        &tree_bytes,
+    ));
+    for tokens in input_box.tokens.chunks(TOKENS_PER_CHUNK) {
+        apdus.push(LedgerApdu::new(
+            INS_ATTEST_INPUT_BOX,
+            P1_BOX_TOKENS,
+            tokens_bytes(tokens)?,
+        ));
+    }
+    apdus.extend(chunked(
+        INS_ATTEST_INPUT_BOX,
+        P1_BOX_REGISTERS_CHUNK,
+        &register_bytes,
+    ));
+    Ok(LedgerInputAttestation {
+        box_id: input_box.box_id().into(),
+        apdus,
+    })
+}
+
+/// Splits the bytes into APDUs of at most `MAX_APDU_DATA_SIZE` bytes
+fn chunked(ins: u8, p1: u8, bytes: &[u8]) -> Vec<LedgerApdu> {
+    bytes
+        .chunks(MAX_APDU_DATA_SIZE)
+        .map(|chunk| LedgerApdu::new(ins, p1, chunk.to_vec()))
+        .collect()
+}
+
+/// Serializes the tokens as `id || amount` pairs
+fn tokens_bytes(tokens: &[Token]) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    for t in tokens {
+        bytes.extend(token_id_bytes(&t.token_id)?);
+        bytes.extend(&u64::from(t.amount).to_be_bytes());
+    }
+    Ok(bytes)
+}
+
+fn token_id_bytes(token_id: &TokenId) -> Result<Vec<u8>> {
+    let token_id_digest32: Digest32 = token_id.clone().into();
+    let token_id: String = token_id_digest32.into();
+    decode_hex(&token_id)
+}
+
+fn ergo_tree_bytes(ergo_tree: &ErgoTree) -> Result<Vec<u8>> {
+    ergo_tree
+        .sigma_serialize_bytes()
+        .map_err(|e| HeadlessDappError::Other(e.to_string()))
+}
+
+/// Serializes the registers as in the box format (the number of registers
+/// followed by their constants), or to nothing if there are none
+fn registers_bytes(registers: &NonMandatoryRegisters) -> Result<Vec<u8>> {
+    let values = registers.get_ordered_values();
+    if values.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut bytes = vec![count_u8(values.len(), "registers in a box")?];
+    for c in values {
+        bytes.extend(
+            c.sigma_serialize_bytes()
+                .map_err(|e| HeadlessDappError::Other(e.to_string()))?,
+        );
+    }
+    Ok(bytes)
+}
+
+/// Converts a count into the single byte the Ledger app expects, erroring
+/// if it does not fit
+fn count_u8(count: usize, name: &str) -> Result<u8> {
+    u8::try_from(count).map_err(|_| {
+        HeadlessDappError::LedgerLimitExceeded(name.to_string(), count, u8::MAX as usize)
+    })
+}
+
+/// Converts a count into the two bytes the Ledger app expects, erroring
+/// if it does not fit
+fn count_u16(count: usize, name: &str) -> Result<u16> {
+    u16::try_from(count).map_err(|_| {
+        HeadlessDappError::LedgerLimitExceeded(name.to_string(), count, u16::MAX as usize)
+    })
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    base16::decode(hex).map_err(|e| HeadlessDappError::Other(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::build_token;
+    use crate::output_builders::TxFeeBox;
+    use crate::test_utils::test_box;
+    use crate::tx_creation::new_unsigned_tx;
+
+    #[test]
+    fn generates_ledger_payloads() {
+        let token = build_token(
+            "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1",
+            10,
+        )
+        .unwrap();
+        let input_box = test_box(
+            2000000,
+            "9fSgJ7BmUxBQJ454prQDQ7fQMBkXPLaAmDnimgTtjym6FYPHjAV",
+            vec![token],
+            vec![],
+        );
+        let outputs = vec![TxFeeBox::output_candidate(2000000, 0).unwrap()];
+        let unsigned_tx = new_unsigned_tx(vec![input_box.clone().into()], vec![], outputs).unwrap();
+        let payloads = ledger_tx_payloads(&unsigned_tx, &[input_box]).unwrap();
+
+        let attestation = &payloads.input_attestations[0].apdus;
+        // Start (32 + 2 + 8 + 4 + 4 + 1 + 4 bytes), tree chunk, and tokens
+        assert_eq!(attestation.len(), 3);
+        assert_eq!(attestation[0].data.len(), 55);
+        assert_eq!(attestation[2].data.len(), 40);
+        assert_eq!(payloads.tx_start.len(), 1);
+        assert_eq!(payloads.tx_body.len(), 2);
+        assert_eq!(payloads.tx_body[1].p1, P1_OUTPUT_BOX_MINERS_FEE_TREE);
+        assert_eq!(attestation[0].to_bytes(7)[3], 7);
+    }
+
+    #[test]
+    fn rejects_counts_above_ledger_limits() {
+        // ergo-lib itself refuses to build boxes with more than 255 tokens,
+        // so the limit is checked on the counts directly
+        match count_u8(256, "tokens") {
+            Err(HeadlessDappError::LedgerLimitExceeded(_, count, max)) => {
+                assert_eq!((count, max), (256, 255))
+            }
+            other => panic!("Expected LedgerLimitExceeded, got {:?}", other),
+        }
+        assert_eq!(count_u8(255, "tokens").unwrap(), u8::MAX);
+        assert!(count_u16(65536, "outputs").is_err());
+        assert_eq!(count_u16(65535, "outputs").unwrap(), u16::MAX);
+    }
+}
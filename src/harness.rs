@@ -0,0 +1,206 @@
+/// This file holds a spec-driven round-trip test harness for entire
+/// protocols. A `SimulatedChain` holds an in-memory UTXO set which
+/// transactions are applied to (without script validation), and the
+/// `ProtocolHarness` runs every declared protocol transition against it:
+/// building the Action, verifying the output candidates against their
+/// stage specs, applying the transaction, and re-verifying the created
+/// boxes.
+use crate::box_spec::BoxSpec;
+use crate::error::{HeadlessDappError, Result};
+use crate::tx_creation::check_unsigned_tx;
+use crate::BlockHeight;
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+
+/// An in-memory UTXO set which unsigned transactions are applied to.
+/// Scripts are not evaluated, only the existence of inputs and
+/// data-inputs is checked.
+#[derive(Clone, Debug, Default)]
+pub struct SimulatedChain {
+    utxos: Vec<ErgoBox>,
+    height: BlockHeight,
+}
+
+impl SimulatedChain {
+    /// Create a new `SimulatedChain` at the given height with no boxes
+    pub fn new(height: BlockHeight) -> SimulatedChain {
+        SimulatedChain {
+            utxos: vec![],
+            height,
+        }
+    }
+
+    /// Adds a box to the UTXO set (ie. a genesis stage box or a user's
+    /// wallet box)
+    pub fn with_box(mut self, ergo_box: ErgoBox) -> Self {
+        self.utxos.push(ergo_box);
+        self
+    }
+
+    /// The current height of the chain
+    pub fn height(&self) -> BlockHeight {
+        self.height
+    }
+
+    /// Advances the height of the chain by the given number of blocks
+    pub fn advance_height(&mut self, blocks: BlockHeight) {
+        self.height += blocks;
+    }
+
+    /// All of the unspent boxes
+    pub fn unspent_boxes(&self) -> &Vec<ErgoBox> {
+        &self.utxos
+    }
+
+    /// All of the unspent boxes which match the provided `BoxSpec`
+    pub fn boxes_matching(&self, box_spec: &BoxSpec) -> Vec<ErgoBox> {
+        self.utxos
+            .iter()
+            .filter(|b| box_spec.verify_box(b).is_ok())
+            .cloned()
+            .collect()
+    }
+
+    /// Applies an unsigned transaction, spending its inputs and creating
+    /// its outputs. Returns the created boxes.
+    pub fn apply(&mut self, unsigned_tx: &UnsignedTransaction) -> Result<Vec<ErgoBox>> {
+        check_unsigned_tx(unsigned_tx)?;
+        for input in unsigned_tx.inputs.as_vec() {
+            if !self.utxos.iter().any(|b| b.box_id() == input.box_id) {
+                let box_id: String = input.box_id.clone().into();
+                return Err(HeadlessDappError::UnknownBox(box_id));
+            }
+        }
+        if let Some(data_inputs) = &unsigned_tx.data_inputs {
+            for data_input in data_inputs.as_vec() {
+                if !self.utxos.iter().any(|b| b.box_id() == data_input.box_id) {
+                    let box_id: String = data_input.box_id.clone().into();
+                    return Err(HeadlessDappError::UnknownBox(box_id));
+                }
+            }
+        }
+
+        let inputs = unsigned_tx.inputs.as_vec();
+        self.utxos
+            .retain(|b| !inputs.iter().any(|i| i.box_id == b.box_id()));
+
+        let mut created = vec![];
+        for (index, candidate) in unsigned_tx.output_candidates.as_vec().iter().enumerate() {
+            let ergo_box = ErgoBox::new(
+                candidate.value,
+                candidate.ergo_tree.clone(),
+                candidate.tokens.clone(),
+                candidate.additional_registers.clone(),
+                candidate.creation_height,
+                unsigned_tx.id(),
+                index as u16,
+            )
+            .map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+            created.push(ergo_box);
+        }
+        self.utxos.extend(created.clone());
+        Ok(created)
+    }
+}
+
+/// A function which builds the unsigned transaction of an Action using the
+/// current state of the `SimulatedChain`
+pub type TransitionAction = Box<dyn Fn(&SimulatedChain) -> Result<UnsignedTransaction>>;
+
+/// A declared protocol transition
+pub struct Transition {
+    name: String,
+    action: TransitionAction,
+    output_specs: Vec<(usize, BoxSpec)>,
+}
+
+/// Runs declared protocol transitions against a `SimulatedChain`,
+/// verifying every output against its stage spec
+pub struct ProtocolHarness {
+    chain: SimulatedChain,
+    transitions: Vec<Transition>,
+}
+
+impl ProtocolHarness {
+    /// Create a new `ProtocolHarness` starting from the provided chain
+    pub fn new(chain: SimulatedChain) -> ProtocolHarness {
+        ProtocolHarness {
+            chain,
+            transitions: vec![],
+        }
+    }
+
+    /// Declares a transition, where `action` builds the transaction and
+    /// `output_specs` pairs the output indexes with the stage `BoxSpec`
+    /// which they must satisfy.
+    pub fn with_transition(
+        mut self,
+        name: &str,
+        action: TransitionAction,
+        output_specs: Vec<(usize, BoxSpec)>,
+    ) -> Self {
+        self.transitions.push(Transition {
+            name: name.to_string(),
+            action,
+            output_specs,
+        });
+        self
+    }
+
+    /// Runs every declared transition in order, returning the resulting
+    /// chain. Errors with `HeadlessDappError::TransitionFailed` on the
+    /// first transition which fails.
+    pub fn run(mut self) -> Result<SimulatedChain> {
+        for transition in &self.transitions {
+            let fail = |e: HeadlessDappError| {
+                HeadlessDappError::TransitionFailed(transition.name.clone(), e.to_string())
+            };
+            let unsigned_tx = (transition.action)(&self.chain).map_err(fail)?;
+            let candidates = unsigned_tx.output_candidates.as_vec();
+            for (index, spec) in &transition.output_specs {
+                let candidate = candidates.get(*index).ok_or_else(|| {
+                    fail(HeadlessDappError::Other(format!(
+                        "No output at index {}",
+                        index
+                    )))
+                })?;
+                spec.verify_candidate(candidate).map_err(fail)?;
+            }
+            let created = self.chain.apply(&unsigned_tx).map_err(fail)?;
+            for (index, spec) in &transition.output_specs {
+                spec.verify_box(&created[*index]).map_err(fail)?;
+            }
+        }
+        Ok(self.chain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output_builders::{TxFeeBox, MINER_FEE_P2S_ADDRESS};
+    use crate::test_utils::test_box;
+    use crate::tx_creation::{create_candidate, new_unsigned_tx};
+
+    #[test]
+    fn runs_transition_and_verifies_outputs() {
+        let address = MINER_FEE_P2S_ADDRESS.to_string();
+        let genesis_box = test_box(3000000, &address, vec![], vec![]);
+        let stage_spec = BoxSpec::new(None, Some(2000000..3000000), vec![], vec![]);
+
+        let action: TransitionAction = Box::new(move |chain: &SimulatedChain| {
+            let input = chain.unspent_boxes()[0].clone();
+            let output = create_candidate(2000000, &address, &vec![], &vec![], chain.height())?;
+            let fee = TxFeeBox::output_candidate(1000000, chain.height())?;
+            new_unsigned_tx(vec![input.into()], vec![], vec![output, fee])
+                .map_err(HeadlessDappError::InvalidTxComposition)
+        });
+
+        let chain = ProtocolHarness::new(SimulatedChain::new(10).with_box(genesis_box))
+            .with_transition("advance", action, vec![(0, stage_spec.clone())])
+            .run()
+            .unwrap();
+        assert_eq!(chain.boxes_matching(&stage_spec).len(), 1);
+        assert_eq!(chain.unspent_boxes().len(), 2);
+    }
+}
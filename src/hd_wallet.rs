@@ -0,0 +1,290 @@
+/// This file holds the `Eip3Wallet` which derives keys and addresses from
+/// a mnemonic along the EIP-3 derivation path (`m/44'/429'/account'/0/index`)
+/// used by standard Ergo wallets. This allows locally-signing bots to use
+/// the seeds of standard wallets, while still finding which of the derived
+/// addresses owns a given box for address-based box specs.
+/// The BIP-39 seed and BIP-32 key derivation which EIP-3 builds upon are
+/// implemented here, as they are not provided by `ergo-lib` 0.14.
+use crate::error::{HeadlessDappError, Result};
+use crate::network::Network;
+use crate::ErgoAddressString;
+use ergo_lib::ergotree_interpreter::sigma_protocol::private_input::DlogProverInput;
+use ergo_lib::ergotree_ir::chain::address::{Address, AddressEncoder};
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+use ergo_lib::wallet::secret_key::SecretKey;
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha512;
+
+/// The purpose and coin type of EIP-3 paths (`m/44'/429'`)
+const EIP3_PURPOSE: u32 = 44;
+const EIP3_COIN_TYPE: u32 = 429;
+/// The flag of hardened child indexes in BIP-32
+const HARDENED: u32 = 0x8000_0000;
+/// The number of PBKDF2 rounds used to stretch a BIP-39 mnemonic
+const MNEMONIC_PBKDF2_ROUNDS: u32 = 2048;
+
+/// A wallet which derives its keys from a mnemonic per EIP-3
+#[derive(Clone)]
+pub struct Eip3Wallet {
+    master_key: ExtSecretKey,
+    account: u32,
+    network: Network,
+}
+
+impl Eip3Wallet {
+    /// Create a new `Eip3Wallet` from a mnemonic phrase and its (optional,
+    /// thus possibly empty) password, deriving keys of the first account
+    pub fn from_mnemonic(
+        mnemonic_phrase: &str,
+        mnemonic_password: &str,
+        network: Network,
+    ) -> Result<Eip3Wallet> {
+        let seed = mnemonic_to_seed(mnemonic_phrase, mnemonic_password)?;
+        let master_key = ExtSecretKey::derive_master(&seed)?;
+        Ok(Eip3Wallet {
+            master_key,
+            account: 0,
+            network,
+        })
+    }
+
+    /// Derives the keys of the `account` rather than the first account
+    pub fn with_account(mut self, account: u32) -> Self {
+        self.account = account;
+        self
+    }
+
+    /// The EIP-3 derivation path of the address at the `index` (ie.
+    /// `m/44'/429'/0'/0/3`)
+    pub fn derivation_path(&self, index: u32) -> Result<String> {
+        let path: Vec<String> = self
+            .child_indexes(index)?
+            .iter()
+            .map(|i| match i & HARDENED {
+                0 => i.to_string(),
+                _ => format!("{}'", i & !HARDENED),
+            })
+            .collect();
+        Ok(format!("m/{}", path.join("/")))
+    }
+
+    /// Derives the secret key of the address at the `index`
+    pub fn derive_secret(&self, index: u32) -> Result<SecretKey> {
+        let mut ext_secret_key = self.master_key.clone();
+        for child_index in self.child_indexes(index)?.iter() {
+            ext_secret_key = ext_secret_key.child(*child_index)?;
+        }
+        Ok(SecretKey::DlogSecretKey(ext_secret_key.secret_key))
+    }
+
+    /// The BIP-32 child indexes of the EIP-3 path of the address at the
+    /// `index`. Errors if the account or the index do not fit in 31 bits.
+    fn child_indexes(&self, index: u32) -> Result<[u32; 5]> {
+        if self.account >= HARDENED || index >= HARDENED {
+            return Err(HeadlessDappError::KeyDerivationFailed(format!(
+                "Account {} or index {} does not fit in 31 bits.",
+                self.account, index
+            )));
+        }
+        Ok([
+            EIP3_PURPOSE | HARDENED,
+            EIP3_COIN_TYPE | HARDENED,
+            self.account | HARDENED,
+            0,
+            index,
+        ])
+    }
+
+    /// Derives the secret keys of the first `count` addresses (ie. for a
+    /// `LocalWalletSigner`)
+    pub fn derive_secrets(&self, count: u32) -> Result<Vec<SecretKey>> {
+        (0..count).map(|i| self.derive_secret(i)).collect()
+    }
+
+    /// Derives the P2PK address at the `index`
+    pub fn derive_address(&self, index: u32) -> Result<ErgoAddressString> {
+        let address = self.derive_secret(index)?.get_address_from_public_image();
+        Ok(AddressEncoder::new(self.network.network_prefix()).address_to_str(&address))
+    }
+
+    /// Derives the P2PK addresses of the first `count` indexes
+    pub fn derive_addresses(&self, count: u32) -> Result<Vec<ErgoAddressString>> {
+        (0..count).map(|i| self.derive_address(i)).collect()
+    }
+
+    /// Finds the index of the derived address (among the first `count`)
+    /// which owns the `ergo_box`, if any
+    pub fn find_owner_index(&self, ergo_box: &ErgoBox, count: u32) -> Result<Option<u32>> {
+        for index in 0..count {
+            let address = self.derive_secret(index)?.get_address_from_public_image();
+            if let Address::P2Pk(_) = address {
+                let ergo_tree = address.script().map_err(derivation_error)?;
+                if ergo_tree == ergo_box.ergo_tree {
+                    return Ok(Some(index));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A BIP-32 extended secret key
+#[derive(Clone)]
+struct ExtSecretKey {
+    secret_key: DlogProverInput,
+    chain_code: [u8; 32],
+}
+
+impl ExtSecretKey {
+    /// Derives the master key from a BIP-39 seed
+    fn derive_master(seed: &[u8]) -> Result<ExtSecretKey> {
+        Self::from_hmac(&hmac_sha512(b"Bitcoin seed", &[seed])?, None)
+    }
+
+    /// Derives the child key at the `index`, which is hardened if the
+    /// `HARDENED` flag is set
+    fn child(&self, index: u32) -> Result<ExtSecretKey> {
+        let index_bytes = index.to_be_bytes();
+        let hmac = if index & HARDENED == 0 {
+            let public_key = self
+                .secret_key
+                .public_image()
+                .h
+                .sigma_serialize_bytes()
+                .map_err(derivation_error)?;
+            hmac_sha512(&self.chain_code, &[&public_key, &index_bytes])?
+        } else {
+            let secret_key = self.secret_key.to_bytes();
+            hmac_sha512(&self.chain_code, &[&[0u8], &secret_key, &index_bytes])?
+        };
+        Self::from_hmac(&hmac, Some(&self.secret_key))
+    }
+
+    /// Builds the key from the left (secret key, added to the `parent` key
+    /// for child keys) and right (chain code) halves of an HMAC-SHA512
+    fn from_hmac(hmac: &[u8; 64], parent: Option<&DlogProverInput>) -> Result<ExtSecretKey> {
+        let mut secret_key_bytes = [0u8; 32];
+        secret_key_bytes.copy_from_slice(&hmac[..32]);
+        let mut secret_key = DlogProverInput::from_bytes(&secret_key_bytes).ok_or_else(|| {
+            HeadlessDappError::KeyDerivationFailed("Derived an invalid secret key.".to_string())
+        })?;
+        if let Some(parent) = parent {
+            secret_key = DlogProverInput::from(secret_key.w + parent.w);
+        }
+        if secret_key.to_bytes() == [0u8; 32] {
+            return Err(HeadlessDappError::KeyDerivationFailed(
+                "Derived a zero secret key.".to_string(),
+            ));
+        }
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(&hmac[32..]);
+        Ok(ExtSecretKey {
+            secret_key,
+            chain_code,
+        })
+    }
+}
+
+/// Stretches a mnemonic phrase and its password into a BIP-39 seed via
+/// PBKDF2-HMAC-SHA512. The phrase and password are used as provided, and
+/// so must already be NFKD normalized if they hold non-ASCII characters.
+fn mnemonic_to_seed(mnemonic_phrase: &str, mnemonic_password: &str) -> Result<[u8; 64]> {
+    let salt = format!("mnemonic{}", mnemonic_password);
+    // The seed is exactly one HMAC-SHA512 output long, so only the first
+    // PBKDF2 block is needed
+    let mut block = hmac_sha512(
+        mnemonic_phrase.as_bytes(),
+        &[salt.as_bytes(), &1u32.to_be_bytes()],
+    )?;
+    let mut seed = block;
+    for _ in 1..MNEMONIC_PBKDF2_ROUNDS {
+        block = hmac_sha512(mnemonic_phrase.as_bytes(), &[&block])?;
+        for (s, b) in seed.iter_mut().zip(block.iter()) {
+            *s ^= b;
+        }
+    }
+    Ok(seed)
+}
+
+/// Computes the HMAC-SHA512 of the concatenated `data` under the `key`
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> Result<[u8; 64]> {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).map_err(derivation_error)?;
+    for d in data {
+        mac.update(d);
+    }
+    let mut output = [0u8; 64];
+    output.copy_from_slice(&mac.finalize().into_bytes());
+    Ok(output)
+}
+
+fn derivation_error<E: std::fmt::Display>(e: E) -> HeadlessDappError {
+    HeadlessDappError::KeyDerivationFailed(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_box;
+
+    const MNEMONIC: &str = "change me do not use me change me do not use me";
+
+    #[test]
+    fn derives_addresses_and_finds_owners() {
+        let wallet = Eip3Wallet::from_mnemonic(MNEMONIC, "", Network::Mainnet).unwrap();
+        let addresses = wallet.derive_addresses(3).unwrap();
+        assert_eq!(addresses.len(), 3);
+        assert_ne!(addresses[0], addresses[1]);
+        assert!(addresses[0].starts_with('9'));
+
+        let ergo_box = test_box(1000000, &addresses[2], vec![], vec![]);
+        assert_eq!(wallet.find_owner_index(&ergo_box, 5).unwrap(), Some(2));
+        assert_eq!(wallet.find_owner_index(&ergo_box, 2).unwrap(), None);
+        assert_ne!(
+            wallet.clone().with_account(1).derive_address(0).unwrap(),
+            addresses[0]
+        );
+    }
+
+    #[test]
+    fn matches_bip39_and_bip32_test_vectors() {
+        let seed = mnemonic_to_seed(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            "TREZOR",
+        )
+        .unwrap();
+        assert_eq!(
+            base16::encode_lower(&seed[..]),
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+        );
+
+        let seed = base16::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master_key = ExtSecretKey::derive_master(&seed).unwrap();
+        assert_eq!(
+            base16::encode_lower(&master_key.secret_key.to_bytes()),
+            "e8f32e723decf4051aefac8e2c93c9c5b214313817cdb01a1494b917c8436b35"
+        );
+        assert_eq!(
+            base16::encode_lower(&master_key.chain_code),
+            "873dff81c02f525623fd1fe5167eac3a55a049de3d314bb42ee227ffed37d508"
+        );
+        let child_key = master_key.child(HARDENED).unwrap().child(1).unwrap();
+        assert_eq!(
+            base16::encode_lower(&child_key.secret_key.to_bytes()),
+            "3c6cb8d0f6a264c91ea8b5030fadaa8e538b020f0a387421a12de9319dc93368"
+        );
+        assert_eq!(
+            base16::encode_lower(&child_key.chain_code),
+            "2a7857631386ba23dacac34180dd1983734e444fdbf774041578e9b6adb37c19"
+        );
+    }
+
+    #[test]
+    fn renders_eip3_derivation_path() {
+        let wallet = Eip3Wallet::from_mnemonic(MNEMONIC, "", Network::Mainnet)
+            .unwrap()
+            .with_account(2);
+        assert_eq!(wallet.derivation_path(7).unwrap(), "m/44'/429'/2'/0/7");
+        assert!(wallet.derivation_path(HARDENED).is_err());
+    }
+}
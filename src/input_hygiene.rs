@@ -0,0 +1,134 @@
+/// This file holds the `InputHygiene` option of box selection, which
+/// excludes boxes originating from a configurable list of addresses (ie.
+/// the output scripts of known mixers). This is intended for dApps with
+/// compliance constraints on the funds they co-spend within a transaction.
+/// The origin of a box is acquired from its funding transaction, and so
+/// the explorer responses of the funding transactions are required.
+use crate::box_traits::WrappedBox;
+use crate::encoding::serialize_address_from_ergo_tree;
+use crate::error::{HeadlessDappError, Result};
+use crate::explorer_url::ExplorerUrl;
+use crate::{ErgoAddressString, TxId};
+use ergo_lib::ergotree_ir::chain::digest32::Digest32;
+use std::collections::{HashMap, HashSet};
+
+/// A list of addresses whose boxes, and any box funded by them, are
+/// excluded from box selection
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InputHygiene {
+    excluded_addresses: HashSet<ErgoAddressString>,
+}
+
+impl InputHygiene {
+    /// Create a new `InputHygiene` which excludes nothing
+    pub fn new() -> InputHygiene {
+        InputHygiene::default()
+    }
+
+    /// Excludes boxes originating from the `address`
+    pub fn with_excluded_address(mut self, address: &ErgoAddressString) -> Self {
+        self.excluded_addresses.insert(address.clone());
+        self
+    }
+
+    /// Excludes boxes originating from any of the `addresses`
+    pub fn with_excluded_addresses(mut self, addresses: &[ErgoAddressString]) -> Self {
+        self.excluded_addresses.extend(addresses.iter().cloned());
+        self
+    }
+
+    /// Whether the `address` is excluded
+    pub fn is_excluded_address(&self, address: &ErgoAddressString) -> bool {
+        self.excluded_addresses.contains(address)
+    }
+
+    /// Returns the ID of the transaction which created the box
+    pub fn funding_tx_id(wrapped_box: &dyn WrappedBox) -> TxId {
+        let tx_id_digest32: Digest32 = wrapped_box.get_box_ref().transaction_id.0.clone();
+        tx_id_digest32.into()
+    }
+
+    /// Generates the explorer endpoints of the funding transactions of the
+    /// boxes as `(tx id, url)` pairs, skipping duplicates. The response
+    /// bodies are provided to `filter_boxes()` keyed by the tx id.
+    pub fn funding_tx_endpoints<T: WrappedBox>(
+        boxes: &[T],
+        explorer_api_url: &str,
+    ) -> Vec<(TxId, String)> {
+        let mut endpoints: Vec<(TxId, String)> = vec![];
+        for b in boxes {
+            let tx_id = Self::funding_tx_id(b);
+            if !endpoints.iter().any(|(id, _)| *id == tx_id) {
+                let url = ExplorerUrl::new(explorer_api_url)
+                    .path("v1/transactions")
+                    .segment(&tx_id)
+                    .build();
+                endpoints.push((tx_id, url));
+            }
+        }
+        endpoints
+    }
+
+    /// Whether any input of the funding transaction (provided as the
+    /// explorer response body) is locked under an excluded address
+    pub fn is_excluded_funding_tx(&self, funding_tx_response_body: &str) -> Result<bool> {
+        let json = json::parse(funding_tx_response_body).map_err(|_| {
+            HeadlessDappError::Other(
+                "Failed to extract json from Ergo Explorer Backend API Response".to_string(),
+            )
+        })?;
+        Ok(json["inputs"].members().any(|input| {
+            input["address"]
+                .as_str()
+                .is_some_and(|a| self.is_excluded_address(&a.to_string()))
+        }))
+    }
+
+    /// Filters out every box which is either locked under an excluded
+    /// address, or whose funding transaction spent a box locked under an
+    /// excluded address. `funding_tx_response_bodies` must hold the
+    /// explorer response body of the funding transaction of every box,
+    /// keyed by tx id.
+    pub fn filter_boxes<T: WrappedBox + Clone>(
+        &self,
+        boxes: &[T],
+        funding_tx_response_bodies: &HashMap<TxId, String>,
+    ) -> Result<Vec<T>> {
+        if self.excluded_addresses.is_empty() {
+            return Ok(boxes.to_vec());
+        }
+        let mut filtered = vec![];
+        for b in boxes {
+            let address = serialize_address_from_ergo_tree(b.get_box_ref().ergo_tree.clone())?;
+            if self.is_excluded_address(&address) {
+                continue;
+            }
+            let tx_id = Self::funding_tx_id(b);
+            let body = funding_tx_response_bodies.get(&tx_id).ok_or_else(|| {
+                HeadlessDappError::Other(format!(
+                    "The funding transaction {} of box {} was not provided",
+                    tx_id,
+                    b.box_id()
+                ))
+            })?;
+            if !self.is_excluded_funding_tx(body)? {
+                filtered.push(b.clone());
+            }
+        }
+        Ok(filtered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_excluded_funding_tx() {
+        let hygiene = InputHygiene::new().with_excluded_address(&"mixer".to_string());
+        let mixed = r#"{"inputs": [{"address": "user"}, {"address": "mixer"}]}"#;
+        let clean = r#"{"inputs": [{"address": "user"}]}"#;
+        assert!(hygiene.is_excluded_funding_tx(mixed).unwrap());
+        assert!(!hygiene.is_excluded_funding_tx(clean).unwrap());
+    }
+}
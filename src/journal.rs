@@ -0,0 +1,374 @@
+/// This file holds the `Journal`, an optional append-only record of the
+/// decisions of a headless dApp service (ie. a bot): every box matched by
+/// a spec, every Action built, and every transaction submitted, together
+/// with the heights and ids involved. Operators can use the journal to
+/// audit and replay what their bot did. Entries are written as JSONL or
+/// CSV lines into any `JournalStorage`, with in-memory and file backed
+/// storages provided.
+use crate::error::{HeadlessDappError, Result};
+use crate::tx_creation::unsigned_tx_fingerprint;
+use crate::{BlockHeight, TxId};
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+const CSV_HEADER: &str = "event,height,name,id";
+
+/// An append-only store of journal lines
+pub trait JournalStorage {
+    /// Append a single line to the end of the journal
+    fn append_line(&self, line: &str) -> Result<()>;
+    /// Acquire every line of the journal in the order they were appended
+    fn read_lines(&self) -> Result<Vec<String>>;
+    /// Whether no line has been appended to the journal yet. Storages
+    /// should override the default, which reads every line.
+    fn is_empty(&self) -> Result<bool> {
+        Ok(self.read_lines()?.is_empty())
+    }
+}
+
+/// A `JournalStorage` which is held in memory and lost on restart
+#[derive(Debug, Default)]
+pub struct MemoryJournalStorage {
+    lines: RwLock<Vec<String>>,
+}
+
+impl MemoryJournalStorage {
+    /// Create a new empty `MemoryJournalStorage`
+    pub fn new() -> MemoryJournalStorage {
+        MemoryJournalStorage::default()
+    }
+}
+
+impl JournalStorage for MemoryJournalStorage {
+    fn append_line(&self, line: &str) -> Result<()> {
+        self.lines.write().unwrap().push(line.to_string());
+        Ok(())
+    }
+
+    fn read_lines(&self) -> Result<Vec<String>> {
+        Ok(self.lines.read().unwrap().clone())
+    }
+
+    fn is_empty(&self) -> Result<bool> {
+        Ok(self.lines.read().unwrap().is_empty())
+    }
+}
+
+/// A `JournalStorage` which appends to a file on disk
+#[derive(Clone, Debug)]
+pub struct FileJournalStorage {
+    path: PathBuf,
+}
+
+impl FileJournalStorage {
+    /// Create a new `FileJournalStorage` which appends to the file at
+    /// `path`, creating it on the first append if it does not exist
+    pub fn new(path: &std::path::Path) -> FileJournalStorage {
+        FileJournalStorage {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl JournalStorage for FileJournalStorage {
+    fn append_line(&self, line: &str) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+        writeln!(file, "{}", line).map_err(|e| HeadlessDappError::Other(e.to_string()))
+    }
+
+    fn read_lines(&self) -> Result<Vec<String>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+        let file =
+            std::fs::File::open(&self.path).map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+        BufReader::new(file)
+            .lines()
+            .map(|l| l.map_err(|e| HeadlessDappError::Other(e.to_string())))
+            .collect()
+    }
+
+    fn is_empty(&self) -> Result<bool> {
+        match std::fs::metadata(&self.path) {
+            Ok(metadata) => Ok(metadata.len() == 0),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(true),
+            Err(e) => Err(HeadlessDappError::Other(e.to_string())),
+        }
+    }
+}
+
+/// The format of the lines written to a `JournalStorage`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JournalFormat {
+    /// One JSON object per line
+    Jsonl,
+    /// Comma-separated values, preceded by a header line
+    Csv,
+}
+
+/// The kind of decision recorded by a `JournalEntry`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalEvent {
+    /// A box matched a spec. The id is the Box ID.
+    BoxMatched,
+    /// An Action was built. The id is the fingerprint of the unsigned
+    /// transaction (see `unsigned_tx_fingerprint()`).
+    ActionBuilt,
+    /// A transaction was submitted. The id is the Transaction ID.
+    TxSubmitted,
+}
+
+impl JournalEvent {
+    fn name(&self) -> &'static str {
+        match self {
+            JournalEvent::BoxMatched => "box_matched",
+            JournalEvent::ActionBuilt => "action_built",
+            JournalEvent::TxSubmitted => "tx_submitted",
+        }
+    }
+
+    fn from_name(name: &str) -> Result<JournalEvent> {
+        match name {
+            "box_matched" => Ok(JournalEvent::BoxMatched),
+            "action_built" => Ok(JournalEvent::ActionBuilt),
+            "tx_submitted" => Ok(JournalEvent::TxSubmitted),
+            _ => Err(HeadlessDappError::Other(format!(
+                "Unknown journal event: {}",
+                name
+            ))),
+        }
+    }
+}
+
+/// A single decision recorded in the journal
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// The kind of decision
+    pub event: JournalEvent,
+    /// The height of the chain when the decision was made
+    pub height: BlockHeight,
+    /// The name of the spec or Action the decision relates to
+    pub name: String,
+    /// The id of the box or transaction (see `JournalEvent`)
+    pub id: String,
+}
+
+impl JournalEntry {
+    fn to_line(&self, format: JournalFormat) -> Result<String> {
+        match format {
+            JournalFormat::Jsonl => {
+                serde_json::to_string(self).map_err(|e| HeadlessDappError::Other(e.to_string()))
+            }
+            JournalFormat::Csv => Ok(format!(
+                "{},{},{},{}",
+                self.event.name(),
+                self.height,
+                csv_field(&self.name),
+                csv_field(&self.id)
+            )),
+        }
+    }
+
+    fn from_line(line: &str, format: JournalFormat) -> Result<JournalEntry> {
+        match format {
+            JournalFormat::Jsonl => {
+                serde_json::from_str(line).map_err(|e| HeadlessDappError::Other(e.to_string()))
+            }
+            JournalFormat::Csv => {
+                let fields = parse_csv_line(line);
+                if fields.len() != 4 {
+                    return Err(HeadlessDappError::Other(format!(
+                        "Invalid journal line: {}",
+                        line
+                    )));
+                }
+                Ok(JournalEntry {
+                    event: JournalEvent::from_name(&fields[0])?,
+                    height: fields[1].parse().map_err(|_| {
+                        HeadlessDappError::Other(format!("Invalid height: {}", fields[1]))
+                    })?,
+                    name: fields[2].clone(),
+                    id: fields[3].clone(),
+                })
+            }
+        }
+    }
+}
+
+/// Quotes a CSV field if it holds a comma or a quote
+fn csv_field(field: &str) -> String {
+    if field.contains(',') || field.contains('"') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Splits a CSV line into its (unquoted) fields
+fn parse_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// An append-only journal of the decisions of a headless dApp service
+pub struct Journal<S: JournalStorage> {
+    storage: S,
+    format: JournalFormat,
+}
+
+impl<S: JournalStorage> Journal<S> {
+    /// Create a new `Journal` writing lines of the `format` into the
+    /// `storage`
+    pub fn new(storage: S, format: JournalFormat) -> Journal<S> {
+        Journal { storage, format }
+    }
+
+    /// Appends the `entry` to the journal
+    pub fn record(&self, entry: &JournalEntry) -> Result<()> {
+        if self.format == JournalFormat::Csv && self.storage.is_empty()? {
+            self.storage.append_line(CSV_HEADER)?;
+        }
+        self.storage.append_line(&entry.to_line(self.format)?)
+    }
+
+    /// Records that the `ergo_box` matched the spec with the `spec_name`
+    pub fn record_box_matched(
+        &self,
+        height: BlockHeight,
+        spec_name: &str,
+        ergo_box: &ErgoBox,
+    ) -> Result<()> {
+        self.record(&JournalEntry {
+            event: JournalEvent::BoxMatched,
+            height,
+            name: spec_name.to_string(),
+            id: ergo_box.box_id().into(),
+        })
+    }
+
+    /// Records that the Action with the `action_name` was built
+    pub fn record_action_built(
+        &self,
+        height: BlockHeight,
+        action_name: &str,
+        unsigned_tx: &UnsignedTransaction,
+    ) -> Result<()> {
+        self.record(&JournalEntry {
+            event: JournalEvent::ActionBuilt,
+            height,
+            name: action_name.to_string(),
+            id: unsigned_tx_fingerprint(unsigned_tx)?,
+        })
+    }
+
+    /// Records that the transaction of the Action with the `action_name`
+    /// was submitted
+    pub fn record_tx_submitted(
+        &self,
+        height: BlockHeight,
+        action_name: &str,
+        tx_id: &TxId,
+    ) -> Result<()> {
+        self.record(&JournalEntry {
+            event: JournalEvent::TxSubmitted,
+            height,
+            name: action_name.to_string(),
+            id: tx_id.clone(),
+        })
+    }
+
+    /// Acquires every entry of the journal in the order they were recorded
+    pub fn entries(&self) -> Result<Vec<JournalEntry>> {
+        self.storage
+            .read_lines()?
+            .iter()
+            .filter(|line| !(self.format == JournalFormat::Csv && line.as_str() == CSV_HEADER))
+            .map(|line| JournalEntry::from_line(line, self.format))
+            .collect()
+    }
+
+    /// Acquires the entries of the journal recorded at or after the
+    /// `height`, in the order they were recorded
+    pub fn entries_since(&self, height: BlockHeight) -> Result<Vec<JournalEntry>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .filter(|e| e.height >= height)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_round_trip_in_both_formats() {
+        for format in [JournalFormat::Jsonl, JournalFormat::Csv].iter() {
+            let journal = Journal::new(MemoryJournalStorage::new(), *format);
+            journal
+                .record(&JournalEntry {
+                    event: JournalEvent::BoxMatched,
+                    height: 100,
+                    name: "stage, \"v2\"".to_string(),
+                    id: "ab".to_string(),
+                })
+                .unwrap();
+            journal
+                .record_tx_submitted(101, "bootstrap", &"cd".to_string())
+                .unwrap();
+            let entries = journal.entries().unwrap();
+            assert_eq!(entries.len(), 2);
+            assert_eq!(entries[0].name, "stage, \"v2\"");
+            assert_eq!(entries[1].event, JournalEvent::TxSubmitted);
+            assert_eq!(journal.entries_since(101).unwrap().len(), 1);
+        }
+    }
+
+    #[test]
+    fn file_journal_writes_csv_header_once() {
+        let path = std::env::temp_dir().join(format!("hdf_journal_{}.csv", std::process::id()));
+        let storage = FileJournalStorage::new(&path);
+        assert!(storage.is_empty().unwrap());
+        let journal = Journal::new(storage.clone(), JournalFormat::Csv);
+        journal
+            .record_tx_submitted(100, "bootstrap", &"ab".to_string())
+            .unwrap();
+        assert!(!storage.is_empty().unwrap());
+        // A journal reopened on the same file does not repeat the header
+        let journal = Journal::new(storage.clone(), JournalFormat::Csv);
+        journal
+            .record_tx_submitted(101, "bootstrap", &"cd".to_string())
+            .unwrap();
+        let lines = storage.read_lines().unwrap();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(journal.entries().unwrap().len(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,94 @@
+/// This file holds a registry of named constants for well-known token ids,
+/// NFT ids, and contract addresses, per `Network`. Specified boxes and
+/// protocols should reference these audited constants rather than
+/// scattering hex literals throughout their code.
+use crate::network::Network;
+
+/// The token id of the NFT of the ERG/USD oracle pool
+pub const ERG_USD_ORACLE_POOL_NFT_ID: &str =
+    "008a94c8c76bbaa1f0a346697d1794eb31d94b37e5533af9cc0b6932bf159339";
+
+/// The token id of the NFT of the ADA/USD oracle pool
+pub const ADA_USD_ORACLE_POOL_NFT_ID: &str =
+    "19475d9a78377ff0f36e9826cec439727bea522f6ffa3bda32e20d2f8b3103ac";
+
+/// The token id of the SigmaUSD stablecoin (SigUSD)
+pub const SIGUSD_TOKEN_ID: &str =
+    "03faf2cb329f2e90d6d23b58d91bbb6c046aa143261cc21f52fbe2824bfcbf04";
+
+/// The token id of the SigmaUSD reserve coin (SigRSV)
+pub const SIGRSV_TOKEN_ID: &str =
+    "003bd19d0187117f130b62e1bcab0939929ff5c7709f843c5c4dd158949285d0";
+
+/// The P2S address of the miner fee contract
+pub const MINER_FEE_P2S_ADDRESS: &str = "2iHkR7CWvD1R4j1yZg5bkeDRQavjAaVPeTDFGGLZduHyfWMuYpmhHocX8GJoaieTx78FntzJbCBVL6rf96ocJoZdmWBL2fci7NqWgAirppPQmZ7fN9V6z13Ay6brPriBKYqLp1bT2Fk4FkFLCfdPpe";
+
+/// All of the known mainnet ids as `(name, id)` pairs
+pub const KNOWN_IDS: &[(&str, &str)] = &[
+    ("erg_usd_oracle_pool_nft", ERG_USD_ORACLE_POOL_NFT_ID),
+    ("ada_usd_oracle_pool_nft", ADA_USD_ORACLE_POOL_NFT_ID),
+    ("sigusd", SIGUSD_TOKEN_ID),
+    ("sigrsv", SIGRSV_TOKEN_ID),
+    ("miner_fee_address", MINER_FEE_P2S_ADDRESS),
+];
+
+/// All of the known testnet ids as `(name, id)` pairs. The ids of the
+/// mainnet oracle pools and SigmaUSD tokens do not exist on testnet, and
+/// so are only listed here once their testnet deployments are audited.
+/// Until then testnet pools are referenced by their NFT id directly (ie.
+/// via `OraclePoolSpec::new()`).
+pub const TESTNET_KNOWN_IDS: &[(&str, &str)] = &[];
+
+/// All of the known ids of the `network` as `(name, id)` pairs
+pub fn known_ids(network: Network) -> &'static [(&'static str, &'static str)] {
+    match network {
+        Network::Mainnet => KNOWN_IDS,
+        Network::Testnet => TESTNET_KNOWN_IDS,
+    }
+}
+
+/// Looks up a known mainnet id by its name
+pub fn known_id(name: &str) -> Option<&'static str> {
+    known_id_on(Network::Mainnet, name)
+}
+
+/// Looks up a known id of the `network` by its name
+pub fn known_id_on(network: Network, name: &str) -> Option<&'static str> {
+    known_ids(network)
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, id)| *id)
+}
+
+/// Looks up the name of a known id
+pub fn known_id_name(id: &str) -> Option<&'static str> {
+    KNOWN_IDS
+        .iter()
+        .find(|(_, i)| *i == id)
+        .map(|(name, _)| *name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_known_ids() {
+        assert_eq!(known_id("sigusd"), Some(SIGUSD_TOKEN_ID));
+        assert_eq!(known_id_name(SIGRSV_TOKEN_ID), Some("sigrsv"));
+        assert_eq!(known_id("unknown"), None);
+    }
+
+    #[test]
+    fn lookup_known_ids_per_network() {
+        assert_eq!(
+            known_id_on(Network::Mainnet, "erg_usd_oracle_pool_nft"),
+            Some(ERG_USD_ORACLE_POOL_NFT_ID)
+        );
+        // Mainnet ids never resolve on testnet
+        assert_eq!(
+            known_id_on(Network::Testnet, "erg_usd_oracle_pool_nft"),
+            None
+        );
+    }
+}
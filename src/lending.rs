@@ -0,0 +1,309 @@
+/// This file holds the specified boxes of Erg lending pool protocols (ie.
+/// Duckpools) along with the interest rate math required to reason about
+/// them, allowing liquidation bots to be built on top of the framework.
+/// A pool box holds the pool NFT and the nanoErgs available to borrow,
+/// with the following registers:
+/// R4: `Long` total nanoErgs currently borrowed from the pool
+/// R5: `Coll[Long]` interest model of the pool, as basis points:
+///     [base rate, slope, jump slope, optimal utilization]
+/// A collateral box is locked under the collateral contract and holds the
+/// collateral token, with the following registers:
+/// R4: `Coll[Byte]` ErgoTree bytes of the borrower
+/// R5: `Long` principal of the loan in nanoErgs
+/// R6: `Int` block height at which the loan was taken
+/// R7: `Long` liquidation threshold in basis points of the collateral value
+use crate::box_spec::{BoxSpec, RegisterSpec, TokenSpec};
+use crate::box_traits::{SpecSource, Specified, WrappedBox};
+use crate::encoding::{deserialize_ergo_tree_constant, unwrap_int, unwrap_long, unwrap_long_coll};
+use crate::error::{HeadlessDappError, Result};
+use crate::math::{apply_bps, BPS_DENOMINATOR};
+use crate::time::TARGET_BLOCK_TIME_SECS;
+use crate::{BlockDuration, BlockHeight, ErgoAddressString, NanoErg, P2SAddressString, TokenID};
+use ergo_lib::ergotree_ir::types::stype::SType;
+
+/// The number of blocks produced in a year at the target block time
+pub const BLOCKS_PER_YEAR: BlockDuration = 365 * 24 * 60 * 60 / TARGET_BLOCK_TIME_SECS;
+
+/// A pool box of the `LendingPools` it was found with
+pub type LendingPoolBox = Specified<LendingPools>;
+/// A collateral box of the `CollateralPositions` it was found with
+pub type CollateralBox = Specified<CollateralPositions>;
+
+/// A kinked interest rate model. The borrow rate grows linearly with the
+/// utilization of the pool by `slope_bps` until the optimal utilization,
+/// and by `jump_slope_bps` past it. All rates are annual.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InterestModel {
+    pub base_rate_bps: u64,
+    pub slope_bps: u64,
+    pub jump_slope_bps: u64,
+    pub optimal_utilization_bps: u64,
+}
+
+impl InterestModel {
+    /// Create a new `InterestModel`
+    pub fn new(
+        base_rate_bps: u64,
+        slope_bps: u64,
+        jump_slope_bps: u64,
+        optimal_utilization_bps: u64,
+    ) -> InterestModel {
+        InterestModel {
+            base_rate_bps,
+            slope_bps,
+            jump_slope_bps,
+            optimal_utilization_bps,
+        }
+    }
+
+    /// Parses the model from its `Coll[Long]` register representation
+    pub fn from_register_values(values: &[i64]) -> Result<InterestModel> {
+        if values.len() != 4 || values.iter().any(|v| *v < 0) {
+            return Err(HeadlessDappError::Other(
+                "The interest model must hold 4 non-negative values".to_string(),
+            ));
+        }
+        Ok(InterestModel::new(
+            values[0] as u64,
+            values[1] as u64,
+            values[2] as u64,
+            values[3] as u64,
+        ))
+    }
+
+    /// The annual borrow rate in basis points at the provided utilization
+    pub fn borrow_rate_bps(&self, utilization_bps: u64) -> Result<u64> {
+        let utilization_bps = utilization_bps.min(BPS_DENOMINATOR);
+        let below_kink = utilization_bps.min(self.optimal_utilization_bps);
+        let above_kink = utilization_bps.saturating_sub(self.optimal_utilization_bps);
+        let slope_rate = apply_bps(self.slope_bps, below_kink)?;
+        let jump_rate = apply_bps(self.jump_slope_bps, above_kink)?;
+        self.base_rate_bps
+            .checked_add(slope_rate)
+            .and_then(|r| r.checked_add(jump_rate))
+            .ok_or(HeadlessDappError::ArithmeticOverflow)
+    }
+}
+
+/// The utilization of a pool in basis points, ie. the share of the total
+/// liquidity which is currently borrowed
+pub fn utilization_bps(borrowed: NanoErg, available: NanoErg) -> u64 {
+    let total = borrowed as u128 + available as u128;
+    if total == 0 {
+        return 0;
+    }
+    (borrowed as u128 * BPS_DENOMINATOR as u128 / total) as u64
+}
+
+/// The interest accrued on `principal` over `blocks` at the provided annual
+/// rate, using simple (non-compounding) interest and rounding down
+pub fn accrued_interest(
+    principal: NanoErg,
+    annual_rate_bps: u64,
+    blocks: BlockDuration,
+) -> Result<NanoErg> {
+    let interest = principal as u128 * annual_rate_bps as u128 * blocks as u128
+        / (BPS_DENOMINATOR as u128 * BLOCKS_PER_YEAR as u128);
+    if interest > u64::MAX as u128 {
+        return Err(HeadlessDappError::ArithmeticOverflow);
+    }
+    Ok(interest as u64)
+}
+
+/// The pool boxes of a lending protocol, identified by the pool NFT
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LendingPools {
+    pool_nft_id: TokenID,
+}
+
+impl LendingPools {
+    /// Create a new `LendingPools` for the `pool_nft_id`
+    pub fn new(pool_nft_id: &str) -> LendingPools {
+        LendingPools {
+            pool_nft_id: pool_nft_id.to_string(),
+        }
+    }
+}
+
+impl SpecSource for LendingPools {
+    /// A box spec for a pool box holding the pool NFT
+    fn box_spec(&self) -> BoxSpec {
+        let registers = vec![
+            RegisterSpec::new(Some(SType::SLong), None),
+            RegisterSpec::new(Some(SType::SColl(Box::new(SType::SLong))), None),
+        ];
+        let tokens = vec![Some(TokenSpec::new(1..2, &self.pool_nft_id))];
+        BoxSpec::new(None, None, registers, tokens)
+    }
+}
+
+/// The collateral boxes of a lending protocol, identified by the address of
+/// the collateral contract
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CollateralPositions {
+    contract_address: P2SAddressString,
+}
+
+impl CollateralPositions {
+    /// Create a new `CollateralPositions` for the collateral contract at
+    /// `contract_address`
+    pub fn new(contract_address: &P2SAddressString) -> CollateralPositions {
+        CollateralPositions {
+            contract_address: contract_address.clone(),
+        }
+    }
+
+    /// Returns the address of the collateral contract
+    pub fn contract_address(&self) -> P2SAddressString {
+        self.contract_address.clone()
+    }
+}
+
+impl SpecSource for CollateralPositions {
+    /// A box spec for a collateral box locked under the collateral contract
+    fn box_spec(&self) -> BoxSpec {
+        let registers = vec![
+            RegisterSpec::new(Some(SType::SColl(Box::new(SType::SByte))), None),
+            RegisterSpec::new(Some(SType::SLong), None),
+            RegisterSpec::new(Some(SType::SInt), None),
+            RegisterSpec::new(Some(SType::SLong), None),
+        ];
+        BoxSpec::new(
+            Some(self.contract_address.clone()),
+            None,
+            registers,
+            vec![None],
+        )
+    }
+}
+
+/// Rust LendingPoolBox Methods
+impl LendingPoolBox {
+    /// Returns the nanoErgs available to be borrowed from the pool
+    pub fn available(&self) -> NanoErg {
+        self.nano_ergs()
+    }
+
+    /// Returns the total nanoErgs currently borrowed from R4
+    pub fn borrowed(&self) -> Result<NanoErg> {
+        Ok(unwrap_long(&self.checked_register(0)?)? as NanoErg)
+    }
+
+    /// Returns the interest model of the pool from R5
+    pub fn interest_model(&self) -> Result<InterestModel> {
+        InterestModel::from_register_values(&unwrap_long_coll(&self.checked_register(1)?)?)
+    }
+
+    /// Returns the current utilization of the pool in basis points
+    pub fn utilization_bps(&self) -> Result<u64> {
+        Ok(utilization_bps(self.borrowed()?, self.available()))
+    }
+
+    /// Returns the current annual borrow rate of the pool in basis points
+    pub fn borrow_rate_bps(&self) -> Result<u64> {
+        self.interest_model()?
+            .borrow_rate_bps(self.utilization_bps()?)
+    }
+}
+
+/// Rust CollateralBox Methods
+impl CollateralBox {
+    /// Returns the address of the borrower from R4
+    pub fn borrower(&self) -> Result<ErgoAddressString> {
+        Ok(deserialize_ergo_tree_constant(&self.checked_register(0)?)?)
+    }
+
+    /// Returns the principal of the loan in nanoErgs from R5
+    pub fn principal(&self) -> Result<NanoErg> {
+        Ok(unwrap_long(&self.checked_register(1)?)? as NanoErg)
+    }
+
+    /// Returns the block height at which the loan was taken from R6
+    pub fn loan_height(&self) -> Result<BlockHeight> {
+        Ok(unwrap_int(&self.checked_register(2)?)? as BlockHeight)
+    }
+
+    /// Returns the liquidation threshold in basis points from R7
+    pub fn liquidation_threshold_bps(&self) -> Result<u64> {
+        Ok(unwrap_long(&self.checked_register(3)?)? as u64)
+    }
+
+    /// Returns the amount of collateral tokens held in the box
+    pub fn collateral_amount(&self) -> u64 {
+        self.tokens()[0].amount.into()
+    }
+
+    /// The debt of the loan (principal and accrued interest) at the
+    /// `current_height`, accruing at the annual `borrow_rate_bps`
+    pub fn debt(&self, borrow_rate_bps: u64, current_height: BlockHeight) -> Result<NanoErg> {
+        let principal = self.principal()?;
+        let blocks = current_height.saturating_sub(self.loan_height()?);
+        principal
+            .checked_add(accrued_interest(principal, borrow_rate_bps, blocks)?)
+            .ok_or(HeadlessDappError::ArithmeticOverflow)
+    }
+
+    /// The value of the collateral in nanoErgs given the price of one
+    /// collateral token in nanoErgs
+    pub fn collateral_value(&self, token_price: NanoErg) -> Result<NanoErg> {
+        self.collateral_amount()
+            .checked_mul(token_price)
+            .ok_or(HeadlessDappError::ArithmeticOverflow)
+    }
+
+    /// The health factor of the loan in basis points, ie. the liquidation
+    /// threshold of the collateral value divided by the debt. A health
+    /// factor below `BPS_DENOMINATOR` means the box can be liquidated.
+    pub fn health_factor_bps(
+        &self,
+        token_price: NanoErg,
+        borrow_rate_bps: u64,
+        current_height: BlockHeight,
+    ) -> Result<u64> {
+        let max_debt = apply_bps(
+            self.collateral_value(token_price)?,
+            self.liquidation_threshold_bps()?,
+        )?;
+        let debt = self.debt(borrow_rate_bps, current_height)?;
+        if debt == 0 {
+            return Ok(u64::MAX);
+        }
+        let health_factor = max_debt as u128 * BPS_DENOMINATOR as u128 / debt as u128;
+        Ok(health_factor.min(u64::MAX as u128) as u64)
+    }
+
+    /// Whether the debt of the loan exceeds the liquidation threshold of the
+    /// collateral value, meaning the box can be liquidated
+    pub fn is_liquidatable(
+        &self,
+        token_price: NanoErg,
+        borrow_rate_bps: u64,
+        current_height: BlockHeight,
+    ) -> Result<bool> {
+        let max_debt = apply_bps(
+            self.collateral_value(token_price)?,
+            self.liquidation_threshold_bps()?,
+        )?;
+        Ok(self.debt(borrow_rate_bps, current_height)? > max_debt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interest_model_math() {
+        let model = InterestModel::new(200, 1000, 10000, 8000);
+        assert_eq!(utilization_bps(0, 0), 0);
+        assert_eq!(utilization_bps(1, 3), 2500);
+        assert_eq!(model.borrow_rate_bps(0).unwrap(), 200);
+        assert_eq!(model.borrow_rate_bps(8000).unwrap(), 1000);
+        assert_eq!(model.borrow_rate_bps(9000).unwrap(), 2000);
+        assert_eq!(
+            accrued_interest(1000000000, 1000, BLOCKS_PER_YEAR).unwrap(),
+            100000000
+        );
+        assert!(InterestModel::from_register_values(&[1, 2, 3]).is_err());
+    }
+}
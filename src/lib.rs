@@ -1,26 +1,165 @@
+// Allows the code generated by the derives to refer to the framework by
+// its crate name, including within the framework itself
+extern crate self as ergo_headless_dapp_framework;
+
+pub mod action_context;
+pub mod action_inputs;
+pub mod action_params;
+pub mod address_book;
+#[cfg(feature = "async")]
+pub mod async_api;
+pub mod auction_house;
+pub mod auth;
 pub mod box_spec;
 pub mod box_traits;
+pub mod cancellation;
+pub mod dao;
+pub mod deposit_address;
+pub mod dry_run;
 pub mod encoding;
 pub mod error;
+pub mod explorer_backend;
+mod explorer_stream;
+pub mod explorer_url;
+pub mod fee_guard;
+pub mod hardware_wallet;
+pub mod harness;
+pub mod hd_wallet;
+pub mod input_hygiene;
+pub mod journal;
+pub mod known_ids;
+pub mod lending;
+pub mod liquidation;
+pub mod math;
+pub mod metrics;
+pub mod migration;
+#[cfg(feature = "async")]
+pub mod multi_spec_scanner;
+pub mod network;
+pub mod oracle_guard;
+pub mod oracle_history;
 pub mod output_builders;
+pub mod outputs_layout;
+pub mod pagination;
+pub mod predicate_registry;
+pub mod prelude;
+pub mod protocol_config;
+pub mod protocol_state;
+pub mod provenance;
+pub mod read_only;
+pub mod refund;
+pub mod register_constants;
+pub mod register_schema;
+pub mod render;
+pub mod replay_guard;
+pub mod response_adapter;
+#[cfg(feature = "simulation")]
+pub mod script_simulation;
+#[cfg(feature = "async")]
+pub mod signer;
 pub mod specified_boxes;
+pub mod state_store;
+pub mod stats;
+pub mod stealth;
+pub mod sweep;
+#[cfg(test)]
+pub(crate) mod test_utils;
+pub mod time;
+pub mod timelock;
+pub mod token_ledger;
+pub mod token_mint;
+pub mod token_policy;
 pub mod tx_assembler;
+pub mod tx_composer;
 pub mod tx_creation;
+pub mod value_query;
+pub mod wallet_report;
+pub mod watcher;
 
-pub use box_spec::{BoxSpec, RegisterSpec, TokenSpec};
-pub use box_traits::{ExplorerFindable, SpecifiedBox, WrappedBox};
-pub use encoding::{erg_to_nano_erg, nano_erg_to_erg};
+pub use action_context::ActionContext;
+pub use action_inputs::ActionInputs;
+pub use action_params::{ActionParams, ParamViolation};
+pub use address_book::{AddressBook, AddressRef, Named};
+pub use auction_house::{ActiveAuctionBox, AuctionHouse};
+pub use auth::{verify_signed_message, AuthRequest, AuthResponse};
+pub use box_spec::{BoxSpec, RegisterSpec, SpecVersion, TargetFormat, TokenSpec};
+pub use box_traits::{
+    DynSpecifiedBox, ExplorerFindable, SpecSource, Specified, SpecifiedBox, WrappedBox,
+};
+pub use cancellation::CancellationToken;
+pub use dao::{DaoProposalBox, DaoProposals, DaoVoteBox, DaoVotes};
+pub use deposit_address::DepositTemplate;
+pub use dry_run::{DryRunLog, ExecutionMode};
+pub use encoding::{
+    constant_from_json, erg_to_nano_erg, format_nano_erg, format_token_amount, nano_erg_to_erg,
+    parse_stype, serialize_string_with, unwrap_string_with, StringEncoding,
+};
 pub use ergo_headless_dapp_framework_derive::{SpecBox, WASMBox, WrapBox};
 pub use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
-pub use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+pub use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, NonMandatoryRegisterId};
 pub use ergo_lib::ergotree_ir::ergo_tree::ErgoTree;
 pub use ergo_lib::ergotree_ir::mir::constant::Constant;
 pub use ergo_lib::ergotree_ir::types::stype::SType;
 pub use error::{HeadlessDappError, Result};
-pub use output_builders::{ChangeBox, TokensChangeBox, TxFeeBox};
-pub use specified_boxes::{ErgUsdOraclePoolBox, ErgsBox};
+pub use explorer_backend::{ExplorerBackend, ExplorerRequest};
+pub use explorer_url::ExplorerUrl;
+pub use fee_guard::FeeGuard;
+pub use hardware_wallet::{ledger_tx_payloads, LedgerApdu, LedgerTxPayloads};
+pub use harness::{ProtocolHarness, SimulatedChain, TransitionAction};
+pub use hd_wallet::Eip3Wallet;
+pub use input_hygiene::InputHygiene;
+pub use journal::{Journal, JournalEntry, JournalFormat, JournalStorage};
+// Used by the code generated by the `WASMBox` derive
+pub use js_sys;
+pub use known_ids::{known_id, known_id_name, known_id_on};
+pub use lending::{
+    CollateralBox, CollateralPositions, InterestModel, LendingPoolBox, LendingPools,
+};
+pub use liquidation::{oracle_pool_datapoint, LiquidationTemplate, PayoutRule};
+pub use metrics::{Metrics, NoopMetrics};
+pub use migration::MigrationAction;
+#[cfg(feature = "async")]
+pub use multi_spec_scanner::MultiSpecScanner;
+pub use network::Network;
+pub use oracle_guard::DeviationGuard;
+pub use oracle_history::DatapointHistory;
+pub use output_builders::{
+    ChangeBox, OutputCandidateBuilder, ProtocolFeeBox, TokensChangeBox, TxFeeBox,
+};
+pub use outputs_layout::TxOutputsLayout;
+pub use pagination::PageCursor;
+pub use predicate_registry::{register_predicate, BoxPredicate};
+pub use protocol_config::ProtocolConfig;
+pub use protocol_state::{ProtocolState, ProtocolStateSnapshot};
+pub use provenance::{BoxProvenance, WithProvenance};
+pub use read_only::{ReadOnly, ReadOnlySpecified};
+pub use refund::{action_refund, RefundPath};
+pub use register_constants::RegisterConstant;
+pub use register_schema::{RegisterCodec, RegisterField, RegisterSchema, RegisterValue, SchemaBox};
+pub use render::{render_constant, RenderedValue};
+pub use replay_guard::ActionMarker;
+pub use response_adapter::{ExplorerResponseAdapter, FieldMappingAdapter, ResponseAdapter};
+pub use specified_boxes::{ErgUsdOraclePoolBox, ErgsBox, OraclePoolBox, OraclePoolSpec};
+pub use state_store::{BotState, MemoryStore, StateStore, SubmittedTx, TxStatus};
+pub use stats::{ProtocolStats, StageStats, StatsHistory};
+pub use stealth::{StealthPayment, StealthReceiver};
+pub use sweep::SweepTemplate;
+pub use timelock::Timelock;
+pub use token_ledger::{LedgerEntry, LedgerEntryKind, TokenLedger};
+pub use token_mint::{MintRecipient, TokenMint};
+pub use token_policy::{TokenPolicy, TokenTreatment};
 pub use tx_assembler::TxAssemblerSpecBuilder;
-pub use tx_creation::{create_candidate, find_and_sum_other_tokens};
+pub use tx_composer::TxComposer;
+pub use tx_creation::{
+    aggregate_tokens, check_unsigned_tx, create_candidate, ergo_box_from_eip12_json,
+    find_and_sum_other_tokens, sum_nano_ergs, sum_tokens, unsigned_tx_fingerprint,
+    unsigned_tx_from_node_json, unsigned_tx_to_eip12_json, unsigned_tx_to_node_json,
+};
+pub use value_query::{total_value_matching, TotalValueQuery};
+pub use wallet_report::WalletReport;
+// Used by the code generated by the `WASMBox` derive
+pub use wasm_bindgen_futures;
+pub use watcher::{AddressWatcher, ReceivedBox};
 
 /// A Base58 encoded String of an Ergo address. Can be either P2PK or P2S.
 pub type ErgoAddressString = String;
@@ -0,0 +1,314 @@
+/// This file holds the `LiquidationTemplate`, a reusable scaffold for the
+/// `action_liquidate` Action of lending-protocol keepers. The template is
+/// parameterized by the spec of the collateral box, the spec of the oracle
+/// box providing the price of the collateral, and the payout rules which
+/// define how the seized collateral is split. Before building the
+/// transaction the health factor of the collateral box is checked against
+/// the threshold of the template.
+use crate::box_spec::BoxSpec;
+use crate::box_traits::WrappedBox;
+use crate::error::{HeadlessDappError, Result};
+use crate::math::{proportional_split, BPS_DENOMINATOR};
+use crate::oracle_history::unwrap_datapoint;
+use crate::output_builders::{ChangeBox, TxFeeBox};
+use crate::specified_boxes::ErgsBox;
+use crate::tx_composer::subtract_output_tokens;
+use crate::tx_creation::{check_unsigned_tx, create_candidate, new_unsigned_tx, sum_tokens};
+use crate::{BlockHeight, ErgoAddressString, NanoErg, P2PKAddressString};
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::chain::transaction::UnsignedInput;
+use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, ErgoBoxCandidate};
+use ergo_lib::ergotree_ir::chain::token::{Token, TokenAmount};
+use std::convert::TryFrom;
+
+/// Acquires the price of the collateral from the oracle box
+pub type OraclePriceFn = fn(&ErgoBox) -> Result<NanoErg>;
+/// Computes the health factor (in basis points) of the collateral box given
+/// the price acquired from the oracle box and the current height
+pub type HealthFactorFn = fn(&ErgoBox, NanoErg, BlockHeight) -> Result<u64>;
+
+/// Reads the price from the `Long` datapoint in R4 of a standard oracle
+/// pool box
+pub fn oracle_pool_datapoint(oracle_box: &ErgoBox) -> Result<NanoErg> {
+    let registers = oracle_box.additional_registers.get_ordered_values();
+    let datapoint = registers
+        .first()
+        .ok_or(HeadlessDappError::FailedRegisterSpec)?;
+    unwrap_datapoint(datapoint)
+}
+
+/// A rule which pays out a share of the seized collateral (both nanoErgs
+/// and tokens) to an address
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PayoutRule {
+    /// The address which receives the payout
+    pub address: ErgoAddressString,
+    /// The share of the collateral paid out, in basis points. The shares
+    /// of all of the payout rules must add up to `BPS_DENOMINATOR`.
+    pub share_bps: u64,
+}
+
+/// A template of the `action_liquidate` Action of a lending protocol
+#[derive(Clone)]
+pub struct LiquidationTemplate {
+    collateral_spec: BoxSpec,
+    oracle_spec: BoxSpec,
+    oracle_price: OraclePriceFn,
+    health_factor: HealthFactorFn,
+    min_health_factor_bps: u64,
+    payout_rules: Vec<PayoutRule>,
+}
+
+impl LiquidationTemplate {
+    /// Create a new `LiquidationTemplate` without any payout rules. By
+    /// default a box is liquidatable when its health factor is below
+    /// `BPS_DENOMINATOR` (100%).
+    pub fn new(
+        collateral_spec: BoxSpec,
+        oracle_spec: BoxSpec,
+        oracle_price: OraclePriceFn,
+        health_factor: HealthFactorFn,
+    ) -> LiquidationTemplate {
+        LiquidationTemplate {
+            collateral_spec,
+            oracle_spec,
+            oracle_price,
+            health_factor,
+            min_health_factor_bps: BPS_DENOMINATOR,
+            payout_rules: vec![],
+        }
+    }
+
+    /// Sets the health factor threshold below which a box is liquidatable
+    pub fn with_min_health_factor_bps(mut self, min_health_factor_bps: u64) -> Self {
+        self.min_health_factor_bps = min_health_factor_bps;
+        self
+    }
+
+    /// Adds a payout rule. The shares of all payout rules must add up to
+    /// `BPS_DENOMINATOR`. Payout outputs are created in the order the rules
+    /// are added.
+    pub fn with_payout(mut self, address: &ErgoAddressString, share_bps: u64) -> Self {
+        self.payout_rules.push(PayoutRule {
+            address: address.clone(),
+            share_bps,
+        });
+        self
+    }
+
+    /// Returns the payout rules of the template
+    pub fn payout_rules(&self) -> &Vec<PayoutRule> {
+        &self.payout_rules
+    }
+
+    /// Verifies the collateral and oracle boxes against their specs and
+    /// returns the health factor of the collateral box, erroring if it is
+    /// not below the threshold of the template.
+    pub fn check_liquidatable(
+        &self,
+        collateral_box: &ErgoBox,
+        oracle_box: &ErgoBox,
+        current_height: BlockHeight,
+    ) -> Result<u64> {
+        self.collateral_spec.verify_box(collateral_box)?;
+        self.oracle_spec.verify_box(oracle_box)?;
+        let price = (self.oracle_price)(oracle_box)?;
+        let health_factor = (self.health_factor)(collateral_box, price, current_height)?;
+        if health_factor >= self.min_health_factor_bps {
+            return Err(HeadlessDappError::NotLiquidatable(health_factor));
+        }
+        Ok(health_factor)
+    }
+
+    /// Creates the payout output candidates which split the nanoErgs and
+    /// tokens of the collateral box according to the payout rules
+    pub fn payout_candidates(
+        &self,
+        collateral_box: &ErgoBox,
+        current_height: BlockHeight,
+    ) -> Result<Vec<ErgoBoxCandidate>> {
+        let total_share = self
+            .payout_rules
+            .iter()
+            .try_fold(0u64, |acc, r| acc.checked_add(r.share_bps));
+        if self.payout_rules.is_empty() || total_share != Some(BPS_DENOMINATOR) {
+            return Err(HeadlessDappError::InvalidLiquidation(format!(
+                "The payout rules must add up to {} bps",
+                BPS_DENOMINATOR
+            )));
+        }
+        let shares: Vec<u64> = self.payout_rules.iter().map(|r| r.share_bps).collect();
+        let values = proportional_split(*collateral_box.value.as_u64(), &shares)?;
+        let mut tokens: Vec<Vec<Token>> = vec![vec![]; shares.len()];
+        for token in &collateral_box.tokens {
+            let amounts = proportional_split(u64::from(token.amount), &shares)?;
+            for (i, amount) in amounts.into_iter().enumerate() {
+                if amount > 0 {
+                    tokens[i].push(Token {
+                        token_id: token.token_id.clone(),
+                        amount: TokenAmount::try_from(amount)
+                            .map_err(|_| HeadlessDappError::TokenAmountOverflow)?,
+                    });
+                }
+            }
+        }
+        self.payout_rules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| {
+                create_candidate(
+                    values[i],
+                    &rule.address,
+                    &tokens[i],
+                    &vec![],
+                    current_height,
+                )
+            })
+            .collect()
+    }
+
+    /// Action to liquidate the `collateral_box` using the `oracle_box` as a
+    /// data-input. The `protocol_outputs` are created first (ie. the
+    /// repayment of the debt to the pool) and are funded by the
+    /// `ergs_boxes` of the liquidator, followed by the payouts of the
+    /// collateral. Any remaining nanoErgs and tokens of the `ergs_boxes`
+    /// are sent to the `change_address`.
+    pub fn action_liquidate(
+        &self,
+        collateral_box: &ErgoBox,
+        oracle_box: &ErgoBox,
+        protocol_outputs: Vec<ErgoBoxCandidate>,
+        ergs_boxes: &Vec<ErgsBox>,
+        transaction_fee: NanoErg,
+        change_address: &P2PKAddressString,
+        current_height: BlockHeight,
+    ) -> Result<UnsignedTransaction> {
+        self.check_liquidatable(collateral_box, oracle_box, current_height)?;
+
+        let protocol_outputs_value: NanoErg =
+            protocol_outputs.iter().map(|o| *o.value.as_u64()).sum();
+        let change_value = ErgsBox::sum_ergs_boxes_value(ergs_boxes)
+            .checked_sub(protocol_outputs_value)
+            .and_then(|v| v.checked_sub(transaction_fee))
+            .ok_or_else(|| {
+                HeadlessDappError::InvalidLiquidation(
+                    "The provided boxes do not hold enough nanoErgs for the outputs and fee"
+                        .to_string(),
+                )
+            })?;
+        let input_boxes: Vec<ErgoBox> = ergs_boxes.iter().map(|b| b.get_box()).collect();
+        let change_tokens = subtract_output_tokens(
+            &sum_tokens(&input_boxes)?,
+            &protocol_outputs,
+            &collateral_box.box_id(),
+        )?;
+
+        let mut outputs = protocol_outputs;
+        outputs.extend(self.payout_candidates(collateral_box, current_height)?);
+        if change_value > 0 || !change_tokens.is_empty() {
+            outputs.push(ChangeBox::output_candidate(
+                &change_tokens,
+                change_value,
+                change_address,
+                current_height,
+            )?);
+        }
+        outputs.push(TxFeeBox::output_candidate(transaction_fee, current_height)?);
+
+        let mut inputs: Vec<UnsignedInput> = vec![collateral_box.clone().into()];
+        inputs.extend(ergs_boxes.iter().map(|b| b.as_unsigned_input()));
+        let unsigned_tx = new_unsigned_tx(inputs, vec![oracle_box.box_id().into()], outputs)
+            .map_err(HeadlessDappError::InvalidLiquidation)?;
+        check_unsigned_tx(&unsigned_tx)?;
+        Ok(unsigned_tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::build_token;
+    use crate::output_builders::MINER_FEE_P2S_ADDRESS;
+    use crate::test_utils::test_box;
+
+    // Health factor is the price in bps of a fixed debt of 1 nanoErg
+    fn price_as_health_factor(_: &ErgoBox, price: NanoErg, _: BlockHeight) -> Result<u64> {
+        Ok(price)
+    }
+
+    #[test]
+    fn liquidates_only_unhealthy_boxes() {
+        let address = MINER_FEE_P2S_ADDRESS.to_string();
+        let token = build_token(
+            "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1",
+            101,
+        )
+        .unwrap();
+        let collateral_box = test_box(4000000, MINER_FEE_P2S_ADDRESS, vec![token], vec![]);
+        let ergs_box =
+            ErgsBox::new(&test_box(5000000, MINER_FEE_P2S_ADDRESS, vec![], vec![])).unwrap();
+        let template = LiquidationTemplate::new(
+            BoxSpec::new(None, None, vec![], vec![]),
+            BoxSpec::new(None, None, vec![], vec![]),
+            oracle_pool_datapoint,
+            price_as_health_factor,
+        )
+        .with_payout(&address, 7500)
+        .with_payout(&address, 2500);
+
+        let healthy_oracle = test_box(
+            1000000,
+            MINER_FEE_P2S_ADDRESS,
+            vec![],
+            vec![10000i64.into()],
+        );
+        assert!(template
+            .action_liquidate(
+                &collateral_box,
+                &healthy_oracle,
+                vec![],
+                &vec![ergs_box.clone()],
+                1000000,
+                &address,
+                10
+            )
+            .is_err());
+
+        let unhealthy_oracle =
+            test_box(1000001, MINER_FEE_P2S_ADDRESS, vec![], vec![9000i64.into()]);
+        let tx = template
+            .action_liquidate(
+                &collateral_box,
+                &unhealthy_oracle,
+                vec![],
+                &vec![ergs_box],
+                1000000,
+                &address,
+                10,
+            )
+            .unwrap();
+        let outputs = tx.output_candidates.as_vec();
+        assert_eq!(*outputs[0].value.as_u64(), 3000000);
+        assert_eq!(u64::from(outputs[0].tokens[0].amount), 75);
+        assert_eq!(u64::from(outputs[1].tokens[0].amount), 26);
+    }
+
+    #[test]
+    fn rejects_overflowing_payout_shares() {
+        let address = MINER_FEE_P2S_ADDRESS.to_string();
+        let collateral_box = test_box(4000000, MINER_FEE_P2S_ADDRESS, vec![], vec![]);
+        // The shares would wrap around to `BPS_DENOMINATOR` if unchecked
+        let template = LiquidationTemplate::new(
+            BoxSpec::new(None, None, vec![], vec![]),
+            BoxSpec::new(None, None, vec![], vec![]),
+            oracle_pool_datapoint,
+            price_as_health_factor,
+        )
+        .with_payout(&address, u64::MAX)
+        .with_payout(&address, BPS_DENOMINATOR + 1);
+        assert!(matches!(
+            template.payout_candidates(&collateral_box, 10),
+            Err(HeadlessDappError::InvalidLiquidation(_))
+        ));
+    }
+}
@@ -0,0 +1,68 @@
+/// This file holds overflow-safe arithmetic helpers which are commonly
+/// required when building protocol Actions, such as taking a percentage
+/// fee out of a box value. All intermediate values are computed as
+/// `u128` so that multiplying large nanoErg/token amounts cannot overflow.
+use crate::error::{HeadlessDappError, Result};
+
+/// The number of basis points in 100%
+pub const BPS_DENOMINATOR: u64 = 10000;
+
+/// Applies a basis-point rate to a value, rounding down.
+/// Ie. `apply_bps(1000, 250)` is 2.5% of 1000, which is `25`.
+pub fn apply_bps(value: u64, bps: u64) -> Result<u64> {
+    let result = (value as u128) * (bps as u128) / (BPS_DENOMINATOR as u128);
+    if result > u64::MAX as u128 {
+        return Err(HeadlessDappError::ArithmeticOverflow);
+    }
+    Ok(result as u64)
+}
+
+/// Splits a value into parts proportional to the provided weights.
+/// Each part is rounded down and any remainder is added to the last part,
+/// guaranteeing that the parts always sum up to exactly `value`.
+pub fn proportional_split(value: u64, parts: &[u64]) -> Result<Vec<u64>> {
+    let total_weight: u128 = parts.iter().map(|p| *p as u128).sum();
+    if total_weight == 0 {
+        return Err(HeadlessDappError::Other(
+            "Cannot split a value into parts with a total weight of zero.".to_string(),
+        ));
+    }
+
+    let mut split: Vec<u64> = parts
+        .iter()
+        .map(|p| ((value as u128) * (*p as u128) / total_weight) as u64)
+        .collect();
+    let distributed: u64 = split.iter().sum();
+    if let Some(last) = split.last_mut() {
+        *last += value - distributed;
+    }
+    Ok(split)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_bps_is_overflow_safe() {
+        assert_eq!(apply_bps(1000, 250).unwrap(), 25);
+        assert_eq!(apply_bps(u64::MAX, BPS_DENOMINATOR).unwrap(), u64::MAX);
+        assert!(apply_bps(u64::MAX, BPS_DENOMINATOR + 1).is_err());
+    }
+
+    #[test]
+    fn proportional_split_sums_to_value() {
+        assert_eq!(
+            proportional_split(100, &[1, 1, 1]).unwrap(),
+            vec![33, 33, 34]
+        );
+        assert_eq!(
+            proportional_split(u64::MAX, &[1, 3])
+                .unwrap()
+                .iter()
+                .sum::<u64>(),
+            u64::MAX
+        );
+        assert!(proportional_split(100, &[0, 0]).is_err());
+    }
+}
@@ -0,0 +1,61 @@
+/// This file holds the hooks which allow a headless dApp to be monitored
+/// while running in production. A `Metrics` implementation can be passed
+/// into the framework's processing methods in order to record how many
+/// boxes were fetched, how many matched a `BoxSpec`, and the outcome of
+/// submitted transactions.
+/// If the `tracing` feature is enabled, structured log events are also
+/// emitted for the same occurrences.
+use crate::TxId;
+
+/// A trait which receives metrics events emitted by the framework.
+/// All methods have no-op default implementations so that an implementor
+/// only needs to override the events they care about.
+pub trait Metrics {
+    /// Called after an explorer/node response has been parsed with the
+    /// number of boxes which were returned.
+    fn boxes_fetched(&self, _count: usize) {}
+    /// Called after boxes have been verified against a `BoxSpec` with the
+    /// number of boxes which matched out of the total checked.
+    fn spec_matched(&self, _matched: usize, _total: usize) {}
+    /// Called when a transaction has been submitted.
+    fn tx_submitted(&self, _tx_id: &TxId) {}
+    /// Called when a submitted transaction has been confirmed.
+    fn tx_confirmed(&self, _tx_id: &TxId) {}
+    /// Called when a submitted transaction has failed.
+    fn tx_failed(&self, _tx_id: &TxId, _reason: &str) {}
+}
+
+/// A `Metrics` implementation which discards every event. Used as the
+/// default when no metrics are provided.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopMetrics;
+
+impl Metrics for NoopMetrics {}
+
+/// Records that a transaction was submitted, emitting a tracing event
+/// if the `tracing` feature is enabled. Called by
+/// `async_api::submit_tx_with_metrics()`.
+pub fn record_tx_submitted(metrics: &dyn Metrics, tx_id: &TxId) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(tx_id = %tx_id, "transaction submitted");
+    metrics.tx_submitted(tx_id);
+}
+
+/// Records that a transaction was confirmed, emitting a tracing event
+/// if the `tracing` feature is enabled. Called by
+/// `async_api::track_tx_with_metrics()`.
+pub fn record_tx_confirmed(metrics: &dyn Metrics, tx_id: &TxId) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(tx_id = %tx_id, "transaction confirmed");
+    metrics.tx_confirmed(tx_id);
+}
+
+/// Records that a transaction failed, emitting a tracing event
+/// if the `tracing` feature is enabled. The framework cannot tell a
+/// dropped transaction apart from a pending one, so this is called by
+/// services when they mark a transaction as `TxStatus::Failed`.
+pub fn record_tx_failed(metrics: &dyn Metrics, tx_id: &TxId, reason: &str) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(tx_id = %tx_id, reason = reason, "transaction failed");
+    metrics.tx_failed(tx_id, reason);
+}
@@ -0,0 +1,302 @@
+/// This file holds the `MigrationAction`, an Action generator used when a
+/// protocol upgrades the contract of one of its stages. It consumes the
+/// boxes which match the spec of the old stage and recreates each of them
+/// at the address of the new contract, with the registers and tokens of
+/// the recreated boxes produced by transforms supplied by the developer.
+/// Many boxes are migrated across multiple transactions, each of which is
+/// kept under both an input count and an estimated size limit.
+use crate::box_spec::BoxSpec;
+use crate::box_traits::WrappedBox;
+use crate::error::{HeadlessDappError, Result};
+use crate::output_builders::{ChangeBox, TxFeeBox};
+use crate::specified_boxes::ErgsBox;
+use crate::tx_creation::{check_unsigned_tx, create_candidate, new_unsigned_tx};
+use crate::{BlockHeight, NanoErg, P2PKAddressString, P2SAddressString};
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, ErgoBoxCandidate};
+use ergo_lib::ergotree_ir::chain::token::Token;
+use ergo_lib::ergotree_ir::mir::constant::Constant;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+
+/// The default maximum number of migrated boxes spent by a single
+/// transaction
+pub const DEFAULT_MAX_INPUTS_PER_TX: usize = 100;
+/// The default maximum estimated size (in bytes) of a single transaction
+pub const DEFAULT_MAX_TX_SIZE: usize = 96 * 1024;
+/// The estimated size of a single signed input (the box id plus a proof)
+const INPUT_SIZE_ESTIMATE: usize = 140;
+/// The estimated size of the fee input, the fee output, and the change
+/// output of a transaction
+const TX_OVERHEAD_ESTIMATE: usize = 512;
+
+/// A transform producing the registers of the recreated box from the box
+/// being migrated
+pub type RegisterTransform = fn(&ErgoBox) -> Result<Vec<Constant>>;
+/// A transform producing the tokens of the recreated box from the box
+/// being migrated
+pub type TokenTransform = fn(&ErgoBox) -> Result<Vec<Token>>;
+
+/// An Action generator which migrates boxes between contract versions
+#[derive(Clone)]
+pub struct MigrationAction {
+    old_spec: BoxSpec,
+    new_address: P2SAddressString,
+    transaction_fee: NanoErg,
+    register_transform: Option<RegisterTransform>,
+    token_transform: Option<TokenTransform>,
+    max_inputs_per_tx: usize,
+    max_tx_size: usize,
+}
+
+impl MigrationAction {
+    /// Create a new `MigrationAction` which recreates the boxes matching
+    /// the `old_spec` at the `new_address`, paying the `transaction_fee`
+    /// for every transaction. By default the registers and tokens of every
+    /// box are carried over unchanged.
+    pub fn new(
+        old_spec: &BoxSpec,
+        new_address: &P2SAddressString,
+        transaction_fee: NanoErg,
+    ) -> MigrationAction {
+        MigrationAction {
+            old_spec: old_spec.clone(),
+            new_address: new_address.clone(),
+            transaction_fee,
+            register_transform: None,
+            token_transform: None,
+            max_inputs_per_tx: DEFAULT_MAX_INPUTS_PER_TX,
+            max_tx_size: DEFAULT_MAX_TX_SIZE,
+        }
+    }
+
+    /// Sets the transform producing the registers of the recreated boxes
+    pub fn with_register_transform(mut self, register_transform: RegisterTransform) -> Self {
+        self.register_transform = Some(register_transform);
+        self
+    }
+
+    /// Sets the transform producing the tokens of the recreated boxes
+    pub fn with_token_transform(mut self, token_transform: TokenTransform) -> Self {
+        self.token_transform = Some(token_transform);
+        self
+    }
+
+    /// Sets the maximum number of migrated boxes spent by a single
+    /// transaction
+    pub fn with_max_inputs_per_tx(mut self, max_inputs_per_tx: usize) -> Self {
+        self.max_inputs_per_tx = max_inputs_per_tx.max(1);
+        self
+    }
+
+    /// Sets the maximum estimated size (in bytes) of a single transaction
+    pub fn with_max_tx_size(mut self, max_tx_size: usize) -> Self {
+        self.max_tx_size = max_tx_size;
+        self
+    }
+
+    /// The spec of the old stage whose boxes are migrated
+    pub fn old_spec(&self) -> BoxSpec {
+        self.old_spec.clone()
+    }
+
+    /// Creates the output candidate recreating the `ergo_box` at the new
+    /// contract address
+    pub fn migrated_candidate(
+        &self,
+        ergo_box: &ErgoBox,
+        current_height: BlockHeight,
+    ) -> Result<ErgoBoxCandidate> {
+        let registers = match self.register_transform {
+            Some(transform) => transform(ergo_box)?,
+            None => ergo_box.additional_registers.get_ordered_values().clone(),
+        };
+        let tokens = match self.token_transform {
+            Some(transform) => transform(ergo_box)?,
+            None => ergo_box.tokens.clone(),
+        };
+        create_candidate(
+            *ergo_box.value.as_u64(),
+            &self.new_address,
+            &tokens,
+            &registers,
+            current_height,
+        )
+    }
+
+    /// Builds the transactions which migrate all of the `boxes`, each of
+    /// which must match the old spec. Every transaction pays its fee from
+    /// the next of the `fee_boxes`, and so at least as many fee boxes as
+    /// transactions must be provided. The change of each fee box is sent
+    /// to the `change_address`.
+    pub fn action_migrate(
+        &self,
+        boxes: &[ErgoBox],
+        fee_boxes: &[ErgsBox],
+        change_address: &P2PKAddressString,
+        current_height: BlockHeight,
+    ) -> Result<Vec<UnsignedTransaction>> {
+        for ergo_box in boxes {
+            self.old_spec.verify_box(ergo_box)?;
+        }
+        let chunks = self.chunk(boxes, current_height)?;
+        if fee_boxes.len() < chunks.len() {
+            return Err(HeadlessDappError::MigrationFailed(format!(
+                "{} transactions are required but only {} fee boxes were provided",
+                chunks.len(),
+                fee_boxes.len()
+            )));
+        }
+        chunks
+            .into_iter()
+            .zip(fee_boxes)
+            .map(|((inputs, outputs), fee_box)| {
+                self.build_migration_tx(inputs, outputs, fee_box, change_address, current_height)
+            })
+            .collect()
+    }
+
+    /// Splits the `boxes` into chunks of boxes paired with their recreated
+    /// output candidates, such that every chunk stays within the input
+    /// count and estimated size limits
+    fn chunk<'a>(
+        &self,
+        boxes: &'a [ErgoBox],
+        current_height: BlockHeight,
+    ) -> Result<Vec<(Vec<&'a ErgoBox>, Vec<ErgoBoxCandidate>)>> {
+        let mut chunks = vec![];
+        let mut inputs: Vec<&ErgoBox> = vec![];
+        let mut outputs = vec![];
+        let mut size = TX_OVERHEAD_ESTIMATE;
+        for ergo_box in boxes {
+            let candidate = self.migrated_candidate(ergo_box, current_height)?;
+            let box_size = INPUT_SIZE_ESTIMATE
+                + candidate
+                    .sigma_serialize_bytes()
+                    .map_err(|e| HeadlessDappError::Other(e.to_string()))?
+                    .len();
+            if TX_OVERHEAD_ESTIMATE + box_size > self.max_tx_size {
+                let box_id: String = ergo_box.box_id().into();
+                return Err(HeadlessDappError::MigrationFailed(format!(
+                    "Box {} is too large to migrate within the maximum transaction size",
+                    box_id
+                )));
+            }
+            if !inputs.is_empty()
+                && (inputs.len() == self.max_inputs_per_tx || size + box_size > self.max_tx_size)
+            {
+                chunks.push((inputs, outputs));
+                inputs = vec![];
+                outputs = vec![];
+                size = TX_OVERHEAD_ESTIMATE;
+            }
+            inputs.push(ergo_box);
+            outputs.push(candidate);
+            size += box_size;
+        }
+        if !inputs.is_empty() {
+            chunks.push((inputs, outputs));
+        }
+        Ok(chunks)
+    }
+
+    /// Builds a single transaction which migrates the `inputs` into the
+    /// `outputs`, paying the fee from the `fee_box`
+    fn build_migration_tx(
+        &self,
+        inputs: Vec<&ErgoBox>,
+        mut outputs: Vec<ErgoBoxCandidate>,
+        fee_box: &ErgsBox,
+        change_address: &P2PKAddressString,
+        current_height: BlockHeight,
+    ) -> Result<UnsignedTransaction> {
+        let change_value = fee_box
+            .nano_ergs()
+            .checked_sub(self.transaction_fee)
+            .ok_or(HeadlessDappError::InsufficientErgs(
+                self.transaction_fee,
+                fee_box.nano_ergs(),
+            ))?;
+        outputs.push(TxFeeBox::output_candidate(
+            self.transaction_fee,
+            current_height,
+        )?);
+        if change_value > 0 {
+            outputs.push(ChangeBox::output_candidate(
+                &vec![],
+                change_value,
+                change_address,
+                current_height,
+            )?);
+        }
+        let mut unsigned_inputs: Vec<_> = inputs.into_iter().map(|b| b.clone().into()).collect();
+        unsigned_inputs.push(fee_box.as_unsigned_input());
+        let unsigned_tx = new_unsigned_tx(unsigned_inputs, vec![], outputs)
+            .map_err(HeadlessDappError::InvalidTxComposition)?;
+        check_unsigned_tx(&unsigned_tx)?;
+        Ok(unsigned_tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_box_at;
+
+    const OLD_ADDRESS: &str = "94hWSMqgxHtRNEWoKrJFGVNQEYX34zfX68FNxWr";
+    const NEW_ADDRESS: &str = "9fSgJ7BmUxBQJ454prQDQ7fQMBkXPLaAmDnimgTtjym6FYPHjAV";
+
+    fn double_r4(ergo_box: &ErgoBox) -> Result<Vec<Constant>> {
+        let r4 = ergo_box.additional_registers.get_ordered_values()[0].clone();
+        let value = crate::encoding::unwrap_long(&r4)?;
+        Ok(vec![Constant::from(value * 2)])
+    }
+
+    #[test]
+    fn migrates_boxes_in_chunks() {
+        let old_spec = BoxSpec::new(Some(OLD_ADDRESS.to_string()), None, vec![], vec![]);
+        let boxes: Vec<ErgoBox> = (0..3)
+            .map(|i| {
+                test_box_at(
+                    10000000,
+                    OLD_ADDRESS,
+                    vec![],
+                    vec![Constant::from(21i64)],
+                    0,
+                    i,
+                )
+            })
+            .collect();
+        let fee_boxes: Vec<ErgsBox> = (0..2)
+            .map(|i| {
+                ErgsBox::new(&test_box_at(
+                    3000000,
+                    NEW_ADDRESS,
+                    vec![],
+                    vec![],
+                    0,
+                    10 + i,
+                ))
+                .unwrap()
+            })
+            .collect();
+        let migration = MigrationAction::new(&old_spec, &NEW_ADDRESS.to_string(), 1000000)
+            .with_register_transform(double_r4)
+            .with_max_inputs_per_tx(2);
+
+        let txs = migration
+            .action_migrate(&boxes, &fee_boxes, &NEW_ADDRESS.to_string(), 100)
+            .unwrap();
+        assert_eq!(txs.len(), 2);
+        assert_eq!(txs[0].inputs.as_vec().len(), 3);
+        let migrated = &txs[0].output_candidates.as_vec()[0];
+        assert_eq!(*migrated.value.as_u64(), 10000000);
+        assert_eq!(
+            migrated.additional_registers.get_ordered_values()[0],
+            Constant::from(42i64)
+        );
+
+        assert!(migration
+            .action_migrate(&boxes, &fee_boxes[..1], &NEW_ADDRESS.to_string(), 100)
+            .is_err());
+    }
+}
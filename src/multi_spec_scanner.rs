@@ -0,0 +1,213 @@
+/// This file holds the `MultiSpecScanner` (behind the `async` feature),
+/// which fetches and verifies the boxes of many `BoxSpec`s concurrently,
+/// giving large protocols with many stages a single polling primitive.
+/// All requests go through one shared `AsyncTransport`, and so through
+/// the connection pool of the HTTP client implementing it, while the
+/// number of requests in flight to any single host is capped so that
+/// public explorers are not overwhelmed.
+use crate::async_api::{fetch_box_spec, AsyncTransport};
+use crate::box_spec::BoxSpec;
+use crate::error::{HeadlessDappError, Result};
+use crate::explorer_backend::ExplorerBackend;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use futures::future::join_all;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// The default maximum number of concurrent requests to a single host
+pub const DEFAULT_MAX_CONCURRENCY_PER_HOST: usize = 4;
+
+/// A named `BoxSpec` and the backend it is fetched from
+#[derive(Clone)]
+struct ScannedSpec {
+    name: String,
+    box_spec: BoxSpec,
+    backend: ExplorerBackend,
+}
+
+/// Fetches and verifies many `BoxSpec`s concurrently
+#[derive(Clone)]
+pub struct MultiSpecScanner {
+    backend: ExplorerBackend,
+    specs: Vec<ScannedSpec>,
+    max_concurrency_per_host: usize,
+    host_limits: HashMap<String, usize>,
+}
+
+impl MultiSpecScanner {
+    /// Create a new `MultiSpecScanner` which fetches its specs from the
+    /// `backend` unless another backend is provided for a spec
+    pub fn new(backend: &ExplorerBackend) -> MultiSpecScanner {
+        MultiSpecScanner {
+            backend: backend.clone(),
+            specs: vec![],
+            max_concurrency_per_host: DEFAULT_MAX_CONCURRENCY_PER_HOST,
+            host_limits: HashMap::new(),
+        }
+    }
+
+    /// Adds the `box_spec` to the scanner under the `name`, replacing any
+    /// spec previously added under the same name
+    pub fn with_spec(self, name: &str, box_spec: &BoxSpec) -> Self {
+        let backend = self.backend.clone();
+        self.with_spec_on(name, box_spec, &backend)
+    }
+
+    /// Same as `with_spec()` but fetches the spec from the `backend`
+    pub fn with_spec_on(
+        mut self,
+        name: &str,
+        box_spec: &BoxSpec,
+        backend: &ExplorerBackend,
+    ) -> Self {
+        self.specs.retain(|s| s.name != name);
+        self.specs.push(ScannedSpec {
+            name: name.to_string(),
+            box_spec: box_spec.clone(),
+            backend: backend.clone(),
+        });
+        self
+    }
+
+    /// Sets the maximum number of concurrent requests to any single host
+    pub fn with_max_concurrency_per_host(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency_per_host = max_concurrency.max(1);
+        self
+    }
+
+    /// Sets the maximum number of concurrent requests to the `host` (ie.
+    /// `api.ergoplatform.com`), overriding the per host default
+    pub fn with_host_limit(mut self, host: &str, max_concurrency: usize) -> Self {
+        self.host_limits
+            .insert(host.to_string(), max_concurrency.max(1));
+        self
+    }
+
+    /// The names of the specs of the scanner, in the order they were added
+    pub fn spec_names(&self) -> Vec<String> {
+        self.specs.iter().map(|s| s.name.clone()).collect()
+    }
+
+    /// The maximum number of concurrent requests to the `host`
+    pub fn concurrency_limit(&self, host: &str) -> usize {
+        *self
+            .host_limits
+            .get(host)
+            .unwrap_or(&self.max_concurrency_per_host)
+    }
+
+    /// Fetches and verifies every spec concurrently, returning the
+    /// matching boxes (or the error) of each spec by name. A failing spec
+    /// does not affect the results of the others.
+    pub async fn scan<T: AsyncTransport + ?Sized>(
+        &self,
+        transport: &T,
+    ) -> BTreeMap<String, Result<Vec<ErgoBox>>> {
+        let mut semaphores: HashMap<String, Arc<Semaphore>> = HashMap::new();
+        for spec in &self.specs {
+            let host = api_host(spec.backend.api_url());
+            let limit = self.concurrency_limit(&host);
+            semaphores
+                .entry(host)
+                .or_insert_with(|| Arc::new(Semaphore::new(limit)));
+        }
+
+        let fetches = self.specs.iter().map(|spec| {
+            let semaphore = semaphores[&api_host(spec.backend.api_url())].clone();
+            async move {
+                let boxes = match semaphore.acquire().await {
+                    Ok(_permit) => fetch_box_spec(transport, &spec.backend, &spec.box_spec).await,
+                    Err(e) => Err(HeadlessDappError::Other(e.to_string())),
+                };
+                (spec.name.clone(), boxes)
+            }
+        });
+        join_all(fetches).await.into_iter().collect()
+    }
+
+    /// Same as `scan()` but errors if fetching any of the specs failed
+    pub async fn scan_all<T: AsyncTransport + ?Sized>(
+        &self,
+        transport: &T,
+    ) -> Result<BTreeMap<String, Vec<ErgoBox>>> {
+        self.scan(transport)
+            .await
+            .into_iter()
+            .map(|(name, boxes)| Ok((name, boxes?)))
+            .collect()
+    }
+}
+
+/// Acquires the host of an explorer API url (ie. `api.ergoplatform.com`
+/// from `https://api.ergoplatform.com/api`)
+fn api_host(api_url: &str) -> String {
+    let without_scheme = api_url.splitn(2, "://").last().unwrap_or(api_url);
+    without_scheme
+        .split('/')
+        .next()
+        .unwrap_or(without_scheme)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{test_box, MockTransport, TEST_ADDRESS, TEST_TOKEN_ID};
+    use crate::TokenSpec;
+    use std::time::Duration;
+
+    #[test]
+    fn limits_concurrency_per_host() {
+        let backend = ExplorerBackend::new("https://api.ergoplatform.com/api");
+        let box_spec = BoxSpec::new(None, None, vec![], vec![]);
+        let scanner = MultiSpecScanner::new(&backend)
+            .with_spec("a", &box_spec)
+            .with_spec("b", &box_spec)
+            .with_spec("a", &box_spec)
+            .with_host_limit("api.ergoplatform.com", 2);
+        assert_eq!(scanner.spec_names(), vec!["b", "a"]);
+        assert_eq!(api_host(backend.api_url()), "api.ergoplatform.com");
+        assert_eq!(scanner.concurrency_limit("api.ergoplatform.com"), 2);
+        assert_eq!(
+            scanner.concurrency_limit("localhost:9053"),
+            DEFAULT_MAX_CONCURRENCY_PER_HOST
+        );
+    }
+
+    #[tokio::test]
+    async fn caps_concurrency_and_isolates_failures() {
+        let body = serde_json::json!({
+            "items": [test_box(1000000, TEST_ADDRESS, vec![], vec![])]
+        })
+        .to_string();
+        let transport = MockTransport::new()
+            .with_failure(TEST_TOKEN_ID, "Explorer unavailable")
+            .with_response(TEST_ADDRESS, &body)
+            .with_delay(Duration::from_millis(20));
+        let backend = ExplorerBackend::new("https://api.ergoplatform.com/api");
+        let address_spec = BoxSpec::new(Some(TEST_ADDRESS.to_string()), None, vec![], vec![]);
+        let token_spec = BoxSpec::new(
+            None,
+            None,
+            vec![],
+            vec![Some(TokenSpec::new(1..2, TEST_TOKEN_ID))],
+        );
+        let scanner = MultiSpecScanner::new(&backend)
+            .with_spec("a", &address_spec)
+            .with_spec("b", &address_spec)
+            .with_spec("broken", &token_spec)
+            .with_spec("c", &address_spec)
+            .with_spec("d", &address_spec)
+            .with_max_concurrency_per_host(2);
+
+        let results = scanner.scan(&transport).await;
+        assert_eq!(transport.requests().len(), 5);
+        assert_eq!(transport.max_in_flight(), 2);
+        assert!(results["broken"].is_err());
+        for name in ["a", "b", "c", "d"].iter() {
+            assert_eq!(results[*name].as_ref().unwrap().len(), 1);
+        }
+        assert!(scanner.scan_all(&transport).await.is_err());
+    }
+}
@@ -0,0 +1,55 @@
+/// This file holds the `Network` type which allows protocols to be
+/// developed end-to-end on testnet using the same code as on mainnet.
+use crate::encoding::parse_address;
+use crate::ErgoAddressString;
+use ergo_lib::ergotree_ir::chain::address::{AddressEncoder, NetworkPrefix};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// The base URL of the mainnet Ergo Explorer Backend API
+pub const MAINNET_EXPLORER_API_URL: &str = "https://api.ergoplatform.com/api";
+/// The base URL of the testnet Ergo Explorer Backend API
+pub const TESTNET_EXPLORER_API_URL: &str = "https://api-testnet.ergoplatform.com/api";
+
+/// An Ergo network
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    Mainnet,
+    Testnet,
+}
+
+impl Network {
+    /// The base URL of the Ergo Explorer Backend API for the network
+    pub fn explorer_api_url(&self) -> &'static str {
+        match self {
+            Network::Mainnet => MAINNET_EXPLORER_API_URL,
+            Network::Testnet => TESTNET_EXPLORER_API_URL,
+        }
+    }
+
+    /// The address `NetworkPrefix` of the network
+    pub fn network_prefix(&self) -> NetworkPrefix {
+        match self {
+            Network::Mainnet => NetworkPrefix::Mainnet,
+            Network::Testnet => NetworkPrefix::Testnet,
+        }
+    }
+
+    /// Detects which network an address belongs to
+    pub fn from_address(address: &ErgoAddressString) -> Option<Network> {
+        // Parse first to verify the address is valid on either network
+        parse_address(address).ok()?;
+        match AddressEncoder::new(NetworkPrefix::Mainnet).parse_address_from_str(address) {
+            Ok(_) => Some(Network::Mainnet),
+            Err(_) => Some(Network::Testnet),
+        }
+    }
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Network::Mainnet
+    }
+}
@@ -0,0 +1,120 @@
+/// This file holds the `DeviationGuard`, a rate-of-change guard for Actions
+/// which depend on an oracle datapoint. The guard compares the current
+/// datapoint against a recently cached one and refuses to build Actions
+/// when the deviation between the two exceeds a configured percentage,
+/// protecting automated protocols from acting on flash-manipulated data.
+use crate::error::{HeadlessDappError, Result};
+use crate::liquidation::oracle_pool_datapoint;
+use crate::oracle_history::DatapointHistory;
+use crate::BlockHeight;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+
+/// The default number of blocks for which a cached datapoint is
+/// considered recent
+pub const DEFAULT_MAX_CACHE_AGE: BlockHeight = 30;
+
+/// A guard which rejects oracle datapoints deviating too far from a
+/// recently cached datapoint
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviationGuard {
+    max_deviation_percent: u64,
+    max_cache_age: BlockHeight,
+    cached: Option<(BlockHeight, u64)>,
+}
+
+impl DeviationGuard {
+    /// Create a new `DeviationGuard` which allows datapoints to deviate at
+    /// most `max_deviation_percent` from the cached datapoint
+    pub fn new(max_deviation_percent: u64) -> DeviationGuard {
+        DeviationGuard {
+            max_deviation_percent,
+            max_cache_age: DEFAULT_MAX_CACHE_AGE,
+            cached: None,
+        }
+    }
+
+    /// Create a new `DeviationGuard` whose cached datapoint is the latest
+    /// datapoint of the `history`
+    pub fn from_history(history: &DatapointHistory, max_deviation_percent: u64) -> DeviationGuard {
+        DeviationGuard {
+            cached: history.datapoints().last().copied(),
+            ..DeviationGuard::new(max_deviation_percent)
+        }
+    }
+
+    /// Sets the number of blocks for which a cached datapoint is
+    /// considered recent
+    pub fn with_max_cache_age(mut self, max_cache_age: BlockHeight) -> Self {
+        self.max_cache_age = max_cache_age;
+        self
+    }
+
+    /// The cached `(height, datapoint)`, if any
+    pub fn cached(&self) -> Option<(BlockHeight, u64)> {
+        self.cached
+    }
+
+    /// Caches the `datapoint` observed at the `height`, as is done after
+    /// the datapoint has been checked and acted upon
+    pub fn record(&mut self, height: BlockHeight, datapoint: u64) {
+        self.cached = Some((height, datapoint));
+    }
+
+    /// Checks that the `datapoint` deviates at most the configured
+    /// percentage from the cached datapoint. Errors if no datapoint has
+    /// been cached within the maximum cache age of the `current_height`.
+    pub fn check(&self, datapoint: u64, current_height: BlockHeight) -> Result<()> {
+        let (cached_height, cached_datapoint) = match self.cached {
+            Some((height, cached))
+                if current_height.saturating_sub(height) <= self.max_cache_age =>
+            {
+                (height, cached)
+            }
+            _ => {
+                return Err(HeadlessDappError::Other(format!(
+                    "No oracle datapoint has been cached within {} blocks of height {}",
+                    self.max_cache_age, current_height
+                )))
+            }
+        };
+        let deviation = (datapoint.max(cached_datapoint) - datapoint.min(cached_datapoint)) as u128;
+        if deviation * 100 > cached_datapoint as u128 * self.max_deviation_percent as u128 {
+            return Err(HeadlessDappError::OracleDeviationExceeded(
+                datapoint,
+                cached_datapoint,
+                cached_height,
+                self.max_deviation_percent,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks the `Long` datapoint in R4 of a standard oracle pool box
+    /// using `check()`
+    pub fn check_oracle_box(
+        &self,
+        oracle_box: &ErgoBox,
+        current_height: BlockHeight,
+    ) -> Result<()> {
+        self.check(oracle_pool_datapoint(oracle_box)?, current_height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_excessive_deviation() {
+        let history = DatapointHistory::new(vec![(90, 900), (100, 1000)]);
+        let mut guard = DeviationGuard::from_history(&history, 10);
+        assert!(guard.check(1100, 110).is_ok());
+        assert!(guard.check(900, 110).is_ok());
+        assert!(guard.check(1101, 110).is_err());
+        assert!(guard.check(1000, 131).is_err());
+
+        guard.record(131, 1100);
+        assert!(guard.check(1200, 131).is_ok());
+        assert!(DeviationGuard::new(10).check(1000, 100).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+/// This file holds `DatapointHistory` which gathers the recent datapoints
+/// posted by an oracle pool (including those in already spent boxes), and
+/// computes the min/max/time-weighted average of them. This allows
+/// protocols which consume oracle datapoints to implement sanity bounds
+/// against oracle manipulation.
+use crate::box_spec::{parse_explorer_response_boxes, BoxSpec};
+use crate::encoding::unwrap_long;
+use crate::error::{HeadlessDappError, Result};
+use crate::explorer_url::ExplorerUrl;
+use crate::BlockHeight;
+use ergo_lib::ergotree_ir::mir::constant::Constant;
+use std::convert::TryFrom;
+
+/// Generates the explorer endpoint for the last `n` boxes (spent or
+/// unspent) which held the oracle pool NFT with the given token id.
+pub fn datapoint_history_endpoint(explorer_api_url: &str, pool_nft_id: &str, n: u64) -> String {
+    ExplorerUrl::new(explorer_api_url)
+        .path("v1/boxes/byTokenId")
+        .segment(pool_nft_id)
+        .query("offset", 0)
+        .query("limit", n)
+        .build()
+}
+
+/// Reads an oracle datapoint out of a `Long` register value, erroring if
+/// it is negative rather than wrapping it into a huge positive number
+pub(crate) fn unwrap_datapoint(c: &Constant) -> Result<u64> {
+    let datapoint = unwrap_long(c)?;
+    u64::try_from(datapoint).map_err(|_| HeadlessDappError::NegativeDatapoint(datapoint))
+}
+
+/// The datapoints posted by an oracle pool, ordered by height
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DatapointHistory {
+    datapoints: Vec<(BlockHeight, u64)>,
+}
+
+impl DatapointHistory {
+    /// Create a new `DatapointHistory` from `(height, datapoint)` pairs
+    pub fn new(mut datapoints: Vec<(BlockHeight, u64)>) -> DatapointHistory {
+        datapoints.sort_by_key(|(height, _)| *height);
+        DatapointHistory { datapoints }
+    }
+
+    /// Processes the explorer response body from the endpoint generated by
+    /// `datapoint_history_endpoint()`, reading the `Long` datapoint in R4
+    /// of every box which matches the oracle pool `BoxSpec`.
+    pub fn from_explorer_response(
+        box_spec: &BoxSpec,
+        explorer_response_body: &str,
+    ) -> Result<DatapointHistory> {
+        let mut datapoints = vec![];
+        for b in parse_explorer_response_boxes(explorer_response_body)? {
+            if box_spec.verify_box(&b).is_err() {
+                continue;
+            }
+            let registers = b.additional_registers.get_ordered_values();
+            let c = registers
+                .first()
+                .ok_or(HeadlessDappError::FailedRegisterSpec)?;
+            datapoints.push((b.creation_height as BlockHeight, unwrap_datapoint(c)?));
+        }
+        Ok(DatapointHistory::new(datapoints))
+    }
+
+    /// The `(height, datapoint)` pairs, ordered by height
+    pub fn datapoints(&self) -> &Vec<(BlockHeight, u64)> {
+        &self.datapoints
+    }
+
+    /// The lowest datapoint in the history
+    pub fn min(&self) -> Option<u64> {
+        self.datapoints.iter().map(|(_, d)| *d).min()
+    }
+
+    /// The highest datapoint in the history
+    pub fn max(&self) -> Option<u64> {
+        self.datapoints.iter().map(|(_, d)| *d).max()
+    }
+
+    /// The most recent datapoint in the history
+    pub fn latest(&self) -> Option<u64> {
+        self.datapoints.last().map(|(_, d)| *d)
+    }
+
+    /// The time-weighted average of the datapoints up until
+    /// `current_height`, where every datapoint is weighted by the number of
+    /// blocks it was the latest datapoint for.
+    pub fn twap(&self, current_height: BlockHeight) -> Option<u64> {
+        let mut weighted_sum: u128 = 0;
+        let mut total_weight: u128 = 0;
+        for (i, (height, datapoint)) in self.datapoints.iter().enumerate() {
+            let until = self
+                .datapoints
+                .get(i + 1)
+                .map(|(next_height, _)| *next_height)
+                .unwrap_or(current_height);
+            let weight = until.saturating_sub(*height) as u128;
+            weighted_sum += weight * *datapoint as u128;
+            total_weight += weight;
+        }
+        if total_weight == 0 {
+            return self.latest();
+        }
+        Some((weighted_sum / total_weight) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_min_max_and_twap() {
+        let history = DatapointHistory::new(vec![(130, 300), (100, 100), (110, 200)]);
+        assert_eq!(history.min(), Some(100));
+        assert_eq!(history.max(), Some(300));
+        assert_eq!(history.latest(), Some(300));
+        // 100 for 10 blocks, 200 for 20 blocks, 300 for 10 blocks
+        assert_eq!(history.twap(140), Some(200));
+    }
+
+    #[test]
+    fn negative_datapoints_are_rejected() {
+        assert_eq!(unwrap_datapoint(&Constant::from(5i64)).unwrap(), 5);
+        assert!(matches!(
+            unwrap_datapoint(&Constant::from(-1i64)),
+            Err(HeadlessDappError::NegativeDatapoint(-1))
+        ));
+    }
+}
@@ -1,14 +1,86 @@
-use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, ErgoBoxCandidate};
+use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
+use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, ErgoBoxCandidate, NonMandatoryRegisterId};
 use ergo_lib::ergotree_ir::chain::token::Token;
+use ergo_lib::ergotree_ir::chain::tx_id::TxId;
 use ergo_lib::ergotree_ir::mir::constant::Constant;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
 
 /// Structs that provide an interface for creating `ErgoBoxCandidate`s
 /// to be used during tx creation in Actions in a protocol.
 /// These "Output Builders" only provide the ability to create output
 /// candidates;
-use crate::error::Result;
+use crate::address_book::{AddressBook, AddressRef};
+use crate::encoding::build_token;
+use crate::error::{HeadlessDappError, Result};
+use crate::math::apply_bps;
+use crate::token_policy::TokenPolicy;
 use crate::tx_creation::{create_candidate, find_and_sum_other_tokens};
-use crate::{NanoErg, P2PKAddressString};
+use crate::{BlockHeight, ErgoAddressString, NanoErg, P2PKAddressString};
+
+pub use crate::known_ids::MINER_FEE_P2S_ADDRESS;
+
+/// The minimum number of nanoErgs a box must hold per byte of its
+/// serialized size.
+pub const MIN_VALUE_PER_BOX_BYTE: NanoErg = 360;
+
+/// Computes the minimum number of nanoErgs the `candidate` must hold for
+/// its serialized size, which depends on its tokens and registers. As the
+/// node does, the size is that of the full box (including its tx id and
+/// output index), and so the `candidate` must already hold its final
+/// value, as the encoding of the value counts towards the size too.
+pub fn min_box_value(candidate: &ErgoBoxCandidate) -> Result<NanoErg> {
+    let box_size = ErgoBox::from_box_candidate(candidate, TxId::zero(), 0)
+        .and_then(|b| b.sigma_serialize_bytes())
+        .map_err(|e| HeadlessDappError::Other(e.to_string()))?
+        .len() as u64;
+    Ok(box_size * MIN_VALUE_PER_BOX_BYTE)
+}
+
+/// Creates an `ErgoBoxCandidate` holding `value` nanoErgs, or rather the
+/// minimum value of the box if `value` is below it. Raising the value can
+/// grow its encoding, and so the minimum is recomputed until the
+/// candidate holds enough.
+pub fn create_min_value_candidate(
+    value: NanoErg,
+    output_address: &ErgoAddressString,
+    tokens: &Vec<Token>,
+    registers: &Vec<Constant>,
+    current_height: BlockHeight,
+) -> Result<ErgoBoxCandidate> {
+    let mut candidate_value = value.max(BoxValue::MIN_RAW);
+    loop {
+        let candidate = create_candidate(
+            candidate_value,
+            output_address,
+            tokens,
+            registers,
+            current_height,
+        )?;
+        let min_value = min_box_value(&candidate)?;
+        if candidate_value >= min_value {
+            return Ok(candidate);
+        }
+        candidate_value = min_value;
+    }
+}
+
+/// Creates an `ErgoBoxCandidate` holding `value` nanoErgs, erroring if
+/// `value` is below the minimum value of the box
+pub fn create_checked_candidate(
+    value: NanoErg,
+    output_address: &ErgoAddressString,
+    tokens: &Vec<Token>,
+    registers: &Vec<Constant>,
+    current_height: BlockHeight,
+) -> Result<ErgoBoxCandidate> {
+    let candidate =
+        create_min_value_candidate(value, output_address, tokens, registers, current_height)?;
+    let min_value = *candidate.value.as_u64();
+    if value < min_value {
+        return Err(HeadlessDappError::BoxValueBelowMinimum(value, min_value));
+    }
+    Ok(candidate)
+}
 
 // /// A function which takes all input boxes/output candidates
 // /// and creates two new output candidates. One tx fee box, and one
@@ -44,7 +116,8 @@ impl TokensChangeBox {
     /// Creates an `ErgoBoxCandidate` which holds all of the tokens from the
     /// the provided inputs. In other words creates a "TokensChange" box for
     /// the tokens inside of the inputs.
-    /// Holds number of nanoErgs value as provided to method.
+    /// Holds number of nanoErgs value as provided to method, erroring if it
+    /// is below the minimum value of the box.
     pub fn output_candidate(
         input_boxes: &Vec<ErgoBox>,
         value: NanoErg,
@@ -52,13 +125,14 @@ impl TokensChangeBox {
         current_height: u64,
     ) -> Result<ErgoBoxCandidate> {
         // Find the tokens that exist in the inputs which need to be preserved
-        let tc_tokens = find_and_sum_other_tokens(&vec![], input_boxes);
-        create_candidate(value, &user_address, &tc_tokens, &vec![], current_height)
+        let tc_tokens = find_and_sum_other_tokens(&vec![], input_boxes)?;
+        create_checked_candidate(value, user_address, &tc_tokens, &vec![], current_height)
     }
 
     /// Creates an `ErgoBoxCandidate` which holds tokens from the
     /// the provided inputs excluding the tokens provided in the filter list.
-    /// Holds number of nanoErgs value as provided to method.
+    /// Holds number of nanoErgs value as provided to method, erroring if it
+    /// is below the minimum value of the box.
     pub fn output_candidate_filtered(
         filter_tokens: &Vec<Token>,
         input_boxes: &Vec<ErgoBox>,
@@ -78,8 +152,9 @@ impl TokensChangeBox {
 
     /// Creates an `ErgoBoxCandidate` which holds tokens from the
     /// the provided inputs excluding the tokens provided in the filter list.
-    /// Holds number of nanoErgs value as provided to method and uses the
-    /// customized registers provided.
+    /// Holds number of nanoErgs value as provided to method (erroring if it
+    /// is below the minimum value of the box) and uses the customized
+    /// registers provided.
     pub fn output_candidate_with_registers_filtered(
         filter_tokens: &Vec<Token>,
         input_boxes: &Vec<ErgoBox>,
@@ -89,9 +164,56 @@ impl TokensChangeBox {
         current_height: u64,
     ) -> Result<ErgoBoxCandidate> {
         // Find the tokens that exist in the inputs which need to be preserved
-        let tc_tokens = find_and_sum_other_tokens(filter_tokens, input_boxes);
+        let tc_tokens = find_and_sum_other_tokens(filter_tokens, input_boxes)?;
+
+        create_checked_candidate(value, user_address, &tc_tokens, registers, current_height)
+    }
+
+    /// Same as `output_candidate_with_registers_filtered()` but rather than
+    /// erroring when `value` is below the minimum value of the box, the
+    /// missing nanoErgs are topped up from the `top_up_box` (a designated
+    /// input whose nanoErgs are otherwise unallocated). Returns the
+    /// candidate along with the number of nanoErgs topped up, which the
+    /// caller must no longer allocate to any other output.
+    pub fn output_candidate_topped_up(
+        filter_tokens: &Vec<Token>,
+        input_boxes: &Vec<ErgoBox>,
+        value: NanoErg,
+        registers: &Vec<Constant>,
+        top_up_box: &ErgoBox,
+        user_address: &P2PKAddressString,
+        current_height: u64,
+    ) -> Result<(ErgoBoxCandidate, NanoErg)> {
+        let tc_tokens = find_and_sum_other_tokens(filter_tokens, input_boxes)?;
+        let candidate =
+            create_min_value_candidate(value, user_address, &tc_tokens, registers, current_height)?;
+        let top_up = *candidate.value.as_u64() - value;
+        if top_up > *top_up_box.value.as_u64() {
+            return Err(HeadlessDappError::BoxValueBelowMinimum(
+                value + *top_up_box.value.as_u64(),
+                *candidate.value.as_u64(),
+            ));
+        }
+        Ok((candidate, top_up))
+    }
+}
 
-        create_candidate(value, &user_address, &tc_tokens, registers, current_height)
+impl TokensChangeBox {
+    /// Creates an `ErgoBoxCandidate` which holds the tokens from the
+    /// provided inputs excluding the tokens provided in the filter list,
+    /// with the remaining tokens treated according to the `TokenPolicy`.
+    /// Holds number of nanoErgs value as provided to method, erroring if it
+    /// is below the minimum value of the box.
+    pub fn output_candidate_with_policy(
+        policy: &TokenPolicy,
+        filter_tokens: &Vec<Token>,
+        input_boxes: &Vec<ErgoBox>,
+        value: NanoErg,
+        user_address: &P2PKAddressString,
+        current_height: u64,
+    ) -> Result<ErgoBoxCandidate> {
+        let tc_tokens = policy.apply(&find_and_sum_other_tokens(filter_tokens, input_boxes)?)?;
+        create_checked_candidate(value, user_address, &tc_tokens, &vec![], current_height)
     }
 }
 
@@ -103,10 +225,299 @@ impl TxFeeBox {
     pub fn output_candidate(tx_fee: u64, current_height: u64) -> Result<ErgoBoxCandidate> {
         create_candidate(
             tx_fee,
-            &"2iHkR7CWvD1R4j1yZg5bkeDRQavjAaVPeTDFGGLZduHyfWMuYpmhHocX8GJoaieTx78FntzJbCBVL6rf96ocJoZdmWBL2fci7NqWgAirppPQmZ7fN9V6z13Ay6brPriBKYqLp1bT2Fk4FkFLCfdPpe".to_string(),
+            &MINER_FEE_P2S_ADDRESS.to_string(),
             &vec![],
             &vec![],
             current_height,
         )
     }
 }
+
+/// A struct used while constructing txs to build a `ErgoBoxCandidate`
+/// that holds a protocol fee, computed as a basis-point rate of a value
+/// and sent to the protocol's fee address.
+pub struct ProtocolFeeBox {}
+
+impl ProtocolFeeBox {
+    /// Computes the protocol fee for a given value at the provided
+    /// basis-point rate.
+    pub fn fee_amount(value: NanoErg, fee_bps: u64) -> Result<NanoErg> {
+        apply_bps(value, fee_bps)
+    }
+
+    /// Creates an `ErgoBoxCandidate` which holds the protocol fee for
+    /// `value` at the `fee_bps` rate, locked under the `fee_address`.
+    pub fn output_candidate(
+        value: NanoErg,
+        fee_bps: u64,
+        fee_address: &ErgoAddressString,
+        current_height: u64,
+    ) -> Result<ErgoBoxCandidate> {
+        let fee = ProtocolFeeBox::fee_amount(value, fee_bps)?;
+        create_candidate(fee, fee_address, &vec![], &vec![], current_height)
+    }
+}
+
+/// A builder for `ErgoBoxCandidate`s which validates the output as it is
+/// built. Registers are set explicitly by id, and `build()` errors if a
+/// register is skipped (ie. R6 set without R5) rather than silently
+/// shifting it into the wrong register as a positional list would.
+/// The first validation error encountered is returned from `build()`.
+#[derive(Default)]
+pub struct OutputCandidateBuilder {
+    value: NanoErg,
+    address: Option<AddressRef>,
+    address_book: AddressBook,
+    tokens: Vec<Token>,
+    registers: Vec<(NonMandatoryRegisterId, Constant)>,
+    creation_height: BlockHeight,
+    error: Option<HeadlessDappError>,
+}
+
+impl OutputCandidateBuilder {
+    /// Create a new empty `OutputCandidateBuilder`
+    pub fn new() -> OutputCandidateBuilder {
+        OutputCandidateBuilder::default()
+    }
+
+    /// Sets the number of nanoErgs held in the output
+    pub fn value(mut self, value: NanoErg) -> Self {
+        self.value = value;
+        self
+    }
+
+    /// Sets the address (P2PK or P2S) which the output is locked under.
+    /// Accepts either an address directly or a `Named` address which is
+    /// resolved via the builder's `AddressBook`.
+    pub fn address<A: Into<AddressRef>>(mut self, address: A) -> Self {
+        self.address = Some(address.into());
+        self
+    }
+
+    /// Sets the `AddressBook` used to resolve `Named` addresses
+    pub fn address_book(mut self, address_book: &AddressBook) -> Self {
+        self.address_book = address_book.clone();
+        self
+    }
+
+    /// Adds a token held in the output
+    pub fn token(mut self, token_id: &str, amount: u64) -> Self {
+        match build_token(token_id, amount) {
+            Ok(token) => self.tokens.push(token),
+            Err(e) => self.record_error(e.into()),
+        }
+        self
+    }
+
+    /// Sets the value of a register. Errors if the register was
+    /// already set.
+    pub fn register(mut self, register_id: NonMandatoryRegisterId, value: Constant) -> Self {
+        if self.registers.iter().any(|(id, _)| *id == register_id) {
+            self.record_error(HeadlessDappError::InvalidRegisterOrder(format!(
+                "{:?} set more than once",
+                register_id
+            )));
+        } else {
+            self.registers.push((register_id, value));
+        }
+        self
+    }
+
+    /// Sets the creation height of the output
+    pub fn creation_height(mut self, creation_height: BlockHeight) -> Self {
+        self.creation_height = creation_height;
+        self
+    }
+
+    /// Builds the `ErgoBoxCandidate`, checking that the registers form a
+    /// contiguous block starting at R4 and that the output holds at least
+    /// the minimum number of nanoErgs for its size.
+    pub fn build(self) -> Result<ErgoBoxCandidate> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        let address_ref = self
+            .address
+            .ok_or_else(|| HeadlessDappError::InvalidAddress("No address set".to_string()))?;
+        let address = self.address_book.resolve(&address_ref)?;
+
+        // Order the registers and verify none were skipped
+        let mut registers = self.registers;
+        registers.sort_by_key(|(id, _)| *id as u8);
+        for (i, (id, _)) in registers.iter().enumerate() {
+            if *id as usize != i + 4 {
+                return Err(HeadlessDappError::InvalidRegisterOrder(format!(
+                    "{:?} set without R{}",
+                    id,
+                    i + 4
+                )));
+            }
+        }
+        let registers: Vec<Constant> = registers.into_iter().map(|(_, c)| c).collect();
+
+        // Verify the output holds the minimum value for its size
+        create_checked_candidate(
+            self.value,
+            &address,
+            &self.tokens,
+            &registers,
+            self.creation_height,
+        )
+    }
+
+    /// Records the error unless an earlier error was already recorded
+    fn record_error(&mut self, error: HeadlessDappError) {
+        if self.error.is_none() {
+            self.error = Some(error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address_book::Named;
+    use crate::test_utils::test_box;
+
+    #[test]
+    fn min_box_value_measures_the_full_box() {
+        let address = "9fSgJ7BmUxBQJ454prQDQ7fQMBkXPLaAmDnimgTtjym6FYPHjAV".to_string();
+        // A plain P2PK box is 75 bytes once its tx id and output index are
+        // included (its candidate alone is only 42 bytes), and so the node
+        // rejects it if it holds less than 75 * 360 nanoErgs
+        let candidate = create_candidate(20000, &address, &vec![], &vec![], 0).unwrap();
+        assert_eq!(min_box_value(&candidate).unwrap(), 27000);
+        assert!(matches!(
+            create_checked_candidate(26999, &address, &vec![], &vec![], 0),
+            Err(HeadlessDappError::BoxValueBelowMinimum(26999, 27000))
+        ));
+        assert!(create_checked_candidate(27000, &address, &vec![], &vec![], 0).is_ok());
+        assert_eq!(
+            *create_min_value_candidate(0, &address, &vec![], &vec![], 0)
+                .unwrap()
+                .value
+                .as_u64(),
+            27000
+        );
+    }
+
+    #[test]
+    fn tokens_change_box_min_value() {
+        let address = "9fSgJ7BmUxBQJ454prQDQ7fQMBkXPLaAmDnimgTtjym6FYPHjAV".to_string();
+        let token = build_token(
+            "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1",
+            10,
+        )
+        .unwrap();
+        let input_box = test_box(5000000, &address, vec![token], vec![]);
+        let input_boxes = vec![input_box.clone()];
+
+        assert!(matches!(
+            TokensChangeBox::output_candidate(&input_boxes, 1000, &address, 0),
+            Err(HeadlessDappError::BoxValueBelowMinimum(1000, _))
+        ));
+
+        let (candidate, top_up) = TokensChangeBox::output_candidate_topped_up(
+            &vec![],
+            &input_boxes,
+            1000,
+            &vec![],
+            &input_box,
+            &address,
+            0,
+        )
+        .unwrap();
+        let value = *candidate.value.as_u64();
+        assert_eq!(value, min_box_value(&candidate).unwrap());
+        assert_eq!(top_up, value - 1000);
+    }
+
+    #[test]
+    fn output_candidate_builder_orders_registers() {
+        let address = "9fSgJ7BmUxBQJ454prQDQ7fQMBkXPLaAmDnimgTtjym6FYPHjAV";
+        let token_id = "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1";
+        let candidate = OutputCandidateBuilder::new()
+            .value(1000000)
+            .address(address)
+            .token(token_id, 5)
+            .register(NonMandatoryRegisterId::R5, Constant::from(2i32))
+            .register(NonMandatoryRegisterId::R4, Constant::from(1i32))
+            .creation_height(10)
+            .build()
+            .unwrap();
+        assert_eq!(candidate.creation_height, 10);
+        assert_eq!(u64::from(candidate.tokens[0].amount), 5);
+        assert_eq!(
+            candidate.additional_registers.get_ordered_values().clone(),
+            vec![Constant::from(1i32), Constant::from(2i32)]
+        );
+    }
+
+    #[test]
+    fn output_candidate_builder_rejects_invalid_outputs() {
+        let address = "9fSgJ7BmUxBQJ454prQDQ7fQMBkXPLaAmDnimgTtjym6FYPHjAV";
+        let skipped = OutputCandidateBuilder::new()
+            .value(1000000)
+            .address(address)
+            .register(NonMandatoryRegisterId::R5, Constant::from(2i32))
+            .build();
+        assert!(matches!(
+            skipped,
+            Err(HeadlessDappError::InvalidRegisterOrder(_))
+        ));
+
+        let duplicated = OutputCandidateBuilder::new()
+            .value(1000000)
+            .address(address)
+            .register(NonMandatoryRegisterId::R4, Constant::from(1i32))
+            .register(NonMandatoryRegisterId::R4, Constant::from(2i32))
+            .build();
+        assert!(matches!(
+            duplicated,
+            Err(HeadlessDappError::InvalidRegisterOrder(_))
+        ));
+
+        let below_minimum = OutputCandidateBuilder::new()
+            .value(1000)
+            .address(address)
+            .build();
+        assert!(matches!(
+            below_minimum,
+            Err(HeadlessDappError::BoxValueBelowMinimum(1000, _))
+        ));
+
+        assert!(OutputCandidateBuilder::new()
+            .value(1000000)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn output_candidate_builder_resolves_named_addresses() {
+        let address = "9fSgJ7BmUxBQJ454prQDQ7fQMBkXPLaAmDnimgTtjym6FYPHjAV";
+        let mut book = AddressBook::new();
+        book.insert("treasury", address).unwrap();
+
+        let named = OutputCandidateBuilder::new()
+            .value(1000000)
+            .address_book(&book)
+            .address(Named("treasury"))
+            .build()
+            .unwrap();
+        let direct = OutputCandidateBuilder::new()
+            .value(1000000)
+            .address(address)
+            .build()
+            .unwrap();
+        assert_eq!(named, direct);
+
+        let unknown = OutputCandidateBuilder::new()
+            .value(1000000)
+            .address(Named("unknown"))
+            .build();
+        assert!(matches!(
+            unknown,
+            Err(HeadlessDappError::UnknownAddressName(_))
+        ));
+    }
+}
@@ -0,0 +1,99 @@
+/// This file holds the `TxOutputsLayout` helper which names the output
+/// positions of a transaction. Multi-stage protocol contracts often rely
+/// on the position of an output (ie. `OUTPUTS(0)` being the next stage
+/// box), and so building the ordered list of output candidates from names
+/// prevents off-by-one output ordering bugs.
+use crate::error::{HeadlessDappError, Result};
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBoxCandidate;
+use std::collections::HashMap;
+
+/// A named layout of the outputs of a transaction, where the position of a
+/// name in the layout is the index of said output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxOutputsLayout {
+    names: Vec<String>,
+}
+
+impl TxOutputsLayout {
+    /// Create a new `TxOutputsLayout` from output names in order.
+    /// Ie. `TxOutputsLayout::new(&["stage_box", "fee", "change"])`
+    pub fn new(names: &[&str]) -> Result<TxOutputsLayout> {
+        let mut layout_names: Vec<String> = vec![];
+        for name in names {
+            if layout_names.iter().any(|n| n == name) {
+                return Err(HeadlessDappError::InvalidOutputsLayout(format!(
+                    "Output {} is defined more than once",
+                    name
+                )));
+            }
+            layout_names.push(name.to_string());
+        }
+        Ok(TxOutputsLayout {
+            names: layout_names,
+        })
+    }
+
+    /// Acquire the output index of the given name
+    pub fn index_of(&self, name: &str) -> Result<usize> {
+        self.names.iter().position(|n| n == name).ok_or_else(|| {
+            HeadlessDappError::InvalidOutputsLayout(format!("Unknown output {}", name))
+        })
+    }
+
+    /// A map of every output name to its index
+    pub fn index_map(&self) -> HashMap<String, usize> {
+        self.names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.clone(), i))
+            .collect()
+    }
+
+    /// Orders the provided named output candidates according to the layout.
+    /// Errors if an output of the layout is missing, if an output is not
+    /// part of the layout, or if an output is provided more than once.
+    pub fn assemble(
+        &self,
+        outputs: Vec<(&str, ErgoBoxCandidate)>,
+    ) -> Result<Vec<ErgoBoxCandidate>> {
+        let mut ordered: Vec<Option<ErgoBoxCandidate>> = vec![None; self.names.len()];
+        for (name, candidate) in outputs {
+            let index = self.index_of(name)?;
+            if ordered[index].is_some() {
+                return Err(HeadlessDappError::InvalidOutputsLayout(format!(
+                    "Output {} was provided more than once",
+                    name
+                )));
+            }
+            ordered[index] = Some(candidate);
+        }
+
+        ordered
+            .into_iter()
+            .enumerate()
+            .map(|(i, candidate)| {
+                candidate.ok_or_else(|| {
+                    HeadlessDappError::InvalidOutputsLayout(format!(
+                        "Output {} was not provided",
+                        self.names[i]
+                    ))
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outputs_layout_indexes() {
+        let layout = TxOutputsLayout::new(&["stage_box", "fee", "change"]).unwrap();
+        assert_eq!(layout.index_of("stage_box").unwrap(), 0);
+        assert_eq!(layout.index_of("change").unwrap(), 2);
+        assert!(layout.index_of("unknown").is_err());
+        assert_eq!(layout.index_map()["fee"], 1);
+        assert!(TxOutputsLayout::new(&["fee", "fee"]).is_err());
+    }
+}
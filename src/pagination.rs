@@ -0,0 +1,87 @@
+/// This file holds the `PageCursor` which tracks the position of a
+/// paginated explorer lookup. Cursors can be persisted, allowing
+/// interrupted scans to be resumed deterministically, and expose whether
+/// more pages remain for "load more" style UIs.
+use serde::{Deserialize, Serialize};
+
+/// The position of a paginated explorer lookup
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageCursor {
+    /// The number of items to skip
+    pub offset: u64,
+    /// The number of items requested per page
+    pub limit: u64,
+    /// The total number of items, once known from the explorer metadata
+    pub total: Option<u64>,
+}
+
+impl PageCursor {
+    /// Create a new `PageCursor` at the given position
+    pub fn new(offset: u64, limit: u64) -> PageCursor {
+        PageCursor {
+            offset,
+            limit: limit.max(1),
+            total: None,
+        }
+    }
+
+    /// Create a `PageCursor` for the first page
+    pub fn first(limit: u64) -> PageCursor {
+        PageCursor::new(0, limit)
+    }
+
+    /// Whether there may be more pages to fetch
+    pub fn has_more(&self) -> bool {
+        match self.total {
+            Some(total) => self.offset < total,
+            None => true,
+        }
+    }
+
+    /// Returns the cursor of the next page, given the number of items
+    /// `fetched` in the current page and the `total` from the explorer
+    /// metadata (if provided). A page holding fewer items than the limit
+    /// is treated as the final page.
+    pub fn advanced(&self, fetched: u64, total: Option<u64>) -> PageCursor {
+        let offset = self.offset + fetched;
+        let total = match total {
+            Some(total) => Some(total),
+            None if fetched < self.limit => Some(offset),
+            None => self.total,
+        };
+        PageCursor {
+            offset,
+            limit: self.limit,
+            total,
+        }
+    }
+}
+
+/// Reads the total number of items from an explorer response body
+pub(crate) fn explorer_response_total(explorer_response_body: &str) -> Option<u64> {
+    json::parse(explorer_response_body).ok()?["total"].as_u64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_advances_until_total() {
+        let cursor = PageCursor::first(10);
+        assert!(cursor.has_more());
+        let cursor = cursor.advanced(10, Some(15));
+        assert_eq!(cursor.offset, 10);
+        assert!(cursor.has_more());
+        let cursor = cursor.advanced(5, None);
+        assert_eq!(cursor.offset, 15);
+        assert!(!cursor.has_more());
+    }
+
+    #[test]
+    fn short_page_without_total_is_final() {
+        let cursor = PageCursor::first(10).advanced(3, None);
+        assert_eq!(cursor.total, Some(3));
+        assert!(!cursor.has_more());
+    }
+}
@@ -0,0 +1,42 @@
+/// This file holds a global registry of `BoxSpec` predicates. Predicates
+/// are plain function pointers and thus cannot be serialized, so instead
+/// they are registered under a string name which a `BoxSpec` can reference.
+/// This allows specs to be shared between services (which register the same
+/// predicates under the same names) and serialized without losing their
+/// custom verification logic.
+use crate::error::{HeadlessDappError, Result};
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// A custom predicate which is used as part of verifying an `ErgoBox`
+/// against a `BoxSpec`.
+pub type BoxPredicate = fn(&ErgoBox) -> bool;
+
+static PREDICATE_REGISTRY: Lazy<RwLock<HashMap<String, BoxPredicate>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Registers a predicate under the provided name. If a predicate was already
+/// registered under said name it is replaced.
+pub fn register_predicate(name: &str, predicate: BoxPredicate) {
+    PREDICATE_REGISTRY
+        .write()
+        .unwrap()
+        .insert(name.to_string(), predicate);
+}
+
+/// Acquires the predicate registered under the provided name.
+pub fn lookup_predicate(name: &str) -> Result<BoxPredicate> {
+    PREDICATE_REGISTRY
+        .read()
+        .unwrap()
+        .get(name)
+        .copied()
+        .ok_or_else(|| HeadlessDappError::UnknownPredicate(name.to_string()))
+}
+
+/// Checks whether a predicate has been registered under the provided name.
+pub fn is_predicate_registered(name: &str) -> bool {
+    PREDICATE_REGISTRY.read().unwrap().contains_key(name)
+}
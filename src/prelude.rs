@@ -0,0 +1,37 @@
+/// This file holds a prelude of stable re-exports for downstream protocol
+/// crates, which should import the ergo-lib types they use from here
+/// (`use ergo_headless_dapp_framework::prelude::*;`) rather than from
+/// ergo-lib directly. This way they do not break when ergo-lib reshuffles
+/// its module paths (ie. `ast::constant` becoming `mir::constant`), as only
+/// the paths in this file are updated. The prelude also holds everything
+/// the `WrapBox`, `SpecBox`, and `WASMBox` derives require to be in scope.
+pub use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+pub use ergo_lib::chain::transaction::{DataInput, TxId as ErgoTxId, UnsignedInput};
+pub use ergo_lib::ergotree_ir::chain::address::{Address, AddressEncoder, NetworkPrefix};
+pub use ergo_lib::ergotree_ir::chain::digest32::Digest32;
+pub use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
+pub use ergo_lib::ergotree_ir::chain::ergo_box::{
+    BoxId, ErgoBox, ErgoBoxCandidate, NonMandatoryRegisterId, NonMandatoryRegisters,
+};
+pub use ergo_lib::ergotree_ir::chain::token::{Token, TokenAmount, TokenId};
+pub use ergo_lib::ergotree_ir::ergo_tree::ErgoTree;
+pub use ergo_lib::ergotree_ir::mir::constant::{Constant, TryExtractFrom};
+pub use ergo_lib::ergotree_ir::types::stype::SType;
+
+// ergo-lib-wasm types used by the `WASMBox` derive
+pub use ergo_lib_wasm::box_coll::ErgoBoxes;
+pub use ergo_lib_wasm::ergo_box::ErgoBox as WErgoBox;
+pub use wasm_bindgen::prelude::*;
+
+// Framework types
+pub use crate::box_spec::{BoxSpec, RegisterSpec, TokenSpec};
+pub use crate::box_traits::{ExplorerFindable, SpecifiedBox, WrappedBox};
+pub use crate::error::{HeadlessDappError, Result};
+pub use crate::output_builders::{ChangeBox, TokensChangeBox, TxFeeBox};
+pub use crate::register_constants::RegisterConstant;
+pub use crate::specified_boxes::ErgsBox;
+pub use crate::tx_creation::create_candidate;
+pub use crate::{
+    js_sys, wasm_bindgen_futures, BlockHeight, ErgoAddressString, NanoErg, P2PKAddressString,
+    P2SAddressString, SpecBox, TokenID, TxId, WASMBox, WrapBox,
+};
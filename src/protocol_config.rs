@@ -0,0 +1,148 @@
+/// This file holds the `ProtocolConfig` loader which reads the parameters
+/// of a protocol (contract addresses, NFT/token ids, fee rates, and the
+/// explorer URL) from a TOML or JSON file, so that they do not have to be
+/// embedded as constants in the source of the protocol.
+use crate::address_book::AddressBook;
+use crate::encoding::StringEncoding;
+use crate::error::{HeadlessDappError, Result};
+use crate::explorer_backend::ExplorerBackend;
+use crate::network::Network;
+use crate::{ErgoAddressString, TokenID};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// The parameters of a protocol loaded from config.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolConfig {
+    /// The network the protocol runs on. Defaults to mainnet.
+    #[serde(default)]
+    pub network: Network,
+    /// A custom Ergo Explorer Backend API URL. Defaults to the URL of
+    /// the `network`.
+    #[serde(default)]
+    pub explorer_api_url: Option<String>,
+    /// Headers (ie. API keys) which must be attached to every request to
+    /// the explorer API
+    #[serde(default)]
+    pub explorer_headers: HashMap<String, String>,
+    /// Named addresses (ie. contract and treasury addresses)
+    #[serde(default)]
+    pub addresses: HashMap<String, ErgoAddressString>,
+    /// Named token ids (ie. protocol NFTs)
+    #[serde(default)]
+    pub token_ids: HashMap<String, TokenID>,
+    /// Named fee rates in basis points
+    #[serde(default)]
+    pub fee_rates: HashMap<String, u64>,
+    /// The encoding of the Strings stored in registers, as expected by the
+    /// contracts of the protocol. Defaults to plain UTF-8 bytes.
+    #[serde(default)]
+    pub string_encoding: StringEncoding,
+}
+
+impl ProtocolConfig {
+    /// Parses a `ProtocolConfig` from a JSON `String`
+    pub fn from_json(json: &str) -> Result<ProtocolConfig> {
+        serde_json::from_str(json).map_err(|e| HeadlessDappError::InvalidConfig(e.to_string()))
+    }
+
+    /// Parses a `ProtocolConfig` from a TOML `String`
+    pub fn from_toml(toml_str: &str) -> Result<ProtocolConfig> {
+        toml::from_str(toml_str).map_err(|e| HeadlessDappError::InvalidConfig(e.to_string()))
+    }
+
+    /// Loads a `ProtocolConfig` from a file. Files ending in `.json` are
+    /// parsed as JSON, all other files are parsed as TOML.
+    pub fn load(path: &Path) -> Result<ProtocolConfig> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| HeadlessDappError::InvalidConfig(e.to_string()))?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => ProtocolConfig::from_json(&contents),
+            _ => ProtocolConfig::from_toml(&contents),
+        }
+    }
+
+    /// The Ergo Explorer Backend API URL of the protocol
+    pub fn explorer_api_url(&self) -> String {
+        self.explorer_api_url
+            .clone()
+            .unwrap_or_else(|| self.network.explorer_api_url().to_string())
+    }
+
+    /// Builds the `ExplorerBackend` of the protocol, with all of the
+    /// configured explorer headers attached
+    pub fn explorer_backend(&self) -> ExplorerBackend {
+        let mut headers: Vec<_> = self.explorer_headers.iter().collect();
+        headers.sort();
+        headers.into_iter().fold(
+            ExplorerBackend::new(&self.explorer_api_url()),
+            |b, (n, v)| b.with_header(n, v),
+        )
+    }
+
+    /// Acquire the address with the given name
+    pub fn address(&self, name: &str) -> Result<ErgoAddressString> {
+        self.addresses
+            .get(name)
+            .cloned()
+            .ok_or_else(|| HeadlessDappError::MissingConfigValue(format!("addresses.{}", name)))
+    }
+
+    /// Acquire the token id with the given name
+    pub fn token_id(&self, name: &str) -> Result<TokenID> {
+        self.token_ids
+            .get(name)
+            .cloned()
+            .ok_or_else(|| HeadlessDappError::MissingConfigValue(format!("token_ids.{}", name)))
+    }
+
+    /// Acquire the fee rate (in basis points) with the given name
+    pub fn fee_bps(&self, name: &str) -> Result<u64> {
+        self.fee_rates
+            .get(name)
+            .cloned()
+            .ok_or_else(|| HeadlessDappError::MissingConfigValue(format!("fee_rates.{}", name)))
+    }
+
+    /// Builds an `AddressBook` holding all of the addresses of the config,
+    /// verifying that every address is valid.
+    pub fn address_book(&self) -> Result<AddressBook> {
+        let mut book = AddressBook::new();
+        for (name, address) in &self.addresses {
+            book.insert(name, address)?;
+        }
+        Ok(book)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_toml_config() {
+        let config = ProtocolConfig::from_toml(
+            r#"
+            network = "testnet"
+
+            [token_ids]
+            oracle_nft = "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1"
+
+            [fee_rates]
+            protocol = 50
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.network, Network::Testnet);
+        assert_eq!(
+            config.explorer_api_url(),
+            "https://api-testnet.ergoplatform.com/api"
+        );
+        assert_eq!(config.fee_bps("protocol").unwrap(), 50);
+        assert!(config.token_id("oracle_nft").is_ok());
+        assert!(config.address("treasury").is_err());
+    }
+}
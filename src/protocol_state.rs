@@ -0,0 +1,223 @@
+/// This file holds the `ProtocolState` abstraction which gathers all of
+/// the stage boxes of a protocol into one read-only snapshot. As the
+/// framework is headless, fetching is left to the front-end/bot: the
+/// `ProtocolState` provides the explorer endpoints for every stage, and
+/// the response bodies are then processed into a `ProtocolStateSnapshot`.
+use crate::box_spec::BoxSpec;
+use crate::box_traits::{ExplorerFindable, SpecifiedBox};
+use crate::error::{HeadlessDappError, Result};
+use crate::BlockHeight;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use std::collections::HashMap;
+
+/// A definition of the stages of a protocol, each named and defined by
+/// a `BoxSpec`.
+#[derive(Clone, Default)]
+pub struct ProtocolState {
+    stages: Vec<(String, BoxSpec)>,
+}
+
+impl ProtocolState {
+    /// Create a new `ProtocolState` with no stages
+    pub fn new() -> ProtocolState {
+        ProtocolState::default()
+    }
+
+    /// Adds a stage defined by the provided `BoxSpec`
+    pub fn with_stage(mut self, name: &str, box_spec: BoxSpec) -> Self {
+        self.stages.push((name.to_string(), box_spec));
+        self
+    }
+
+    /// Adds a stage defined by the `BoxSpec` of a `SpecifiedBox`
+    pub fn with_specified_box<T: SpecifiedBox>(self, name: &str) -> Self {
+        self.with_stage(name, T::box_spec())
+    }
+
+    /// Returns the names of all of the stages
+    pub fn stage_names(&self) -> Vec<String> {
+        self.stages.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// Generates the Ergo Explorer Backend API endpoint for every stage
+    /// as `(stage name, url)` pairs. The response bodies of these
+    /// endpoints are then provided to `snapshot()`.
+    pub fn explorer_endpoints(&self, explorer_api_url: &str) -> Result<Vec<(String, String)>> {
+        self.stages
+            .iter()
+            .map(|(name, spec)| Ok((name.clone(), spec.explorer_endpoint(explorer_api_url)?)))
+            .collect()
+    }
+
+    /// Processes the explorer response bodies (keyed by stage name) of
+    /// every stage into a `ProtocolStateSnapshot`, recording the height and
+    /// timestamp (milliseconds since the unix epoch) of the fetch.
+    pub fn snapshot(
+        &self,
+        explorer_responses: &HashMap<String, String>,
+        height: BlockHeight,
+        fetched_at_ms: u64,
+    ) -> Result<ProtocolStateSnapshot> {
+        let mut stages = HashMap::new();
+        for (name, spec) in &self.stages {
+            let body = explorer_responses
+                .get(name)
+                .ok_or_else(|| HeadlessDappError::UnknownStage(name.clone()))?;
+            stages.insert(name.clone(), spec.process_explorer_response(body)?);
+        }
+        Ok(ProtocolStateSnapshot {
+            stages,
+            height,
+            fetched_at_ms,
+        })
+    }
+}
+
+/// A read-only snapshot of all of the stage boxes of a protocol at a
+/// given height.
+#[derive(Clone, Debug)]
+pub struct ProtocolStateSnapshot {
+    stages: HashMap<String, Vec<ErgoBox>>,
+    height: BlockHeight,
+    fetched_at_ms: u64,
+}
+
+impl ProtocolStateSnapshot {
+    /// The block height at which the snapshot was taken
+    pub fn height(&self) -> BlockHeight {
+        self.height
+    }
+
+    /// The timestamp (milliseconds since the unix epoch) at which the
+    /// snapshot was taken
+    pub fn fetched_at_ms(&self) -> u64 {
+        self.fetched_at_ms
+    }
+
+    /// Returns the names of all of the stages in the snapshot, sorted
+    pub fn stage_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.stages.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// The `ErgoBox`es of the stage with the given name
+    pub fn boxes(&self, name: &str) -> Result<&Vec<ErgoBox>> {
+        self.stages
+            .get(name)
+            .ok_or_else(|| HeadlessDappError::UnknownStage(name.to_string()))
+    }
+
+    /// The boxes of the stage with the given name as instances of the
+    /// provided `SpecifiedBox` struct
+    pub fn stage<T: ExplorerFindable>(&self, name: &str) -> Result<Vec<T>> {
+        self.boxes(name)?
+            .iter()
+            .map(|b| T::from_ergo_box(b))
+            .collect()
+    }
+
+    /// The total number of boxes across all stages
+    pub fn total_boxes(&self) -> usize {
+        self.stages.values().map(|boxes| boxes.len()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::box_spec::TokenSpec;
+    use crate::encoding::build_token;
+    use crate::specified_boxes::ErgsBox;
+    use crate::test_utils::{test_box_at, TEST_ADDRESS, TEST_TOKEN_ID};
+
+    fn build_explorer_response(boxes: &Vec<ErgoBox>) -> String {
+        let items: Vec<String> = boxes
+            .iter()
+            .map(|b| serde_json::to_string(b).unwrap())
+            .collect();
+        format!("{{\"items\": [{}]}}", items.join(","))
+    }
+
+    fn build_state() -> ProtocolState {
+        ProtocolState::new()
+            .with_stage(
+                "wallet",
+                BoxSpec::new(
+                    Some(TEST_ADDRESS.to_string()),
+                    Some(1000000..u64::MAX),
+                    vec![],
+                    vec![],
+                ),
+            )
+            .with_stage(
+                "pool",
+                BoxSpec::new(
+                    None,
+                    None,
+                    vec![],
+                    vec![Some(TokenSpec::new(1..2, TEST_TOKEN_ID))],
+                ),
+            )
+    }
+
+    #[test]
+    fn generates_an_endpoint_per_stage() {
+        let state = build_state();
+        assert_eq!(state.stage_names(), vec!["wallet", "pool"]);
+        let endpoints = state
+            .explorer_endpoints("https://api.ergoplatform.com/api")
+            .unwrap();
+        assert_eq!(endpoints.len(), 2);
+        assert_eq!(endpoints[0].0, "wallet");
+        assert!(endpoints[0].1.contains(TEST_ADDRESS));
+        assert_eq!(endpoints[1].0, "pool");
+        assert!(endpoints[1].1.contains(TEST_TOKEN_ID));
+    }
+
+    #[test]
+    fn snapshots_every_stage() {
+        let token = build_token(TEST_TOKEN_ID, 1).unwrap();
+        let wallet_boxes = vec![
+            test_box_at(2000000, TEST_ADDRESS, vec![], vec![], 0, 0),
+            test_box_at(100000, TEST_ADDRESS, vec![], vec![], 0, 1),
+        ];
+        let pool_boxes = vec![test_box_at(
+            1000000,
+            TEST_ADDRESS,
+            vec![token],
+            vec![],
+            0,
+            2,
+        )];
+        let mut responses = HashMap::new();
+        responses.insert("wallet".to_string(), build_explorer_response(&wallet_boxes));
+        responses.insert("pool".to_string(), build_explorer_response(&pool_boxes));
+
+        let snapshot = build_state().snapshot(&responses, 100, 1234).unwrap();
+        assert_eq!(snapshot.height(), 100);
+        assert_eq!(snapshot.fetched_at_ms(), 1234);
+        assert_eq!(snapshot.stage_names(), vec!["pool", "wallet"]);
+        assert_eq!(
+            snapshot.boxes("wallet").unwrap(),
+            &vec![wallet_boxes[0].clone()]
+        );
+        assert_eq!(snapshot.boxes("pool").unwrap(), &pool_boxes);
+        assert_eq!(snapshot.total_boxes(), 2);
+        assert_eq!(snapshot.stage::<ErgsBox>("wallet").unwrap().len(), 1);
+        assert!(matches!(
+            snapshot.boxes("unknown"),
+            Err(HeadlessDappError::UnknownStage(_))
+        ));
+    }
+
+    #[test]
+    fn missing_stage_response_is_an_error() {
+        let mut responses = HashMap::new();
+        responses.insert("wallet".to_string(), build_explorer_response(&vec![]));
+        assert!(matches!(
+            build_state().snapshot(&responses, 100, 0),
+            Err(HeadlessDappError::UnknownStage(name)) if name == "pool"
+        ));
+    }
+}
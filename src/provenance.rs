@@ -0,0 +1,116 @@
+/// This file holds the `BoxProvenance` metadata which is returned by the
+/// explorer alongside every box (the inclusion height and the spending
+/// Transaction ID, if any), and the `WithProvenance` wrapper which carries
+/// it along with a specified box. This allows callers to avoid selecting
+/// boxes which were just spent but are still returned by a lagging indexer,
+/// or boxes which do not yet have enough confirmations.
+use crate::box_spec::BoxSpec;
+use crate::box_traits::WrappedBox;
+use crate::error::{HeadlessDappError, Result};
+use crate::{BlockHeight, TxId};
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use serde_json::from_str;
+use std::ops::Deref;
+
+/// The provenance metadata of a box fetched from the explorer
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BoxProvenance {
+    /// The height of the block the box was included in
+    pub inclusion_height: Option<BlockHeight>,
+    /// The Transaction ID of the transaction which spent the box, if any
+    pub spent_transaction_id: Option<TxId>,
+}
+
+impl BoxProvenance {
+    /// Whether the explorer reports the box as already spent
+    pub fn is_spent(&self) -> bool {
+        self.spent_transaction_id.is_some()
+    }
+
+    /// The number of confirmations of the box at the `current_height`
+    pub fn confirmations(&self, current_height: BlockHeight) -> Option<u64> {
+        self.inclusion_height
+            .map(|h| (current_height + 1).saturating_sub(h))
+    }
+}
+
+/// A wrapped box along with its `BoxProvenance`
+#[derive(Clone, Debug)]
+pub struct WithProvenance<T: WrappedBox> {
+    inner: T,
+    provenance: BoxProvenance,
+}
+
+impl<T: WrappedBox> WithProvenance<T> {
+    /// Create a new `WithProvenance`
+    pub fn new(inner: T, provenance: BoxProvenance) -> WithProvenance<T> {
+        WithProvenance { inner, provenance }
+    }
+
+    /// The provenance metadata of the box
+    pub fn provenance(&self) -> &BoxProvenance {
+        &self.provenance
+    }
+
+    /// Unwraps the inner wrapped box, dropping the provenance
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Whether the box is unspent and has at least `min_confirmations`
+    /// at the `current_height`
+    pub fn is_selectable(&self, current_height: BlockHeight, min_confirmations: u64) -> bool {
+        !self.provenance.is_spent()
+            && self
+                .provenance
+                .confirmations(current_height)
+                .map(|c| c >= min_confirmations)
+                .unwrap_or(min_confirmations == 0)
+    }
+}
+
+impl<T: WrappedBox> Deref for WithProvenance<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T: WrappedBox> WrappedBox for WithProvenance<T> {
+    fn get_box_ref(&self) -> &ErgoBox {
+        self.inner.get_box_ref()
+    }
+}
+
+/// Parses the response JSON (as a String) from the Ergo Explorer API into
+/// a list of `ErgoBox`es along with their `BoxProvenance`, keeping only the
+/// boxes which match the `BoxSpec`.
+pub fn process_explorer_response_with_provenance(
+    box_spec: &BoxSpec,
+    explorer_response_body: &str,
+) -> Result<Vec<(ErgoBox, BoxProvenance)>> {
+    let json = json::parse(explorer_response_body).map_err(|_| {
+        HeadlessDappError::Other(
+            "Failed to extract json from Ergo Explorer Backend API Response".to_string(),
+        )
+    })?;
+
+    let mut boxes = vec![];
+    for box_json in json["items"].members() {
+        let ergo_box: ErgoBox = from_str(&box_json.to_string()).map_err(|e| {
+            let mess = format!("Box Json: {}\nError: {:?}", box_json, e);
+            HeadlessDappError::Other(mess)
+        })?;
+        if box_spec.verify_box(&ergo_box).is_err() {
+            continue;
+        }
+        let provenance = BoxProvenance {
+            inclusion_height: box_json["settlementHeight"].as_u64(),
+            spent_transaction_id: box_json["spentTransactionId"]
+                .as_str()
+                .map(|s| s.to_string()),
+        };
+        boxes.push((ergo_box, provenance));
+    }
+    Ok(boxes)
+}
@@ -0,0 +1,79 @@
+/// This file holds the `ReadOnly` type-state which marks a protocol
+/// instance as read-only. A `ReadOnly` protocol can fetch and verify the
+/// boxes of the protocol, but does not expose the protocol itself and so
+/// none of its Actions can be used to build transactions. The boxes it
+/// finds hold the `ReadOnly` protocol as their source as well, and thus
+/// cannot be used to reach the Actions either. This is intended for public
+/// dashboards and analytics services which must never spend.
+use crate::box_spec::BoxSpec;
+use crate::box_traits::{SpecSource, Specified};
+use crate::error::Result;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+
+/// A box found by a `ReadOnly` protocol
+pub type ReadOnlySpecified<S> = Specified<ReadOnly<S>>;
+
+/// A protocol instance which can only fetch and verify boxes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReadOnly<S: SpecSource> {
+    protocol: S,
+}
+
+impl<S: SpecSource> SpecSource for ReadOnly<S> {
+    fn box_spec(&self) -> BoxSpec {
+        self.protocol.box_spec()
+    }
+}
+
+impl<S: SpecSource> ReadOnly<S> {
+    /// Marks the `protocol` as read-only. The protocol is consumed, and
+    /// cannot be acquired back.
+    pub fn new(protocol: S) -> ReadOnly<S> {
+        ReadOnly { protocol }
+    }
+
+    /// Generates the explorer endpoint for the boxes of the protocol
+    pub fn explorer_endpoint(&self, explorer_api_url: &str) -> Result<String> {
+        ReadOnlySpecified::explorer_endpoint(self, explorer_api_url)
+    }
+
+    /// Processes the explorer response body from the endpoint generated by
+    /// `explorer_endpoint()` into read-only boxes
+    pub fn process_explorer_response(
+        &self,
+        explorer_response_body: &str,
+    ) -> Result<Vec<ReadOnlySpecified<S>>>
+    where
+        S: Clone,
+    {
+        ReadOnlySpecified::process_explorer_response(explorer_response_body, self)
+    }
+
+    /// Verifies the `ergo_box` against the spec of the protocol
+    pub fn verify_box(&self, ergo_box: &ErgoBox) -> Result<ReadOnlySpecified<S>>
+    where
+        S: Clone,
+    {
+        Specified::new(ergo_box, self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_box;
+    use crate::AuctionHouse;
+
+    #[test]
+    fn read_only_protocol_verifies_boxes() {
+        let address = "9fSgJ7BmUxBQJ454prQDQ7fQMBkXPLaAmDnimgTtjym6FYPHjAV".to_string();
+        let read_only = ReadOnly::new(AuctionHouse::new(&address));
+        assert!(read_only
+            .explorer_endpoint("https://api.ergoplatform.com/api")
+            .is_ok());
+
+        // A box without the auction registers and token is rejected
+        let ergo_box = test_box(1000000, &address, vec![], vec![]);
+        assert!(read_only.verify_box(&ergo_box).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+/// This file holds the `RefundPath` metadata which can be attached to a
+/// `BoxSpec` to declare that the box's script allows refunding the box to
+/// a beneficiary after a given height, as well as `action_refund()` which
+/// builds the refund transaction for any such box. This is the common
+/// escape hatch of protocols whose boxes may otherwise become stuck.
+use crate::box_spec::BoxSpec;
+use crate::box_traits::SpecifiedBox;
+use crate::encoding::deserialize_ergo_tree_constant;
+use crate::error::{HeadlessDappError, Result};
+use crate::output_builders::{create_checked_candidate, TxFeeBox};
+use crate::timelock::Timelock;
+use crate::tx_creation::{check_unsigned_tx, new_unsigned_tx};
+use crate::{BlockHeight, NanoErg, P2SAddressString};
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use serde::{Deserialize, Serialize};
+
+/// A refund path of a box's script, as declared on its `BoxSpec`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RefundPath {
+    /// The height-based lock after which the refund path may be used
+    pub timelock: Timelock,
+    /// The index of the register (0 is R4, 1 is R5, etc.) which holds the
+    /// ErgoTree bytes of the beneficiary as a `Coll[Byte]`
+    pub beneficiary_register: usize,
+}
+
+impl RefundPath {
+    /// Create a new `RefundPath`
+    pub fn new(timelock: Timelock, beneficiary_register: usize) -> RefundPath {
+        RefundPath {
+            timelock,
+            beneficiary_register,
+        }
+    }
+
+    /// Acquires the address of the beneficiary of the refund from the box
+    pub fn beneficiary(&self, ergo_box: &ErgoBox) -> Result<P2SAddressString> {
+        let registers = ergo_box.additional_registers.get_ordered_values();
+        let c = registers
+            .get(self.beneficiary_register)
+            .ok_or(HeadlessDappError::FailedRegisterSpec)?;
+        Ok(deserialize_ergo_tree_constant(c)?)
+    }
+}
+
+/// Builds a transaction which refunds the provided `SpecifiedBox`, whose
+/// `BoxSpec` declares a `RefundPath`, to its beneficiary. The refund
+/// output holds all of the box's tokens and all of its nanoErgs minus the
+/// `transaction_fee`, erroring if said nanoErgs are below the minimum
+/// value of the refund output.
+pub fn action_refund<T: SpecifiedBox>(
+    refund_box: &T,
+    transaction_fee: NanoErg,
+    current_height: BlockHeight,
+) -> Result<UnsignedTransaction> {
+    build_refund_tx(
+        &T::box_spec(),
+        refund_box.get_box_ref(),
+        transaction_fee,
+        current_height,
+    )
+}
+
+/// Same as `action_refund()` but uses the `RefundPath` declared on the
+/// provided `BoxSpec` to refund an unwrapped `ErgoBox`.
+pub fn build_refund_tx(
+    box_spec: &BoxSpec,
+    ergo_box: &ErgoBox,
+    transaction_fee: NanoErg,
+    current_height: BlockHeight,
+) -> Result<UnsignedTransaction> {
+    let refund_path = box_spec
+        .refund_path()
+        .ok_or(HeadlessDappError::MissingRefundPath)?;
+    box_spec.verify_box(ergo_box)?;
+    refund_path.timelock.check(ergo_box, current_height)?;
+
+    let refund_value = ergo_box
+        .value
+        .as_u64()
+        .checked_sub(transaction_fee)
+        .ok_or(HeadlessDappError::InvalidBoxValue(*ergo_box.value.as_u64()))?;
+    let refund_candidate = create_checked_candidate(
+        refund_value,
+        &refund_path.beneficiary(ergo_box)?,
+        &ergo_box.tokens,
+        &vec![],
+        current_height,
+    )?;
+    let fee_candidate = TxFeeBox::output_candidate(transaction_fee, current_height)?;
+
+    let unsigned_tx = new_unsigned_tx(
+        vec![ergo_box.clone().into()],
+        vec![],
+        vec![refund_candidate, fee_candidate],
+    )
+    .map_err(HeadlessDappError::InvalidTxComposition)?;
+    check_unsigned_tx(&unsigned_tx)?;
+    Ok(unsigned_tx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::address_string_to_ergo_tree;
+    use crate::test_utils::{test_box_at, TEST_ADDRESS};
+    use ergo_lib::ergotree_ir::mir::constant::Constant;
+    use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+
+    fn refund_spec() -> BoxSpec {
+        BoxSpec::new(None, None, vec![], vec![])
+            .modified_refund_path(Some(RefundPath::new(Timelock::AbsoluteHeight(100), 0)))
+    }
+
+    fn refundable_box(value: NanoErg) -> ErgoBox {
+        let beneficiary = address_string_to_ergo_tree(&TEST_ADDRESS.to_string())
+            .unwrap()
+            .sigma_serialize_bytes()
+            .unwrap();
+        test_box_at(
+            value,
+            TEST_ADDRESS,
+            vec![],
+            vec![Constant::from(beneficiary)],
+            10,
+            0,
+        )
+    }
+
+    #[test]
+    fn refunds_to_beneficiary_after_timelock() {
+        let refund_box = refundable_box(5000000);
+        assert!(matches!(
+            build_refund_tx(&refund_spec(), &refund_box, 1000000, 99),
+            Err(HeadlessDappError::TimelockNotSatisfied(100))
+        ));
+        let unsigned_tx = build_refund_tx(&refund_spec(), &refund_box, 1000000, 100).unwrap();
+        let outputs = unsigned_tx.output_candidates.as_vec();
+        assert_eq!(*outputs[0].value.as_u64(), 4000000);
+        assert_eq!(outputs[0].ergo_tree, refund_box.ergo_tree);
+        assert_eq!(*outputs[1].value.as_u64(), 1000000);
+    }
+
+    #[test]
+    fn rejects_refunds_below_minimum_value() {
+        let refund_box = refundable_box(1001000);
+        assert!(matches!(
+            build_refund_tx(&refund_spec(), &refund_box, 1000000, 100),
+            Err(HeadlessDappError::BoxValueBelowMinimum(1000, _))
+        ));
+        let refund_box = refundable_box(1000000);
+        assert!(matches!(
+            build_refund_tx(&refund_spec(), &refund_box, 1000000, 100),
+            Err(HeadlessDappError::BoxValueBelowMinimum(0, _))
+        ));
+    }
+
+    #[test]
+    fn requires_refund_path() {
+        let refund_box = refundable_box(5000000);
+        let spec = BoxSpec::new(None, None, vec![], vec![]);
+        assert!(matches!(
+            build_refund_tx(&spec, &refund_box, 1000000, 100),
+            Err(HeadlessDappError::MissingRefundPath)
+        ));
+    }
+}
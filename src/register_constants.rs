@@ -0,0 +1,111 @@
+/// This file holds the `RegisterConstant` extension trait which provides
+/// framework-level constructors of register `Constant`s, such as
+/// `Constant::long(answer)`. User code built on these constructors does not
+/// depend on which conversion impls the current ergo-lib version provides
+/// (ie. whether byte collections are built from `Vec<i8>` or `Vec<u8>`), as
+/// only this file is updated when ergo-lib changes.
+use crate::encoding::address_string_to_ergo_tree;
+use crate::error::{HeadlessDappError, Result};
+use crate::ErgoAddressString;
+use ergo_lib::ergotree_ir::ergo_tree::ErgoTree;
+use ergo_lib::ergotree_ir::mir::constant::Constant;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+
+/// Constructors of register `Constant`s. The trait must be in scope (it is
+/// re-exported from the crate root and the prelude) to call them as
+/// `Constant::long(5)`.
+pub trait RegisterConstant: Sized {
+    /// A `Boolean` constant
+    fn boolean(value: bool) -> Self;
+    /// A `Byte` constant
+    fn byte(value: i8) -> Self;
+    /// A `Short` constant
+    fn short(value: i16) -> Self;
+    /// An `Int` constant
+    fn int(value: i32) -> Self;
+    /// A `Long` constant
+    fn long(value: i64) -> Self;
+    /// A `Coll[Byte]` constant
+    fn byte_coll(bytes: &[u8]) -> Self;
+    /// A `Coll[Int]` constant
+    fn int_coll(values: &[i32]) -> Self;
+    /// A `Coll[Long]` constant
+    fn long_coll(values: &[i64]) -> Self;
+    /// A `Coll[Byte]` constant holding the UTF-8 bytes of a String
+    fn utf8_string(value: &str) -> Self;
+    /// A `Coll[Byte]` constant holding the serialized bytes of an
+    /// `ErgoTree` (ie. for storing a script or its hash preimage)
+    fn ergo_tree_bytes(ergo_tree: &ErgoTree) -> Result<Self>;
+    /// A `Coll[Byte]` constant holding the serialized bytes of the
+    /// `ErgoTree` of an address
+    fn address_bytes(address: &ErgoAddressString) -> Result<Self>;
+}
+
+impl RegisterConstant for Constant {
+    fn boolean(value: bool) -> Self {
+        value.into()
+    }
+
+    fn byte(value: i8) -> Self {
+        value.into()
+    }
+
+    fn short(value: i16) -> Self {
+        value.into()
+    }
+
+    fn int(value: i32) -> Self {
+        value.into()
+    }
+
+    fn long(value: i64) -> Self {
+        value.into()
+    }
+
+    fn byte_coll(bytes: &[u8]) -> Self {
+        let signed_bytes: Vec<i8> = bytes.iter().map(|b| *b as i8).collect();
+        signed_bytes.into()
+    }
+
+    fn int_coll(values: &[i32]) -> Self {
+        values.to_vec().into()
+    }
+
+    fn long_coll(values: &[i64]) -> Self {
+        values.to_vec().into()
+    }
+
+    fn utf8_string(value: &str) -> Self {
+        Self::byte_coll(value.as_bytes())
+    }
+
+    fn ergo_tree_bytes(ergo_tree: &ErgoTree) -> Result<Self> {
+        let bytes = ergo_tree
+            .sigma_serialize_bytes()
+            .map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+        Ok(Self::byte_coll(&bytes))
+    }
+
+    fn address_bytes(address: &ErgoAddressString) -> Result<Self> {
+        let ergo_tree = address_string_to_ergo_tree(address)
+            .map_err(|_| HeadlessDappError::InvalidP2PKAddress(address.clone()))?;
+        Self::ergo_tree_bytes(&ergo_tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::{unwrap_long, unwrap_string};
+
+    #[test]
+    fn builds_constants() {
+        assert_eq!(unwrap_long(&Constant::long(42)).unwrap(), 42);
+        assert_eq!(Constant::long(42), Constant::from(42i64));
+        assert_eq!(unwrap_string(&Constant::utf8_string("abc")).unwrap(), "abc");
+        assert_eq!(
+            Constant::byte_coll(&[255, 1]),
+            Constant::from(vec![-1i8, 1])
+        );
+    }
+}
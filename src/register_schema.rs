@@ -0,0 +1,286 @@
+/// This file holds the `RegisterSchema` abstraction which allows a protocol
+/// to define the register layout of a box once. The schema is used both to
+/// build the `RegisterSpec`s of a `BoxSpec` and to decode/encode the
+/// register values by name, rather than duplicating the layout across the
+/// spec, the accessor methods, and the documentation.
+use crate::box_spec::RegisterSpec;
+use crate::box_traits::SpecifiedBox;
+use crate::encoding::{
+    address_string_to_ergo_tree, deserialize_ergo_tree_constant, serialize_hex_encoded_string,
+    serialize_string_with, unwrap_hex_encoded_string, unwrap_int, unwrap_long, unwrap_string_with,
+    StringEncoding,
+};
+use crate::error::{HeadlessDappError, Result};
+use crate::P2SAddressString;
+use ergo_lib::ergotree_ir::base16_str::Base16Str;
+use ergo_lib::ergotree_ir::mir::constant::Constant;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+use ergo_lib::ergotree_ir::types::stype::SType;
+
+/// The codec which is used to encode/decode the value of a register.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegisterCodec {
+    /// An `i32` Int
+    Int,
+    /// An `i64` Long
+    Long,
+    /// A UTF-8 String stored as a byte collection
+    String,
+    /// A hex-encoded String stored as a byte collection
+    HexString,
+    /// A serialized `ErgoTree` stored as a byte collection, represented
+    /// as a P2S address
+    ErgoTree,
+}
+
+impl RegisterCodec {
+    /// The `SType` of the `Constant` which this codec produces
+    pub fn value_type(&self) -> SType {
+        match self {
+            RegisterCodec::Int => SType::SInt,
+            RegisterCodec::Long => SType::SLong,
+            RegisterCodec::String | RegisterCodec::HexString | RegisterCodec::ErgoTree => {
+                Constant::from(Vec::<i8>::new()).tpe
+            }
+        }
+    }
+}
+
+/// A decoded register value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RegisterValue {
+    Int(i32),
+    Long(i64),
+    String(String),
+    HexString(String),
+    ErgoTree(P2SAddressString),
+}
+
+/// A single named register field within a `RegisterSchema`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegisterField {
+    /// The name of the field
+    pub name: String,
+    /// The index of the register. 0 is R4, 1 is R5, and so on.
+    pub index: usize,
+    /// The codec used to encode/decode the value of the register
+    pub codec: RegisterCodec,
+}
+
+impl RegisterField {
+    pub fn new(name: &str, index: usize, codec: RegisterCodec) -> RegisterField {
+        RegisterField {
+            name: name.to_string(),
+            index: index,
+            codec: codec,
+        }
+    }
+
+    /// The `SType` of the register
+    pub fn value_type(&self) -> SType {
+        self.codec.value_type()
+    }
+}
+
+/// A mapping of names to registers which defines the register layout
+/// of a box.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct RegisterSchema {
+    fields: Vec<RegisterField>,
+    string_encoding: StringEncoding,
+}
+
+impl RegisterSchema {
+    /// Create a new `RegisterSchema`. Errors if two fields share a name
+    /// or a register index, or if an index is past R9.
+    pub fn new(fields: Vec<RegisterField>) -> Result<RegisterSchema> {
+        for (i, field) in fields.iter().enumerate() {
+            if field.index > 5 {
+                return Err(HeadlessDappError::InvalidRegisterSchema(field.name.clone()));
+            }
+            let duplicate = fields[..i]
+                .iter()
+                .any(|f| f.name == field.name || f.index == field.index);
+            if duplicate {
+                return Err(HeadlessDappError::InvalidRegisterSchema(field.name.clone()));
+            }
+        }
+        Ok(RegisterSchema {
+            fields: fields,
+            string_encoding: StringEncoding::default(),
+        })
+    }
+
+    /// Sets the `StringEncoding` of the `String` fields, as expected by
+    /// the contract of the protocol
+    pub fn with_string_encoding(mut self, string_encoding: StringEncoding) -> Self {
+        self.string_encoding = string_encoding;
+        self
+    }
+
+    /// Returns the fields of the schema
+    pub fn fields(&self) -> &Vec<RegisterField> {
+        &self.fields
+    }
+
+    /// Acquire the field with the given name
+    pub fn field(&self, name: &str) -> Result<&RegisterField> {
+        self.fields
+            .iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| HeadlessDappError::UnknownRegisterField(name.to_string()))
+    }
+
+    /// Builds the ordered list of `RegisterSpec`s for a `BoxSpec`. Any
+    /// register which is not part of the schema but comes before the last
+    /// field is left unspecified.
+    pub fn register_specs(&self) -> Vec<RegisterSpec> {
+        let len = self.fields.iter().map(|f| f.index + 1).max().unwrap_or(0);
+        (0..len)
+            .map(|i| match self.fields.iter().find(|f| f.index == i) {
+                Some(field) => RegisterSpec::new(Some(field.value_type()), None),
+                None => RegisterSpec::new(None, None),
+            })
+            .collect()
+    }
+
+    /// Decodes the value of the named field from the ordered registers
+    /// of a box (as returned by `WrappedBox::registers()`)
+    pub fn decode(&self, name: &str, registers: &Vec<Constant>) -> Result<RegisterValue> {
+        let field = self.field(name)?;
+        let c = registers
+            .get(field.index)
+            .ok_or(HeadlessDappError::FailedRegisterSpec)?;
+        let value = match field.codec {
+            RegisterCodec::Int => RegisterValue::Int(unwrap_int(c)?),
+            RegisterCodec::Long => RegisterValue::Long(unwrap_long(c)?),
+            RegisterCodec::String => {
+                RegisterValue::String(unwrap_string_with(c, self.string_encoding)?)
+            }
+            RegisterCodec::HexString => RegisterValue::HexString(unwrap_hex_encoded_string(c)?),
+            RegisterCodec::ErgoTree => RegisterValue::ErgoTree(deserialize_ergo_tree_constant(c)?),
+        };
+        Ok(value)
+    }
+
+    /// Encodes a value for the named field into a `Constant`, checking that
+    /// the value matches the codec of the field.
+    pub fn encode(&self, name: &str, value: &RegisterValue) -> Result<Constant> {
+        let field = self.field(name)?;
+        let constant = match (field.codec, value) {
+            (RegisterCodec::Int, RegisterValue::Int(i)) => (*i).into(),
+            (RegisterCodec::Long, RegisterValue::Long(l)) => (*l).into(),
+            (RegisterCodec::String, RegisterValue::String(s)) => {
+                serialize_string_with(s, self.string_encoding)
+            }
+            (RegisterCodec::HexString, RegisterValue::HexString(s)) => {
+                serialize_hex_encoded_string(s)?
+            }
+            (RegisterCodec::ErgoTree, RegisterValue::ErgoTree(address)) => {
+                let ergo_tree = address_string_to_ergo_tree(address)?;
+                let bytes = ergo_tree
+                    .sigma_serialize_bytes()
+                    .map_err(|_| HeadlessDappError::InvalidP2SAddress(address.clone()))?;
+                serialize_hex_encoded_string(&base16::encode_lower(&bytes))?
+            }
+            _ => return Err(HeadlessDappError::InvalidRegisterValues()),
+        };
+        Ok(constant)
+    }
+
+    /// Encodes a list of named values into the ordered list of register
+    /// `Constant`s expected by `create_candidate()`. Every field of the
+    /// schema must be provided and the fields must form a contiguous
+    /// block of registers starting at R4.
+    pub fn encode_all(&self, values: &Vec<(&str, RegisterValue)>) -> Result<Vec<Constant>> {
+        let mut registers = vec![];
+        for i in 0..self.fields.len() {
+            let field = self
+                .fields
+                .iter()
+                .find(|f| f.index == i)
+                .ok_or(HeadlessDappError::InvalidRegisterValues())?;
+            let (_, value) = values
+                .iter()
+                .find(|(name, _)| *name == field.name)
+                .ok_or_else(|| HeadlessDappError::UnknownRegisterField(field.name.clone()))?;
+            registers.push(self.encode(&field.name, value)?);
+        }
+        Ok(registers)
+    }
+
+    /// Renders a human-readable description of the register layout,
+    /// intended for documentation.
+    pub fn describe(&self) -> String {
+        let mut fields = self.fields.clone();
+        fields.sort_by_key(|f| f.index);
+        fields
+            .iter()
+            .map(|f| format!("R{}: {} ({:?})", f.index + 4, f.name, f.codec))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/// A trait for `SpecifiedBox`es whose register layout is defined by a
+/// `RegisterSchema`, providing accessors for the registers by name.
+pub trait SchemaBox: SpecifiedBox {
+    /// Associated fn which returns the `RegisterSchema` of the box
+    fn register_schema() -> RegisterSchema;
+
+    /// Decodes the value of the named register field
+    fn register_value(&self, name: &str) -> Result<RegisterValue> {
+        Self::register_schema().decode(name, &self.registers())
+    }
+
+    /// Returns the base16 encoded `Constant` of the named register field
+    fn register_base16(&self, name: &str) -> Result<String> {
+        let field = Self::register_schema().field(name)?.clone();
+        let c = self.checked_register(field.index)?;
+        c.base16_str()
+            .map_err(|_| HeadlessDappError::FailedRegisterSpec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_schema() -> RegisterSchema {
+        RegisterSchema::new(vec![
+            RegisterField::new("datapoint", 0, RegisterCodec::Long),
+            RegisterField::new("epoch_id", 1, RegisterCodec::Int),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn register_schema_round_trip() {
+        let schema = test_schema();
+        let registers = schema
+            .encode_all(&vec![
+                ("epoch_id", RegisterValue::Int(7)),
+                ("datapoint", RegisterValue::Long(123456)),
+            ])
+            .unwrap();
+
+        assert_eq!(
+            schema.decode("datapoint", &registers).unwrap(),
+            RegisterValue::Long(123456)
+        );
+        assert_eq!(
+            schema.decode("epoch_id", &registers).unwrap(),
+            RegisterValue::Int(7)
+        );
+        assert_eq!(schema.register_specs().len(), 2);
+    }
+
+    #[test]
+    fn register_schema_rejects_duplicate_index() {
+        let schema = RegisterSchema::new(vec![
+            RegisterField::new("a", 0, RegisterCodec::Long),
+            RegisterField::new("b", 0, RegisterCodec::Int),
+        ]);
+        assert!(schema.is_err());
+    }
+}
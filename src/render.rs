@@ -0,0 +1,109 @@
+/// This file holds utilities for rendering register `Constant`s into a
+/// human-readable typed representation, mirroring the `sigmaType` and
+/// `renderedValue` fields which the Ergo Explorer provides for registers.
+use crate::encoding::unwrap_hex_encoded_string;
+use ergo_lib::ergotree_ir::base16_str::Base16Str;
+use ergo_lib::ergotree_ir::mir::constant::{Constant, TryExtractFrom};
+use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+use ergo_lib::ergotree_ir::types::stype::SType;
+use serde::Serialize;
+use std::fmt;
+use wasm_bindgen::prelude::*;
+
+/// A human-readable typed representation of a `Constant`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RenderedValue {
+    /// The name of the type of the `Constant`, ie. `SLong` or `Coll[SByte]`
+    #[serde(rename = "sigmaType")]
+    pub sigma_type: String,
+    /// The rendered value of the `Constant`
+    #[serde(rename = "renderedValue")]
+    pub rendered_value: String,
+}
+
+impl fmt::Display for RenderedValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.sigma_type, self.rendered_value)
+    }
+}
+
+/// Renders a `Constant` into a `RenderedValue`. Numbers and booleans are
+/// rendered as is, byte collections as hex, and other collections as
+/// lists. Values of types which have no simple representation are
+/// rendered as the base16 encoding of the serialized `Constant`.
+pub fn render_constant(c: &Constant) -> RenderedValue {
+    RenderedValue {
+        sigma_type: stype_name(&c.tpe),
+        rendered_value: render_value(c).unwrap_or_else(|| c.base16_str().unwrap_or_default()),
+    }
+}
+
+/// WASM-compatible wrapper for `render_constant()` which takes the base16
+/// encoded serialized `Constant` (as found in a box's registers) and
+/// returns the `RenderedValue` as a JSON `String`.
+#[wasm_bindgen]
+pub fn w_render_constant(constant_base16: &str) -> std::result::Result<String, JsValue> {
+    let bytes =
+        base16::decode(constant_base16).map_err(|e| JsValue::from_str(&format! {"{:?}", e}))?;
+    let c =
+        Constant::sigma_parse_bytes(&bytes).map_err(|e| JsValue::from_str(&format! {"{:?}", e}))?;
+    serde_json::to_string(&render_constant(&c)).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Acquires the name of an `SType` in the format used by the explorer
+pub fn stype_name(stype: &SType) -> String {
+    match stype {
+        SType::SColl(elem_type) => format!("Coll[{}]", stype_name(elem_type.as_ref())),
+        SType::SOption(elem_type) => format!("Option[{}]", stype_name(elem_type.as_ref())),
+        _ => format!("{:?}", stype),
+    }
+}
+
+/// Renders the value of the `Constant` if it has a simple representation
+fn render_value(c: &Constant) -> Option<String> {
+    let rendered = match &c.tpe {
+        SType::SBoolean => bool::try_extract_from(c.clone()).ok()?.to_string(),
+        SType::SByte => i8::try_extract_from(c.clone()).ok()?.to_string(),
+        SType::SShort => i16::try_extract_from(c.clone()).ok()?.to_string(),
+        SType::SInt => i32::try_extract_from(c.clone()).ok()?.to_string(),
+        SType::SLong => i64::try_extract_from(c.clone()).ok()?.to_string(),
+        SType::SColl(elem_type) => match elem_type.as_ref() {
+            SType::SByte => unwrap_hex_encoded_string(c).ok()?,
+            SType::SShort => render_list(Vec::<i16>::try_extract_from(c.clone()).ok()?),
+            SType::SInt => render_list(Vec::<i32>::try_extract_from(c.clone()).ok()?),
+            SType::SLong => render_list(Vec::<i64>::try_extract_from(c.clone()).ok()?),
+            SType::SBoolean => render_list(Vec::<bool>::try_extract_from(c.clone()).ok()?),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(rendered)
+}
+
+/// Renders a list of values as `[a,b,c]`
+fn render_list<T: ToString>(values: Vec<T>) -> String {
+    let items: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+    format!("[{}]", items.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_constant_test() {
+        let long: Constant = 1234i64.into();
+        assert_eq!(
+            render_constant(&long),
+            RenderedValue {
+                sigma_type: "SLong".to_string(),
+                rendered_value: "1234".to_string(),
+            }
+        );
+
+        let bytes: Constant = vec![10i8, 11i8].into();
+        let rendered = render_constant(&bytes);
+        assert_eq!(rendered.sigma_type, "Coll[SByte]");
+        assert_eq!(rendered.rendered_value, "0a0b");
+    }
+}
@@ -0,0 +1,171 @@
+/// This file holds the marker-register convention which protects one-shot
+/// Actions (ie. protocol bootstraps) from being executed twice by a bot
+/// which was restarted. An Action stores the `ActionMarker` of its
+/// execution (the blake2b256 hash of the Action name and a nonce) as a
+/// `Coll[Byte]` in a register of one of its outputs. Before the Action is
+/// built again, `ActionMarker::check_not_executed()` checks whether an
+/// output holding the marker already exists on-chain or in the mempool.
+/// Only unspent outputs can be found, and so the marked output should be
+/// one which remains unspent (ie. the bootstrapped protocol box).
+use crate::encoding::address_string_to_ergo_tree;
+use crate::error::{HeadlessDappError, Result};
+use crate::ErgoAddressString;
+use blake2b_simd::Params;
+use ergo_lib::ergotree_ir::base16_str::Base16Str;
+use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, ErgoBoxCandidate};
+use ergo_lib::ergotree_ir::mir::constant::Constant;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+
+/// The marker of a single execution of an Action
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActionMarker {
+    action_name: String,
+    nonce: u64,
+    /// The index of the register (0 is R4, 1 is R5, etc.) of the output
+    /// which holds the marker
+    register_index: usize,
+}
+
+impl ActionMarker {
+    /// Create a new `ActionMarker` for the execution of the Action with
+    /// the `action_name` identified by the `nonce`, stored in the register
+    /// at `register_index` (0 is R4, 1 is R5, etc.)
+    pub fn new(action_name: &str, nonce: u64, register_index: usize) -> ActionMarker {
+        ActionMarker {
+            action_name: action_name.to_string(),
+            nonce,
+            register_index,
+        }
+    }
+
+    /// The blake2b256 hash of the Action name followed by the big-endian
+    /// bytes of the nonce
+    pub fn marker_bytes(&self) -> Vec<u8> {
+        let mut preimage = self.action_name.as_bytes().to_vec();
+        preimage.extend(&self.nonce.to_be_bytes());
+        let mut params = Params::new();
+        params.hash_length(32);
+        params.hash(&preimage).as_bytes().to_vec()
+    }
+
+    /// The `Coll[Byte]` register constant which marks the output
+    pub fn register_constant(&self) -> Constant {
+        self.marker_bytes().into()
+    }
+
+    /// Whether the box holds the marker
+    pub fn is_marked(&self, ergo_box: &ErgoBox) -> bool {
+        ergo_box
+            .additional_registers
+            .get_ordered_values()
+            .get(self.register_index)
+            == Some(&self.register_constant())
+    }
+
+    /// Whether the output candidate holds the marker
+    pub fn is_marked_candidate(&self, candidate: &ErgoBoxCandidate) -> bool {
+        candidate
+            .additional_registers
+            .get_ordered_values()
+            .get(self.register_index)
+            == Some(&self.register_constant())
+    }
+
+    /// The explorer API path of the mempool transactions which hold
+    /// outputs locked under the `address` of the marked output
+    pub fn mempool_path(address: &ErgoAddressString) -> String {
+        format!("/v1/mempool/transactions/byAddress/{}", address)
+    }
+
+    /// Whether an output locked under the `address` in the response of the
+    /// endpoint of `mempool_path()` holds the marker
+    pub fn in_mempool_response(
+        &self,
+        address: &ErgoAddressString,
+        mempool_response_body: &str,
+    ) -> Result<bool> {
+        let response: serde_json::Value = serde_json::from_str(mempool_response_body)
+            .map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+        let ergo_tree_hex = base16::encode_lower(
+            &address_string_to_ergo_tree(address)?
+                .sigma_serialize_bytes()
+                .map_err(|e| HeadlessDappError::Other(e.to_string()))?,
+        );
+        let marker_hex = self
+            .register_constant()
+            .base16_str()
+            .map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+        let register_name = format!("R{}", self.register_index + 4);
+        let empty = vec![];
+        let txs = response["items"].as_array().unwrap_or(&empty);
+        for output in txs
+            .iter()
+            .flat_map(|tx| tx["outputs"].as_array().unwrap_or(&empty))
+        {
+            if output["ergoTree"].as_str() != Some(ergo_tree_hex.as_str()) {
+                continue;
+            }
+            // Registers are either the serialized value, or an object
+            // which holds the serialized value and its rendering
+            let register = &output["additionalRegisters"][register_name.as_str()];
+            let serialized = register
+                .as_str()
+                .or_else(|| register["serializedValue"].as_str());
+            if serialized == Some(marker_hex.as_str()) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Errors if any of the on-chain boxes (ie. fetched via the `BoxSpec`
+    /// of the marked output) holds the marker, or if the marker is found
+    /// in the `mempool_response_body` (see `in_mempool_response()`)
+    pub fn check_not_executed(
+        &self,
+        onchain_boxes: &[ErgoBox],
+        address: &ErgoAddressString,
+        mempool_response_body: Option<&str>,
+    ) -> Result<()> {
+        let mut executed = onchain_boxes.iter().any(|b| self.is_marked(b));
+        if let Some(body) = mempool_response_body {
+            executed = executed || self.in_mempool_response(address, body)?;
+        }
+        if executed {
+            return Err(HeadlessDappError::ActionAlreadyExecuted(
+                self.action_name.clone(),
+                self.nonce,
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_box;
+
+    #[test]
+    fn detects_executed_actions() {
+        let address = "9fSgJ7BmUxBQJ454prQDQ7fQMBkXPLaAmDnimgTtjym6FYPHjAV".to_string();
+        let marker = ActionMarker::new("bootstrap", 1, 0);
+        assert_ne!(
+            marker.marker_bytes(),
+            ActionMarker::new("bootstrap", 2, 0).marker_bytes()
+        );
+        let ergo_box = test_box(1000000, &address, vec![], vec![marker.register_constant()]);
+        assert!(marker.is_marked(&ergo_box));
+        assert!(marker.check_not_executed(&[], &address, None).is_ok());
+        assert!(marker
+            .check_not_executed(std::slice::from_ref(&ergo_box), &address, None)
+            .is_err());
+
+        let mempool_response = serde_json::json!({
+            "items": [{ "outputs": [serde_json::to_value(&ergo_box).unwrap()] }]
+        });
+        assert!(marker
+            .in_mempool_response(&address, &mempool_response.to_string())
+            .unwrap());
+    }
+}
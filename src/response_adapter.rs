@@ -0,0 +1,185 @@
+/// This file holds the `ResponseAdapter` trait which adapts the JSON
+/// returned by self-hosted explorers and indexers into the format of the
+/// Ergo Explorer API, so that `BoxSpec::process_explorer_response_with_adapter()`
+/// can be pointed at non-standard indexers without forking the parser.
+/// The default implementation of every method handles the standard format,
+/// and `FieldMappingAdapter` covers the common differences (renamed fields,
+/// nested `box` fields, and amounts encoded as Strings).
+use crate::error::{HeadlessDappError, Result};
+use crate::tx_creation::numberify_eip12_amounts;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Adapts the response JSON of an explorer or indexer into `ErgoBox`es
+pub trait ResponseAdapter {
+    /// Extracts the JSON of every box from the response. Defaults to the
+    /// `items` list of the Ergo Explorer API.
+    fn box_jsons(&self, response: &Value) -> Result<Vec<Value>> {
+        response["items"]
+            .as_array()
+            .cloned()
+            .ok_or_else(|| HeadlessDappError::Other("The response holds no `items`".to_string()))
+    }
+
+    /// Normalizes the JSON of a single box into the Ergo Explorer API
+    /// format. Defaults to no changes.
+    fn normalize_box(&self, box_json: Value) -> Value {
+        box_json
+    }
+
+    /// Parses the response body into `ErgoBox`es
+    fn parse_boxes(&self, response_body: &str) -> Result<Vec<ErgoBox>> {
+        let response: Value = serde_json::from_str(response_body)
+            .map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+        self.box_jsons(&response)?
+            .into_iter()
+            .map(|box_json| {
+                serde_json::from_value(self.normalize_box(box_json))
+                    .map_err(|e| HeadlessDappError::Other(e.to_string()))
+            })
+            .collect()
+    }
+}
+
+/// The adapter for the standard Ergo Explorer API format
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExplorerResponseAdapter;
+
+impl ResponseAdapter for ExplorerResponseAdapter {}
+
+/// A configurable adapter for indexers whose format differs from the Ergo
+/// Explorer API in field names and nesting
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldMappingAdapter {
+    items_path: Vec<String>,
+    box_field: Option<String>,
+    renamed_fields: HashMap<String, String>,
+    string_amounts: bool,
+}
+
+impl Default for FieldMappingAdapter {
+    fn default() -> Self {
+        FieldMappingAdapter {
+            items_path: vec!["items".to_string()],
+            box_field: None,
+            renamed_fields: HashMap::new(),
+            string_amounts: false,
+        }
+    }
+}
+
+impl FieldMappingAdapter {
+    /// Create a new `FieldMappingAdapter` which reads the standard format
+    pub fn new() -> FieldMappingAdapter {
+        FieldMappingAdapter::default()
+    }
+
+    /// Sets the path of the list of boxes within the response
+    /// (ie. `&["data", "boxes"]`)
+    pub fn with_items_path(mut self, items_path: &[&str]) -> Self {
+        self.items_path = items_path.iter().map(|p| p.to_string()).collect();
+        self
+    }
+
+    /// Reads every box from the `field` of its list entry
+    /// (ie. `{"box": {...}, "spent": false}`)
+    pub fn with_box_field(mut self, field: &str) -> Self {
+        self.box_field = Some(field.to_string());
+        self
+    }
+
+    /// Renames the field `from` (at any depth of a box) to `to`
+    /// (ie. `"box_id"` to `"boxId"`)
+    pub fn with_renamed_field(mut self, from: &str, to: &str) -> Self {
+        self.renamed_fields.insert(from.to_string(), to.to_string());
+        self
+    }
+
+    /// Parses nanoErg values and token amounts which are encoded as
+    /// Strings into numbers
+    pub fn with_string_amounts(mut self) -> Self {
+        self.string_amounts = true;
+        self
+    }
+
+    /// Renames the fields of every object within the value
+    fn rename_fields(&self, value: Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(k, v)| {
+                        let key = self.renamed_fields.get(&k).cloned().unwrap_or(k);
+                        (key, self.rename_fields(v))
+                    })
+                    .collect(),
+            ),
+            Value::Array(values) => {
+                Value::Array(values.into_iter().map(|v| self.rename_fields(v)).collect())
+            }
+            v => v,
+        }
+    }
+}
+
+impl ResponseAdapter for FieldMappingAdapter {
+    fn box_jsons(&self, response: &Value) -> Result<Vec<Value>> {
+        let items = self
+            .items_path
+            .iter()
+            .fold(response, |value, key| &value[key.as_str()]);
+        let items = items.as_array().ok_or_else(|| {
+            HeadlessDappError::Other(format!(
+                "The response holds no boxes at `{}`",
+                self.items_path.join(".")
+            ))
+        })?;
+        Ok(items
+            .iter()
+            .map(|item| match &self.box_field {
+                Some(field) => item[field.as_str()].clone(),
+                None => item.clone(),
+            })
+            .collect())
+    }
+
+    fn normalize_box(&self, box_json: Value) -> Value {
+        let mut box_json = self.rename_fields(box_json);
+        if self.string_amounts {
+            numberify_eip12_amounts(&mut box_json);
+        }
+        box_json
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_box;
+
+    #[test]
+    fn adapts_nested_indexer_responses() {
+        let ergo_box = test_box(
+            1000000,
+            "9fSgJ7BmUxBQJ454prQDQ7fQMBkXPLaAmDnimgTtjym6FYPHjAV",
+            vec![],
+            vec![],
+        );
+        let mut box_json = serde_json::to_value(&ergo_box).unwrap();
+        let box_id = box_json["boxId"].take();
+        box_json["box_id"] = box_id;
+        box_json["value"] = Value::from("1000000");
+        let response = serde_json::json!({ "result": { "boxes": [{ "box": box_json }] } });
+
+        let adapter = FieldMappingAdapter::new()
+            .with_items_path(&["result", "boxes"])
+            .with_box_field("box")
+            .with_renamed_field("box_id", "boxId")
+            .with_string_amounts();
+        let boxes = adapter.parse_boxes(&response.to_string()).unwrap();
+        assert_eq!(boxes, vec![ergo_box]);
+        assert!(ExplorerResponseAdapter
+            .parse_boxes(&response.to_string())
+            .is_err());
+    }
+}
@@ -0,0 +1,305 @@
+/// This file holds the simulation of the script execution of input boxes
+/// (behind the `simulation` feature). The ErgoTree of each selected input
+/// is reduced by the ergo-lib interpreter against a context constructed
+/// from the unsigned transaction (self box, outputs, data-inputs, and
+/// height), so that Actions can detect unsatisfiable spending conditions
+/// (ie. a wrong register value or output ordering) before submission.
+use crate::error::{HeadlessDappError, Result};
+use ergo_lib::chain::ergo_state_context::ErgoStateContext;
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::ergotree_interpreter::eval::context::Context;
+use ergo_lib::ergotree_interpreter::eval::env::Env;
+use ergo_lib::ergotree_interpreter::eval::Evaluator;
+use ergo_lib::ergotree_interpreter::sigma_protocol::prover::ContextExtension;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib::ergotree_ir::sigma_protocol::sigma_boolean::SigmaBoolean;
+use std::rc::Rc;
+
+/// The outcome of simulating the script of a single input box
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScriptOutcome {
+    /// The script reduced to `true`, and so the box can be spent without
+    /// any proof
+    Satisfied,
+    /// The script reduced to `false`, and so the box cannot be spent by
+    /// the transaction
+    Unsatisfiable,
+    /// The script reduced to a sigma proposition (ie. a P2PK check) which
+    /// must be proven by the signer
+    RequiresProof,
+    /// The interpreter failed to evaluate the script
+    EvalError(String),
+}
+
+impl ScriptOutcome {
+    /// Whether the input may be spendable by the transaction
+    pub fn is_spendable(&self) -> bool {
+        matches!(
+            self,
+            ScriptOutcome::Satisfied | ScriptOutcome::RequiresProof
+        )
+    }
+}
+
+/// The simulated outcome of an input box
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InputSimulation {
+    /// The index of the input in the transaction
+    pub input_index: usize,
+    /// The Box ID of the input box
+    pub box_id: String,
+    /// The outcome of simulating the script of the input box
+    pub outcome: ScriptOutcome,
+}
+
+/// Simulates the scripts of the inputs of the `unsigned_tx` at
+/// `input_indices`. `input_boxes` and `data_input_boxes` must hold the
+/// boxes of every input and data-input of the transaction, and the height
+/// of the context is the height of the pre-header of the `state_context`.
+pub fn simulate_inputs(
+    unsigned_tx: &UnsignedTransaction,
+    input_boxes: &[ErgoBox],
+    data_input_boxes: &[ErgoBox],
+    state_context: &ErgoStateContext,
+    input_indices: &[usize],
+) -> Result<Vec<InputSimulation>> {
+    let inputs = unsigned_tx.inputs.as_vec();
+    let ordered_inputs = inputs
+        .iter()
+        .map(|input| {
+            input_boxes
+                .iter()
+                .find(|b| b.box_id() == input.box_id)
+                .cloned()
+                .map(Rc::new)
+                .ok_or_else(|| missing_box("input", input.box_id.clone().into()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let ordered_data_inputs = match &unsigned_tx.data_inputs {
+        Some(data_inputs) => data_inputs
+            .as_vec()
+            .iter()
+            .map(|d| {
+                data_input_boxes
+                    .iter()
+                    .find(|b| b.box_id() == d.box_id)
+                    .cloned()
+                    .map(Rc::new)
+                    .ok_or_else(|| missing_box("data-input", d.box_id.clone().into()))
+            })
+            .collect::<Result<Vec<_>>>()?,
+        None => vec![],
+    };
+    let tx_id = unsigned_tx.id();
+    let outputs = unsigned_tx
+        .output_candidates
+        .as_vec()
+        .iter()
+        .enumerate()
+        .map(|(i, candidate)| {
+            ErgoBox::from_box_candidate(candidate, tx_id.clone(), i as u16)
+                .map(Rc::new)
+                .map_err(|e| HeadlessDappError::Other(e.to_string()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut simulations = vec![];
+    for &input_index in input_indices {
+        let self_box = ordered_inputs.get(input_index).cloned().ok_or_else(|| {
+            HeadlessDappError::Other(format!(
+                "The transaction has no input with the index {}",
+                input_index
+            ))
+        })?;
+        let ctx = Context {
+            height: state_context.pre_header.height,
+            self_box: self_box.clone(),
+            outputs: outputs.clone(),
+            data_inputs: ordered_data_inputs.clone(),
+            inputs: ordered_inputs.clone(),
+            pre_header: state_context.pre_header.clone(),
+            extension: ContextExtension::empty(),
+            headers: state_context.headers.clone(),
+        };
+        simulations.push(InputSimulation {
+            input_index,
+            box_id: self_box.box_id().into(),
+            outcome: simulate_script(&self_box, ctx),
+        });
+    }
+    Ok(simulations)
+}
+
+/// Simulates the scripts of all of the inputs of the `unsigned_tx`, and
+/// errors if any of them is unsatisfiable or fails to evaluate
+pub fn check_inputs_spendable(
+    unsigned_tx: &UnsignedTransaction,
+    input_boxes: &[ErgoBox],
+    data_input_boxes: &[ErgoBox],
+    state_context: &ErgoStateContext,
+) -> Result<()> {
+    let input_indices: Vec<usize> = (0..unsigned_tx.inputs.len()).collect();
+    let simulations = simulate_inputs(
+        unsigned_tx,
+        input_boxes,
+        data_input_boxes,
+        state_context,
+        &input_indices,
+    )?;
+    for simulation in simulations {
+        match simulation.outcome {
+            ScriptOutcome::Unsatisfiable => {
+                return Err(HeadlessDappError::UnspendableInput(
+                    simulation.box_id,
+                    "The script reduced to false".to_string(),
+                ))
+            }
+            ScriptOutcome::EvalError(e) => {
+                return Err(HeadlessDappError::UnspendableInput(simulation.box_id, e))
+            }
+            ScriptOutcome::Satisfied | ScriptOutcome::RequiresProof => (),
+        }
+    }
+    Ok(())
+}
+
+/// The interpreter used to reduce the scripts of the simulated inputs
+struct ScriptSimulator;
+
+impl Evaluator for ScriptSimulator {}
+
+/// Reduces the ErgoTree of the `self_box` within the `ctx`
+fn simulate_script(self_box: &ErgoBox, ctx: Context) -> ScriptOutcome {
+    let expr = match self_box.ergo_tree.proposition() {
+        Ok(expr) => expr,
+        Err(e) => return ScriptOutcome::EvalError(e.to_string()),
+    };
+    match ScriptSimulator.reduce_to_crypto(&expr, &Env::empty(), Rc::new(ctx)) {
+        Ok(reduction) => match reduction.sigma_prop {
+            SigmaBoolean::TrivialProp(true) => ScriptOutcome::Satisfied,
+            SigmaBoolean::TrivialProp(false) => ScriptOutcome::Unsatisfiable,
+            _ => ScriptOutcome::RequiresProof,
+        },
+        Err(e) => ScriptOutcome::EvalError(e.to_string()),
+    }
+}
+
+fn missing_box(kind: &str, box_id: String) -> HeadlessDappError {
+    HeadlessDappError::Other(format!(
+        "The box of the {} {} was not provided",
+        kind, box_id
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{test_box_at, test_state_context, TEST_ADDRESS};
+    use crate::tx_creation::{create_candidate, new_unsigned_tx};
+    use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
+    use ergo_lib::ergotree_ir::chain::ergo_box::NonMandatoryRegisters;
+    use ergo_lib::ergotree_ir::ergo_tree::ErgoTree;
+    use ergo_lib::ergotree_ir::mir::constant::Constant;
+    use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+    use std::convert::TryFrom;
+
+    /// `sigmaProp(true)`
+    const TRUE_TREE: &str = "00d10101";
+    /// `sigmaProp(false)`
+    const FALSE_TREE: &str = "00d10100";
+    /// `sigmaProp(SELF.R4[Long].get == 5L)`
+    const R4_IS_FIVE_TREE: &str = "00d193e4c6a70405050a";
+
+    fn script_box(tree_hex: &str, registers: Vec<Constant>, index: u16) -> ErgoBox {
+        let ergo_tree = ErgoTree::sigma_parse_bytes(&base16::decode(tree_hex).unwrap()).unwrap();
+        ErgoBox::new(
+            BoxValue::new(1000000).unwrap(),
+            ergo_tree,
+            vec![],
+            NonMandatoryRegisters::try_from(registers).unwrap(),
+            0,
+            ergo_lib::chain::transaction::TxId::zero(),
+            index,
+        )
+        .unwrap()
+    }
+
+    fn spending_tx(input_boxes: &[ErgoBox]) -> UnsignedTransaction {
+        let output =
+            create_candidate(1000000, &TEST_ADDRESS.to_string(), &vec![], &vec![], 100).unwrap();
+        let inputs = input_boxes.iter().map(|b| b.clone().into()).collect();
+        new_unsigned_tx(inputs, vec![], vec![output]).unwrap()
+    }
+
+    fn outcomes(input_boxes: &[ErgoBox]) -> Vec<ScriptOutcome> {
+        let indices: Vec<usize> = (0..input_boxes.len()).collect();
+        simulate_inputs(
+            &spending_tx(input_boxes),
+            input_boxes,
+            &[],
+            &test_state_context(100),
+            &indices,
+        )
+        .unwrap()
+        .into_iter()
+        .map(|s| s.outcome)
+        .collect()
+    }
+
+    #[test]
+    fn simulates_trivial_scripts() {
+        let boxes = vec![
+            script_box(TRUE_TREE, vec![], 0),
+            script_box(FALSE_TREE, vec![], 1),
+        ];
+        assert_eq!(
+            outcomes(&boxes),
+            vec![ScriptOutcome::Satisfied, ScriptOutcome::Unsatisfiable]
+        );
+        assert!(!ScriptOutcome::Unsatisfiable.is_spendable());
+    }
+
+    #[test]
+    fn p2pk_script_requires_proof() {
+        let p2pk_box = test_box_at(1000000, TEST_ADDRESS, vec![], vec![], 0, 0);
+        assert_eq!(outcomes(&[p2pk_box]), vec![ScriptOutcome::RequiresProof]);
+    }
+
+    #[test]
+    fn wrong_register_is_unspendable() {
+        let state_context = test_state_context(100);
+        let valid_box = script_box(R4_IS_FIVE_TREE, vec![5i64.into()], 0);
+        assert!(check_inputs_spendable(
+            &spending_tx(std::slice::from_ref(&valid_box)),
+            &[valid_box],
+            &[],
+            &state_context
+        )
+        .is_ok());
+
+        let invalid_box = script_box(R4_IS_FIVE_TREE, vec![6i64.into()], 0);
+        let result = check_inputs_spendable(
+            &spending_tx(std::slice::from_ref(&invalid_box)),
+            &[invalid_box],
+            &[],
+            &state_context,
+        );
+        assert!(matches!(
+            result,
+            Err(HeadlessDappError::UnspendableInput(_, _))
+        ));
+    }
+
+    #[test]
+    fn errors_on_missing_input_box() {
+        let input_box = script_box(TRUE_TREE, vec![], 0);
+        let result = simulate_inputs(
+            &spending_tx(&[input_box]),
+            &[],
+            &[],
+            &test_state_context(100),
+            &[0],
+        );
+        assert!(result.is_err());
+    }
+}
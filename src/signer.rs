@@ -0,0 +1,276 @@
+/// This file holds the `TxSigner` trait (behind the `async` feature) which
+/// abstracts over the key-management used to sign the transactions built
+/// by Actions. Signing backends are provided for the wallet of an Ergo
+/// node, a local wallet holding secret keys in memory, and a remote signer
+/// (ie. an HSM service) reached over HTTP. Organizations with their own
+/// key-management implement `TxSigner` themselves and pass it to
+/// `async_api::sign_and_submit_tx()`.
+use crate::async_api::{post_with_timeout, AsyncTransport};
+use crate::error::{HeadlessDappError, Result};
+use crate::explorer_backend::ExplorerBackend;
+use crate::tx_creation::{unsigned_tx_to_eip12_json, unsigned_tx_to_node_json};
+use async_trait::async_trait;
+use ergo_lib::chain::ergo_state_context::ErgoStateContext;
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+use ergo_lib::wallet::secret_key::SecretKey;
+use ergo_lib::wallet::signing::TransactionContext;
+use ergo_lib::wallet::Wallet;
+
+/// An unsigned transaction along with the boxes it spends and reads, as
+/// required by every signing backend
+#[derive(Clone, Debug)]
+pub struct SigningRequest {
+    /// The transaction to sign
+    pub unsigned_tx: UnsignedTransaction,
+    /// The boxes spent by the inputs of the transaction
+    pub input_boxes: Vec<ErgoBox>,
+    /// The boxes read by the data-inputs of the transaction
+    pub data_input_boxes: Vec<ErgoBox>,
+}
+
+impl SigningRequest {
+    /// Create a new `SigningRequest`
+    pub fn new(
+        unsigned_tx: UnsignedTransaction,
+        input_boxes: Vec<ErgoBox>,
+        data_input_boxes: Vec<ErgoBox>,
+    ) -> SigningRequest {
+        SigningRequest {
+            unsigned_tx,
+            input_boxes,
+            data_input_boxes,
+        }
+    }
+}
+
+/// A signing backend which signs `SigningRequest`s, returning the signed
+/// transaction in the JSON form used by the Ergo node (which is accepted
+/// by `async_api::submit_tx()`). The returned futures are not `Send` as
+/// the boxes of a `SigningRequest` are not `Sync`.
+#[async_trait(?Send)]
+pub trait TxSigner: Send + Sync {
+    /// Signs the transaction of the `request`
+    async fn sign(&self, request: &SigningRequest) -> Result<String>;
+}
+
+/// Signs using the wallet of an Ergo node via its
+/// `/wallet/transaction/sign` endpoint. The wallet must be unlocked.
+pub struct NodeWalletSigner<T: AsyncTransport> {
+    transport: T,
+    node: ExplorerBackend,
+}
+
+impl<T: AsyncTransport> NodeWalletSigner<T> {
+    /// Create a new `NodeWalletSigner` for the node at `node_url`, using
+    /// the `api_key` of the node
+    pub fn new(transport: T, node_url: &str, api_key: &str) -> NodeWalletSigner<T> {
+        NodeWalletSigner {
+            transport,
+            node: ExplorerBackend::new(node_url).with_api_key("api_key", api_key),
+        }
+    }
+
+    /// Create a new `NodeWalletSigner` with a pre-configured backend for
+    /// the node (ie. with a custom timeout)
+    pub fn with_backend(transport: T, node: ExplorerBackend) -> NodeWalletSigner<T> {
+        NodeWalletSigner { transport, node }
+    }
+}
+
+#[async_trait(?Send)]
+impl<T: AsyncTransport> TxSigner for NodeWalletSigner<T> {
+    async fn sign(&self, request: &SigningRequest) -> Result<String> {
+        let tx_json: serde_json::Value =
+            serde_json::from_str(&unsigned_tx_to_node_json(&request.unsigned_tx)?)
+                .map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+        let body = serde_json::json!({
+            "tx": tx_json,
+            "inputsRaw": raw_boxes(&request.input_boxes)?,
+            "dataInputsRaw": raw_boxes(&request.data_input_boxes)?,
+        });
+        let node_request = self.node.request("/wallet/transaction/sign");
+        post_with_timeout(&self.transport, &node_request, body.to_string()).await
+    }
+}
+
+/// Signs using a remote signer (ie. an HSM service) reached over HTTP.
+/// The unsigned transaction is POSTed as EIP-12 JSON, and the response
+/// body must be the signed transaction as node JSON.
+pub struct RemoteSigner<T: AsyncTransport> {
+    transport: T,
+    backend: ExplorerBackend,
+    path: String,
+}
+
+impl<T: AsyncTransport> RemoteSigner<T> {
+    /// Create a new `RemoteSigner` which POSTs to the `path` of the
+    /// `backend` (which holds the URL, auth headers, and timeout of the
+    /// signer service)
+    pub fn new(transport: T, backend: ExplorerBackend, path: &str) -> RemoteSigner<T> {
+        RemoteSigner {
+            transport,
+            backend,
+            path: path.to_string(),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<T: AsyncTransport> TxSigner for RemoteSigner<T> {
+    async fn sign(&self, request: &SigningRequest) -> Result<String> {
+        let body = unsigned_tx_to_eip12_json(&request.unsigned_tx, &request.input_boxes)?;
+        let signer_request = self.backend.request(&self.path);
+        post_with_timeout(&self.transport, &signer_request, body).await
+    }
+}
+
+/// Signs locally using secret keys held in memory. Intended for bots
+/// whose keys are managed by the process itself.
+pub struct LocalWalletSigner {
+    secrets: Vec<SecretKey>,
+    state_context: ErgoStateContext,
+}
+
+impl LocalWalletSigner {
+    /// Create a new `LocalWalletSigner` from the secret keys of the
+    /// wallet and the current state context of the blockchain (the last
+    /// block headers, which scripts may read)
+    pub fn new(secrets: Vec<SecretKey>, state_context: ErgoStateContext) -> LocalWalletSigner {
+        LocalWalletSigner {
+            secrets,
+            state_context,
+        }
+    }
+
+    /// Updates the state context, which should be done as new blocks
+    /// are mined
+    pub fn set_state_context(&mut self, state_context: ErgoStateContext) {
+        self.state_context = state_context;
+    }
+}
+
+#[async_trait(?Send)]
+impl TxSigner for LocalWalletSigner {
+    async fn sign(&self, request: &SigningRequest) -> Result<String> {
+        // The wallet is rebuilt per request as its prover is not `Sync`
+        let wallet = Wallet::from_secrets(self.secrets.clone());
+        let tx_context = TransactionContext {
+            spending_tx: request.unsigned_tx.clone(),
+            boxes_to_spend: request.input_boxes.clone(),
+            data_boxes: request.data_input_boxes.clone(),
+        };
+        let signed_tx = wallet
+            .sign_transaction(tx_context, &self.state_context)
+            .map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+        serde_json::to_string(&signed_tx).map_err(|e| HeadlessDappError::Other(e.to_string()))
+    }
+}
+
+/// Serializes the boxes into base16 encoded bytes, as used by the node
+fn raw_boxes(boxes: &[ErgoBox]) -> Result<Vec<String>> {
+    boxes
+        .iter()
+        .map(|b| {
+            b.sigma_serialize_bytes()
+                .map(|bytes| base16::encode_lower(&bytes))
+                .map_err(|e| HeadlessDappError::Other(e.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{test_box, test_state_context, MockTransport, TEST_ADDRESS};
+    use crate::tx_creation::{create_candidate, new_unsigned_tx};
+    use ergo_lib::chain::transaction::Transaction;
+    use ergo_lib::ergotree_interpreter::sigma_protocol::private_input::DlogProverInput;
+    use ergo_lib::ergotree_interpreter::sigma_protocol::prover::ProofBytes;
+    use ergo_lib::ergotree_ir::chain::address::{AddressEncoder, NetworkPrefix};
+
+    fn secret() -> SecretKey {
+        SecretKey::DlogSecretKey(DlogProverInput::from_bytes(&[5u8; 32]).unwrap())
+    }
+
+    /// A request spending a box locked under the P2PK address of `secret()`
+    fn signing_request() -> SigningRequest {
+        let address = AddressEncoder::new(NetworkPrefix::Mainnet)
+            .address_to_str(&secret().get_address_from_public_image());
+        let input_box = test_box(2000000, &address, vec![], vec![]);
+        let data_input_box = test_box(1000000, TEST_ADDRESS, vec![], vec![]);
+        let output =
+            create_candidate(2000000, &TEST_ADDRESS.to_string(), &vec![], &vec![], 100).unwrap();
+        let unsigned_tx = new_unsigned_tx(
+            vec![input_box.clone().into()],
+            vec![data_input_box.box_id().into()],
+            vec![output],
+        )
+        .unwrap();
+        SigningRequest::new(unsigned_tx, vec![input_box], vec![data_input_box])
+    }
+
+    #[tokio::test]
+    async fn node_wallet_signer_posts_tx_and_raw_boxes() {
+        let transport = MockTransport::new().with_response("/wallet/transaction/sign", "signed");
+        let signer = NodeWalletSigner::new(transport, "http://node.test:9053", "hello");
+        let request = signing_request();
+        assert_eq!(signer.sign(&request).await.unwrap(), "signed");
+
+        let requests = signer.transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].0,
+            "http://node.test:9053/wallet/transaction/sign"
+        );
+        let body: serde_json::Value =
+            serde_json::from_str(requests[0].1.as_ref().unwrap()).unwrap();
+        let tx_json: serde_json::Value =
+            serde_json::from_str(&unsigned_tx_to_node_json(&request.unsigned_tx).unwrap()).unwrap();
+        assert_eq!(body["tx"], tx_json);
+        assert_eq!(
+            body["inputsRaw"],
+            serde_json::json!(raw_boxes(&request.input_boxes).unwrap())
+        );
+        assert_eq!(
+            body["dataInputsRaw"],
+            serde_json::json!(raw_boxes(&request.data_input_boxes).unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn remote_signer_posts_eip12_tx() {
+        let transport = MockTransport::new().with_response("/sign", "signed");
+        let backend = ExplorerBackend::new("https://hsm.test");
+        let signer = RemoteSigner::new(transport, backend, "/sign");
+        let request = signing_request();
+        assert_eq!(signer.sign(&request).await.unwrap(), "signed");
+
+        let requests = signer.transport.requests();
+        assert_eq!(requests[0].0, "https://hsm.test/sign");
+        assert_eq!(
+            requests[0].1,
+            Some(unsigned_tx_to_eip12_json(&request.unsigned_tx, &request.input_boxes).unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn local_wallet_signer_signs_p2pk_inputs() {
+        let signer = LocalWalletSigner::new(vec![secret()], test_state_context(100));
+        let request = signing_request();
+        let signed_tx: Transaction =
+            serde_json::from_str(&signer.sign(&request).await.unwrap()).unwrap();
+        assert_eq!(signed_tx.id(), request.unsigned_tx.id());
+        assert!(matches!(
+            signed_tx.inputs.as_vec()[0].spending_proof.proof,
+            ProofBytes::Some(_)
+        ));
+
+        // A wallet without the secret key cannot sign
+        let other_secret =
+            SecretKey::DlogSecretKey(DlogProverInput::from_bytes(&[6u8; 32]).unwrap());
+        let other_signer = LocalWalletSigner::new(vec![other_secret], test_state_context(100));
+        assert!(other_signer.sign(&request).await.is_err());
+    }
+}
@@ -1,18 +1,24 @@
 use crate::box_spec::BoxSpec;
-use crate::box_traits::{ExplorerFindable, SpecifiedBox, WrappedBox};
-use crate::encoding::unwrap_long;
+use crate::box_traits::{ExplorerFindable, SpecSource, Specified, SpecifiedBox, WrappedBox};
 use crate::error::{HeadlessDappError, Result};
+use crate::input_hygiene::InputHygiene;
+use crate::known_ids::{known_id_on, ADA_USD_ORACLE_POOL_NFT_ID, ERG_USD_ORACLE_POOL_NFT_ID};
+use crate::network::Network;
+use crate::oracle_history::{datapoint_history_endpoint, unwrap_datapoint, DatapointHistory};
+use crate::tx_creation::ergo_box_from_eip12_json;
 /// This file holds a number of default general "Specified Boxes".
 /// These are wrapper structs for `ErgoBox`es which meet a given
 /// specification and provide you with a simple interface
 /// for implementing Actions of your protocols.
 use crate::WASMBox;
+use crate::{BlockHeight, NanoErg, TxId};
 use crate::{RegisterSpec, TokenSpec};
 use ergo_headless_dapp_framework_derive::{SpecBox, WrapBox};
 use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
 use ergo_lib::ergotree_ir::types::stype::SType;
 use ergo_lib_wasm::box_coll::ErgoBoxes;
 use ergo_lib_wasm::ergo_box::ErgoBox as WErgoBox;
+use std::collections::HashMap;
 use wasm_bindgen::prelude::*;
 
 /// A specified box which is intended to be spent for the Ergs inside.
@@ -52,13 +58,211 @@ impl ErgsBox {
     pub fn sum_ergs_boxes_value(boxes: &Vec<ErgsBox>) -> u64 {
         boxes
             .into_iter()
-            .fold(0, |acc, pb| pb.get_box().value.as_u64().clone() + acc)
+            .fold(0, |acc, pb| pb.nano_ergs() + acc)
+    }
+
+    /// Selects two different `ErgsBox`es where the first holds at least
+    /// `amount_a` nanoErgs and the second holds at least `amount_b`
+    /// nanoErgs. The smallest sufficient boxes are preferred. Useful
+    /// for Actions which require one box to pay for something (ie. a
+    /// bounty) and another box to pay for something else (ie. the tx fee).
+    pub fn select_two_distinct(
+        boxes: &Vec<ErgsBox>,
+        amount_a: NanoErg,
+        amount_b: NanoErg,
+    ) -> Result<(ErgsBox, ErgsBox)> {
+        // Indexes of the boxes sorted by ascending value
+        let mut sorted: Vec<usize> = (0..boxes.len()).collect();
+        sorted.sort_by_key(|i| boxes[*i].nano_ergs());
+
+        // Finds the smallest box covering `amount` which isn't `skip`
+        let smallest_covering = |amount: NanoErg, skip: Option<usize>| {
+            sorted
+                .iter()
+                .cloned()
+                .find(|i| Some(*i) != skip && boxes[*i].nano_ergs() >= amount)
+        };
+
+        // Attempt picking the box for `amount_a` first, then for
+        // `amount_b` first, as either ordering may be the only one
+        // which succeeds.
+        let a_first = smallest_covering(amount_a, None)
+            .and_then(|a| smallest_covering(amount_b, Some(a)).map(|b| (a, b)));
+        let b_first = || {
+            smallest_covering(amount_b, None)
+                .and_then(|b| smallest_covering(amount_a, Some(b)).map(|a| (a, b)))
+        };
+        match a_first.or_else(b_first) {
+            Some((a, b)) => Ok((boxes[a].clone(), boxes[b].clone())),
+            None => Err(HeadlessDappError::NoDistinctBoxPair(amount_a, amount_b)),
+        }
+    }
+
+    /// Same as `select_two_distinct()` but only selects from the boxes
+    /// which pass the `InputHygiene` (ie. which were not funded by a known
+    /// mixer). `funding_tx_response_bodies` must hold the explorer
+    /// response body of the funding transaction of every box, keyed by
+    /// tx id.
+    pub fn select_two_distinct_with_hygiene(
+        boxes: &Vec<ErgsBox>,
+        amount_a: NanoErg,
+        amount_b: NanoErg,
+        hygiene: &InputHygiene,
+        funding_tx_response_bodies: &HashMap<TxId, String>,
+    ) -> Result<(ErgsBox, ErgsBox)> {
+        let clean_boxes = hygiene.filter_boxes(boxes, funding_tx_response_bodies)?;
+        Self::select_two_distinct(&clean_boxes, amount_a, amount_b)
+    }
+
+    /// Selects `ErgsBox`es covering the `target` nanoErgs from a JSON list
+    /// of EIP-12 UTXOs (as returned by the `get_utxos()` of dApp wallet
+    /// connectors). Boxes which fail the `ErgsBox` spec or which have
+    /// fewer than `min_confirmations` are skipped. Boxes marked as
+    /// `"confirmed": false` have 0 confirmations, boxes with an
+    /// `inclusionHeight` have their confirmations counted up to the
+    /// `current_height`, and all other boxes are assumed to have 1.
+    /// The largest boxes are selected first to keep the number of inputs
+    /// low.
+    pub fn select_from_eip12_utxos(
+        utxos_json: &str,
+        target: NanoErg,
+        min_confirmations: u64,
+        current_height: BlockHeight,
+    ) -> Result<Vec<ErgsBox>> {
+        let utxos: Vec<serde_json::Value> = serde_json::from_str(utxos_json)
+            .map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+        let mut ergs_boxes = vec![];
+        for utxo in utxos {
+            let confirmations = if utxo["confirmed"] == serde_json::Value::Bool(false) {
+                0
+            } else if let Some(inclusion_height) = utxo["inclusionHeight"].as_u64() {
+                (current_height + 1).saturating_sub(inclusion_height)
+            } else {
+                1
+            };
+            if confirmations < min_confirmations {
+                continue;
+            }
+            if let Ok(ergs_box) = ErgsBox::new(&ergo_box_from_eip12_json(&utxo)?) {
+                ergs_boxes.push(ergs_box);
+            }
+        }
+        ergs_boxes.sort_by_key(|b| std::cmp::Reverse(b.nano_ergs()));
+
+        let mut selected = vec![];
+        let mut total: NanoErg = 0;
+        for ergs_box in ergs_boxes {
+            if total >= target {
+                break;
+            }
+            total += ergs_box.nano_ergs();
+            selected.push(ergs_box);
+        }
+        if total < target {
+            return Err(HeadlessDappError::InsufficientErgs(target, total));
+        }
+        Ok(selected)
+    }
+}
+
+/// WASM ErgsBox Methods
+#[wasm_bindgen]
+impl ErgsBox {
+    #[wasm_bindgen]
+    /// Selects boxes covering the `target` nanoErgs from a JSON list of
+    /// EIP-12 UTXOs (see `select_from_eip12_utxos()`)
+    pub fn w_select_from_eip12_utxos(
+        utxos_json: &str,
+        target: u64,
+        min_confirmations: u64,
+        current_height: u64,
+    ) -> std::result::Result<ErgoBoxes, JsValue> {
+        let selected =
+            Self::select_from_eip12_utxos(utxos_json, target, min_confirmations, current_height)
+                .map_err(|e| JsValue::from_str(&format! {"{:?}", e}))?;
+        let boxes: Vec<ErgoBox> = selected.iter().map(|b| b.get_box()).collect();
+        Ok(ErgoBoxes::from(boxes))
+    }
+}
+
+/// The spec of an Oracle Pool box which holds the pool NFT with the given
+/// token id and a `Long` datapoint in R4. Used as the `SpecSource` of
+/// `OraclePoolBox`es for pools whose NFT is not known ahead of time (ie.
+/// when an oracle pool has migrated to a new contract with a new NFT).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OraclePoolSpec {
+    nft_token_id: String,
+}
+
+impl OraclePoolSpec {
+    /// Create a new `OraclePoolSpec` for the pool NFT with the given id
+    pub fn new(nft_token_id: &str) -> OraclePoolSpec {
+        OraclePoolSpec {
+            nft_token_id: nft_token_id.to_string(),
+        }
+    }
+
+    /// Create a new `OraclePoolSpec` for the pool whose NFT is registered
+    /// under the `nft_name` in the known ids of the `network`
+    pub fn known(network: Network, nft_name: &str) -> Result<OraclePoolSpec> {
+        let nft_token_id = known_id_on(network, nft_name).ok_or_else(|| {
+            HeadlessDappError::Other(format!(
+                "No oracle pool NFT is known as {} on {:?}",
+                nft_name, network
+            ))
+        })?;
+        Ok(OraclePoolSpec::new(nft_token_id))
+    }
+
+    /// The token id of the pool NFT
+    pub fn nft_token_id(&self) -> &str {
+        &self.nft_token_id
+    }
+
+    /// Generates the explorer endpoint for the last `n` oracle pool boxes,
+    /// including those which have already been spent.
+    pub fn datapoint_history_endpoint(&self, explorer_api_url: &str, n: u64) -> String {
+        datapoint_history_endpoint(explorer_api_url, &self.nft_token_id, n)
+    }
+
+    /// Processes the explorer response body from the endpoint generated by
+    /// `datapoint_history_endpoint()` into a `DatapointHistory`.
+    pub fn process_datapoint_history_response(
+        &self,
+        explorer_response_body: &str,
+    ) -> Result<DatapointHistory> {
+        DatapointHistory::from_explorer_response(&self.box_spec(), explorer_response_body)
+    }
+}
+
+impl SpecSource for OraclePoolSpec {
+    fn box_spec(&self) -> BoxSpec {
+        let registers = vec![RegisterSpec::new(Some(SType::SLong), None)];
+        let tokens = vec![Some(TokenSpec::new(1..2, &self.nft_token_id))];
+        BoxSpec::new(None, None, registers, tokens)
+    }
+}
+
+/// An Oracle Pool box which was verified against an `OraclePoolSpec`, and
+/// so carries the NFT of its pool
+pub type OraclePoolBox = Specified<OraclePoolSpec>;
+
+impl Specified<OraclePoolSpec> {
+    /// Extracts the Long datapoint out of register R4, erroring if the box
+    /// does not hold it
+    pub fn checked_datapoint(&self) -> Result<u64> {
+        oracle_pool_box_datapoint(self)
     }
 }
 
+/// Extracts the Long datapoint out of register R4 of an Oracle Pool box
+fn oracle_pool_box_datapoint(oracle_box: &dyn WrappedBox) -> Result<u64> {
+    unwrap_datapoint(&oracle_box.checked_register(0)?)
+}
+
 /// A specified box which is an Oracle Pool box that stores a `Long` integer
 /// datapoint inside of R4 that represents how many nanoErgs can be bought
-/// for 1 USD.
+/// for 1 USD. Use an `OraclePoolBox` for pools with a custom NFT.
 #[wasm_bindgen]
 #[derive(Clone, Debug, WrapBox, SpecBox, WASMBox)]
 pub struct ErgUsdOraclePoolBox {
@@ -69,21 +273,46 @@ impl SpecifiedBox for ErgUsdOraclePoolBox {
     /// A box spec for an Oracle Pool Box with the correct NFT + a Long value
     /// in R4
     fn box_spec() -> BoxSpec {
-        let registers = vec![RegisterSpec::new(Some(SType::SLong), None)];
-        let tokens = vec![Some(TokenSpec::new(
-            1..2,
-            "008a94c8c76bbaa1f0a346697d1794eb31d94b37e5533af9cc0b6932bf159339",
-        ))];
-        BoxSpec::new(None, None, registers, tokens)
+        Self::spec_source().box_spec()
     }
 }
+/// Rust ErgUsdOraclePoolBox Methods
+impl ErgUsdOraclePoolBox {
+    /// The `OraclePoolSpec` of the ERG/USD oracle pool
+    pub fn spec_source() -> OraclePoolSpec {
+        OraclePoolSpec::new(ERG_USD_ORACLE_POOL_NFT_ID)
+    }
+
+    /// Extracts the Long datapoint out of register R4, erroring if the box
+    /// does not hold it
+    pub fn checked_datapoint(&self) -> Result<u64> {
+        oracle_pool_box_datapoint(self)
+    }
+
+    /// Generates the explorer endpoint for the last `n` oracle pool boxes,
+    /// including those which have already been spent.
+    pub fn datapoint_history_endpoint(explorer_api_url: &str, n: u64) -> String {
+        Self::spec_source().datapoint_history_endpoint(explorer_api_url, n)
+    }
+
+    /// Processes the explorer response body from the endpoint generated by
+    /// `datapoint_history_endpoint()` into a `DatapointHistory`.
+    pub fn process_datapoint_history_response(
+        explorer_response_body: &str,
+    ) -> Result<DatapointHistory> {
+        Self::spec_source().process_datapoint_history_response(explorer_response_body)
+    }
+}
+
 /// WASM-compatible ErgUsdOraclePoolBox Methods
 #[wasm_bindgen]
 impl ErgUsdOraclePoolBox {
     #[wasm_bindgen]
-    /// Extracts the Long datapoint out of register R4.
+    /// Extracts the Long datapoint out of register R4. A negative
+    /// datapoint, which the pool should never post, is reported as 0. Use
+    /// `checked_datapoint()` to have it rejected instead.
     pub fn datapoint(&self) -> u64 {
-        return unwrap_long(&self.registers()[0]).unwrap() as u64;
+        self.checked_datapoint().unwrap_or(0)
     }
 
     #[wasm_bindgen]
@@ -95,7 +324,7 @@ impl ErgUsdOraclePoolBox {
 
 /// A specified box which is an Oracle Pool box that stores a `Long` integer
 /// datapoint inside of R4 that represents how many lovelaces can be bought
-/// for 1 USD.
+/// for 1 USD. Use an `OraclePoolBox` for pools with a custom NFT.
 #[wasm_bindgen]
 #[derive(Clone, Debug, WrapBox, SpecBox, WASMBox)]
 pub struct AdaUsdOraclePoolBox {
@@ -106,21 +335,46 @@ impl SpecifiedBox for AdaUsdOraclePoolBox {
     /// A box spec for an Oracle Pool Box with the correct NFT + a Long value
     /// in R4
     fn box_spec() -> BoxSpec {
-        let registers = vec![RegisterSpec::new(Some(SType::SLong), None)];
-        let tokens = vec![Some(TokenSpec::new(
-            1..2,
-            "19475d9a78377ff0f36e9826cec439727bea522f6ffa3bda32e20d2f8b3103ac",
-        ))];
-        BoxSpec::new(None, None, registers, tokens)
+        Self::spec_source().box_spec()
+    }
+}
+/// Rust AdaUsdOraclePoolBox Methods
+impl AdaUsdOraclePoolBox {
+    /// The `OraclePoolSpec` of the ADA/USD oracle pool
+    pub fn spec_source() -> OraclePoolSpec {
+        OraclePoolSpec::new(ADA_USD_ORACLE_POOL_NFT_ID)
+    }
+
+    /// Extracts the Long datapoint out of register R4, erroring if the box
+    /// does not hold it
+    pub fn checked_datapoint(&self) -> Result<u64> {
+        oracle_pool_box_datapoint(self)
+    }
+
+    /// Generates the explorer endpoint for the last `n` oracle pool boxes,
+    /// including those which have already been spent.
+    pub fn datapoint_history_endpoint(explorer_api_url: &str, n: u64) -> String {
+        Self::spec_source().datapoint_history_endpoint(explorer_api_url, n)
+    }
+
+    /// Processes the explorer response body from the endpoint generated by
+    /// `datapoint_history_endpoint()` into a `DatapointHistory`.
+    pub fn process_datapoint_history_response(
+        explorer_response_body: &str,
+    ) -> Result<DatapointHistory> {
+        Self::spec_source().process_datapoint_history_response(explorer_response_body)
     }
 }
+
 /// WASM-compatible AdaUsdOraclePoolBox Methods
 #[wasm_bindgen]
 impl AdaUsdOraclePoolBox {
     #[wasm_bindgen]
-    /// Extracts the Long datapoint out of register R4.
+    /// Extracts the Long datapoint out of register R4. A negative
+    /// datapoint, which the pool should never post, is reported as 0. Use
+    /// `checked_datapoint()` to have it rejected instead.
     pub fn datapoint(&self) -> u64 {
-        return unwrap_long(&self.registers()[0]).unwrap() as u64;
+        self.checked_datapoint().unwrap_or(0)
     }
 
     #[wasm_bindgen]
@@ -129,3 +383,181 @@ impl AdaUsdOraclePoolBox {
         return (self.datapoint() / 100) as u64;
     }
 }
+
+/// Verifies that an `ErgoBox` is an Oracle Pool box holding the pool NFT
+/// with the given token id, and extracts the Long datapoint out of its R4.
+/// WASM counterpart of `OraclePoolBox`.
+#[wasm_bindgen]
+pub fn oracle_pool_datapoint_with_nft(
+    ergo_box: WErgoBox,
+    nft_token_id: &str,
+) -> std::result::Result<u64, JsValue> {
+    OraclePoolBox::new(&ergo_box.into(), OraclePoolSpec::new(nft_token_id))
+        .and_then(|b| b.checked_datapoint())
+        .map_err(|e| JsValue::from_str(&format! {"{:?}", e}))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::box_traits::DynSpecifiedBox;
+    use crate::test_utils::{test_box_at, TEST_ADDRESS};
+    use crate::tx_creation::stringify_eip12_amounts;
+
+    /// An EIP-12 UTXO holding `value` nanoErgs, with the `extra` fields
+    fn eip12_utxo(value: NanoErg, index: u16, extra: serde_json::Value) -> serde_json::Value {
+        let ergo_box = test_box_at(value, TEST_ADDRESS, vec![], vec![], 0, index);
+        let mut utxo = serde_json::to_value(&ergo_box).unwrap();
+        stringify_eip12_amounts(&mut utxo);
+        for (key, value) in extra.as_object().unwrap() {
+            utxo[key] = value.clone();
+        }
+        utxo
+    }
+
+    fn selected_values(boxes: &[ErgsBox]) -> Vec<NanoErg> {
+        boxes.iter().map(|b| b.nano_ergs()).collect()
+    }
+
+    fn ergs_boxes(values: &[NanoErg]) -> Vec<ErgsBox> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                ErgsBox::from_ergo_box(&test_box_at(*v, TEST_ADDRESS, vec![], vec![], 0, i as u16))
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn selects_two_distinct_smallest_sufficient_boxes() {
+        let boxes = ergs_boxes(&[9000000, 2000000, 5000000, 3000000]);
+        let (a, b) = ErgsBox::select_two_distinct(&boxes, 4000000, 1000000).unwrap();
+        assert_eq!((a.nano_ergs(), b.nano_ergs()), (5000000, 2000000));
+
+        // Both amounts are covered by the same smallest box, so the next
+        // smallest sufficient box is used for the second amount
+        let (a, b) = ErgsBox::select_two_distinct(&boxes, 2000000, 2000000).unwrap();
+        assert_eq!((a.nano_ergs(), b.nano_ergs()), (2000000, 3000000));
+        assert_ne!(a.box_id(), b.box_id());
+    }
+
+    #[test]
+    fn select_two_distinct_never_reuses_a_box() {
+        // A single box covers both amounts, but two distinct boxes are
+        // required
+        let boxes = ergs_boxes(&[9000000, 1000000]);
+        assert!(matches!(
+            ErgsBox::select_two_distinct(&boxes, 2000000, 3000000),
+            Err(HeadlessDappError::NoDistinctBoxPair(2000000, 3000000))
+        ));
+        assert!(ErgsBox::select_two_distinct(&vec![], 1, 1).is_err());
+    }
+
+    #[test]
+    fn selects_largest_eip12_utxos_first() {
+        let utxos = serde_json::json!([
+            eip12_utxo(2000000, 0, serde_json::json!({})),
+            eip12_utxo(9000000, 1, serde_json::json!({})),
+            eip12_utxo(5000000, 2, serde_json::json!({})),
+            // Fails the `ErgsBox` spec
+            eip12_utxo(100000, 3, serde_json::json!({})),
+        ])
+        .to_string();
+        let selected = ErgsBox::select_from_eip12_utxos(&utxos, 12000000, 0, 100).unwrap();
+        assert_eq!(selected_values(&selected), vec![9000000, 5000000]);
+    }
+
+    #[test]
+    fn skips_unconfirmed_eip12_utxos() {
+        let utxos = serde_json::json!([
+            eip12_utxo(9000000, 0, serde_json::json!({ "confirmed": false })),
+            eip12_utxo(5000000, 1, serde_json::json!({ "inclusionHeight": 100 })),
+            eip12_utxo(3000000, 2, serde_json::json!({ "inclusionHeight": 90 })),
+            eip12_utxo(2000000, 3, serde_json::json!({})),
+        ])
+        .to_string();
+        // Only the box included 11 blocks ago has 5 confirmations
+        let selected = ErgsBox::select_from_eip12_utxos(&utxos, 1000000, 5, 100).unwrap();
+        assert_eq!(selected_values(&selected), vec![3000000]);
+        // Boxes without an inclusion height are assumed to have 1
+        let selected = ErgsBox::select_from_eip12_utxos(&utxos, 10000000, 1, 100).unwrap();
+        assert_eq!(selected_values(&selected), vec![5000000, 3000000, 2000000]);
+    }
+
+    #[test]
+    fn errors_on_insufficient_eip12_utxos() {
+        let utxos = serde_json::json!([
+            eip12_utxo(9000000, 0, serde_json::json!({ "confirmed": false })),
+            eip12_utxo(2000000, 1, serde_json::json!({})),
+        ])
+        .to_string();
+        let result = ErgsBox::select_from_eip12_utxos(&utxos, 5000000, 1, 100);
+        assert!(matches!(
+            result,
+            Err(HeadlessDappError::InsufficientErgs(5000000, 2000000))
+        ));
+    }
+
+    fn oracle_box(nft_token_id: &str, datapoint: i64, index: u16) -> ErgoBox {
+        let nft = crate::encoding::build_token(nft_token_id, 1).unwrap();
+        test_box_at(
+            1000000,
+            TEST_ADDRESS,
+            vec![nft],
+            vec![datapoint.into()],
+            10,
+            index,
+        )
+    }
+
+    #[test]
+    fn oracle_pool_box_carries_custom_nft() {
+        let custom_nft = crate::test_utils::TEST_TOKEN_ID;
+        let ergo_box = oracle_box(custom_nft, 25000, 0);
+        assert!(ErgUsdOraclePoolBox::new(&ergo_box).is_err());
+
+        let oracle_pool_box =
+            OraclePoolBox::new(&ergo_box, OraclePoolSpec::new(custom_nft)).unwrap();
+        assert_eq!(oracle_pool_box.checked_datapoint().unwrap(), 25000);
+        assert_eq!(oracle_pool_box.source().nft_token_id(), custom_nft);
+        // The instance verifies against its own NFT rather than the default
+        assert!(oracle_pool_box.verify().is_ok());
+        assert!(OraclePoolBox::new(&ergo_box, ErgUsdOraclePoolBox::spec_source()).is_err());
+    }
+
+    #[test]
+    fn negative_datapoint_does_not_panic() {
+        let ergo_box = oracle_box(ERG_USD_ORACLE_POOL_NFT_ID, -5, 0);
+        let oracle_pool_box = ErgUsdOraclePoolBox::new(&ergo_box).unwrap();
+        assert!(matches!(
+            oracle_pool_box.checked_datapoint(),
+            Err(HeadlessDappError::NegativeDatapoint(-5))
+        ));
+        assert_eq!(oracle_pool_box.datapoint(), 0);
+        assert_eq!(oracle_pool_box.datapoint_in_cents(), 0);
+    }
+
+    #[test]
+    fn datapoint_history_endpoint_with_custom_nft() {
+        let custom_nft = crate::test_utils::TEST_TOKEN_ID;
+        let endpoint = OraclePoolSpec::new(custom_nft)
+            .datapoint_history_endpoint("https://api.ergoplatform.com/api", 5);
+        assert!(endpoint.contains(custom_nft));
+        assert_eq!(
+            ErgUsdOraclePoolBox::datapoint_history_endpoint("https://api.ergoplatform.com/api", 5),
+            ErgUsdOraclePoolBox::spec_source()
+                .datapoint_history_endpoint("https://api.ergoplatform.com/api", 5)
+        );
+
+        let body = serde_json::json!({
+            "items": [oracle_box(custom_nft, 200, 0), oracle_box(custom_nft, 100, 1)]
+        })
+        .to_string();
+        let history = OraclePoolSpec::new(custom_nft)
+            .process_datapoint_history_response(&body)
+            .unwrap();
+        assert_eq!(history, DatapointHistory::new(vec![(10, 200), (10, 100)]));
+    }
+}
@@ -0,0 +1,399 @@
+/// This file holds the `StateStore` trait which allows long running
+/// headless dApp services (ie. bots) to persist their state across process
+/// restarts: reserved inputs, submitted transaction ids, and the
+/// confirmation state of said transactions. `BotState` provides the typed
+/// layer over any `StateStore`. An in-memory store is always available,
+/// while sled and SQLite backed stores are available behind the `sled` and
+/// `sqlite` features.
+use crate::error::{HeadlessDappError, Result};
+use crate::TxId;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// A simple persistent key-value store
+pub trait StateStore {
+    /// Acquire the value stored under `key`
+    fn get(&self, key: &str) -> Result<Option<String>>;
+    /// Store `value` under `key`, replacing any previous value
+    fn put(&self, key: &str, value: &str) -> Result<()>;
+    /// Remove the value stored under `key`
+    fn remove(&self, key: &str) -> Result<()>;
+    /// Acquire all keys which start with `prefix`
+    fn keys_with_prefix(&self, prefix: &str) -> Result<Vec<String>>;
+}
+
+/// A `StateStore` which is held in memory and lost on restart
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    entries: RwLock<BTreeMap<String, String>>,
+}
+
+impl MemoryStore {
+    /// Create a new empty `MemoryStore`
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+}
+
+impl StateStore for MemoryStore {
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.entries.read().unwrap().get(key).cloned())
+    }
+
+    fn put(&self, key: &str, value: &str) -> Result<()> {
+        self.entries
+            .write()
+            .unwrap()
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<()> {
+        self.entries.write().unwrap().remove(key);
+        Ok(())
+    }
+
+    fn keys_with_prefix(&self, prefix: &str) -> Result<Vec<String>> {
+        Ok(self
+            .entries
+            .read()
+            .unwrap()
+            .keys()
+            .filter(|k| k.starts_with(prefix))
+            .cloned()
+            .collect())
+    }
+}
+
+/// A `StateStore` backed by a sled database
+#[cfg(feature = "sled")]
+pub struct SledStore {
+    db: sled::Db,
+}
+
+#[cfg(feature = "sled")]
+impl SledStore {
+    /// Opens (or creates) the sled database at `path`
+    pub fn open(path: &std::path::Path) -> Result<SledStore> {
+        let db = sled::open(path).map_err(|e| HeadlessDappError::StorageError(e.to_string()))?;
+        Ok(SledStore { db })
+    }
+}
+
+#[cfg(feature = "sled")]
+impl StateStore for SledStore {
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        let value = self
+            .db
+            .get(key)
+            .map_err(|e| HeadlessDappError::StorageError(e.to_string()))?;
+        Ok(value.map(|v| String::from_utf8_lossy(&v).to_string()))
+    }
+
+    fn put(&self, key: &str, value: &str) -> Result<()> {
+        self.db
+            .insert(key, value.as_bytes())
+            .map_err(|e| HeadlessDappError::StorageError(e.to_string()))?;
+        self.db
+            .flush()
+            .map_err(|e| HeadlessDappError::StorageError(e.to_string()))?;
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<()> {
+        self.db
+            .remove(key)
+            .map_err(|e| HeadlessDappError::StorageError(e.to_string()))?;
+        self.db
+            .flush()
+            .map_err(|e| HeadlessDappError::StorageError(e.to_string()))?;
+        Ok(())
+    }
+
+    fn keys_with_prefix(&self, prefix: &str) -> Result<Vec<String>> {
+        self.db
+            .scan_prefix(prefix)
+            .keys()
+            .map(|k| {
+                k.map(|k| String::from_utf8_lossy(&k).to_string())
+                    .map_err(|e| HeadlessDappError::StorageError(e.to_string()))
+            })
+            .collect()
+    }
+}
+
+/// A `StateStore` backed by a SQLite database
+#[cfg(feature = "sqlite")]
+pub struct SqliteStore {
+    conn: std::sync::Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStore {
+    /// Opens (or creates) the SQLite database at `path`
+    pub fn open(path: &std::path::Path) -> Result<SqliteStore> {
+        let conn = rusqlite::Connection::open(path)
+            .map_err(|e| HeadlessDappError::StorageError(e.to_string()))?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS state (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            [],
+        )
+        .map_err(|e| HeadlessDappError::StorageError(e.to_string()))?;
+        Ok(SqliteStore {
+            conn: std::sync::Mutex::new(conn),
+        })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl StateStore for SqliteStore {
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        use rusqlite::OptionalExtension;
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT value FROM state WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()
+            .map_err(|e| HeadlessDappError::StorageError(e.to_string()))
+    }
+
+    fn put(&self, key: &str, value: &str) -> Result<()> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO state (key, value) VALUES (?1, ?2)",
+                [key, value],
+            )
+            .map_err(|e| HeadlessDappError::StorageError(e.to_string()))?;
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<()> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM state WHERE key = ?1", [key])
+            .map_err(|e| HeadlessDappError::StorageError(e.to_string()))?;
+        Ok(())
+    }
+
+    fn keys_with_prefix(&self, prefix: &str) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT key FROM state WHERE substr(key, 1, length(?1)) = ?1")
+            .map_err(|e| HeadlessDappError::StorageError(e.to_string()))?;
+        let keys = stmt
+            .query_map([prefix], |row| row.get(0))
+            .map_err(|e| HeadlessDappError::StorageError(e.to_string()))?
+            .collect::<std::result::Result<Vec<String>, _>>()
+            .map_err(|e| HeadlessDappError::StorageError(e.to_string()))?;
+        Ok(keys)
+    }
+}
+
+/// The confirmation state of a submitted transaction
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TxStatus {
+    /// Submitted, but not yet included in a block
+    Pending,
+    /// Included in a block with the given number of confirmations
+    Confirmed(u64),
+    /// Dropped from the mempool or rejected
+    Failed,
+}
+
+/// A transaction which was submitted by the service
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubmittedTx {
+    /// The fingerprint of the unsigned transaction (see
+    /// `unsigned_tx_fingerprint()`)
+    pub fingerprint: String,
+    /// The Transaction ID of the submitted transaction
+    pub tx_id: TxId,
+    /// The confirmation state of the transaction
+    pub status: TxStatus,
+}
+
+const RESERVED_INPUT_PREFIX: &str = "reserved_input/";
+const SUBMITTED_TX_PREFIX: &str = "submitted_tx/";
+
+/// The typed state of a service persisted in a `StateStore`
+pub struct BotState<S: StateStore> {
+    store: S,
+}
+
+impl<S: StateStore> BotState<S> {
+    /// Create a new `BotState` over the provided `StateStore`
+    pub fn new(store: S) -> BotState<S> {
+        BotState { store }
+    }
+
+    /// Reserves an input box (by Box ID) so it is not used by another
+    /// Action while the transaction spending it is pending
+    pub fn reserve_input(&self, box_id: &str) -> Result<()> {
+        self.store
+            .put(&(RESERVED_INPUT_PREFIX.to_string() + box_id), "")
+    }
+
+    /// Releases a reserved input box
+    pub fn release_input(&self, box_id: &str) -> Result<()> {
+        self.store
+            .remove(&(RESERVED_INPUT_PREFIX.to_string() + box_id))
+    }
+
+    /// Whether the input box is reserved
+    pub fn is_reserved(&self, box_id: &str) -> Result<bool> {
+        Ok(self
+            .store
+            .get(&(RESERVED_INPUT_PREFIX.to_string() + box_id))?
+            .is_some())
+    }
+
+    /// All of the reserved input Box IDs
+    pub fn reserved_inputs(&self) -> Result<Vec<String>> {
+        Ok(self
+            .store
+            .keys_with_prefix(RESERVED_INPUT_PREFIX)?
+            .into_iter()
+            .map(|k| k[RESERVED_INPUT_PREFIX.len()..].to_string())
+            .collect())
+    }
+
+    /// Records (or updates) a submitted transaction, keyed by its
+    /// fingerprint
+    pub fn record_submitted(&self, submitted_tx: &SubmittedTx) -> Result<()> {
+        let value = serde_json::to_string(submitted_tx)
+            .map_err(|e| HeadlessDappError::StorageError(e.to_string()))?;
+        self.store.put(
+            &(SUBMITTED_TX_PREFIX.to_string() + &submitted_tx.fingerprint),
+            &value,
+        )
+    }
+
+    /// Acquire the submitted transaction with the given fingerprint
+    pub fn submitted(&self, fingerprint: &str) -> Result<Option<SubmittedTx>> {
+        match self
+            .store
+            .get(&(SUBMITTED_TX_PREFIX.to_string() + fingerprint))?
+        {
+            Some(value) => serde_json::from_str(&value)
+                .map(Some)
+                .map_err(|e| HeadlessDappError::StorageError(e.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// Updates the confirmation state of the submitted transaction with
+    /// the given fingerprint
+    pub fn set_status(&self, fingerprint: &str, status: TxStatus) -> Result<()> {
+        let mut submitted_tx = self.submitted(fingerprint)?.ok_or_else(|| {
+            HeadlessDappError::StorageError(format!("Unknown transaction {}", fingerprint))
+        })?;
+        submitted_tx.status = status;
+        self.record_submitted(&submitted_tx)
+    }
+
+    /// All of the submitted transactions which are still pending
+    pub fn pending(&self) -> Result<Vec<SubmittedTx>> {
+        let mut pending = vec![];
+        for key in self.store.keys_with_prefix(SUBMITTED_TX_PREFIX)? {
+            if let Some(submitted_tx) = self.submitted(&key[SUBMITTED_TX_PREFIX.len()..])? {
+                if submitted_tx.status == TxStatus::Pending {
+                    pending.push(submitted_tx);
+                }
+            }
+        }
+        Ok(pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_store<S: StateStore>(store: S) {
+        store.put("a/1", "one").unwrap();
+        store.put("a/2", "two").unwrap();
+        store.put("b/1", "three").unwrap();
+        store.put("a/1", "uno").unwrap();
+        assert_eq!(store.get("a/1").unwrap(), Some("uno".to_string()));
+        let mut keys = store.keys_with_prefix("a/").unwrap();
+        keys.sort();
+        assert_eq!(keys, vec!["a/1".to_string(), "a/2".to_string()]);
+        store.remove("a/2").unwrap();
+        assert_eq!(store.get("a/2").unwrap(), None);
+        assert_eq!(
+            store.keys_with_prefix("a/").unwrap(),
+            vec!["a/1".to_string()]
+        );
+        check_bot_state(BotState::new(store));
+    }
+
+    fn check_bot_state<S: StateStore>(state: BotState<S>) {
+        state.reserve_input("abc").unwrap();
+        assert!(state.is_reserved("abc").unwrap());
+        assert_eq!(state.reserved_inputs().unwrap(), vec!["abc".to_string()]);
+        state.release_input("abc").unwrap();
+        assert!(!state.is_reserved("abc").unwrap());
+
+        let submitted_tx = SubmittedTx {
+            fingerprint: "f1".to_string(),
+            tx_id: "t1".to_string(),
+            status: TxStatus::Pending,
+        };
+        state.record_submitted(&submitted_tx).unwrap();
+        assert_eq!(state.pending().unwrap(), vec![submitted_tx]);
+        state.set_status("f1", TxStatus::Confirmed(1)).unwrap();
+        assert!(state.pending().unwrap().is_empty());
+    }
+
+    #[test]
+    fn memory_store() {
+        check_store(MemoryStore::new());
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    fn sled_store() {
+        let path = std::env::temp_dir().join(format!("hdf_sled_store_{}", std::process::id()));
+        {
+            let store = SledStore::open(&path).unwrap();
+            check_store(store);
+        }
+        // The state persists across reopening the database. The background
+        // flusher of sled may briefly hold the lock after the store is
+        // dropped, so reopening is retried.
+        let store = (0..50)
+            .find_map(|_| {
+                SledStore::open(&path).ok().or_else(|| {
+                    std::thread::sleep(std::time::Duration::from_millis(20));
+                    None
+                })
+            })
+            .unwrap();
+        assert_eq!(store.get("a/1").unwrap(), Some("uno".to_string()));
+        assert_eq!(store.get("a/2").unwrap(), None);
+        drop(store);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_store() {
+        let path = std::env::temp_dir().join(format!("hdf_sqlite_store_{}.db", std::process::id()));
+        {
+            let store = SqliteStore::open(&path).unwrap();
+            check_store(store);
+        }
+        // The state persists across reopening the database
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.get("a/1").unwrap(), Some("uno".to_string()));
+        assert_eq!(store.get("a/2").unwrap(), None);
+        drop(store);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
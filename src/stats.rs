@@ -0,0 +1,169 @@
+/// This file holds the `stats` reporting layer which computes the total
+/// value locked (TVL), box counts and token distributions of every stage
+/// of a protocol from `ProtocolStateSnapshot`s. Stats can be recorded over
+/// time into a `StatsHistory`, which can be persisted as JSON.
+use crate::error::{HeadlessDappError, Result};
+use crate::protocol_state::ProtocolStateSnapshot;
+use crate::tx_creation::sum_tokens;
+use crate::{BlockHeight, NanoErg, TokenID};
+use ergo_lib::ergotree_ir::chain::digest32::Digest32;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The statistics of a single stage of a protocol
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StageStats {
+    /// The number of boxes in the stage
+    pub box_count: u64,
+    /// The total nanoErgs held by the boxes in the stage
+    pub total_nano_ergs: NanoErg,
+    /// The total amount of every token held by the boxes in the stage
+    pub tokens: BTreeMap<TokenID, u64>,
+}
+
+/// The statistics of every stage of a protocol at a given height
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolStats {
+    /// The block height at which the underlying snapshot was taken
+    pub height: BlockHeight,
+    /// The timestamp (milliseconds since the unix epoch) at which the
+    /// underlying snapshot was taken
+    pub fetched_at_ms: u64,
+    /// The statistics of every stage, keyed by stage name
+    pub stages: BTreeMap<String, StageStats>,
+}
+
+impl ProtocolStats {
+    /// Computes the statistics of every stage of a `ProtocolStateSnapshot`
+    pub fn from_snapshot(snapshot: &ProtocolStateSnapshot) -> Result<ProtocolStats> {
+        let mut stages = BTreeMap::new();
+        for name in snapshot.stage_names() {
+            let boxes = snapshot.boxes(&name)?;
+            let total_nano_ergs = boxes.iter().try_fold(0u64, |acc, b| {
+                acc.checked_add(*b.value.as_u64())
+                    .ok_or(HeadlessDappError::ArithmeticOverflow)
+            })?;
+            let mut tokens = BTreeMap::new();
+            for t in sum_tokens(boxes)? {
+                let token_id_digest32: Digest32 = t.token_id.into();
+                let token_id: String = token_id_digest32.into();
+                tokens.insert(token_id, u64::from(t.amount));
+            }
+            let stage_stats = StageStats {
+                box_count: boxes.len() as u64,
+                total_nano_ergs,
+                tokens,
+            };
+            stages.insert(name, stage_stats);
+        }
+        Ok(ProtocolStats {
+            height: snapshot.height(),
+            fetched_at_ms: snapshot.fetched_at_ms(),
+            stages,
+        })
+    }
+
+    /// The total value locked (in nanoErgs) across all stages
+    pub fn tvl(&self) -> Result<NanoErg> {
+        self.stages.values().try_fold(0u64, |acc, s| {
+            acc.checked_add(s.total_nano_ergs)
+                .ok_or(HeadlessDappError::ArithmeticOverflow)
+        })
+    }
+
+    /// The statistics of the stage with the given name
+    pub fn stage(&self, name: &str) -> Result<&StageStats> {
+        self.stages
+            .get(name)
+            .ok_or_else(|| HeadlessDappError::UnknownStage(name.to_string()))
+    }
+}
+
+/// A history of `ProtocolStats` recorded over time, ordered by height
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatsHistory {
+    entries: Vec<ProtocolStats>,
+}
+
+impl StatsHistory {
+    /// Create a new empty `StatsHistory`
+    pub fn new() -> StatsHistory {
+        StatsHistory::default()
+    }
+
+    /// Records the stats of a snapshot into the history
+    pub fn record(&mut self, stats: ProtocolStats) {
+        self.entries.push(stats);
+        self.entries.sort_by_key(|e| (e.height, e.fetched_at_ms));
+    }
+
+    /// Computes the stats of a `ProtocolStateSnapshot` and records them
+    pub fn record_snapshot(&mut self, snapshot: &ProtocolStateSnapshot) -> Result<()> {
+        self.record(ProtocolStats::from_snapshot(snapshot)?);
+        Ok(())
+    }
+
+    /// All of the recorded entries, ordered by height
+    pub fn entries(&self) -> &Vec<ProtocolStats> {
+        &self.entries
+    }
+
+    /// The most recently recorded entry
+    pub fn latest(&self) -> Option<&ProtocolStats> {
+        self.entries.last()
+    }
+
+    /// The TVL of every recorded entry as `(height, tvl)` pairs
+    pub fn tvl_series(&self) -> Result<Vec<(BlockHeight, NanoErg)>> {
+        self.entries
+            .iter()
+            .map(|e| Ok((e.height, e.tvl()?)))
+            .collect()
+    }
+
+    /// Serializes the history into JSON for persistence
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| HeadlessDappError::Other(e.to_string()))
+    }
+
+    /// Restores a history which was persisted using `to_json()`
+    pub fn from_json(json: &str) -> Result<StatsHistory> {
+        serde_json::from_str(json).map_err(|e| HeadlessDappError::Other(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_stats(height: BlockHeight, nano_ergs: NanoErg) -> ProtocolStats {
+        let mut stages = BTreeMap::new();
+        stages.insert(
+            "bounty".to_string(),
+            StageStats {
+                box_count: 2,
+                total_nano_ergs: nano_ergs,
+                tokens: BTreeMap::new(),
+            },
+        );
+        ProtocolStats {
+            height,
+            fetched_at_ms: height * 1000,
+            stages,
+        }
+    }
+
+    #[test]
+    fn history_round_trips_and_orders_by_height() {
+        let mut history = StatsHistory::new();
+        history.record(build_stats(20, 5000000));
+        history.record(build_stats(10, 2000000));
+        assert_eq!(
+            history.tvl_series().unwrap(),
+            vec![(10, 2000000), (20, 5000000)]
+        );
+        let restored = StatsHistory::from_json(&history.to_json().unwrap()).unwrap();
+        assert_eq!(restored, history);
+        assert_eq!(restored.latest().unwrap().height, 20);
+    }
+}
@@ -0,0 +1,240 @@
+/// This file holds support for scanning one-time (stealth) payment scripts
+/// which are derived from a secret shared between a sender and a receiver.
+/// Following the community stealth-address scheme, the one-time public key
+/// at a given index is `P * g^t` where `P` is the public key of the
+/// receiver and `t = blake2b256(shared_secret || index)` reduced into the
+/// group order. The receiver is the only party who can spend from these
+/// scripts, using the secret key `x + t`. The one-time scripts are never
+/// published on chain before being paid to, and so the receiver derives a
+/// window of candidate addresses and scans them.
+use crate::box_spec::BoxSpec;
+use crate::box_traits::WrappedBox;
+use crate::encoding::parse_address;
+use crate::error::{HeadlessDappError, Result};
+use crate::network::Network;
+use crate::{ErgoAddressString, NanoErg, P2PKAddressString};
+use blake2b_simd::Params;
+use ergo_lib::ergotree_interpreter::sigma_protocol::private_input::DlogProverInput;
+use ergo_lib::ergotree_ir::chain::address::{Address, AddressEncoder};
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib::ergotree_ir::sigma_protocol::dlog_group::EcPoint;
+use ergo_lib::ergotree_ir::sigma_protocol::sigma_boolean::ProveDlog;
+use ergo_lib::wallet::secret_key::SecretKey;
+use std::ops::Range;
+
+/// A box received at one of the derived one-time addresses
+#[derive(Clone, Debug)]
+pub struct StealthPayment {
+    /// The derivation index of the one-time address
+    pub index: u32,
+    /// The one-time address the box was received at
+    pub address: ErgoAddressString,
+    ergo_box: ErgoBox,
+}
+
+impl WrappedBox for StealthPayment {
+    fn get_box_ref(&self) -> &ErgoBox {
+        &self.ergo_box
+    }
+}
+
+/// Derives the one-time addresses of a receiver from a shared secret and
+/// aggregates the boxes received at them
+#[derive(Clone, Debug)]
+pub struct StealthReceiver {
+    public_key: EcPoint,
+    shared_secret: Vec<u8>,
+    network: Network,
+    min_nano_ergs: NanoErg,
+}
+
+impl StealthReceiver {
+    /// Create a new `StealthReceiver` from the P2PK address of the receiver
+    /// and the secret shared with the sender, deriving the one-time
+    /// addresses of the `network`
+    pub fn new(
+        receiver_address: &P2PKAddressString,
+        shared_secret: &[u8],
+        network: Network,
+    ) -> Result<StealthReceiver> {
+        let public_key = match parse_address(receiver_address)? {
+            Address::P2Pk(prove_dlog) => *prove_dlog.h,
+            _ => {
+                return Err(HeadlessDappError::InvalidP2PKAddress(
+                    receiver_address.clone(),
+                ))
+            }
+        };
+        Ok(StealthReceiver {
+            public_key,
+            shared_secret: shared_secret.to_vec(),
+            network,
+            min_nano_ergs: 0,
+        })
+    }
+
+    /// Only match boxes holding at least `min_nano_ergs`
+    pub fn with_min_nano_ergs(mut self, min_nano_ergs: NanoErg) -> Self {
+        self.min_nano_ergs = min_nano_ergs;
+        self
+    }
+
+    /// The scalar `t` which tweaks the public key of the receiver at the
+    /// `index`. The scalar is built via ergo-lib so that it is always of
+    /// the curve types used by its group operations.
+    fn tweak(&self, index: u32) -> Result<DlogProverInput> {
+        let mut params = Params::new();
+        params.hash_length(32);
+        let mut state = params.to_state();
+        state.update(&self.shared_secret);
+        state.update(&index.to_be_bytes());
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(state.finalize().as_bytes());
+        // Hashes at or above the group order (with negligible probability)
+        // are rejected rather than reduced
+        DlogProverInput::from_bytes(&bytes).ok_or_else(|| {
+            HeadlessDappError::KeyDerivationFailed(format!(
+                "The tweak of index {} is outside of the group order",
+                index
+            ))
+        })
+    }
+
+    /// Derives the one-time address at the `index`
+    pub fn derive_address(&self, index: u32) -> Result<ErgoAddressString> {
+        let tweak_point = *self.tweak(index)?.public_image().h;
+        let one_time_key = self.public_key.clone() * &tweak_point;
+        let address = Address::P2Pk(ProveDlog::new(one_time_key));
+        Ok(AddressEncoder::new(self.network.network_prefix()).address_to_str(&address))
+    }
+
+    /// Derives the secret key `x + t` which spends from the one-time
+    /// address at the `index`, given the secret key `x` of the receiver
+    pub fn derive_secret(
+        &self,
+        receiver_secret: &DlogProverInput,
+        index: u32,
+    ) -> Result<SecretKey> {
+        let w = receiver_secret.w + self.tweak(index)?.w;
+        Ok(SecretKey::DlogSecretKey(DlogProverInput { w }))
+    }
+
+    /// Derives the one-time addresses within the `indexes`
+    pub fn derive_addresses(&self, indexes: Range<u32>) -> Result<Vec<ErgoAddressString>> {
+        indexes.map(|i| self.derive_address(i)).collect()
+    }
+
+    /// Builds the `BoxSpec` of every one-time address within the `indexes`
+    /// as `(index, spec)` pairs
+    pub fn box_specs(&self, indexes: Range<u32>) -> Result<Vec<(u32, BoxSpec)>> {
+        indexes
+            .map(|i| Ok((i, self.box_spec(&self.derive_address(i)?))))
+            .collect()
+    }
+
+    /// The `BoxSpec` of the one-time `address`
+    fn box_spec(&self, address: &ErgoAddressString) -> BoxSpec {
+        BoxSpec::new(
+            Some(address.clone()),
+            Some(self.min_nano_ergs..u64::MAX),
+            vec![],
+            vec![],
+        )
+    }
+
+    /// Generates the explorer endpoint of every one-time address within
+    /// the `indexes` as `(index, url)` pairs. The response bodies are
+    /// provided to `process_explorer_responses()`.
+    pub fn explorer_endpoints(
+        &self,
+        explorer_api_url: &str,
+        indexes: Range<u32>,
+    ) -> Result<Vec<(u32, String)>> {
+        self.box_specs(indexes)?
+            .into_iter()
+            .map(|(i, spec)| Ok((i, spec.explorer_endpoint(explorer_api_url)?)))
+            .collect()
+    }
+
+    /// Processes the explorer response bodies of the endpoints generated
+    /// by `explorer_endpoints()` (provided as `(index, body)` pairs),
+    /// aggregating the boxes received at every one-time address.
+    pub fn process_explorer_responses(
+        &self,
+        explorer_response_bodies: &[(u32, String)],
+    ) -> Result<Vec<StealthPayment>> {
+        let mut payments = vec![];
+        for (index, body) in explorer_response_bodies {
+            let address = self.derive_address(*index)?;
+            for ergo_box in self.box_spec(&address).process_explorer_response(body)? {
+                payments.push(StealthPayment {
+                    index: *index,
+                    address: address.clone(),
+                    ergo_box,
+                });
+            }
+        }
+        Ok(payments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_box;
+
+    fn receiver_key() -> DlogProverInput {
+        DlogProverInput::from_bytes(&[7u8; 32]).unwrap()
+    }
+
+    fn receiver_address(network: Network) -> ErgoAddressString {
+        let address = Address::P2Pk(receiver_key().public_image());
+        AddressEncoder::new(network.network_prefix()).address_to_str(&address)
+    }
+
+    #[test]
+    fn derives_distinct_deterministic_addresses() {
+        let address = receiver_address(Network::Mainnet);
+        let receiver = StealthReceiver::new(&address, b"shared secret", Network::Mainnet).unwrap();
+        let addresses = receiver.derive_addresses(0..3).unwrap();
+        assert_eq!(addresses, receiver.derive_addresses(0..3).unwrap());
+        assert_ne!(addresses[0], addresses[1]);
+        assert_ne!(addresses[0], address);
+
+        let other = StealthReceiver::new(&address, b"other secret", Network::Mainnet).unwrap();
+        assert_ne!(other.derive_address(0).unwrap(), addresses[0]);
+
+        let testnet_address = receiver_address(Network::Testnet);
+        let testnet = StealthReceiver::new(&testnet_address, b"shared secret", Network::Testnet)
+            .unwrap()
+            .derive_address(0)
+            .unwrap();
+        assert_eq!(Network::from_address(&testnet), Some(Network::Testnet));
+    }
+
+    #[test]
+    fn receiver_recovers_payment() {
+        let address = receiver_address(Network::Mainnet);
+        // The sender derives the one-time address and pays to it
+        let sender = StealthReceiver::new(&address, b"shared secret", Network::Mainnet).unwrap();
+        let one_time_address = sender.derive_address(2).unwrap();
+        let payment = test_box(5000000, &one_time_address, vec![], vec![]);
+        let body = serde_json::json!({ "items": [payment] }).to_string();
+
+        // The receiver scans its window of one-time addresses
+        let receiver = StealthReceiver::new(&address, b"shared secret", Network::Mainnet)
+            .unwrap()
+            .with_min_nano_ergs(1000000);
+        let bodies: Vec<(u32, String)> = (0..4).map(|i| (i, body.clone())).collect();
+        let payments = receiver.process_explorer_responses(&bodies).unwrap();
+        assert_eq!(payments.len(), 1);
+        assert_eq!(payments[0].index, 2);
+        assert_eq!(payments[0].address, one_time_address);
+
+        // Only the receiver can derive the key which spends the payment
+        let secret = receiver.derive_secret(&receiver_key(), 2).unwrap();
+        let secret_address = AddressEncoder::new(Network::Mainnet.network_prefix())
+            .address_to_str(&secret.get_address_from_public_image());
+        assert_eq!(secret_address, one_time_address);
+    }
+}
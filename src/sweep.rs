@@ -0,0 +1,175 @@
+/// This file holds the `SweepTemplate`, an Action template which sweeps
+/// all of the spendable boxes of an address (Ergs and tokens) to a
+/// destination address, as used for treasury management by dApp
+/// operators. Addresses holding many boxes are swept across multiple
+/// transactions, each spending at most `max_inputs_per_tx` boxes so that
+/// the transactions stay within the size limits of the network. Tokens
+/// are split across multiple outputs when a single output would hold too
+/// many of them.
+use crate::box_spec::BoxSpec;
+use crate::error::{HeadlessDappError, Result};
+use crate::output_builders::{create_checked_candidate, create_min_value_candidate, TxFeeBox};
+use crate::token_ledger::TokenLedger;
+use crate::tx_creation::{check_unsigned_tx, new_unsigned_tx};
+use crate::{BlockHeight, ErgoAddressString, NanoErg};
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, ErgoBoxCandidate};
+use ergo_lib::ergotree_ir::chain::token::Token;
+
+/// The default maximum number of inputs of a single sweep transaction
+pub const DEFAULT_MAX_INPUTS_PER_TX: usize = 100;
+/// The default maximum number of distinct tokens held by a single output
+pub const DEFAULT_MAX_TOKENS_PER_OUTPUT: usize = 100;
+
+/// A template for sweeping all boxes of an address to a destination
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SweepTemplate {
+    destination: ErgoAddressString,
+    transaction_fee: NanoErg,
+    max_inputs_per_tx: usize,
+    max_tokens_per_output: usize,
+}
+
+impl SweepTemplate {
+    /// Create a new `SweepTemplate` which sweeps to the `destination`,
+    /// paying the `transaction_fee` for every transaction
+    pub fn new(destination: &ErgoAddressString, transaction_fee: NanoErg) -> SweepTemplate {
+        SweepTemplate {
+            destination: destination.clone(),
+            transaction_fee,
+            max_inputs_per_tx: DEFAULT_MAX_INPUTS_PER_TX,
+            max_tokens_per_output: DEFAULT_MAX_TOKENS_PER_OUTPUT,
+        }
+    }
+
+    /// Sets the maximum number of inputs of a single transaction
+    pub fn with_max_inputs_per_tx(mut self, max_inputs_per_tx: usize) -> Self {
+        self.max_inputs_per_tx = max_inputs_per_tx.max(1);
+        self
+    }
+
+    /// Sets the maximum number of distinct tokens held by a single output
+    pub fn with_max_tokens_per_output(mut self, max_tokens_per_output: usize) -> Self {
+        self.max_tokens_per_output = max_tokens_per_output.max(1);
+        self
+    }
+
+    /// A `BoxSpec` which finds every box of the `source_address`
+    pub fn source_spec(source_address: &ErgoAddressString) -> BoxSpec {
+        BoxSpec::new(Some(source_address.clone()), None, vec![], vec![])
+    }
+
+    /// Builds the transactions which sweep all of the `boxes`. The boxes
+    /// must be spendable by the signer (ie. the boxes of a P2PK address
+    /// found via `source_spec()`).
+    pub fn action_sweep(
+        &self,
+        boxes: &[ErgoBox],
+        current_height: BlockHeight,
+    ) -> Result<Vec<UnsignedTransaction>> {
+        boxes
+            .chunks(self.max_inputs_per_tx)
+            .map(|chunk| self.build_sweep_tx(chunk, current_height))
+            .collect()
+    }
+
+    /// Builds a single transaction which sweeps the `boxes`
+    fn build_sweep_tx(
+        &self,
+        boxes: &[ErgoBox],
+        current_height: BlockHeight,
+    ) -> Result<UnsignedTransaction> {
+        let total_value = boxes.iter().try_fold(0u64, |acc, b| {
+            acc.checked_add(*b.value.as_u64())
+                .ok_or(HeadlessDappError::ArithmeticOverflow)
+        })?;
+        let mut ledger = TokenLedger::new();
+        ledger.add_inputs(boxes);
+        let tokens = ledger.residual()?;
+        let token_chunks: Vec<&[Token]> = if tokens.is_empty() {
+            vec![&[]]
+        } else {
+            tokens.chunks(self.max_tokens_per_output).collect()
+        };
+
+        // Every output but the first holds the minimum value required
+        // for its tokens, while the first holds the remaining value
+        let mut extra_outputs: Vec<ErgoBoxCandidate> = vec![];
+        for chunk in &token_chunks[1..] {
+            extra_outputs.push(create_min_value_candidate(
+                0,
+                &self.destination,
+                &chunk.to_vec(),
+                &vec![],
+                current_height,
+            )?);
+        }
+        let extra_value: NanoErg = extra_outputs.iter().map(|o| *o.value.as_u64()).sum();
+        let main_value = total_value
+            .checked_sub(self.transaction_fee)
+            .and_then(|v| v.checked_sub(extra_value))
+            .ok_or(HeadlessDappError::InsufficientErgs(
+                self.transaction_fee + extra_value,
+                total_value,
+            ))?;
+        let main_output = create_checked_candidate(
+            main_value,
+            &self.destination,
+            &token_chunks[0].to_vec(),
+            &vec![],
+            current_height,
+        )?;
+
+        let mut outputs = vec![main_output];
+        outputs.extend(extra_outputs);
+        outputs.push(TxFeeBox::output_candidate(
+            self.transaction_fee,
+            current_height,
+        )?);
+        let unsigned_tx = new_unsigned_tx(
+            boxes.iter().map(|b| b.clone().into()).collect(),
+            vec![],
+            outputs,
+        )
+        .map_err(HeadlessDappError::InvalidTxComposition)?;
+        check_unsigned_tx(&unsigned_tx)?;
+        Ok(unsigned_tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::build_token;
+    use crate::test_utils::test_box_at;
+
+    const ADDRESS: &str = "9fSgJ7BmUxBQJ454prQDQ7fQMBkXPLaAmDnimgTtjym6FYPHjAV";
+    const TOKEN: &str = "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1";
+
+    #[test]
+    fn sweeps_boxes_in_chunks() {
+        let boxes: Vec<ErgoBox> = (0..5)
+            .map(|i| {
+                test_box_at(
+                    10000000,
+                    ADDRESS,
+                    vec![build_token(TOKEN, 10).unwrap()],
+                    vec![],
+                    0,
+                    i,
+                )
+            })
+            .collect();
+        let txs = SweepTemplate::new(&ADDRESS.to_string(), 1000000)
+            .with_max_inputs_per_tx(2)
+            .action_sweep(&boxes, 100)
+            .unwrap();
+        assert_eq!(txs.len(), 3);
+        let output = &txs[0].output_candidates.as_vec()[0];
+        assert_eq!(*output.value.as_u64(), 19000000);
+        assert_eq!(u64::from(output.tokens[0].amount), 20);
+        assert!(SweepTemplate::new(&ADDRESS.to_string(), 20000000)
+            .action_sweep(&boxes[..1], 100)
+            .is_err());
+    }
+}
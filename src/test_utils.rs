@@ -0,0 +1,210 @@
+/// This file holds the fixtures shared by the unit tests of the crate.
+use crate::encoding::address_string_to_ergo_tree;
+use crate::NanoErg;
+use ergo_lib::chain::transaction::TxId;
+use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
+use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, NonMandatoryRegisters};
+use ergo_lib::ergotree_ir::chain::token::Token;
+use ergo_lib::ergotree_ir::mir::constant::Constant;
+use std::convert::TryFrom;
+
+/// A P2PK address used across the tests
+pub(crate) const TEST_ADDRESS: &str = "9fSgJ7BmUxBQJ454prQDQ7fQMBkXPLaAmDnimgTtjym6FYPHjAV";
+/// A token id used across the tests
+pub(crate) const TEST_TOKEN_ID: &str =
+    "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1";
+
+/// Builds an `ErgoBox` locked under the `address`, created at height 0 as
+/// output 0 of the zero Transaction ID
+pub(crate) fn test_box(
+    value: NanoErg,
+    address: &str,
+    tokens: Vec<Token>,
+    registers: Vec<Constant>,
+) -> ErgoBox {
+    test_box_at(value, address, tokens, registers, 0, 0)
+}
+
+/// Same as `test_box()` but with the provided creation height and output
+/// index, so that boxes with equal contents have distinct Box IDs
+pub(crate) fn test_box_at(
+    value: NanoErg,
+    address: &str,
+    tokens: Vec<Token>,
+    registers: Vec<Constant>,
+    creation_height: u32,
+    index: u16,
+) -> ErgoBox {
+    ErgoBox::new(
+        BoxValue::new(value).unwrap(),
+        address_string_to_ergo_tree(&address.to_string()).unwrap(),
+        tokens,
+        NonMandatoryRegisters::try_from(registers).unwrap(),
+        creation_height,
+        TxId::zero(),
+        index,
+    )
+    .unwrap()
+}
+
+/// Builds an `ErgoStateContext` at the `height`, where every header is a
+/// placeholder which only the height of the pre-header is taken from
+#[cfg(any(feature = "simulation", feature = "async"))]
+pub(crate) fn test_state_context(
+    height: u32,
+) -> ergo_lib::chain::ergo_state_context::ErgoStateContext {
+    use ergo_lib::chain::ergo_state_context::ErgoStateContext;
+    use ergo_lib::ergotree_ir::chain::header::Header;
+    use ergo_lib::ergotree_ir::chain::preheader::PreHeader;
+
+    let zero_digest = "0".repeat(64);
+    let header: Header = serde_json::from_value(serde_json::json!({
+        "id": zero_digest,
+        "parentId": zero_digest,
+        "version": 2,
+        "height": height,
+        "timestamp": 1634000000000u64,
+        "nBits": 117767710,
+        "adProofsRoot": zero_digest,
+        "stateRoot": "0".repeat(66),
+        "transactionsRoot": zero_digest,
+        "extensionHash": zero_digest,
+        "votes": "000000",
+        "powSolutions": {
+            "pk": "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            "w": "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            "n": "0000000000000000",
+            "d": 0
+        }
+    }))
+    .unwrap();
+    let mut pre_header = PreHeader::from(header.clone());
+    pre_header.height = height;
+    let headers: Vec<Header> = (0..10).map(|_| header.clone()).collect();
+    ErgoStateContext {
+        pre_header,
+        headers: std::convert::TryInto::try_into(headers).unwrap(),
+    }
+}
+
+/// The response of a `MockTransport` to a request
+#[cfg(feature = "async")]
+enum MockResponse {
+    Body(String),
+    Failure(String),
+    Status(u16),
+}
+
+/// An `AsyncTransport` which records every request and answers with the
+/// body registered for the first matching url fragment
+#[cfg(feature = "async")]
+pub(crate) struct MockTransport {
+    responses: Vec<(String, MockResponse)>,
+    delay: Option<std::time::Duration>,
+    requests: std::sync::Mutex<Vec<(String, Option<String>)>>,
+    in_flight: std::sync::atomic::AtomicUsize,
+    max_in_flight: std::sync::atomic::AtomicUsize,
+}
+
+#[cfg(feature = "async")]
+impl MockTransport {
+    pub(crate) fn new() -> MockTransport {
+        MockTransport {
+            responses: vec![],
+            delay: None,
+            requests: std::sync::Mutex::new(vec![]),
+            in_flight: std::sync::atomic::AtomicUsize::new(0),
+            max_in_flight: std::sync::atomic::AtomicUsize::new(0),
+        }
+    }
+
+    /// Answers requests whose url contains `url_fragment` with the `body`
+    pub(crate) fn with_response(mut self, url_fragment: &str, body: &str) -> Self {
+        self.responses.push((
+            url_fragment.to_string(),
+            MockResponse::Body(body.to_string()),
+        ));
+        self
+    }
+
+    /// Fails requests whose url contains `url_fragment` with the `message`
+    pub(crate) fn with_failure(mut self, url_fragment: &str, message: &str) -> Self {
+        self.responses.push((
+            url_fragment.to_string(),
+            MockResponse::Failure(message.to_string()),
+        ));
+        self
+    }
+
+    /// Answers requests whose url contains `url_fragment` with the
+    /// non-success HTTP `status`
+    pub(crate) fn with_status(mut self, url_fragment: &str, status: u16) -> Self {
+        self.responses
+            .push((url_fragment.to_string(), MockResponse::Status(status)));
+        self
+    }
+
+    /// Holds every request in flight for the `delay`
+    pub(crate) fn with_delay(mut self, delay: std::time::Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// The `(url, body)` of every request performed, in order
+    pub(crate) fn requests(&self) -> Vec<(String, Option<String>)> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The maximum number of requests which were in flight at once
+    pub(crate) fn max_in_flight(&self) -> usize {
+        self.max_in_flight.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    async fn respond(&self, url: &str, body: Option<String>) -> crate::Result<String> {
+        use std::sync::atomic::Ordering;
+        self.requests.lock().unwrap().push((url.to_string(), body));
+        let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+        if let Some(delay) = self.delay {
+            tokio::time::sleep(delay).await;
+        }
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        match self
+            .responses
+            .iter()
+            .find(|(f, _)| url.contains(f.as_str()))
+        {
+            Some((_, MockResponse::Body(body))) => Ok(body.clone()),
+            Some((_, MockResponse::Failure(message))) => {
+                Err(crate::HeadlessDappError::Other(message.clone()))
+            }
+            Some((_, MockResponse::Status(status))) => Err(crate::HeadlessDappError::HttpStatus(
+                *status,
+                url.to_string(),
+            )),
+            None => Err(crate::HeadlessDappError::Other(format!(
+                "No mock response for {}",
+                url
+            ))),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl crate::async_api::AsyncTransport for MockTransport {
+    async fn get(
+        &self,
+        request: &crate::explorer_backend::ExplorerRequest,
+    ) -> crate::Result<String> {
+        self.respond(&request.url, None).await
+    }
+
+    async fn post(
+        &self,
+        request: &crate::explorer_backend::ExplorerRequest,
+        body: String,
+    ) -> crate::Result<String> {
+        self.respond(&request.url, Some(body)).await
+    }
+}
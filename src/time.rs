@@ -0,0 +1,124 @@
+/// This file holds utilities for reasoning about time in terms of block
+/// heights. Ergo targets a 2 minute block time, however in practice the
+/// average block time drifts from the target, and so all of the utilities
+/// can be parameterized with a drift via `BlockTimeParams`.
+use crate::{BlockDuration, BlockHeight};
+use std::time::Duration;
+
+/// The target block time of the Ergo blockchain in seconds
+pub const TARGET_BLOCK_TIME_SECS: u64 = 120;
+
+/// Parameters which define the expected block time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlockTimeParams {
+    /// The target time between blocks
+    pub target_block_time: Duration,
+    /// The fractional drift of the actual average block time from the
+    /// target. Ie. `0.05` means blocks are on average 5% slower than the
+    /// target, `-0.05` means 5% faster.
+    pub drift: f64,
+}
+
+impl Default for BlockTimeParams {
+    fn default() -> Self {
+        BlockTimeParams {
+            target_block_time: Duration::from_secs(TARGET_BLOCK_TIME_SECS),
+            drift: 0.0,
+        }
+    }
+}
+
+impl BlockTimeParams {
+    /// Create new `BlockTimeParams` with the 2 minute target block time
+    /// and the provided drift.
+    pub fn with_drift(drift: f64) -> BlockTimeParams {
+        BlockTimeParams {
+            drift: drift,
+            ..BlockTimeParams::default()
+        }
+    }
+
+    /// The expected average time between blocks after applying drift
+    pub fn block_time(&self) -> Duration {
+        self.target_block_time.mul_f64(1.0 + self.drift)
+    }
+
+    /// Estimates the duration of a number of blocks
+    pub fn blocks_to_duration(&self, blocks: BlockDuration) -> Duration {
+        self.block_time().mul_f64(blocks as f64)
+    }
+
+    /// Estimates the number of blocks in a duration, rounded up so the
+    /// full duration has always passed.
+    pub fn duration_to_blocks(&self, duration: Duration) -> BlockDuration {
+        let block_time_ms = self.block_time().as_millis().max(1);
+        let duration_ms = duration.as_millis();
+        duration_ms.div_ceil(block_time_ms) as BlockDuration
+    }
+
+    /// Estimates the block height at a given timestamp (milliseconds since
+    /// the unix epoch) using a known reference height and the timestamp
+    /// of said reference height (ie. the current height/time).
+    pub fn estimated_height_at(
+        &self,
+        timestamp_ms: u64,
+        reference_height: BlockHeight,
+        reference_timestamp_ms: u64,
+    ) -> BlockHeight {
+        let block_time_ms = self.block_time().as_millis().max(1) as u64;
+        if timestamp_ms >= reference_timestamp_ms {
+            let blocks = (timestamp_ms - reference_timestamp_ms) / block_time_ms;
+            reference_height.saturating_add(blocks)
+        } else {
+            let blocks = (reference_timestamp_ms - timestamp_ms) / block_time_ms;
+            reference_height.saturating_sub(blocks)
+        }
+    }
+}
+
+/// Estimates the duration of a number of blocks using the target block time
+pub fn blocks_to_duration(blocks: BlockDuration) -> Duration {
+    BlockTimeParams::default().blocks_to_duration(blocks)
+}
+
+/// Estimates the number of blocks in a duration using the target block time
+pub fn duration_to_blocks(duration: Duration) -> BlockDuration {
+    BlockTimeParams::default().duration_to_blocks(duration)
+}
+
+/// Estimates the block height at a given timestamp (milliseconds since the
+/// unix epoch) using the target block time and a known reference height.
+pub fn estimated_height_at(
+    timestamp_ms: u64,
+    reference_height: BlockHeight,
+    reference_timestamp_ms: u64,
+) -> BlockHeight {
+    BlockTimeParams::default().estimated_height_at(
+        timestamp_ms,
+        reference_height,
+        reference_timestamp_ms,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_duration_conversions() {
+        assert_eq!(blocks_to_duration(30), Duration::from_secs(3600));
+        assert_eq!(duration_to_blocks(Duration::from_secs(3600)), 30);
+        assert_eq!(duration_to_blocks(Duration::from_secs(121)), 2);
+        assert_eq!(
+            BlockTimeParams::with_drift(1.0).duration_to_blocks(Duration::from_secs(3600)),
+            15
+        );
+    }
+
+    #[test]
+    fn estimated_height_is_relative_to_reference() {
+        let now = 1_600_000_000_000;
+        assert_eq!(estimated_height_at(now + 3_600_000, 1000, now), 1030);
+        assert_eq!(estimated_height_at(now - 3_600_000, 1000, now), 970);
+    }
+}
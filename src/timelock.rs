@@ -0,0 +1,121 @@
+/// This file holds the `Timelock` metadata which can be attached to a
+/// `BoxSpec` to declare the height-based lock of the box's script. This
+/// allows checking that a box is spendable at the current height before
+/// including it as an input, rather than building a transaction which
+/// will fail script validation due to an immature lock.
+use crate::encoding::{unwrap_int, unwrap_long};
+use crate::error::{HeadlessDappError, Result};
+use crate::{BlockDuration, BlockHeight};
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use serde::{Deserialize, Serialize};
+
+/// A height-based lock on spending a box, as enforced by its script.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Timelock {
+    /// The box is spendable at or after the given height
+    AbsoluteHeight(BlockHeight),
+    /// The box is spendable once the given number of blocks have passed
+    /// since its creation height
+    RelativeToCreation(BlockDuration),
+    /// The box is spendable at or after the height held in the register
+    /// at the given index (0 is R4, 1 is R5, etc.) as an Int or Long
+    HeightInRegister(usize),
+}
+
+impl Timelock {
+    /// Acquires the height at which the box becomes spendable
+    pub fn unlock_height(&self, ergo_box: &ErgoBox) -> Result<BlockHeight> {
+        match self {
+            Timelock::AbsoluteHeight(height) => Ok(*height),
+            Timelock::RelativeToCreation(duration) => (ergo_box.creation_height as BlockHeight)
+                .checked_add(*duration)
+                .ok_or(HeadlessDappError::ArithmeticOverflow),
+            Timelock::HeightInRegister(index) => {
+                let registers = ergo_box.additional_registers.get_ordered_values();
+                let c = registers
+                    .get(*index)
+                    .ok_or(HeadlessDappError::FailedRegisterSpec)?;
+                let height = match unwrap_int(c) {
+                    Ok(i) => i as i64,
+                    Err(_) => unwrap_long(c)?,
+                };
+                Ok(height.max(0) as BlockHeight)
+            }
+        }
+    }
+
+    /// Checks that the box is spendable at the `current_height`
+    pub fn check(&self, ergo_box: &ErgoBox, current_height: BlockHeight) -> Result<()> {
+        let unlock_height = self.unlock_height(ergo_box)?;
+        if current_height < unlock_height {
+            return Err(HeadlessDappError::TimelockNotSatisfied(unlock_height));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{test_box_at, TEST_ADDRESS};
+    use ergo_lib::ergotree_ir::mir::constant::Constant;
+
+    fn timelocked_box(creation_height: u32, registers: Vec<Constant>) -> ErgoBox {
+        test_box_at(1000000, TEST_ADDRESS, vec![], registers, creation_height, 0)
+    }
+
+    #[test]
+    fn absolute_height() {
+        let ergo_box = timelocked_box(10, vec![]);
+        let timelock = Timelock::AbsoluteHeight(100);
+        assert_eq!(timelock.unlock_height(&ergo_box).unwrap(), 100);
+        assert!(matches!(
+            timelock.check(&ergo_box, 99),
+            Err(HeadlessDappError::TimelockNotSatisfied(100))
+        ));
+        assert!(timelock.check(&ergo_box, 100).is_ok());
+    }
+
+    #[test]
+    fn relative_to_creation() {
+        let ergo_box = timelocked_box(10, vec![]);
+        let timelock = Timelock::RelativeToCreation(30);
+        assert_eq!(timelock.unlock_height(&ergo_box).unwrap(), 40);
+        assert!(timelock.check(&ergo_box, 39).is_err());
+        assert!(timelock.check(&ergo_box, 40).is_ok());
+        assert!(matches!(
+            Timelock::RelativeToCreation(u64::MAX).unlock_height(&ergo_box),
+            Err(HeadlessDappError::ArithmeticOverflow)
+        ));
+    }
+
+    #[test]
+    fn height_in_register() {
+        let int_box = timelocked_box(10, vec![Constant::from(1i32), Constant::from(50i32)]);
+        let long_box = timelocked_box(10, vec![Constant::from(60i64)]);
+        assert_eq!(
+            Timelock::HeightInRegister(1)
+                .unlock_height(&int_box)
+                .unwrap(),
+            50
+        );
+        assert_eq!(
+            Timelock::HeightInRegister(0)
+                .unlock_height(&long_box)
+                .unwrap(),
+            60
+        );
+        assert!(Timelock::HeightInRegister(0).check(&long_box, 59).is_err());
+        assert!(Timelock::HeightInRegister(0).check(&long_box, 60).is_ok());
+    }
+
+    #[test]
+    fn height_in_invalid_register() {
+        // The register is missing
+        let ergo_box = timelocked_box(10, vec![Constant::from(50i64)]);
+        assert!(Timelock::HeightInRegister(1).check(&ergo_box, 100).is_err());
+        // The register is neither an Int nor a Long
+        let ergo_box = timelocked_box(10, vec![Constant::from(vec![1u8, 2u8])]);
+        assert!(Timelock::HeightInRegister(0).check(&ergo_box, 100).is_err());
+    }
+}
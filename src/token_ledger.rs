@@ -0,0 +1,207 @@
+/// This file holds the `TokenLedger` which tracks the token inflows and
+/// outflows of an Action (inputs, outputs, mints, and burns) as an
+/// auditable list of entries. The residual tokens which are left over
+/// once every entry is accounted for are the tokens of the change box.
+use crate::error::{HeadlessDappError, Result};
+use ergo_lib::ergotree_ir::chain::digest32::Digest32;
+use ergo_lib::ergotree_ir::chain::ergo_box::{BoxId, ErgoBox, ErgoBoxCandidate};
+use ergo_lib::ergotree_ir::chain::token::{Token, TokenAmount, TokenId};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// The kind of a `LedgerEntry`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LedgerEntryKind {
+    /// Tokens held in an input box (inflow)
+    Input,
+    /// Tokens held in an output (outflow)
+    Output,
+    /// Tokens minted by the transaction (inflow)
+    Mint,
+    /// Tokens burned by the transaction (outflow)
+    Burn,
+}
+
+impl LedgerEntryKind {
+    /// Whether the entry adds tokens to the ledger
+    pub fn is_inflow(&self) -> bool {
+        match self {
+            LedgerEntryKind::Input | LedgerEntryKind::Mint => true,
+            LedgerEntryKind::Output | LedgerEntryKind::Burn => false,
+        }
+    }
+}
+
+/// A single token movement recorded in a `TokenLedger`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LedgerEntry {
+    pub kind: LedgerEntryKind,
+    pub token_id: TokenId,
+    pub amount: u64,
+}
+
+/// Tracks the token inflows and outflows across an Action
+#[derive(Clone, Debug, Default)]
+pub struct TokenLedger {
+    entries: Vec<LedgerEntry>,
+    excluded: Vec<TokenId>,
+    mintable_id: Option<TokenId>,
+}
+
+impl TokenLedger {
+    /// Create a new empty `TokenLedger`
+    pub fn new() -> TokenLedger {
+        TokenLedger::default()
+    }
+
+    /// Allows the outputs to hold tokens with the id of the `first_input_id`
+    /// which are not in the inputs. Per the Ergo minting rules, any such
+    /// surplus is recorded as a mint when computing the residual.
+    pub fn with_mintable_id(mut self, first_input_id: &BoxId) -> Self {
+        self.mintable_id = Some(TokenId::from(first_input_id.clone()));
+        self
+    }
+
+    /// Excludes the tokens with the ids of the `tokens` from the residual.
+    /// Their entries are still recorded.
+    pub fn exclude_tokens(&mut self, tokens: &[Token]) {
+        self.excluded
+            .extend(tokens.iter().map(|t| t.token_id.clone()));
+    }
+
+    /// Records the tokens held in the input boxes as inflows
+    pub fn add_inputs(&mut self, input_boxes: &[ErgoBox]) {
+        for b in input_boxes {
+            self.add_input_tokens(&b.tokens);
+        }
+    }
+
+    /// Records the tokens as inflows from inputs
+    pub fn add_input_tokens(&mut self, tokens: &[Token]) {
+        self.record(LedgerEntryKind::Input, tokens);
+    }
+
+    /// Records the tokens held in the outputs as outflows
+    pub fn subtract_outputs(&mut self, outputs: &[ErgoBoxCandidate]) {
+        for output in outputs {
+            self.subtract_output_tokens(&output.tokens);
+        }
+    }
+
+    /// Records the tokens as outflows into outputs
+    pub fn subtract_output_tokens(&mut self, tokens: &[Token]) {
+        self.record(LedgerEntryKind::Output, tokens);
+    }
+
+    /// Declares that the transaction mints the `token`
+    pub fn declare_mint(&mut self, token: &Token) {
+        self.record(LedgerEntryKind::Mint, std::slice::from_ref(token));
+    }
+
+    /// Declares that the transaction burns the `token`
+    pub fn declare_burn(&mut self, token: &Token) {
+        self.record(LedgerEntryKind::Burn, std::slice::from_ref(token));
+    }
+
+    /// All of the recorded entries in the order they were recorded
+    pub fn entries(&self) -> &Vec<LedgerEntry> {
+        &self.entries
+    }
+
+    /// Computes the residual tokens (inflows minus outflows) which are
+    /// left over for the change box, in the order the tokens were first
+    /// recorded. Errors if the outflows of a token exceed its inflows,
+    /// unless the surplus is a mint allowed by `with_mintable_id()`.
+    pub fn residual(&self) -> Result<Vec<Token>> {
+        // Token IDs in the order they were first seen
+        let mut token_order: Vec<TokenId> = vec![];
+        // Accumulated (inflow, outflow) for every Token ID
+        let mut flows: HashMap<TokenId, (u64, u64)> = HashMap::new();
+        for entry in &self.entries {
+            let flow = flows.entry(entry.token_id.clone()).or_insert_with(|| {
+                token_order.push(entry.token_id.clone());
+                (0, 0)
+            });
+            let total = if entry.kind.is_inflow() {
+                &mut flow.0
+            } else {
+                &mut flow.1
+            };
+            *total = total
+                .checked_add(entry.amount)
+                .ok_or(HeadlessDappError::TokenAmountOverflow)?;
+        }
+
+        let mut residual = vec![];
+        for token_id in token_order {
+            if self.excluded.contains(&token_id) {
+                continue;
+            }
+            let (inflow, outflow) = flows[&token_id];
+            if outflow > inflow {
+                if self.mintable_id.as_ref() == Some(&token_id) {
+                    continue;
+                }
+                let token_id_digest32: Digest32 = token_id.into();
+                return Err(HeadlessDappError::TokenDeficit(
+                    token_id_digest32.into(),
+                    inflow,
+                    outflow,
+                ));
+            }
+            let amount = inflow - outflow;
+            if amount > 0 {
+                residual.push(Token {
+                    token_id,
+                    amount: TokenAmount::try_from(amount)
+                        .map_err(|_| HeadlessDappError::TokenAmountOverflow)?,
+                });
+            }
+        }
+        Ok(residual)
+    }
+
+    /// Records an entry for each of the tokens
+    fn record(&mut self, kind: LedgerEntryKind, tokens: &[Token]) {
+        for t in tokens {
+            self.entries.push(LedgerEntry {
+                kind,
+                token_id: t.token_id.clone(),
+                amount: u64::from(t.amount),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::build_token;
+
+    const TOKEN_A: &str = "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1";
+    const TOKEN_B: &str = "1fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1";
+
+    #[test]
+    fn computes_residual_tokens() {
+        let mut ledger = TokenLedger::new();
+        ledger.add_input_tokens(&[
+            build_token(TOKEN_A, 10).unwrap(),
+            build_token(TOKEN_B, 5).unwrap(),
+        ]);
+        ledger.subtract_output_tokens(&[build_token(TOKEN_A, 4).unwrap()]);
+        ledger.declare_burn(&build_token(TOKEN_B, 5).unwrap());
+        assert_eq!(
+            ledger.residual().unwrap(),
+            vec![build_token(TOKEN_A, 6).unwrap()]
+        );
+        assert_eq!(ledger.entries().len(), 4);
+
+        ledger.subtract_output_tokens(&[build_token(TOKEN_A, 7).unwrap()]);
+        assert!(matches!(
+            ledger.residual(),
+            Err(HeadlessDappError::TokenDeficit(_, 10, 11))
+        ));
+        ledger.declare_mint(&build_token(TOKEN_A, 1).unwrap());
+        assert!(ledger.residual().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,238 @@
+/// This file holds the `TokenMint` builder which mints a new EIP-4 token
+/// and distributes the full supply across many recipients within the same
+/// transaction (ie. an airdrop-at-mint).
+use crate::box_traits::WrappedBox;
+use crate::encoding::{build_token, serialize_string};
+use crate::error::{HeadlessDappError, Result};
+use crate::output_builders::{ChangeBox, TxFeeBox};
+use crate::specified_boxes::ErgsBox;
+use crate::tx_creation::{check_unsigned_tx, create_candidate, new_unsigned_tx, sum_tokens};
+use crate::{BlockHeight, ErgoAddressString, NanoErg, P2PKAddressString};
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::chain::transaction::UnsignedInput;
+use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, ErgoBoxCandidate};
+use ergo_lib::ergotree_ir::mir::constant::Constant;
+
+/// A recipient of a portion of a newly minted token
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MintRecipient {
+    /// The address the recipient output is locked to
+    pub address: ErgoAddressString,
+    /// The amount of the minted token the recipient receives
+    pub token_amount: u64,
+    /// The nanoErgs held in the recipient output
+    pub nano_ergs: NanoErg,
+}
+
+/// Builds a transaction which mints a new token following EIP-4 and splits
+/// it across many recipients.
+/// EIP-4 requires the entire supply to be minted in a single transaction
+/// with the id of the first input box as the token id. The supply is thus
+/// defined as the sum of the recipient amounts, and every recipient output
+/// is created in that same transaction. The first recipient output is the
+/// issuance box and holds the EIP-4 registers (R4 name, R5 description,
+/// R6 decimals).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenMint {
+    name: String,
+    description: String,
+    decimals: u32,
+    recipients: Vec<MintRecipient>,
+}
+
+impl TokenMint {
+    /// Create a new `TokenMint` without any recipients
+    pub fn new(name: &str, description: &str, decimals: u32) -> TokenMint {
+        TokenMint {
+            name: name.to_string(),
+            description: description.to_string(),
+            decimals: decimals,
+            recipients: vec![],
+        }
+    }
+
+    /// Adds a recipient who receives `token_amount` of the minted token in
+    /// an output holding `nano_ergs`. Recipients are output in the order
+    /// they are added.
+    pub fn with_recipient(
+        mut self,
+        address: &ErgoAddressString,
+        token_amount: u64,
+        nano_ergs: NanoErg,
+    ) -> Self {
+        self.recipients.push(MintRecipient {
+            address: address.clone(),
+            token_amount: token_amount,
+            nano_ergs: nano_ergs,
+        });
+        self
+    }
+
+    /// Returns the recipients of the mint
+    pub fn recipients(&self) -> &Vec<MintRecipient> {
+        &self.recipients
+    }
+
+    /// The total supply minted, ie. the sum of all recipient amounts
+    pub fn total_supply(&self) -> Result<u64> {
+        self.recipients.iter().try_fold(0u64, |acc, r| {
+            acc.checked_add(r.token_amount)
+                .ok_or(HeadlessDappError::TokenAmountOverflow)
+        })
+    }
+
+    /// The total nanoErgs held across all recipient outputs
+    pub fn total_nano_ergs(&self) -> Result<NanoErg> {
+        self.recipients
+            .iter()
+            .try_fold(0u64, |acc, r| acc.checked_add(r.nano_ergs))
+            .ok_or(HeadlessDappError::ArithmeticOverflow)
+    }
+
+    /// The EIP-4 registers placed in the issuance box
+    pub fn eip4_registers(&self) -> Vec<Constant> {
+        vec![
+            serialize_string(&self.name),
+            serialize_string(&self.description),
+            serialize_string(&self.decimals.to_string()),
+        ]
+    }
+
+    /// Creates the recipient output candidates for the token minted with
+    /// the id of the `first_input_box`.
+    pub fn output_candidates(
+        &self,
+        first_input_box: &ErgoBox,
+        current_height: BlockHeight,
+    ) -> Result<Vec<ErgoBoxCandidate>> {
+        if self.recipients.is_empty() {
+            return Err(HeadlessDappError::InvalidTokenMint(
+                "At least one recipient is required".to_string(),
+            ));
+        }
+        self.total_supply()?;
+        let token_id: String = first_input_box.box_id().into();
+        self.recipients
+            .iter()
+            .enumerate()
+            .map(|(i, r)| {
+                if r.token_amount == 0 {
+                    return Err(HeadlessDappError::InvalidTokenMint(format!(
+                        "Recipient {} receives no tokens",
+                        r.address
+                    )));
+                }
+                let token = build_token(&token_id, r.token_amount)?;
+                let registers = if i == 0 {
+                    self.eip4_registers()
+                } else {
+                    vec![]
+                };
+                create_candidate(
+                    r.nano_ergs,
+                    &r.address,
+                    &vec![token],
+                    &registers,
+                    current_height,
+                )
+            })
+            .collect()
+    }
+
+    /// Builds the `UnsignedTransaction` which mints the token and
+    /// distributes it to every recipient. The first of the `ergs_boxes`
+    /// becomes the first input, and thus defines the token id. Any
+    /// remaining nanoErgs and tokens are sent to the `change_address`.
+    pub fn action_mint_and_distribute(
+        &self,
+        ergs_boxes: &Vec<ErgsBox>,
+        transaction_fee: NanoErg,
+        change_address: &P2PKAddressString,
+        current_height: BlockHeight,
+    ) -> Result<UnsignedTransaction> {
+        let first_box = ergs_boxes.first().ok_or_else(|| {
+            HeadlessDappError::InvalidTokenMint("No input boxes were provided".to_string())
+        })?;
+        let mut outputs = self.output_candidates(first_box.get_box_ref(), current_height)?;
+
+        let input_value = ErgsBox::sum_ergs_boxes_value(ergs_boxes);
+        let change_value = input_value
+            .checked_sub(self.total_nano_ergs()?)
+            .and_then(|v| v.checked_sub(transaction_fee))
+            .ok_or_else(|| {
+                HeadlessDappError::InvalidTokenMint(
+                    "The inputs do not hold enough nanoErgs for the recipients and fee".to_string(),
+                )
+            })?;
+        let input_boxes: Vec<ErgoBox> = ergs_boxes.iter().map(|b| b.get_box()).collect();
+        let change_tokens = sum_tokens(&input_boxes)?;
+        if change_value > 0 || !change_tokens.is_empty() {
+            outputs.push(ChangeBox::output_candidate(
+                &change_tokens,
+                change_value,
+                change_address,
+                current_height,
+            )?);
+        }
+        outputs.push(TxFeeBox::output_candidate(transaction_fee, current_height)?);
+
+        let inputs: Vec<UnsignedInput> = ergs_boxes.iter().map(|b| b.as_unsigned_input()).collect();
+        let unsigned_tx = new_unsigned_tx(inputs, vec![], outputs)
+            .map_err(HeadlessDappError::InvalidTokenMint)?;
+        check_unsigned_tx(&unsigned_tx)?;
+        Ok(unsigned_tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::box_traits::ExplorerFindable;
+    use crate::output_builders::MINER_FEE_P2S_ADDRESS;
+    use crate::test_utils::test_box;
+
+    #[test]
+    fn mints_full_supply_across_recipients() {
+        let address = MINER_FEE_P2S_ADDRESS.to_string();
+        let input = test_box(10000000, &address, vec![], vec![]);
+        let ergs_box = ErgsBox::from_ergo_box(&input).unwrap();
+
+        let mint = TokenMint::new("Test", "A test token", 2)
+            .with_recipient(&address, 600, 1000000)
+            .with_recipient(&address, 400, 1000000);
+        assert_eq!(mint.total_supply().unwrap(), 1000);
+
+        let tx = mint
+            .action_mint_and_distribute(&vec![ergs_box], 1000000, &address, 10)
+            .unwrap();
+        let outputs = tx.output_candidates.as_vec();
+        // Two recipients, change, and tx fee
+        assert_eq!(outputs.len(), 4);
+        let minted: u64 = outputs
+            .iter()
+            .flat_map(|o| o.tokens.iter())
+            .map(|t| u64::from(t.amount))
+            .sum();
+        assert_eq!(minted, 1000);
+        assert_eq!(
+            outputs[0].additional_registers.get_ordered_values().len(),
+            3
+        );
+        assert!(outputs[1]
+            .additional_registers
+            .get_ordered_values()
+            .is_empty());
+    }
+
+    #[test]
+    fn total_nano_ergs_overflow_is_an_error() {
+        let address = MINER_FEE_P2S_ADDRESS.to_string();
+        let mint = TokenMint::new("Test", "A test token", 0)
+            .with_recipient(&address, 1, u64::MAX)
+            .with_recipient(&address, 1, 1);
+        assert!(matches!(
+            mint.total_nano_ergs(),
+            Err(HeadlessDappError::ArithmeticOverflow)
+        ));
+    }
+}
@@ -0,0 +1,163 @@
+/// This file holds the `TokenPolicy` which gives protocols explicit
+/// control over which tokens may be forwarded into change boxes, rather
+/// than forwarding every token which was not filtered out.
+use crate::error::{HeadlessDappError, Result};
+use crate::TokenID;
+use ergo_lib::ergotree_ir::chain::digest32::Digest32;
+use ergo_lib::ergotree_ir::chain::token::Token;
+use std::collections::HashMap;
+
+/// How a token is treated when building change
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenTreatment {
+    /// The token is forwarded into the change box
+    PassThrough,
+    /// The token is left out of the change box and thus burned
+    Burn,
+    /// The token must not appear in the inputs. Building change errors
+    /// if it does.
+    Forbidden,
+}
+
+/// A policy which classifies tokens by their id. Tokens without a
+/// specific rule are treated according to the default treatment. Token
+/// ids are matched case-insensitively, as they are hex encoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenPolicy {
+    default_treatment: TokenTreatment,
+    rules: HashMap<TokenID, TokenTreatment>,
+}
+
+impl Default for TokenPolicy {
+    fn default() -> Self {
+        TokenPolicy::pass_through_all()
+    }
+}
+
+impl TokenPolicy {
+    /// A policy which forwards every token (a blacklist with no entries)
+    pub fn pass_through_all() -> TokenPolicy {
+        TokenPolicy {
+            default_treatment: TokenTreatment::PassThrough,
+            rules: HashMap::new(),
+        }
+    }
+
+    /// A policy which only forwards the listed tokens, and forbids all
+    /// others from appearing.
+    pub fn whitelist(token_ids: &[&str]) -> TokenPolicy {
+        let mut policy = TokenPolicy {
+            default_treatment: TokenTreatment::Forbidden,
+            rules: HashMap::new(),
+        };
+        for token_id in token_ids {
+            policy = policy.with_rule(token_id, TokenTreatment::PassThrough);
+        }
+        policy
+    }
+
+    /// A policy which forwards all tokens except the listed tokens, which
+    /// are forbidden from appearing.
+    pub fn blacklist(token_ids: &[&str]) -> TokenPolicy {
+        let mut policy = TokenPolicy::pass_through_all();
+        for token_id in token_ids {
+            policy = policy.with_rule(token_id, TokenTreatment::Forbidden);
+        }
+        policy
+    }
+
+    /// Sets the treatment of a specific token
+    pub fn with_rule(mut self, token_id: &str, treatment: TokenTreatment) -> Self {
+        self.rules.insert(token_id.to_lowercase(), treatment);
+        self
+    }
+
+    /// Acquire the treatment of the given token
+    pub fn treatment(&self, token_id: &str) -> TokenTreatment {
+        self.rules
+            .get(&token_id.to_lowercase())
+            .cloned()
+            .unwrap_or(self.default_treatment)
+    }
+
+    /// Applies the policy to a list of tokens, returning the tokens which
+    /// are forwarded. Errors if a forbidden token is present.
+    pub fn apply(&self, tokens: &Vec<Token>) -> Result<Vec<Token>> {
+        let mut forwarded = vec![];
+        for t in tokens {
+            let token_id_digest32: Digest32 = t.token_id.clone().into();
+            let token_id: String = token_id_digest32.into();
+            match self.treatment(&token_id) {
+                TokenTreatment::PassThrough => forwarded.push(t.clone()),
+                TokenTreatment::Burn => (),
+                TokenTreatment::Forbidden => {
+                    return Err(HeadlessDappError::ForbiddenToken(token_id))
+                }
+            }
+        }
+        Ok(forwarded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::build_token;
+    use crate::test_utils::TEST_TOKEN_ID;
+
+    const OTHER_TOKEN_ID: &str = "a0ad9b4077e3b276bf93855d51a042d10fb1eca4646950743bc5a8c341c16871";
+
+    fn tokens() -> Vec<Token> {
+        vec![
+            build_token(TEST_TOKEN_ID, 5).unwrap(),
+            build_token(OTHER_TOKEN_ID, 7).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn pass_through_all_forwards_every_token() {
+        assert_eq!(TokenPolicy::default().apply(&tokens()).unwrap(), tokens());
+    }
+
+    #[test]
+    fn whitelist_forbids_unlisted_tokens() {
+        let policy = TokenPolicy::whitelist(&[TEST_TOKEN_ID]);
+        assert_eq!(policy.treatment(TEST_TOKEN_ID), TokenTreatment::PassThrough);
+        assert!(matches!(
+            policy.apply(&tokens()),
+            Err(HeadlessDappError::ForbiddenToken(id)) if id == OTHER_TOKEN_ID
+        ));
+        assert_eq!(
+            policy.apply(&vec![tokens()[0].clone()]).unwrap(),
+            vec![tokens()[0].clone()]
+        );
+    }
+
+    #[test]
+    fn blacklist_forbids_listed_tokens() {
+        let policy = TokenPolicy::blacklist(&[OTHER_TOKEN_ID]);
+        assert!(policy.apply(&tokens()).is_err());
+        assert_eq!(
+            policy.apply(&vec![tokens()[0].clone()]).unwrap(),
+            vec![tokens()[0].clone()]
+        );
+    }
+
+    #[test]
+    fn burned_tokens_are_left_out() {
+        let policy =
+            TokenPolicy::pass_through_all().with_rule(OTHER_TOKEN_ID, TokenTreatment::Burn);
+        assert_eq!(policy.apply(&tokens()).unwrap(), vec![tokens()[0].clone()]);
+    }
+
+    #[test]
+    fn token_ids_are_matched_case_insensitively() {
+        let policy = TokenPolicy::whitelist(&[&TEST_TOKEN_ID.to_uppercase()]);
+        assert_eq!(policy.treatment(TEST_TOKEN_ID), TokenTreatment::PassThrough);
+        assert_eq!(
+            policy.treatment(&OTHER_TOKEN_ID.to_uppercase()),
+            TokenTreatment::Forbidden
+        );
+        assert!(policy.apply(&vec![tokens()[0].clone()]).is_ok());
+    }
+}
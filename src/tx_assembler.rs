@@ -1,7 +1,10 @@
 use crate::{
-    encoding::deserialize_p2s_to_ergo_tree,
+    encoding::{build_token, deserialize_p2s_to_ergo_tree},
     encoding::{serialize_address_from_ergo_tree, serialize_p2s_from_ergo_tree},
-    ErgsBox, NanoErg,
+    error::{HeadlessDappError, Result},
+    explorer_url::ExplorerUrl,
+    known_ids::MINER_FEE_P2S_ADDRESS,
+    ErgoAddressString, ErgsBox, NanoErg, WrappedBox,
 };
 use ergo_lib::{
     chain::transaction::unsigned::UnsignedTransaction,
@@ -11,11 +14,14 @@ use ergo_lib::{
         chain::{
             digest32::Digest32,
             ergo_box::{box_value::BoxValue, ErgoBox, NonMandatoryRegisters},
+            token::Token,
         },
     },
 };
+use ergo_lib_wasm::ergo_box::ErgoBox as WErgoBox;
 use ergo_lib_wasm::transaction::UnsignedTransaction as WUnsignedTransaction;
-use json::object;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use wasm_bindgen::prelude::*;
 
 /// This is a struct which is used to generate Ergo Tx Assembler Spec `String`s
@@ -23,6 +29,12 @@ use wasm_bindgen::prelude::*;
 #[wasm_bindgen]
 pub struct TxAssemblerSpecBuilder {
     unsigned_tx: UnsignedTransaction,
+    /// Placeholder boxes which stand in for the user's deposit. These
+    /// are replaced by `$userIns` in the generated spec.
+    placeholder_boxes: Vec<ErgoBox>,
+    /// Full data-input boxes which are embedded into the generated spec
+    /// rather than only their Box IDs.
+    data_input_boxes: Vec<ErgoBox>,
 }
 
 #[wasm_bindgen]
@@ -41,110 +53,466 @@ impl TxAssemblerSpecBuilder {
     /// and replaced with the user's actual input box from the assembler)
     #[wasm_bindgen]
     pub fn create_placeholder_ergs_box(nano_ergs: NanoErg) -> Option<ErgsBox> {
-        let placeholder_address = "2iHkR7CWvD1R4j1yZg5bkeDRQavjAaVPeTDFGGLZduHyfWMuYpmhHocX8GJoaieTx78FntzJbCBVL6rf96ocJoZdmWBL2fci7NqWgAirppPQmZ7fN9V6z13Ay6brPriBKYqLp1bT2Fk4FkFLCfdPpe".to_string();
+        Self::create_placeholder_ergs_box_at_index(nano_ergs, 0)
+    }
+
+    /// Same as `create_placeholder_ergs_box()`, but the placeholder box is
+    /// created at the given output `index`. Every placeholder box of a
+    /// transaction must be given a distinct `index`, so that boxes with
+    /// equal values have distinct Box IDs.
+    #[wasm_bindgen]
+    pub fn create_placeholder_ergs_box_at_index(nano_ergs: NanoErg, index: u16) -> Option<ErgsBox> {
+        Self::create_placeholder_ergs_box_with_tokens(nano_ergs, vec![], index)
+    }
+
+    /// WASM wrapper for `create_placeholder_ergs_box_with_tokens()` which
+    /// creates a placeholder box holding a single placeholder token.
+    #[wasm_bindgen]
+    pub fn w_create_placeholder_ergs_box_with_token(
+        nano_ergs: NanoErg,
+        token_id: &str,
+        token_amount: u64,
+        index: u16,
+    ) -> Option<ErgsBox> {
+        let token = build_token(token_id, token_amount).ok()?;
+        Self::create_placeholder_ergs_box_with_tokens(nano_ergs, vec![token], index)
+    }
+
+    /// Builds a JSON `String` which
+    /// is formatted as a transaction spec for working with the
+    /// Ergo Transaction Assembler Service.
+    #[wasm_bindgen]
+    pub fn build_assembler_spec(&self, transaction_fee: NanoErg) -> String {
+        self.build_assembler_spec_struct(transaction_fee).to_json()
+    }
+
+    /// Registers an `ErgsBox` (generally created via
+    /// `create_placeholder_ergs_box()`) as a placeholder for the user's
+    /// deposit. Any input of the `UnsignedTransaction` which is a
+    /// placeholder box is emitted as `$userIns` in the generated spec.
+    #[wasm_bindgen]
+    pub fn add_placeholder_box(&mut self, placeholder_box: ErgsBox) {
+        self.placeholder_boxes.push(placeholder_box.get_box());
+    }
+
+    /// Provides the full box data of a data-input of the
+    /// `UnsignedTransaction`. The box is then embedded into the generated
+    /// spec's `dataInputs` rather than only its Box ID, as some services
+    /// require it.
+    #[wasm_bindgen]
+    pub fn add_data_input_box(&mut self, data_input_box: WErgoBox) {
+        self.data_input_boxes.push(data_input_box.into());
+    }
+
+    /// Builds a JSON `String` which is formatted as a full assembler
+    /// request for user-deposit flows. The assembler watches the
+    /// `deposit_address` until the user has deposited the nanoErgs/tokens
+    /// held in the placeholder boxes, and then executes the tx spec with
+    /// the user's deposit as `$userIns`. If the protocol fails the deposit
+    /// is returned to the `return_to` address.
+    #[wasm_bindgen]
+    pub fn build_deposit_assembler_spec(
+        &self,
+        transaction_fee: NanoErg,
+        deposit_address: &str,
+        return_to: &str,
+    ) -> std::result::Result<String, JsValue> {
+        self.build_deposit_assembler_spec_struct(transaction_fee, deposit_address, return_to)
+            .map(|spec| spec.to_json())
+            .map_err(|e| JsValue::from_str(&format! {"{:?}", e}))
+    }
+}
+
+/// Non-WASM methods
+impl TxAssemblerSpecBuilder {
+    /// Create a new `TxAssemblerSpecBuilder`
+    pub fn new(unsigned_tx: UnsignedTransaction) -> Self {
+        TxAssemblerSpecBuilder {
+            unsigned_tx: unsigned_tx,
+            placeholder_boxes: vec![],
+            data_input_boxes: vec![],
+        }
+    }
+
+    /// Create a placeholder box that holds an amount of nanoErgs equal to the
+    /// input `nano_ergs` value as well as the provided placeholder `tokens`
+    /// and then wrap said box as a `ErgsBox`.
+    /// This is useful for assembler flows where the user deposits tokens
+    /// (ie. DEX orders) so that an assembler spec can be created with the
+    /// placeholder box standing in for the user's actual deposit.
+    /// The `index` is used as the output index of the placeholder box, and
+    /// so must be distinct for every placeholder box of a transaction.
+    pub fn create_placeholder_ergs_box_with_tokens(
+        nano_ergs: NanoErg,
+        tokens: Vec<Token>,
+        index: u16,
+    ) -> Option<ErgsBox> {
+        let placeholder_address = MINER_FEE_P2S_ADDRESS.to_string();
         let ergo_tree = deserialize_p2s_to_ergo_tree(placeholder_address).ok()?;
         let box_value = BoxValue::new(nano_ergs).ok()?;
         let placeholder_box = ErgoBox::new(
             box_value,
             ergo_tree,
-            vec![],
+            tokens,
             NonMandatoryRegisters::empty(),
             0,
             TxId::zero(),
-            0,
+            index,
         )
-        .unwrap();
+        .ok()?;
 
         ErgsBox::new(&placeholder_box).ok()
     }
 
-    /// Builds a JSON `String` which
-    /// is formatted as a transaction spec for working with the
+    /// Builds a typed `AssemblerSpec` from the `UnsignedTransaction`
+    /// which can be serialized into the JSON format expected by the
     /// Ergo Transaction Assembler Service.
-    #[wasm_bindgen]
-    pub fn build_assembler_spec(&self, transaction_fee: NanoErg) -> String {
-        let mut tx_spec = object! {
-            "requests":  [],
-        };
+    pub fn build_assembler_spec_struct(&self, transaction_fee: NanoErg) -> AssemblerSpec {
+        let requests = self
+            .unsigned_tx
+            .output_candidates
+            .as_vec()
+            .iter()
+            .map(|output| {
+                let address = serialize_address_from_ergo_tree(output.ergo_tree.clone())
+                    .unwrap_or_else(|_| serialize_p2s_from_ergo_tree(output.ergo_tree.clone()));
+                let assets = output
+                    .tokens
+                    .iter()
+                    .map(|token| {
+                        let token_id_digest32: Digest32 = token.token_id.clone().into();
+                        AssemblerAsset {
+                            token_id: token_id_digest32.into(),
+                            amount: token.amount.into(),
+                        }
+                    })
+                    .collect();
+                let registers = output
+                    .additional_registers
+                    .get_ordered_values()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| (format!("R{}", i + 4), c.base16_str().unwrap()))
+                    .collect();
+                AssemblerRequest {
+                    address,
+                    value: output.value.as_u64().clone(),
+                    assets,
+                    registers,
+                }
+            })
+            .collect();
 
-        for i in 0..self.unsigned_tx.output_candidates.len() {
-            let output = self.unsigned_tx.output_candidates.as_vec()[i].clone();
-            // Base values
-            tx_spec["requests"][i]["value"] = output.value.as_u64().clone().into();
-            if let Ok(address_string) = serialize_address_from_ergo_tree(output.ergo_tree.clone()) {
-                tx_spec["requests"][i]["address"] = address_string.into();
+        // Placeholder inputs are replaced by a single `$userIns`
+        let placeholder_ids: Vec<String> = self
+            .placeholder_boxes
+            .iter()
+            .map(|b| b.box_id().into())
+            .collect();
+        let mut inputs: Vec<String> = vec![];
+        for input in self.unsigned_tx.inputs.as_vec() {
+            let box_id: String = input.box_id.clone().into();
+            if placeholder_ids.contains(&box_id) {
+                if !inputs.iter().any(|i| i == USER_INPUTS_PLACEHOLDER) {
+                    inputs.push(USER_INPUTS_PLACEHOLDER.to_string());
+                }
             } else {
-                tx_spec["requests"][i]["address"] =
-                    serialize_p2s_from_ergo_tree(output.ergo_tree).into();
-            }
-
-            // Tokens
-            for n in 0..output.tokens.len() {
-                let token = output.tokens[n].clone();
-                let token_id_digest32: Digest32 = token.token_id.into();
-                let tok_id: String = token_id_digest32.into();
-                let tok_amount: u64 = token.amount.into();
-                tx_spec["requests"][i]["assets"][n]["tokenId"] = tok_id.into();
-                tx_spec["requests"][i]["assets"][n]["amount"] = tok_amount.into();
+                inputs.push(box_id);
             }
+        }
+        // Data-inputs with full box data provided are embedded
+        let data_inputs = match &self.unsigned_tx.data_inputs {
+            Some(data_inputs) => data_inputs
+                .as_vec()
+                .iter()
+                .map(|data_input| {
+                    let full_box = self
+                        .data_input_boxes
+                        .iter()
+                        .find(|b| b.box_id() == data_input.box_id)
+                        .and_then(|b| serde_json::to_value(b).ok());
+                    match full_box {
+                        Some(box_json) => AssemblerDataInput::FullBox(box_json),
+                        None => AssemblerDataInput::BoxId(data_input.box_id.clone().into()),
+                    }
+                })
+                .collect(),
+            None => vec![],
+        };
 
-            // Registers
-            let registers = output.additional_registers.get_ordered_values();
-            for y in 0..registers.len() {
-                if y == 0 {
-                    tx_spec["requests"][i]["registers"]["R4"] =
-                        registers[y].base16_str().unwrap().into();
-                }
-                if y == 1 {
-                    tx_spec["requests"][i]["registers"]["R5"] =
-                        registers[y].base16_str().unwrap().into();
-                }
-                if y == 2 {
-                    tx_spec["requests"][i]["registers"]["R6"] =
-                        registers[y].base16_str().unwrap().into();
-                }
-                if y == 3 {
-                    tx_spec["requests"][i]["registers"]["R7"] =
-                        registers[y].base16_str().unwrap().into();
-                }
-                if y == 4 {
-                    tx_spec["requests"][i]["registers"]["R8"] =
-                        registers[y].base16_str().unwrap().into();
-                }
-                if y == 5 {
-                    tx_spec["requests"][i]["registers"]["R9"] =
-                        registers[y].base16_str().unwrap().into();
-                }
-            }
+        AssemblerSpec {
+            requests,
+            fee: transaction_fee,
+            inputs,
+            data_inputs,
+        }
+    }
 
-            // Fee
-            tx_spec["fee"] = transaction_fee.into();
+    /// Generates the Ergo Explorer Backend API endpoints for every
+    /// data-input whose full box data has not yet been provided. The
+    /// response bodies of these endpoints are then provided to
+    /// `add_data_input_box_from_explorer_response()`.
+    pub fn data_input_endpoints(&self, explorer_api_url: &str) -> Vec<String> {
+        let data_inputs = match &self.unsigned_tx.data_inputs {
+            Some(data_inputs) => data_inputs.as_vec().clone(),
+            None => vec![],
+        };
+        data_inputs
+            .iter()
+            .filter(|d| !self.data_input_boxes.iter().any(|b| b.box_id() == d.box_id))
+            .map(|d| {
+                let box_id: String = d.box_id.clone().into();
+                ExplorerUrl::new(explorer_api_url)
+                    .path("v1/boxes")
+                    .segment(&box_id)
+                    .build()
+            })
+            .collect()
+    }
 
-            // Inputs
-            let inputs = self.unsigned_tx.inputs.clone();
-            for y in 0..inputs.len() {
-                let box_id: String = inputs.as_vec()[y].box_id.clone().into();
-                tx_spec["inputs"][y] = box_id.into();
-            }
+    /// Parses the response JSON (as a String) from the Ergo Explorer API
+    /// endpoint generated by `data_input_endpoints()` and provides the box
+    /// as the full box data of a data-input.
+    pub fn add_data_input_box_from_explorer_response(
+        &mut self,
+        explorer_response_body: &str,
+    ) -> Result<()> {
+        let data_input_box: ErgoBox = serde_json::from_str(explorer_response_body)
+            .map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+        self.data_input_boxes.push(data_input_box);
+        Ok(())
+    }
 
-            // Inputs
-            let data_inputs = self.unsigned_tx.data_inputs.clone().unwrap();
-            for y in 0..data_inputs.len() {
-                let box_id: String = data_inputs.as_vec()[y].box_id.clone().into();
-                tx_spec["dataInputs"][y] = box_id.into();
+    /// Builds a typed `AssemblerDepositSpec` for user-deposit flows.
+    /// The required deposit is derived from the placeholder boxes.
+    pub fn build_deposit_assembler_spec_struct(
+        &self,
+        transaction_fee: NanoErg,
+        deposit_address: &str,
+        return_to: &str,
+    ) -> Result<AssemblerDepositSpec> {
+        let mut start_when = BTreeMap::new();
+        let nano_ergs = self
+            .placeholder_boxes
+            .iter()
+            .try_fold(0u64, |acc, b| acc.checked_add(*b.value.as_u64()))
+            .ok_or(HeadlessDappError::ArithmeticOverflow)?;
+        start_when.insert("erg".to_string(), nano_ergs);
+        for b in &self.placeholder_boxes {
+            for token in &b.tokens {
+                let token_id_digest32: Digest32 = token.token_id.clone().into();
+                let amount = start_when.entry(token_id_digest32.into()).or_insert(0);
+                *amount = amount
+                    .checked_add(u64::from(token.amount))
+                    .ok_or(HeadlessDappError::TokenAmountOverflow)?;
             }
         }
 
-        tx_spec.pretty(2)
+        Ok(AssemblerDepositSpec {
+            address: deposit_address.to_string(),
+            return_to: return_to.to_string(),
+            start_when,
+            tx_spec: self.build_assembler_spec_struct(transaction_fee),
+        })
     }
 }
 
-/// Non-WASM methods
-impl TxAssemblerSpecBuilder {
-    /// Create a new `TxAssemblerSpecBuilder`
-    pub fn new(unsigned_tx: UnsignedTransaction) -> Self {
-        TxAssemblerSpecBuilder {
-            unsigned_tx: unsigned_tx,
+/// The placeholder the assembler replaces with the user's deposited boxes
+pub const USER_INPUTS_PLACEHOLDER: &str = "$userIns";
+
+/// A typed representation of a full assembler request for user-deposit
+/// flows, wrapping an `AssemblerSpec`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AssemblerDepositSpec {
+    /// The address which the user deposits to and the assembler watches
+    pub address: ErgoAddressString,
+    /// The address which the deposit is returned to if the tx fails
+    #[serde(rename = "returnTo")]
+    pub return_to: ErgoAddressString,
+    /// The required deposit before the tx spec is executed. Keyed by
+    /// `erg` for nanoErgs and by base16 Token ID for tokens.
+    #[serde(rename = "startWhen")]
+    pub start_when: BTreeMap<String, u64>,
+    /// The tx spec which is executed once the deposit is made
+    #[serde(rename = "txSpec")]
+    pub tx_spec: AssemblerSpec,
+}
+
+impl AssemblerDepositSpec {
+    /// Parses an assembler deposit request JSON `String`
+    pub fn from_json(json: &str) -> Result<AssemblerDepositSpec> {
+        serde_json::from_str(json).map_err(|e| HeadlessDappError::Other(e.to_string()))
+    }
+
+    /// Serializes the `AssemblerDepositSpec` into a pretty-printed JSON `String`
+    pub fn to_json(&self) -> String {
+        // Serializing a struct made of Strings/integers/maps cannot fail
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+/// A typed representation of a transaction spec for the
+/// Ergo Transaction Assembler Service.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AssemblerSpec {
+    /// The outputs which the assembler should create
+    pub requests: Vec<AssemblerRequest>,
+    /// The transaction fee in nanoErgs
+    pub fee: NanoErg,
+    /// The Box IDs of the inputs
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    /// The data-inputs, either as Box IDs or full box data
+    #[serde(rename = "dataInputs", default, skip_serializing_if = "Vec::is_empty")]
+    pub data_inputs: Vec<AssemblerDataInput>,
+}
+
+/// A data-input inside of an `AssemblerSpec`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AssemblerDataInput {
+    /// The base16 encoded Box ID of the data-input
+    BoxId(String),
+    /// The full box data of the data-input in the node/explorer JSON format
+    FullBox(serde_json::Value),
+}
+
+impl AssemblerSpec {
+    /// Parses an assembler spec JSON `String` into an `AssemblerSpec`
+    pub fn from_json(json: &str) -> Result<AssemblerSpec> {
+        serde_json::from_str(json).map_err(|e| HeadlessDappError::Other(e.to_string()))
+    }
+
+    /// Serializes the `AssemblerSpec` into a pretty-printed JSON `String`
+    pub fn to_json(&self) -> String {
+        // Serializing a struct made of Strings/integers/maps cannot fail
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+/// A single output request inside of an `AssemblerSpec`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssemblerRequest {
+    /// The Base58 encoded address of the output
+    pub address: ErgoAddressString,
+    /// The number of nanoErgs held in the output
+    pub value: NanoErg,
+    /// The tokens held in the output
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assets: Vec<AssemblerAsset>,
+    /// The registers of the output as base16 encoded `Constant`s keyed
+    /// by register name (R4, R5, etc.)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub registers: BTreeMap<String, String>,
+}
+
+/// A token held inside of an `AssemblerRequest`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssemblerAsset {
+    /// The base16 encoded Token ID
+    #[serde(rename = "tokenId")]
+    pub token_id: String,
+    /// The amount of the token
+    pub amount: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{TEST_ADDRESS, TEST_TOKEN_ID};
+    use crate::tx_creation::{create_candidate, new_unsigned_tx};
+
+    #[test]
+    fn assembler_spec_round_trip() {
+        let spec = AssemblerSpec {
+            requests: vec![AssemblerRequest {
+                address: "9aFbqNsmDwSxCdcLDKmSxVTL58ms2A39Rpn2zodVzkBN5MzB8zvW".to_string(),
+                value: 1000000,
+                assets: vec![AssemblerAsset {
+                    token_id: "0fb1eca4646950743bc5a8c341c16871a0ad9b4077e3b276bf93855d51a042d1"
+                        .to_string(),
+                    amount: 5,
+                }],
+                registers: BTreeMap::new(),
+            }],
+            fee: 2000000,
+            inputs: vec!["$userIns".to_string()],
+            data_inputs: vec![],
+        };
+
+        let parsed = AssemblerSpec::from_json(&spec.to_json()).unwrap();
+        assert_eq!(spec, parsed);
+    }
+
+    fn builder_with_placeholders(placeholders: Vec<ErgsBox>) -> TxAssemblerSpecBuilder {
+        let inputs = placeholders.iter().map(|b| b.get_box().into()).collect();
+        let output = create_candidate(
+            1000000,
+            &MINER_FEE_P2S_ADDRESS.to_string(),
+            &vec![],
+            &vec![],
+            0,
+        )
+        .unwrap();
+        let unsigned_tx = new_unsigned_tx(inputs, vec![], vec![output]).unwrap();
+        let mut builder = TxAssemblerSpecBuilder::new(unsigned_tx);
+        for placeholder in placeholders {
+            builder.add_placeholder_box(placeholder);
         }
+        builder
+    }
+
+    #[test]
+    fn placeholder_boxes_have_distinct_ids() {
+        let a = TxAssemblerSpecBuilder::create_placeholder_ergs_box(1000000).unwrap();
+        let b = TxAssemblerSpecBuilder::create_placeholder_ergs_box_at_index(1000000, 1).unwrap();
+        assert_ne!(a.box_id(), b.box_id());
+
+        let builder = builder_with_placeholders(vec![a, b]);
+        let spec = builder
+            .build_deposit_assembler_spec_struct(1000000, TEST_ADDRESS, TEST_ADDRESS)
+            .unwrap();
+        assert_eq!(spec.start_when["erg"], 2000000);
+        assert_eq!(
+            spec.tx_spec.inputs,
+            vec![USER_INPUTS_PLACEHOLDER.to_string()]
+        );
+    }
+
+    #[test]
+    fn deposit_spec_sums_are_checked() {
+        let max_value = i64::MAX as u64;
+        let placeholders = (0..3)
+            .map(|i| {
+                TxAssemblerSpecBuilder::create_placeholder_ergs_box_at_index(max_value, i).unwrap()
+            })
+            .collect();
+        let result = builder_with_placeholders(placeholders).build_deposit_assembler_spec_struct(
+            1000000,
+            TEST_ADDRESS,
+            TEST_ADDRESS,
+        );
+        assert!(matches!(result, Err(HeadlessDappError::ArithmeticOverflow)));
+
+        let placeholders = (0..3)
+            .map(|i| {
+                let token = build_token(TEST_TOKEN_ID, max_value).unwrap();
+                TxAssemblerSpecBuilder::create_placeholder_ergs_box_with_tokens(
+                    1000000,
+                    vec![token],
+                    i,
+                )
+                .unwrap()
+            })
+            .collect();
+        let result = builder_with_placeholders(placeholders).build_deposit_assembler_spec_struct(
+            1000000,
+            TEST_ADDRESS,
+            TEST_ADDRESS,
+        );
+        assert!(matches!(
+            result,
+            Err(HeadlessDappError::TokenAmountOverflow)
+        ));
     }
 }
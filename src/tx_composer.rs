@@ -0,0 +1,353 @@
+/// This file holds the `TxComposer` which merges multiple independently
+/// built Action transactions into a single `UnsignedTransaction`. This
+/// enables atomic cross-protocol operations (ie. "swap then stake") where
+/// either every Action succeeds or none of them do.
+use crate::encoding::address_string_to_ergo_tree;
+use crate::error::{HeadlessDappError, Result};
+use crate::fee_guard::FeeGuard;
+use crate::output_builders::{create_checked_candidate, TxFeeBox, MINER_FEE_P2S_ADDRESS};
+use crate::token_ledger::TokenLedger;
+use crate::token_policy::TokenPolicy;
+use crate::tx_creation::{check_unsigned_tx, new_unsigned_tx};
+use crate::{BlockHeight, NanoErg, P2PKAddressString};
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::chain::transaction::{DataInput, UnsignedInput};
+use ergo_lib::ergotree_ir::chain::ergo_box::{BoxId, ErgoBox, ErgoBoxCandidate};
+use ergo_lib::ergotree_ir::chain::token::Token;
+
+/// Merges the inputs, data-inputs, and outputs of multiple
+/// `UnsignedTransaction`s into one. The tx fee boxes and the outputs
+/// marked as change of the individual transactions are dropped, and a
+/// single tx fee box and change box are created for the composed
+/// transaction.
+#[derive(Clone, Debug, Default)]
+pub struct TxComposer {
+    /// The added transactions with the indexes of their change outputs
+    txs: Vec<(UnsignedTransaction, Vec<usize>)>,
+    token_policy: TokenPolicy,
+    fee_guard: FeeGuard,
+}
+
+impl TxComposer {
+    /// Create a new empty `TxComposer`
+    pub fn new() -> TxComposer {
+        TxComposer::default()
+    }
+
+    /// Adds an `UnsignedTransaction` to be composed. All of its outputs
+    /// other than the tx fee box are preserved.
+    pub fn add(mut self, tx: UnsignedTransaction) -> Self {
+        self.txs.push((tx, vec![]));
+        self
+    }
+
+    /// Adds an `UnsignedTransaction` to be composed, where the outputs at
+    /// the `change_indexes` are the change of the transaction and are
+    /// merged into the change box of the composed transaction
+    pub fn add_with_change(mut self, tx: UnsignedTransaction, change_indexes: &[usize]) -> Self {
+        self.txs.push((tx, change_indexes.to_vec()));
+        self
+    }
+
+    /// Sets the `TokenPolicy` applied to the tokens of the change box
+    pub fn with_token_policy(mut self, token_policy: TokenPolicy) -> Self {
+        self.token_policy = token_policy;
+        self
+    }
+
+    /// Sets the `FeeGuard` which checks the transaction fee of the
+    /// composed transaction. By default `FeeGuard::new()` is used.
+    pub fn with_fee_guard(mut self, fee_guard: FeeGuard) -> Self {
+        self.fee_guard = fee_guard;
+        self
+    }
+
+    /// Composes all of the added transactions into a single
+    /// `UnsignedTransaction`.
+    /// `input_boxes` must hold every input box of the added transactions,
+    /// as their values are required to rebalance the change.
+    /// Outputs locked under the `change_address` are only merged into the
+    /// change box if they were marked as change via `add_with_change()`.
+    pub fn compose(
+        &self,
+        input_boxes: &Vec<ErgoBox>,
+        transaction_fee: NanoErg,
+        change_address: &P2PKAddressString,
+        current_height: BlockHeight,
+    ) -> Result<UnsignedTransaction> {
+        let miner_fee_tree = address_string_to_ergo_tree(&MINER_FEE_P2S_ADDRESS.to_string())?;
+        let change_tree = address_string_to_ergo_tree(change_address)
+            .map_err(|_| HeadlessDappError::InvalidP2PKAddress(change_address.clone()))?;
+
+        if self.txs.is_empty() {
+            return Err(HeadlessDappError::InvalidTxComposition(
+                "No transactions were added to compose".to_string(),
+            ));
+        }
+
+        let mut inputs: Vec<UnsignedInput> = vec![];
+        let mut data_inputs: Vec<DataInput> = vec![];
+        let mut outputs: Vec<ErgoBoxCandidate> = vec![];
+        for (tx, change_indexes) in &self.txs {
+            // Two Actions spending the same box would conflict
+            for input in tx.inputs.as_vec() {
+                if inputs.iter().any(|i| i.box_id == input.box_id) {
+                    let box_id: String = input.box_id.clone().into();
+                    return Err(HeadlessDappError::InvalidTxComposition(format!(
+                        "Box {} is spent by more than one transaction",
+                        box_id
+                    )));
+                }
+                inputs.push(input.clone());
+            }
+            // Data-inputs can be shared between Actions
+            if let Some(tx_data_inputs) = &tx.data_inputs {
+                for data_input in tx_data_inputs.as_vec() {
+                    if !data_inputs.iter().any(|d| d.box_id == data_input.box_id) {
+                        data_inputs.push(data_input.clone());
+                    }
+                }
+            }
+            // Drop the individual tx fee & change boxes
+            let tx_outputs = tx.output_candidates.as_vec();
+            for index in change_indexes {
+                match tx_outputs.get(*index) {
+                    Some(output) if output.ergo_tree == change_tree => (),
+                    _ => {
+                        return Err(HeadlessDappError::InvalidTxComposition(format!(
+                            "Output {} is not a change box locked under the change address",
+                            index
+                        )))
+                    }
+                }
+            }
+            for (index, output) in tx_outputs.iter().enumerate() {
+                if output.ergo_tree != miner_fee_tree && !change_indexes.contains(&index) {
+                    outputs.push(output.clone());
+                }
+            }
+        }
+
+        // Acquire the input boxes in the order of the inputs
+        let mut ordered_input_boxes: Vec<ErgoBox> = vec![];
+        for input in &inputs {
+            let input_box = input_boxes
+                .iter()
+                .find(|b| b.box_id() == input.box_id)
+                .ok_or_else(|| {
+                    let box_id: String = input.box_id.clone().into();
+                    HeadlessDappError::InvalidTxComposition(format!(
+                        "Input box {} was not provided",
+                        box_id
+                    ))
+                })?;
+            ordered_input_boxes.push(input_box.clone());
+        }
+
+        // Compute the change nanoErgs
+        let input_value = ordered_input_boxes
+            .iter()
+            .try_fold(0u64, |acc, b| acc.checked_add(*b.value.as_u64()))
+            .ok_or(HeadlessDappError::ArithmeticOverflow)?;
+        let output_value = outputs
+            .iter()
+            .try_fold(0u64, |acc, o| acc.checked_add(*o.value.as_u64()))
+            .ok_or(HeadlessDappError::ArithmeticOverflow)?;
+        self.fee_guard.check(transaction_fee, output_value)?;
+        let change_value = input_value
+            .checked_sub(output_value)
+            .and_then(|v| v.checked_sub(transaction_fee))
+            .ok_or_else(|| {
+                HeadlessDappError::InvalidTxComposition(
+                    "The inputs do not hold enough nanoErgs for the outputs and fee".to_string(),
+                )
+            })?;
+
+        // Compute the change tokens
+        let mut token_ledger = TokenLedger::new().with_mintable_id(&inputs[0].box_id);
+        token_ledger.add_inputs(&ordered_input_boxes);
+        token_ledger.subtract_outputs(&outputs);
+        let change_tokens = self.token_policy.apply(&token_ledger.residual()?)?;
+
+        if change_value > 0 || !change_tokens.is_empty() {
+            // The change box must hold the minimum value for its size
+            let change_box = create_checked_candidate(
+                change_value,
+                change_address,
+                &change_tokens,
+                &vec![],
+                current_height,
+            )?;
+            outputs.push(change_box);
+        }
+        outputs.push(TxFeeBox::output_candidate(transaction_fee, current_height)?);
+
+        let unsigned_tx = new_unsigned_tx(inputs, data_inputs, outputs)
+            .map_err(HeadlessDappError::InvalidTxComposition)?;
+        check_unsigned_tx(&unsigned_tx)?;
+        Ok(unsigned_tx)
+    }
+}
+
+/// Subtracts the tokens held in the outputs from the input tokens,
+/// returning the tokens which are left over as change. Tokens in the
+/// outputs which are not in the inputs are only allowed if they are being
+/// minted (their id is the id of the first input box).
+pub(crate) fn subtract_output_tokens(
+    input_tokens: &Vec<Token>,
+    outputs: &Vec<ErgoBoxCandidate>,
+    first_input_id: &BoxId,
+) -> Result<Vec<Token>> {
+    let mut ledger = TokenLedger::new().with_mintable_id(first_input_id);
+    ledger.add_input_tokens(input_tokens);
+    ledger.subtract_outputs(outputs);
+    ledger.residual()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::build_token;
+    use crate::test_utils::{test_box_at, TEST_ADDRESS, TEST_TOKEN_ID};
+    use crate::tx_creation::create_candidate;
+
+    const RECIPIENT: &str = "9fgGinDXJZHmmGYnPNnHqGLCpZ5JV8roKzou3B7fvzR9sQRNtFZ";
+    const FEE: NanoErg = 1000000;
+
+    fn tx(input_box: &ErgoBox, outputs: Vec<(NanoErg, &str)>) -> UnsignedTransaction {
+        let outputs = outputs
+            .into_iter()
+            .map(|(value, address)| {
+                create_candidate(value, &address.to_string(), &vec![], &vec![], 100).unwrap()
+            })
+            .collect();
+        new_unsigned_tx(vec![input_box.clone().into()], vec![], outputs).unwrap()
+    }
+
+    fn values(tx: &UnsignedTransaction) -> Vec<NanoErg> {
+        tx.output_candidates
+            .as_vec()
+            .iter()
+            .map(|o| *o.value.as_u64())
+            .collect()
+    }
+
+    #[test]
+    fn merges_marked_change_boxes() {
+        let box_a = test_box_at(10000000, TEST_ADDRESS, vec![], vec![], 0, 0);
+        let box_b = test_box_at(20000000, TEST_ADDRESS, vec![], vec![], 0, 1);
+        let tx_a = tx(
+            &box_a,
+            vec![
+                (2000000, RECIPIENT),
+                (7000000, TEST_ADDRESS),
+                (FEE, MINER_FEE_P2S_ADDRESS),
+            ],
+        );
+        // A payment to the change address which is not change
+        let tx_b = tx(
+            &box_b,
+            vec![
+                (5000000, TEST_ADDRESS),
+                (14000000, TEST_ADDRESS),
+                (FEE, MINER_FEE_P2S_ADDRESS),
+            ],
+        );
+
+        let composed = TxComposer::new()
+            .add_with_change(tx_a, &[1])
+            .add_with_change(tx_b, &[1])
+            .compose(&vec![box_a, box_b], FEE, &TEST_ADDRESS.to_string(), 100)
+            .unwrap();
+        assert_eq!(composed.inputs.len(), 2);
+        assert_eq!(values(&composed), vec![2000000, 5000000, 22000000, FEE]);
+    }
+
+    #[test]
+    fn unmarked_outputs_to_change_address_are_preserved() {
+        let input_box = test_box_at(10000000, TEST_ADDRESS, vec![], vec![], 0, 0);
+        let payment = tx(
+            &input_box,
+            vec![(3000000, TEST_ADDRESS), (FEE, MINER_FEE_P2S_ADDRESS)],
+        );
+        let composed = TxComposer::new()
+            .add(payment)
+            .compose(&vec![input_box], FEE, &TEST_ADDRESS.to_string(), 100)
+            .unwrap();
+        assert_eq!(values(&composed), vec![3000000, 6000000, FEE]);
+    }
+
+    #[test]
+    fn rejects_invalid_change_index() {
+        let input_box = test_box_at(10000000, TEST_ADDRESS, vec![], vec![], 0, 0);
+        let payment = tx(
+            &input_box,
+            vec![(3000000, RECIPIENT), (FEE, MINER_FEE_P2S_ADDRESS)],
+        );
+        let result = TxComposer::new().add_with_change(payment, &[0]).compose(
+            &vec![input_box],
+            FEE,
+            &TEST_ADDRESS.to_string(),
+            100,
+        );
+        assert!(matches!(
+            result,
+            Err(HeadlessDappError::InvalidTxComposition(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_dust_change() {
+        let input_box = test_box_at(9001000, TEST_ADDRESS, vec![], vec![], 0, 0);
+        let payment = tx(
+            &input_box,
+            vec![(8000000, RECIPIENT), (FEE, MINER_FEE_P2S_ADDRESS)],
+        );
+        let result = TxComposer::new().add(payment).compose(
+            &vec![input_box],
+            FEE,
+            &TEST_ADDRESS.to_string(),
+            100,
+        );
+        assert!(matches!(
+            result,
+            Err(HeadlessDappError::BoxValueBelowMinimum(1000, _))
+        ));
+    }
+
+    #[test]
+    fn returns_left_over_tokens_as_change() {
+        let token = build_token(TEST_TOKEN_ID, 50).unwrap();
+        let input_box = test_box_at(10000000, TEST_ADDRESS, vec![token.clone()], vec![], 0, 0);
+        let payment = tx(
+            &input_box,
+            vec![(3000000, RECIPIENT), (FEE, MINER_FEE_P2S_ADDRESS)],
+        );
+        let composed = TxComposer::new()
+            .add(payment)
+            .compose(&vec![input_box], FEE, &TEST_ADDRESS.to_string(), 100)
+            .unwrap();
+        let change_box = &composed.output_candidates.as_vec()[1];
+        assert_eq!(*change_box.value.as_u64(), 6000000);
+        assert_eq!(change_box.tokens, vec![token]);
+    }
+
+    #[test]
+    fn rejects_conflicting_inputs() {
+        let input_box = test_box_at(10000000, TEST_ADDRESS, vec![], vec![], 0, 0);
+        let tx_a = tx(
+            &input_box,
+            vec![(3000000, RECIPIENT), (FEE, MINER_FEE_P2S_ADDRESS)],
+        );
+        let result = TxComposer::new().add(tx_a.clone()).add(tx_a).compose(
+            &vec![input_box],
+            FEE,
+            &TEST_ADDRESS.to_string(),
+            100,
+        );
+        assert!(matches!(
+            result,
+            Err(HeadlessDappError::InvalidTxComposition(_))
+        ));
+    }
+}
@@ -1,12 +1,19 @@
+use ergo_lib::chain::transaction::unsigned::UnsignedTransaction;
+use ergo_lib::chain::transaction::{DataInput, TxIoVec, UnsignedInput};
 use ergo_lib::ergotree_ir::chain::ergo_box::box_value::BoxValue;
 use ergo_lib::ergotree_ir::chain::ergo_box::{ErgoBox, ErgoBoxCandidate, NonMandatoryRegisters};
-use ergo_lib::ergotree_ir::chain::token::{Token, TokenAmount};
+use ergo_lib::ergotree_ir::chain::token::{Token, TokenAmount, TokenId};
 use ergo_lib::ergotree_ir::mir::constant::Constant;
 
 // This file holds a number of functions which aid in tx creation and using the Tx Assembler.
+use crate::box_traits::WrappedBox;
 use crate::encoding::address_string_to_ergo_tree;
 use crate::error::{HeadlessDappError, Result};
+use crate::token_ledger::TokenLedger;
 use crate::{BlockHeight, ErgoAddressString, NanoErg};
+use blake2b_simd::Params;
+use ergo_lib::ergotree_ir::serialization::SigmaSerializable;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 
 /// Helper function for creating an `ErgoBoxCandidate`
@@ -34,48 +41,297 @@ pub fn create_candidate(
     Ok(output_bank_candidate)
 }
 
+/// Helper function for creating an `UnsignedTransaction` from plain lists
+/// of inputs, data-inputs and output candidates. An empty list of
+/// data-inputs is left out of the transaction. Errors with a description
+/// of the problem if there are no inputs or outputs, or too many of any.
+pub fn new_unsigned_tx(
+    inputs: Vec<UnsignedInput>,
+    data_inputs: Vec<DataInput>,
+    output_candidates: Vec<ErgoBoxCandidate>,
+) -> std::result::Result<UnsignedTransaction, String> {
+    let inputs = TxIoVec::from_vec(inputs).map_err(|e| format!("Inputs: {}", e))?;
+    let data_inputs = if data_inputs.is_empty() {
+        None
+    } else {
+        Some(TxIoVec::from_vec(data_inputs).map_err(|e| format!("Data-inputs: {}", e))?)
+    };
+    let output_candidates =
+        TxIoVec::from_vec(output_candidates).map_err(|e| format!("Outputs: {}", e))?;
+    UnsignedTransaction::new(inputs, data_inputs, output_candidates).map_err(|e| e.to_string())
+}
+
+/// Checks an `UnsignedTransaction` for self-conflicts which the node would
+/// reject, returning a descriptive error before the transaction is
+/// submitted. Errors if the same box is spent by more than one input, or
+/// if a box which is spent is also used as a data-input.
+pub fn check_unsigned_tx(unsigned_tx: &UnsignedTransaction) -> Result<()> {
+    let mut input_ids: Vec<String> = vec![];
+    for input in unsigned_tx.inputs.as_vec() {
+        let box_id: String = input.box_id.clone().into();
+        if input_ids.contains(&box_id) {
+            return Err(HeadlessDappError::DuplicateInput(box_id));
+        }
+        input_ids.push(box_id);
+    }
+
+    if let Some(data_inputs) = &unsigned_tx.data_inputs {
+        for data_input in data_inputs.as_vec() {
+            let box_id: String = data_input.box_id.clone().into();
+            if input_ids.contains(&box_id) {
+                return Err(HeadlessDappError::InputUsedAsDataInput(box_id));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Computes a stable fingerprint (a blake2b hash, hex-encoded) of an
+/// `UnsignedTransaction` over its inputs, data-inputs, and outputs. The
+/// creation heights of the outputs and the context extensions of the
+/// inputs are excluded, so that a retry of the same Action at a later
+/// height produces the same fingerprint. Intended as an idempotency key
+/// for deduplicating retries and tracking Action instances across
+/// restarts without storing full transactions.
+pub fn unsigned_tx_fingerprint(unsigned_tx: &UnsignedTransaction) -> Result<String> {
+    let mut bytes: Vec<u8> = vec![];
+    for input in unsigned_tx.inputs.as_vec() {
+        let box_id: String = input.box_id.clone().into();
+        bytes.extend_from_slice(box_id.as_bytes());
+    }
+    bytes.push(0);
+    if let Some(data_inputs) = &unsigned_tx.data_inputs {
+        for data_input in data_inputs.as_vec() {
+            let box_id: String = data_input.box_id.clone().into();
+            bytes.extend_from_slice(box_id.as_bytes());
+        }
+    }
+    bytes.push(0);
+    for candidate in unsigned_tx.output_candidates.as_vec() {
+        let candidate = ErgoBoxCandidate {
+            creation_height: 0,
+            ..candidate.clone()
+        };
+        let candidate_bytes = candidate
+            .sigma_serialize_bytes()
+            .map_err(|e| HeadlessDappError::Other(e.to_string()))?;
+        bytes.extend_from_slice(&candidate_bytes);
+    }
+
+    let mut params = Params::new();
+    params.hash_length(32);
+    Ok(params.hash(&bytes).to_hex().to_string())
+}
+
+/// Serializes an `UnsignedTransaction` into the canonical JSON form used by
+/// the Ergo node (ie. for persisting an Action's transaction, or handing it
+/// to a wallet/service which expects node-shaped transactions).
+pub fn unsigned_tx_to_node_json(unsigned_tx: &UnsignedTransaction) -> Result<String> {
+    serde_json::to_string(unsigned_tx).map_err(|e| HeadlessDappError::Other(e.to_string()))
+}
+
+/// Parses an `UnsignedTransaction` from the canonical JSON form used by the
+/// Ergo node, so that transactions built elsewhere (or persisted) can be
+/// loaded for inspection, assembly-spec generation, or EIP-12 conversion.
+pub fn unsigned_tx_from_node_json(node_json: &str) -> Result<UnsignedTransaction> {
+    serde_json::from_str(node_json).map_err(|e| {
+        HeadlessDappError::Other(format!("Failed to parse node transaction JSON: {}", e))
+    })
+}
+
+/// Serializes an `UnsignedTransaction` into the EIP-12 JSON form expected
+/// by the `sign_tx` method of dApp connector wallets. EIP-12 requires the
+/// full data of every input box, and so the `input_boxes` must hold the box
+/// of every input of the transaction. nanoErg values and token amounts are
+/// encoded as strings, as required by the standard.
+pub fn unsigned_tx_to_eip12_json(
+    unsigned_tx: &UnsignedTransaction,
+    input_boxes: &[ErgoBox],
+) -> Result<String> {
+    let to_json_value = |v: serde_json::Result<serde_json::Value>| {
+        v.map_err(|e| HeadlessDappError::Other(e.to_string()))
+    };
+    let mut inputs = vec![];
+    for input in unsigned_tx.inputs.as_vec() {
+        let input_box = input_boxes
+            .iter()
+            .find(|b| b.box_id() == input.box_id)
+            .ok_or_else(|| {
+                HeadlessDappError::Other(format!(
+                    "The box of the input {} was not provided",
+                    String::from(input.box_id.clone())
+                ))
+            })?;
+        let mut input_json = to_json_value(serde_json::to_value(input_box))?;
+        stringify_eip12_amounts(&mut input_json);
+        input_json["extension"] = serde_json::json!({});
+        inputs.push(input_json);
+    }
+    let data_inputs: Vec<serde_json::Value> = unsigned_tx
+        .data_inputs
+        .iter()
+        .flat_map(|d| d.as_vec().iter())
+        .map(|d| serde_json::json!({ "boxId": String::from(d.box_id.clone()) }))
+        .collect();
+    let mut outputs = vec![];
+    for candidate in unsigned_tx.output_candidates.as_vec() {
+        let mut output_json = to_json_value(serde_json::to_value(candidate))?;
+        stringify_eip12_amounts(&mut output_json);
+        outputs.push(output_json);
+    }
+    let tx_json = serde_json::json!({
+        "inputs": inputs,
+        "dataInputs": data_inputs,
+        "outputs": outputs,
+    });
+    Ok(tx_json.to_string())
+}
+
+/// Parses a box in the EIP-12 JSON format (as returned by the
+/// `get_utxos()` of dApp wallet connectors) into an `ErgoBox`. EIP-12
+/// encodes nanoErg values and token amounts as Strings, which are
+/// converted back into numbers before parsing.
+pub fn ergo_box_from_eip12_json(box_json: &serde_json::Value) -> Result<ErgoBox> {
+    let mut box_json = box_json.clone();
+    numberify_eip12_amounts(&mut box_json);
+    serde_json::from_value(box_json).map_err(|e| HeadlessDappError::Other(e.to_string()))
+}
+
+/// Converts the nanoErg value and token amounts of a box which are encoded
+/// as strings (as used by EIP-12) into numbers, as used by the node JSON
+/// form
+pub(crate) fn numberify_eip12_amounts(box_json: &mut serde_json::Value) {
+    let to_number = |v: &mut serde_json::Value| {
+        if let Some(n) = v.as_str().and_then(|s| s.parse::<u64>().ok()) {
+            *v = serde_json::Value::from(n);
+        }
+    };
+    to_number(&mut box_json["value"]);
+    if let Some(assets) = box_json["assets"].as_array_mut() {
+        for asset in assets {
+            to_number(&mut asset["amount"]);
+        }
+    }
+}
+
+/// Converts the nanoErg value and token amounts of a box in node JSON form
+/// into strings, as used by EIP-12
+pub(crate) fn stringify_eip12_amounts(box_json: &mut serde_json::Value) {
+    if let Some(value) = box_json["value"].as_u64() {
+        box_json["value"] = value.to_string().into();
+    }
+    if let Some(assets) = box_json["assets"].as_array_mut() {
+        for asset in assets {
+            if let Some(amount) = asset["amount"].as_u64() {
+                asset["amount"] = amount.to_string().into();
+            }
+        }
+    }
+}
+
 /// Finds all tokens held by `ErgoBox`es (generally from a list of inputs),
 /// which are not in the list of `filter_tokens`. Once found the tokens are
-/// also summed and then returned.
+/// also summed and then returned in the order they were first seen.
+/// Actions which also account for outputs, mints, or burns should use a
+/// `TokenLedger` directly.
 pub fn find_and_sum_other_tokens(
     filter_tokens: &Vec<Token>,
     input_boxes: &Vec<ErgoBox>,
-) -> Vec<Token> {
-    let mut new_tokens: Vec<Token> = vec![];
-    for b in input_boxes {
-        for t in b.tokens.clone() {
-            // If token `t` has an id of one of the known tokens
-            let filter_tokens_check = filter_tokens.iter().any(|tok| tok.token_id == t.token_id);
-            // If token `t` has an id of one of the already added new tokens
-            let new_tokens_check = new_tokens.iter().any(|tok| t.token_id == tok.token_id);
-            if filter_tokens_check {
+) -> Result<Vec<Token>> {
+    let mut ledger = TokenLedger::new();
+    ledger.add_inputs(input_boxes);
+    ledger.exclude_tokens(filter_tokens);
+    ledger.residual()
+}
+
+/// Sums all of the tokens held by the provided `ErgoBox`es, returning
+/// one `Token` per Token ID in the order they were first seen.
+pub fn sum_tokens(boxes: &Vec<ErgoBox>) -> Result<Vec<Token>> {
+    find_and_sum_other_tokens(&vec![], boxes)
+}
+
+/// Sums the nanoErg value of a list of wrapped boxes, which may each be
+/// of a different wrapped box type.
+pub fn sum_nano_ergs(boxes: &[&dyn WrappedBox]) -> Result<NanoErg> {
+    boxes.iter().try_fold(0u64, |acc, b| {
+        acc.checked_add(b.nano_ergs())
+            .ok_or(HeadlessDappError::ArithmeticOverflow)
+    })
+}
+
+/// Sums all of the tokens held by a list of wrapped boxes, which may each
+/// be of a different wrapped box type. Returns one `Token` per Token ID in
+/// the order they were first seen.
+pub fn aggregate_tokens(boxes: &[&dyn WrappedBox]) -> Result<Vec<Token>> {
+    sum_token_lists(&vec![], boxes.iter().map(|b| b.tokens_ref()))
+}
+
+/// Sums the tokens of each of the provided token lists, skipping any tokens
+/// which have the id of one of the `filter_tokens`.
+pub(crate) fn sum_token_lists<'a>(
+    filter_tokens: &Vec<Token>,
+    token_lists: impl Iterator<Item = &'a [Token]>,
+) -> Result<Vec<Token>> {
+    // Token IDs in the order they were first seen
+    let mut token_order: Vec<TokenId> = vec![];
+    // Accumulated amount for every Token ID
+    let mut token_amounts: HashMap<TokenId, u64> = HashMap::new();
+    for tokens in token_lists {
+        for t in tokens {
+            // Skip token `t` if it has an id of one of the filter tokens
+            if filter_tokens.iter().any(|tok| tok.token_id == t.token_id) {
                 continue;
-            } else if new_tokens_check {
-                new_tokens = new_tokens
-                    .iter()
-                    .map(|tok| {
-                        if tok.token_id == t.token_id {
-                            // Replace the previous token with a new struct
-                            // that has the new total amount.
-                            Token {
-                                token_id: tok.token_id.clone(),
-                                amount: TokenAmount::try_from(
-                                    u64::from(tok.amount) + u64::from(t.amount),
-                                )
-                                // This unwrap is safe due to the values being
-                                // added originally coming from `TokenAmount`s
-                                // and the TokenIDs being the same.
-                                .unwrap(),
-                            }
-                        } else {
-                            tok.clone()
-                        }
-                    })
-                    .collect();
-            } else {
-                new_tokens.push(t)
             }
+            let amount = token_amounts.entry(t.token_id.clone()).or_insert_with(|| {
+                token_order.push(t.token_id.clone());
+                0
+            });
+            *amount = amount
+                .checked_add(u64::from(t.amount))
+                .ok_or(HeadlessDappError::TokenAmountOverflow)?;
         }
     }
-    new_tokens
+
+    token_order
+        .into_iter()
+        .map(|token_id| {
+            let amount = TokenAmount::try_from(token_amounts[&token_id])
+                .map_err(|_| HeadlessDappError::TokenAmountOverflow)?;
+            Ok(Token { token_id, amount })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output_builders::TxFeeBox;
+    use crate::test_utils::{test_box, TEST_ADDRESS};
+
+    fn build_tx(input_box: &ErgoBox, value: NanoErg, height: BlockHeight) -> UnsignedTransaction {
+        let outputs = vec![
+            create_candidate(value, &TEST_ADDRESS.to_string(), &vec![], &vec![], height).unwrap(),
+            TxFeeBox::output_candidate(1000000, height).unwrap(),
+        ];
+        new_unsigned_tx(vec![input_box.clone().into()], vec![], outputs).unwrap()
+    }
+
+    #[test]
+    fn fingerprint_is_stable_across_rebuilds() {
+        let input_box = test_box(3000000, TEST_ADDRESS, vec![], vec![]);
+        let fingerprint = unsigned_tx_fingerprint(&build_tx(&input_box, 2000000, 10)).unwrap();
+        assert_eq!(
+            unsigned_tx_fingerprint(&build_tx(&input_box, 2000000, 10)).unwrap(),
+            fingerprint
+        );
+        // The creation height of the outputs is ignored
+        assert_eq!(
+            unsigned_tx_fingerprint(&build_tx(&input_box, 2000000, 250)).unwrap(),
+            fingerprint
+        );
+        assert_ne!(
+            unsigned_tx_fingerprint(&build_tx(&input_box, 1500000, 10)).unwrap(),
+            fingerprint
+        );
+    }
 }
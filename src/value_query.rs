@@ -0,0 +1,167 @@
+/// This file holds `TotalValueQuery` which computes the total value locked
+/// in boxes matching a `BoxSpec` (ie. a protocol stage) by summing the
+/// explorer results page by page, never holding more than a single page
+/// of boxes in memory. As the framework is headless, fetching each page is
+/// left to the front-end/bot.
+use crate::box_spec::{parse_explorer_response_boxes, BoxSpec};
+use crate::cancellation::CancellationToken;
+use crate::error::{HeadlessDappError, Result};
+use crate::pagination::{explorer_response_total, PageCursor};
+use crate::tx_creation::sum_token_lists;
+use crate::NanoErg;
+use ergo_lib::ergotree_ir::chain::token::Token;
+
+/// The default number of boxes requested per explorer page
+pub const DEFAULT_PAGE_SIZE: u64 = 100;
+
+/// Creates a `TotalValueQuery` for the boxes matching the given `BoxSpec`
+pub fn total_value_matching(spec: &BoxSpec) -> TotalValueQuery {
+    TotalValueQuery::new(spec, DEFAULT_PAGE_SIZE)
+}
+
+/// A paginated query which sums the nanoErgs and tokens of all boxes
+/// matching a `BoxSpec`. Use `next_endpoint()` to acquire the url of the
+/// next page, fetch it, and provide the response body to
+/// `process_page()` until `next_endpoint()` returns `None`.
+#[derive(Clone)]
+pub struct TotalValueQuery {
+    spec: BoxSpec,
+    cursor: PageCursor,
+    total_nano_ergs: NanoErg,
+    box_count: u64,
+    tokens: Vec<Token>,
+    cancellation: Option<CancellationToken>,
+}
+
+impl TotalValueQuery {
+    /// Create a new `TotalValueQuery` which requests `page_size` boxes
+    /// per explorer page
+    pub fn new(spec: &BoxSpec, page_size: u64) -> TotalValueQuery {
+        TotalValueQuery {
+            spec: spec.clone(),
+            cursor: PageCursor::first(page_size),
+            total_nano_ergs: 0,
+            box_count: 0,
+            tokens: vec![],
+            cancellation: None,
+        }
+    }
+
+    /// Resumes an interrupted query from the provided `PageCursor`. The
+    /// totals of the already processed pages are not restored.
+    pub fn with_cursor(mut self, cursor: PageCursor) -> Self {
+        self.cursor = cursor;
+        self
+    }
+
+    /// Uses the provided `CancellationToken` to allow aborting the query.
+    /// Once cancelled, `next_endpoint()` and `process_page()` error with
+    /// `HeadlessDappError::Cancelled`.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// The explorer endpoint of the next page to fetch, or `None` if all
+    /// pages have been processed.
+    pub fn next_endpoint(&self, explorer_api_url: &str) -> Result<Option<String>> {
+        self.check_cancelled()?;
+        if !self.cursor.has_more() {
+            return Ok(None);
+        }
+        self.spec
+            .explorer_endpoint_for_page(explorer_api_url, &self.cursor)
+            .map(Some)
+    }
+
+    /// Processes the response body of the page acquired from
+    /// `next_endpoint()`, adding the value of all boxes which match the
+    /// `BoxSpec` to the totals.
+    pub fn process_page(&mut self, explorer_response_body: &str) -> Result<()> {
+        self.check_cancelled()?;
+        let box_list = parse_explorer_response_boxes(explorer_response_body)?;
+        let fetched = box_list.len() as u64;
+        let matched: Vec<_> = box_list
+            .into_iter()
+            .filter(|b| self.spec.verify_box(b).is_ok())
+            .collect();
+
+        for b in &matched {
+            self.total_nano_ergs = self
+                .total_nano_ergs
+                .checked_add(*b.value.as_u64())
+                .ok_or(HeadlessDappError::ArithmeticOverflow)?;
+        }
+        self.box_count += matched.len() as u64;
+        let token_lists = matched.iter().map(|b| &b.tokens[..]);
+        self.tokens = sum_token_lists(&vec![], token_lists.chain(Some(&self.tokens[..])))?;
+
+        self.cursor = self
+            .cursor
+            .advanced(fetched, explorer_response_total(explorer_response_body));
+        Ok(())
+    }
+
+    /// Errors if the `CancellationToken` of the query has been cancelled
+    fn check_cancelled(&self) -> Result<()> {
+        match &self.cancellation {
+            Some(token) => token.check(),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether all pages have been processed
+    pub fn is_finished(&self) -> bool {
+        !self.cursor.has_more()
+    }
+
+    /// The `PageCursor` of the next page to fetch
+    pub fn cursor(&self) -> PageCursor {
+        self.cursor
+    }
+
+    /// The total nanoErgs held by the matching boxes processed so far
+    pub fn total_nano_ergs(&self) -> NanoErg {
+        self.total_nano_ergs
+    }
+
+    /// The number of matching boxes processed so far
+    pub fn box_count(&self) -> u64 {
+        self.box_count
+    }
+
+    /// The summed tokens held by the matching boxes processed so far
+    pub fn tokens(&self) -> Vec<Token> {
+        self.tokens.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paginates_until_short_page() {
+        let spec = BoxSpec::new(
+            Some("9hy2fs8KKPr2MZNuhUcs8vxkq4TLLX9gftqHXV4Kp7iKFXYe6Ww".to_string()),
+            None,
+            vec![],
+            vec![],
+        );
+        let mut query = TotalValueQuery::new(&spec, 50);
+        assert_eq!(
+            query.next_endpoint("https://api.ergoplatform.com/api").unwrap(),
+            Some("https://api.ergoplatform.com/api/v1/boxes/unspent/byAddress/9hy2fs8KKPr2MZNuhUcs8vxkq4TLLX9gftqHXV4Kp7iKFXYe6Ww?offset=0&limit=50".to_string())
+        );
+        query.process_page("{\"items\": [], \"total\": 0}").unwrap();
+        assert!(query.is_finished());
+        assert_eq!(query.total_nano_ergs(), 0);
+        assert_eq!(query.box_count(), 0);
+        assert_eq!(
+            query
+                .next_endpoint("https://api.ergoplatform.com/api")
+                .unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,258 @@
+/// This file holds the `WalletReport` utility which categorizes a user's
+/// unspent boxes into dust, token-carrying boxes, and spendable Ergs, and
+/// suggests consolidation actions. It is WASM-compatible so that
+/// wallet-adjacent dApps can surface it directly to users.
+use crate::NanoErg;
+use ergo_lib::ergotree_ir::chain::digest32::Digest32;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use ergo_lib_wasm::box_coll::ErgoBoxes;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+
+/// The default value below which a box without tokens is considered dust
+pub const DEFAULT_DUST_THRESHOLD: NanoErg = 1000000;
+/// The number of spendable Erg boxes above which consolidating is suggested
+pub const ERGS_BOX_CONSOLIDATION_THRESHOLD: usize = 20;
+
+/// A suggested consolidation action for a wallet
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "type")]
+pub enum ConsolidationSuggestion {
+    /// Merge all dust boxes into a single box
+    ConsolidateDust {
+        box_count: usize,
+        nano_ergs: NanoErg,
+    },
+    /// Merge the many spendable Erg boxes into fewer boxes
+    ConsolidateErgs {
+        box_count: usize,
+        nano_ergs: NanoErg,
+    },
+    /// Merge the boxes which hold the same token into a single box
+    ConsolidateToken { token_id: String, box_count: usize },
+}
+
+/// A report on the health of a wallet based on its unspent boxes.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct WalletReport {
+    dust_boxes: Vec<ErgoBox>,
+    token_boxes: Vec<ErgoBox>,
+    ergs_boxes: Vec<ErgoBox>,
+}
+
+/// WASM-compatible WalletReport Methods
+#[wasm_bindgen]
+impl WalletReport {
+    /// WASM wrapper for `new()` using the default dust threshold
+    #[wasm_bindgen(constructor)]
+    pub fn w_new(unspent_boxes: &ErgoBoxes) -> WalletReport {
+        let boxes: Vec<ErgoBox> = unspent_boxes.clone().into();
+        WalletReport::new(&boxes, DEFAULT_DUST_THRESHOLD)
+    }
+
+    /// The number of dust boxes
+    #[wasm_bindgen]
+    pub fn dust_box_count(&self) -> usize {
+        self.dust_boxes.len()
+    }
+
+    /// The total nanoErgs held in dust boxes
+    #[wasm_bindgen]
+    pub fn dust_nano_ergs(&self) -> NanoErg {
+        sum_nano_ergs(&self.dust_boxes)
+    }
+
+    /// The number of boxes which hold tokens
+    #[wasm_bindgen]
+    pub fn token_box_count(&self) -> usize {
+        self.token_boxes.len()
+    }
+
+    /// The total nanoErgs held in boxes which also hold tokens
+    #[wasm_bindgen]
+    pub fn token_box_nano_ergs(&self) -> NanoErg {
+        sum_nano_ergs(&self.token_boxes)
+    }
+
+    /// The number of boxes which only hold spendable Ergs
+    #[wasm_bindgen]
+    pub fn ergs_box_count(&self) -> usize {
+        self.ergs_boxes.len()
+    }
+
+    /// The total nanoErgs held in boxes which only hold spendable Ergs
+    #[wasm_bindgen]
+    pub fn spendable_nano_ergs(&self) -> NanoErg {
+        sum_nano_ergs(&self.ergs_boxes)
+    }
+
+    /// The total nanoErgs held across all of the boxes
+    #[wasm_bindgen]
+    pub fn total_nano_ergs(&self) -> NanoErg {
+        self.dust_nano_ergs() + self.token_box_nano_ergs() + self.spendable_nano_ergs()
+    }
+
+    /// The suggested consolidation actions as a JSON `String`
+    #[wasm_bindgen]
+    pub fn suggestions_json(&self) -> String {
+        // Serializing a list of simple enums cannot fail
+        serde_json::to_string(&self.suggestions()).unwrap()
+    }
+}
+
+/// Rust WalletReport Methods
+impl WalletReport {
+    /// Create a new `WalletReport` from a user's unspent boxes. Boxes
+    /// without tokens holding less than `dust_threshold` nanoErgs are
+    /// considered dust.
+    pub fn new(unspent_boxes: &Vec<ErgoBox>, dust_threshold: NanoErg) -> WalletReport {
+        let mut report = WalletReport {
+            dust_boxes: vec![],
+            token_boxes: vec![],
+            ergs_boxes: vec![],
+        };
+        for b in unspent_boxes {
+            if !b.tokens.is_empty() {
+                report.token_boxes.push(b.clone());
+            } else if *b.value.as_u64() < dust_threshold {
+                report.dust_boxes.push(b.clone());
+            } else {
+                report.ergs_boxes.push(b.clone());
+            }
+        }
+        report
+    }
+
+    /// The boxes which are considered dust
+    pub fn dust_boxes(&self) -> &Vec<ErgoBox> {
+        &self.dust_boxes
+    }
+
+    /// The boxes which hold tokens
+    pub fn token_boxes(&self) -> &Vec<ErgoBox> {
+        &self.token_boxes
+    }
+
+    /// The boxes which only hold spendable Ergs
+    pub fn ergs_boxes(&self) -> &Vec<ErgoBox> {
+        &self.ergs_boxes
+    }
+
+    /// The suggested consolidation actions for the wallet
+    pub fn suggestions(&self) -> Vec<ConsolidationSuggestion> {
+        let mut suggestions = vec![];
+        if self.dust_boxes.len() > 1 {
+            suggestions.push(ConsolidationSuggestion::ConsolidateDust {
+                box_count: self.dust_boxes.len(),
+                nano_ergs: self.dust_nano_ergs(),
+            });
+        }
+        if self.ergs_boxes.len() > ERGS_BOX_CONSOLIDATION_THRESHOLD {
+            suggestions.push(ConsolidationSuggestion::ConsolidateErgs {
+                box_count: self.ergs_boxes.len(),
+                nano_ergs: self.spendable_nano_ergs(),
+            });
+        }
+
+        // Count the number of boxes each token is spread across
+        let mut token_box_counts: BTreeMap<String, usize> = BTreeMap::new();
+        for b in &self.token_boxes {
+            for t in &b.tokens {
+                let token_id_digest32: Digest32 = t.token_id.clone().into();
+                *token_box_counts
+                    .entry(token_id_digest32.into())
+                    .or_insert(0) += 1;
+            }
+        }
+        for (token_id, box_count) in token_box_counts {
+            if box_count > 1 {
+                suggestions.push(ConsolidationSuggestion::ConsolidateToken {
+                    token_id,
+                    box_count,
+                });
+            }
+        }
+        suggestions
+    }
+}
+
+/// Sums the nanoErg value of a list of `ErgoBox`es
+fn sum_nano_ergs(boxes: &Vec<ErgoBox>) -> NanoErg {
+    boxes.iter().map(|b| *b.value.as_u64()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::build_token;
+    use crate::test_utils::{test_box_at, TEST_ADDRESS, TEST_TOKEN_ID};
+
+    fn build_wallet(ergs_box_count: u16) -> Vec<ErgoBox> {
+        let token = build_token(TEST_TOKEN_ID, 1).unwrap();
+        let mut boxes = vec![
+            test_box_at(500000, TEST_ADDRESS, vec![], vec![], 0, 0),
+            test_box_at(600000, TEST_ADDRESS, vec![], vec![], 0, 1),
+            test_box_at(1000000, TEST_ADDRESS, vec![token.clone()], vec![], 0, 2),
+            test_box_at(1000000, TEST_ADDRESS, vec![token], vec![], 0, 3),
+        ];
+        for i in 0..ergs_box_count {
+            boxes.push(test_box_at(2000000, TEST_ADDRESS, vec![], vec![], 0, 4 + i));
+        }
+        boxes
+    }
+
+    #[test]
+    fn categorizes_boxes() {
+        let report = WalletReport::new(&build_wallet(3), DEFAULT_DUST_THRESHOLD);
+        assert_eq!(report.dust_box_count(), 2);
+        assert_eq!(report.dust_nano_ergs(), 1100000);
+        assert_eq!(report.token_box_count(), 2);
+        assert_eq!(report.token_box_nano_ergs(), 2000000);
+        assert_eq!(report.ergs_box_count(), 3);
+        assert_eq!(report.spendable_nano_ergs(), 6000000);
+        assert_eq!(report.total_nano_ergs(), 9100000);
+    }
+
+    #[test]
+    fn suggests_consolidation() {
+        let report = WalletReport::new(&build_wallet(3), DEFAULT_DUST_THRESHOLD);
+        assert_eq!(
+            report.suggestions(),
+            vec![
+                ConsolidationSuggestion::ConsolidateDust {
+                    box_count: 2,
+                    nano_ergs: 1100000,
+                },
+                ConsolidationSuggestion::ConsolidateToken {
+                    token_id: TEST_TOKEN_ID.to_string(),
+                    box_count: 2,
+                },
+            ]
+        );
+
+        let report = WalletReport::new(
+            &build_wallet(ERGS_BOX_CONSOLIDATION_THRESHOLD as u16 + 1),
+            DEFAULT_DUST_THRESHOLD,
+        );
+        assert!(report
+            .suggestions()
+            .contains(&ConsolidationSuggestion::ConsolidateErgs {
+                box_count: ERGS_BOX_CONSOLIDATION_THRESHOLD + 1,
+                nano_ergs: 2000000 * (ERGS_BOX_CONSOLIDATION_THRESHOLD as u64 + 1),
+            }));
+
+        let json: serde_json::Value = serde_json::from_str(&report.suggestions_json()).unwrap();
+        assert_eq!(json[0]["type"], "ConsolidateDust");
+        assert_eq!(json[0]["box_count"], 2);
+    }
+
+    #[test]
+    fn dust_threshold_is_configurable() {
+        let report = WalletReport::new(&build_wallet(3), 100000);
+        assert_eq!(report.dust_box_count(), 0);
+        assert_eq!(report.ergs_box_count(), 5);
+        assert!(report.dust_boxes().is_empty());
+    }
+}
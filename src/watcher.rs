@@ -0,0 +1,206 @@
+/// This file holds the `AddressWatcher` preset which monitors an address
+/// (P2PK or P2S) for newly received boxes holding at least a threshold of
+/// nanoErgs. This is intended for deposit-detection flows, where a
+/// headless dApp must react to payments sent to it. As the framework is
+/// headless, polling the explorer is left to the front-end/bot.
+use crate::box_spec::BoxSpec;
+use crate::box_traits::WrappedBox;
+use crate::error::{HeadlessDappError, Result};
+use crate::explorer_url::ExplorerUrl;
+use crate::{ErgoAddressString, NanoErg, TxId};
+use ergo_lib::ergotree_ir::chain::digest32::Digest32;
+use ergo_lib::ergotree_ir::chain::ergo_box::ErgoBox;
+use std::collections::HashSet;
+
+/// Monitors an address for newly received boxes above a threshold
+#[derive(Clone)]
+pub struct AddressWatcher {
+    box_spec: BoxSpec,
+    seen_box_ids: HashSet<String>,
+}
+
+impl AddressWatcher {
+    /// Create a new `AddressWatcher` for boxes at `address` holding at
+    /// least `min_nano_ergs`
+    pub fn new(address: &ErgoAddressString, min_nano_ergs: NanoErg) -> AddressWatcher {
+        let box_spec = BoxSpec::new(
+            Some(address.clone()),
+            Some(min_nano_ergs..u64::MAX),
+            vec![],
+            vec![],
+        );
+        AddressWatcher {
+            box_spec,
+            seen_box_ids: HashSet::new(),
+        }
+    }
+
+    /// The `BoxSpec` which received boxes are matched against
+    pub fn box_spec(&self) -> BoxSpec {
+        self.box_spec.clone()
+    }
+
+    /// Marks Box IDs as already seen (ie. ones which were processed before
+    /// a restart) so that they are not reported again
+    pub fn with_seen_box_ids(mut self, box_ids: &[String]) -> Self {
+        self.seen_box_ids.extend(box_ids.iter().cloned());
+        self
+    }
+
+    /// Generates the explorer endpoint which is polled for received boxes
+    pub fn explorer_endpoint(&self, explorer_api_url: &str) -> Result<String> {
+        self.box_spec.explorer_endpoint(explorer_api_url)
+    }
+
+    /// Processes the explorer response body from the endpoint generated
+    /// by `explorer_endpoint()`, returning only the boxes which match the
+    /// spec and have not been acknowledged via `ack()`. Boxes are reported
+    /// again by every poll until they are acknowledged, so that a deposit
+    /// whose processing failed is not lost.
+    pub fn poll(&self, explorer_response_body: &str) -> Result<Vec<ReceivedBox>> {
+        let boxes = self
+            .box_spec
+            .process_explorer_response_sorted(explorer_response_body)?;
+        Ok(boxes
+            .into_iter()
+            .filter(|b| {
+                let box_id: String = b.box_id().into();
+                !self.seen_box_ids.contains(&box_id)
+            })
+            .map(ReceivedBox::new)
+            .collect())
+    }
+
+    /// Acknowledges that the boxes with the provided Box IDs have been
+    /// processed, so that they are not returned by future polls
+    pub fn ack(&mut self, box_ids: &[String]) {
+        self.seen_box_ids.extend(box_ids.iter().cloned());
+    }
+
+    /// The Box IDs which have been acknowledged, to be persisted and
+    /// restored via `with_seen_box_ids()` across restarts
+    pub fn seen_box_ids(&self) -> Vec<String> {
+        self.seen_box_ids.iter().cloned().collect()
+    }
+}
+
+/// A box which was received at a watched address, along with metadata
+/// about the transaction which funded it.
+#[derive(Clone, Debug)]
+pub struct ReceivedBox {
+    ergo_box: ErgoBox,
+    sender: Option<ErgoAddressString>,
+}
+
+impl WrappedBox for ReceivedBox {
+    fn get_box_ref(&self) -> &ErgoBox {
+        &self.ergo_box
+    }
+}
+
+impl ReceivedBox {
+    /// Create a new `ReceivedBox` with no sender inferred yet
+    pub fn new(ergo_box: ErgoBox) -> ReceivedBox {
+        ReceivedBox {
+            ergo_box,
+            sender: None,
+        }
+    }
+
+    /// The Transaction ID of the transaction which created the box
+    pub fn funding_tx_id(&self) -> TxId {
+        let tx_id_digest32: Digest32 = self.ergo_box.transaction_id.0.clone();
+        tx_id_digest32.into()
+    }
+
+    /// Generates the explorer endpoint of the funding transaction, whose
+    /// response body is provided to `infer_sender()`
+    pub fn funding_tx_endpoint(&self, explorer_api_url: &str) -> String {
+        ExplorerUrl::new(explorer_api_url)
+            .path("v1/transactions")
+            .segment(&self.funding_tx_id())
+            .build()
+    }
+
+    /// Infers the sender of the box as the address of the first input of
+    /// the funding transaction, using the explorer response body from the
+    /// endpoint generated by `funding_tx_endpoint()`.
+    pub fn infer_sender(&mut self, funding_tx_response_body: &str) -> Result<ErgoAddressString> {
+        let json = json::parse(funding_tx_response_body).map_err(|_| {
+            HeadlessDappError::Other(
+                "Failed to extract json from Ergo Explorer Backend API Response".to_string(),
+            )
+        })?;
+        let sender = json["inputs"][0]["address"]
+            .as_str()
+            .ok_or_else(|| {
+                HeadlessDappError::Other(
+                    "The funding transaction response holds no input address".to_string(),
+                )
+            })?
+            .to_string();
+        self.sender = Some(sender.clone());
+        Ok(sender)
+    }
+
+    /// The inferred sender of the box, if `infer_sender()` was used
+    pub fn sender(&self) -> Option<ErgoAddressString> {
+        self.sender.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{test_box_at, TEST_ADDRESS};
+
+    fn build_explorer_response(boxes: &Vec<ErgoBox>) -> String {
+        let items: Vec<String> = boxes
+            .iter()
+            .map(|b| serde_json::to_string(b).unwrap())
+            .collect();
+        format!("{{\"items\": [{}]}}", items.join(","))
+    }
+
+    #[test]
+    fn poll_reports_boxes_until_acknowledged() {
+        let deposit = test_box_at(5000000, TEST_ADDRESS, vec![], vec![], 10, 0);
+        let dust = test_box_at(100000, TEST_ADDRESS, vec![], vec![], 10, 1);
+        let response = build_explorer_response(&vec![deposit.clone(), dust]);
+        let mut watcher = AddressWatcher::new(&TEST_ADDRESS.to_string(), 1000000);
+
+        let deposit_id: String = deposit.box_id().into();
+        let received = watcher.poll(&response).unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].box_id(), deposit_id);
+        // Not acknowledged, so reported again
+        assert_eq!(watcher.poll(&response).unwrap().len(), 1);
+
+        watcher.ack(&[received[0].box_id()]);
+        assert!(watcher.poll(&response).unwrap().is_empty());
+        assert_eq!(watcher.seen_box_ids(), vec![received[0].box_id()]);
+    }
+
+    #[test]
+    fn restored_box_ids_are_not_reported() {
+        let deposit = test_box_at(5000000, TEST_ADDRESS, vec![], vec![], 10, 0);
+        let response = build_explorer_response(&vec![deposit.clone()]);
+        let box_id: String = deposit.box_id().into();
+        let watcher =
+            AddressWatcher::new(&TEST_ADDRESS.to_string(), 1000000).with_seen_box_ids(&[box_id]);
+        assert!(watcher.poll(&response).unwrap().is_empty());
+    }
+
+    #[test]
+    fn infers_sender_from_funding_tx() {
+        let deposit = test_box_at(5000000, TEST_ADDRESS, vec![], vec![], 10, 0);
+        let mut received = ReceivedBox::new(deposit);
+        assert!(received
+            .funding_tx_endpoint("https://api.ergoplatform.com/api")
+            .ends_with(&format!("v1/transactions/{}", received.funding_tx_id())));
+        let body = format!("{{\"inputs\": [{{\"address\": \"{}\"}}]}}", TEST_ADDRESS);
+        assert_eq!(received.infer_sender(&body).unwrap(), TEST_ADDRESS);
+        assert_eq!(received.sender(), Some(TEST_ADDRESS.to_string()));
+        assert!(received.infer_sender("{\"inputs\": []}").is_err());
+    }
+}
@@ -44,9 +44,79 @@ fn main() {
 
             println!("Bootstrap Math Bounty Box Tx ID: {}", tx_id);
         }
+        // User wishes to solve the math problem of a `MathBountyBox` and
+        // withdraw the bounty inside
+        else if args[1] == "solve" {
+            // Taking user input as the answer to the math problem
+            let math_problem_answer = args[2].parse::<u64>().unwrap();
+
+            // Acquire the math_bounty_box
+            let math_bounty_box = get_math_bounty_box();
+
+            // Acquire the ergs_box_for_fee
+            let ergs_box_for_fee = get_ergs_box_for_solve_fee(user_address.clone(), tx_fee);
+
+            // Create the "Solve Math Problem" action unsigned transaction
+            let unsigned_tx = MathBountyProtocol::action_solve_math_problem(
+                math_problem_answer,
+                math_bounty_box,
+                block_height,
+                tx_fee,
+                ergs_box_for_fee,
+                user_address,
+            );
+
+            // Sign and submit the transaction
+            let tx_id = node.sign_and_submit_transaction(&unsigned_tx).unwrap();
+
+            println!("Solve Math Problem Tx ID: {}", tx_id);
+        }
     }
 }
 
+pub fn get_math_bounty_box() -> MathBountyBox {
+    // Acquire the Ergo Explorer API endpoint in order to find a
+    // `MathBountyBox` using its `BoxSpec`.
+    let math_bounty_box_url = MathBountyBox::box_spec()
+        .explorer_endpoint("https://api.ergoplatform.com/api")
+        .unwrap();
+    // Make a get request to the Ergo Explorer API endpoint
+    let get_response = get(&math_bounty_box_url).unwrap().text().unwrap();
+    // Process the `get_response` into `MathBountyBox`es which match
+    // the `BoxSpec` of the `MathBountyBox`
+    let list_of_math_bounty_boxes =
+        MathBountyBox::process_explorer_response(&get_response).unwrap();
+
+    // Return the first `MathBountyBox` from the list
+    list_of_math_bounty_boxes[0].clone()
+}
+
+pub fn get_ergs_box_for_solve_fee(user_address: String, tx_fee: u64) -> ErgsBox {
+    // Take the generalized `BoxSpec` from an `ErgsBox` and modify it
+    // for our use case. Specifically change the address to be our
+    // user's address, and change the value_range so that the box
+    // has enough to cover the fee amount. Unlike when creating a bounty,
+    // the other input is locked under the Math Bounty contract, and so
+    // any of the user's boxes can be used.
+    let ergs_box_for_fee_spec = ErgsBox::box_spec()
+        .modified_address(Some(user_address))
+        .modified_value_range(Some(tx_fee..u64::MAX));
+    // Acquire the Ergo Explorer API endpoint in order to find
+    // the our `ergs_box_for_fee`.
+    let ergs_box_for_fee_url = ergs_box_for_fee_spec
+        .explorer_endpoint("https://api.ergoplatform.com/api")
+        .unwrap();
+    // Make a get request to the Ergo Explorer API endpoint
+    let get_response = get(&ergs_box_for_fee_url).unwrap().text().unwrap();
+    // Process the `get_response` into `ErgsBox`es which match our
+    // `ergs_box_for_fee_spec`
+    let list_of_ergs_boxes =
+        ErgsBox::process_explorer_response_custom(&get_response, ergs_box_for_fee_spec).unwrap();
+
+    // Return the first `ErgsBox` from the list
+    list_of_ergs_boxes[0].clone()
+}
+
 pub fn get_ergs_box_for_bounty(user_address: String, bounty_amount_in_nano_ergs: u64) -> ErgsBox {
     // Take the generalized `BoxSpec` from an `ErgsBox` and modify it
     // for our use case. Specifically change the address to be our
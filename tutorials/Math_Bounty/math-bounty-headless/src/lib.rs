@@ -1,8 +1,12 @@
 use std::convert::TryInto;
 
+use ergo_headless_dapp_framework::action_params::min_output_value;
+use ergo_headless_dapp_framework::prelude::WErgoBox;
 pub use ergo_headless_dapp_framework::*;
+use wasm_bindgen::prelude::*;
 
-#[derive(Debug, Clone, WrapBox, SpecBox)]
+#[wasm_bindgen]
+#[derive(Debug, Clone, WrapBox, SpecBox, WASMBox)]
 pub struct MathBountyBox {
     ergo_box: ErgoBox,
 }
@@ -14,8 +18,70 @@ impl SpecifiedBox for MathBountyBox {
     }
 }
 
+#[wasm_bindgen]
 pub struct MathBountyProtocol {}
 
+/// WASM wrappers of the Actions which take the input boxes as `WErgoBox`es
+/// and return the unsigned transaction as EIP-12 JSON, ready to be passed
+/// to the `sign_tx` method of a dApp connector wallet.
+#[wasm_bindgen]
+impl MathBountyProtocol {
+    /// WASM wrapper for `action_solve_math_problem()`
+    #[wasm_bindgen]
+    pub fn w_solve(
+        math_problem_answer: u64,
+        math_bounty_box: WErgoBox,
+        current_height: u64,
+        transaction_fee: u64,
+        ergs_box_for_fee: WErgoBox,
+        user_address: String,
+    ) -> std::result::Result<String, JsValue> {
+        let math_bounty_box = MathBountyBox::new(&math_bounty_box.into()).map_err(to_js_error)?;
+        let ergs_box_for_fee = ErgsBox::new(&ergs_box_for_fee.into()).map_err(to_js_error)?;
+        let input_boxes = vec![math_bounty_box.get_box(), ergs_box_for_fee.get_box()];
+        let ctx =
+            ActionContext::new(current_height, &user_address).with_transaction_fee(transaction_fee);
+        let unsigned_tx = Self::solve(&ctx, math_problem_answer, math_bounty_box, ergs_box_for_fee)
+            .map_err(to_js_error)?;
+        unsigned_tx_to_eip12_json(&unsigned_tx, &input_boxes).map_err(to_js_error)
+    }
+
+    /// WASM wrapper for `action_bootstrap_math_bounty_box()`
+    #[wasm_bindgen]
+    pub fn w_bootstrap(
+        bounty_amount_in_nano_ergs: u64,
+        ergs_box_for_bounty: WErgoBox,
+        current_height: u64,
+        transaction_fee: u64,
+        ergs_box_for_fee: WErgoBox,
+        user_address: String,
+    ) -> std::result::Result<String, JsValue> {
+        let ergs_box_for_bounty = ErgsBox::new(&ergs_box_for_bounty.into()).map_err(to_js_error)?;
+        let ergs_box_for_fee = ErgsBox::new(&ergs_box_for_fee.into()).map_err(to_js_error)?;
+        let input_boxes = vec![ergs_box_for_bounty.get_box(), ergs_box_for_fee.get_box()];
+        let ctx =
+            ActionContext::new(current_height, &user_address).with_transaction_fee(transaction_fee);
+        let unsigned_tx = Self::bootstrap(
+            &ctx,
+            bounty_amount_in_nano_ergs,
+            ergs_box_for_bounty,
+            ergs_box_for_fee,
+        )
+        .map_err(to_js_error)?;
+        unsigned_tx_to_eip12_json(&unsigned_tx, &input_boxes).map_err(to_js_error)
+    }
+}
+
+/// Converts a framework error into a `JsValue` for the WASM wrappers
+fn to_js_error(e: HeadlessDappError) -> JsValue {
+    JsValue::from_str(&format! {"{:?}", e})
+}
+
+/// Converts an ergo-lib error into a framework error
+fn to_other_error<E: std::fmt::Display>(e: E) -> HeadlessDappError {
+    HeadlessDappError::Other(e.to_string())
+}
+
 impl MathBountyProtocol {
     /// An action to solve the math problem inside of a `MathBountyBox`
     /// and thus to withdraw the bounty nanoErgs inside as a reward.
@@ -27,43 +93,64 @@ impl MathBountyProtocol {
         ergs_box_for_fee: ErgsBox,
         user_address: String,
     ) -> UnsignedTransaction {
+        let ctx =
+            ActionContext::new(current_height, &user_address).with_transaction_fee(transaction_fee);
+        Self::solve(&ctx, math_problem_answer, math_bounty_box, ergs_box_for_fee).unwrap()
+    }
+
+    /// `action_solve_math_problem()` taking the shared parameters of the
+    /// Action from an `ActionContext`. Errors naming the invalid parameter
+    /// if the bounty cannot cover the tx fee.
+    pub fn solve(
+        ctx: &ActionContext,
+        math_problem_answer: u64,
+        math_bounty_box: MathBountyBox,
+        ergs_box_for_fee: ErgsBox,
+    ) -> Result<UnsignedTransaction> {
+        // The withdrawn bounty must still be a valid box after paying the fee
+        ActionParams::new("solve")
+            .covers(
+                "math_bounty_box",
+                math_bounty_box.nano_ergs(),
+                &[min_output_value(), ctx.transaction_fee],
+            )
+            .check()?;
+
         let tx_inputs = vec![
             math_bounty_box.as_unsigned_input(),
             ergs_box_for_fee.as_unsigned_input(),
         ];
 
         // Calculating the leftover bounty after paying for the tx fee
-        let bounty_after_fee = math_bounty_box.nano_ergs() - transaction_fee;
+        let bounty_after_fee = math_bounty_box.nano_ergs() - ctx.transaction_fee;
 
         // Converting our `math_problem_answer` from a `u64` to a `Constant`.
         // This is the datatype that registers are encoded as inside of
         // `ErgoBox`es. Note: register integers are signed, which is why
-        // we converted first to an `i64`, and then into a `Constant`.
-        let r4 = Constant::from(math_problem_answer as i64);
+        // we converted first to an `i64`, and then into a `Long` `Constant`.
+        let r4 = Constant::long(math_problem_answer as i64);
 
         // A candidate with the withdrawn bounty funds +  the answer to the
         // math problem being held in R4.
         let withdrawn_bounty_candidate = create_candidate(
             bounty_after_fee,
-            &user_address,
+            &ctx.change_address,
             &vec![],
             &vec![r4],
-            current_height,
-        )
-        .unwrap();
+            ctx.current_height,
+        )?;
 
         // Create the Transaction Fee box candidate
-        let transaction_fee_candidate =
-            TxFeeBox::output_candidate(transaction_fee, current_height).unwrap();
+        let transaction_fee_candidate = ctx.tx_fee_output()?;
 
         let output_candidates = vec![withdrawn_bounty_candidate, transaction_fee_candidate];
 
         UnsignedTransaction::new(
-            tx_inputs.try_into().unwrap(),
+            tx_inputs.try_into().map_err(to_other_error)?,
             None,
-            output_candidates.try_into().unwrap(),
+            output_candidates.try_into().map_err(to_other_error)?,
         )
-        .unwrap()
+        .map_err(to_other_error)
     }
 
     /// A bootstrap action which allows a user to create a `MathBountyBox`
@@ -76,14 +163,52 @@ impl MathBountyProtocol {
         ergs_box_for_fee: ErgsBox,
         user_address: String,
     ) -> UnsignedTransaction {
+        let ctx =
+            ActionContext::new(current_height, &user_address).with_transaction_fee(transaction_fee);
+        Self::bootstrap(
+            &ctx,
+            bounty_amount_in_nano_ergs,
+            ergs_box_for_bounty,
+            ergs_box_for_fee,
+        )
+        .unwrap()
+    }
+
+    /// `action_bootstrap_math_bounty_box()` taking the shared parameters of
+    /// the Action from an `ActionContext`. Errors naming the invalid
+    /// parameter if the bounty is too small or the input boxes cannot
+    /// cover the bounty, the tx fee, and the change box.
+    pub fn bootstrap(
+        ctx: &ActionContext,
+        bounty_amount_in_nano_ergs: u64,
+        ergs_box_for_bounty: ErgsBox,
+        ergs_box_for_fee: ErgsBox,
+    ) -> Result<UnsignedTransaction> {
+        let total_nano_ergs = ergs_box_for_bounty.nano_ergs() + ergs_box_for_fee.nano_ergs();
+        ActionParams::new("bootstrap")
+            .at_least(
+                "bounty_amount_in_nano_ergs",
+                bounty_amount_in_nano_ergs,
+                min_output_value(),
+            )
+            .covers(
+                "ergs_box_for_bounty + ergs_box_for_fee",
+                total_nano_ergs,
+                &[
+                    bounty_amount_in_nano_ergs,
+                    ctx.transaction_fee,
+                    min_output_value(),
+                ],
+            )
+            .check()?;
+
         let tx_inputs = vec![
             ergs_box_for_bounty.as_unsigned_input(),
             ergs_box_for_fee.as_unsigned_input(),
         ];
 
         // Calculating left over change nanoErgs
-        let total_nano_ergs = ergs_box_for_bounty.nano_ergs() + ergs_box_for_fee.nano_ergs();
-        let total_change = total_nano_ergs - bounty_amount_in_nano_ergs - transaction_fee;
+        let total_change = total_nano_ergs - bounty_amount_in_nano_ergs - ctx.transaction_fee;
 
         // Creating our Math Bounty Box output candidate
         let math_bounty_candidate = create_candidate(
@@ -91,18 +216,14 @@ impl MathBountyProtocol {
             &"94hWSMqgxHtRNEWoKrJFGVNQEYX34zfX68FNxWr".to_string(),
             &vec![],
             &vec![],
-            current_height,
-        )
-        .unwrap();
+            ctx.current_height,
+        )?;
 
         // Create the Transaction Fee box candidate
-        let transaction_fee_candidate =
-            TxFeeBox::output_candidate(transaction_fee, current_height).unwrap();
+        let transaction_fee_candidate = ctx.tx_fee_output()?;
 
         // Create the Change box candidate
-        let change_box_candidate =
-            ChangeBox::output_candidate(&vec![], total_change, &user_address, current_height)
-                .unwrap();
+        let change_box_candidate = ctx.change_output(&vec![], total_change)?;
 
         // Our output candidates list, specifically with the Math Bounty box
         // candidate being the first, meaning Output #0.
@@ -113,10 +234,81 @@ impl MathBountyProtocol {
         ];
 
         UnsignedTransaction::new(
-            tx_inputs.try_into().unwrap(),
+            tx_inputs.try_into().map_err(to_other_error)?,
             None,
-            output_candidates.try_into().unwrap(),
+            output_candidates.try_into().map_err(to_other_error)?,
+        )
+        .map_err(to_other_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ergo_headless_dapp_framework::encoding::address_string_to_ergo_tree;
+    use ergo_headless_dapp_framework::prelude::{BoxValue, ErgoTxId, NonMandatoryRegisters};
+
+    const USER_ADDRESS: &str = "9fSgJ7BmUxBQJ454prQDQ7fQMBkXPLaAmDnimgTtjym6FYPHjAV";
+
+    fn new_wasm_box(value: u64, address: &str, index: u16) -> WErgoBox {
+        ErgoBox::new(
+            BoxValue::new(value).unwrap(),
+            address_string_to_ergo_tree(&address.to_string()).unwrap(),
+            vec![],
+            NonMandatoryRegisters::empty(),
+            0,
+            ErgoTxId::zero(),
+            index,
         )
         .unwrap()
+        .into()
+    }
+
+    #[test]
+    fn wasm_actions_return_eip12_json() {
+        let bootstrap_json = MathBountyProtocol::w_bootstrap(
+            10000000,
+            new_wasm_box(20000000, USER_ADDRESS, 0),
+            100,
+            1000000,
+            new_wasm_box(2000000, USER_ADDRESS, 1),
+            USER_ADDRESS.to_string(),
+        )
+        .unwrap();
+        let tx: serde_json::Value = serde_json::from_str(&bootstrap_json).unwrap();
+        assert_eq!(tx["inputs"].as_array().unwrap().len(), 2);
+        assert_eq!(tx["inputs"][0]["value"], "20000000");
+        assert!(tx["inputs"][0]["extension"].is_object());
+        assert_eq!(tx["outputs"][0]["value"], "10000000");
+
+        let math_bounty_box = new_wasm_box(10000000, "94hWSMqgxHtRNEWoKrJFGVNQEYX34zfX68FNxWr", 0);
+        let solve_json = MathBountyProtocol::w_solve(
+            4,
+            math_bounty_box,
+            101,
+            1000000,
+            new_wasm_box(2000000, USER_ADDRESS, 1),
+            USER_ADDRESS.to_string(),
+        )
+        .unwrap();
+        let tx: serde_json::Value = serde_json::from_str(&solve_json).unwrap();
+        assert_eq!(tx["outputs"][0]["value"], "9000000");
+    }
+
+    #[test]
+    fn invalid_parameters_are_reported_by_name() {
+        let ctx = ActionContext::new(100, &USER_ADDRESS.to_string());
+        let ergs_box_for_bounty = ErgsBox::new(&new_wasm_box(5000000, USER_ADDRESS, 0).into());
+        let ergs_box_for_fee = ErgsBox::new(&new_wasm_box(2000000, USER_ADDRESS, 1).into());
+        let error = MathBountyProtocol::bootstrap(
+            &ctx,
+            10000000,
+            ergs_box_for_bounty.unwrap(),
+            ergs_box_for_fee.unwrap(),
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("`ergs_box_for_bounty + ergs_box_for_fee`"));
     }
 }